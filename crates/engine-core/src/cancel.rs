@@ -0,0 +1,57 @@
+//! Cooperative cancellation for long-running generation.
+
+use crate::error::EngineError;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Error string returned by cancelled operations, so callers can match it.
+pub const CANCELLED: &str = "cancelled";
+
+/// A shared cancellation flag. Cloning shares the flag; generators poll it
+/// periodically (per shape, per fill row) and bail out promptly once set.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Reset so a session-scoped token can be reused for the next export.
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+
+    /// Convenience guard: `Err(Cancelled)` once the flag is set.
+    pub fn check(&self) -> Result<(), EngineError> {
+        if self.is_cancelled() {
+            Err(EngineError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn token_is_shared_between_clones() {
+        let token = CancelToken::new();
+        let clone = token.clone();
+        assert!(token.check().is_ok());
+        clone.cancel();
+        assert_eq!(token.check().unwrap_err(), EngineError::Cancelled);
+        token.reset();
+        assert!(clone.check().is_ok());
+    }
+}
@@ -0,0 +1,851 @@
+//! Undoable editing: commands, history, and checkpoints.
+//!
+//! Every scene mutation the host performs goes through [`CommandHistory`] so
+//! undo/redo stays consistent. Entries carry enough state to replay in both
+//! directions (removed subtrees are snapshotted in full).
+
+use crate::error::EngineError;
+use crate::geometry::Transform;
+use crate::scene::{Node, NodeId, NodeKind, Scene};
+use crate::shapes::Color;
+use serde::{Deserialize, Serialize};
+
+/// A requested scene edit.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum Command {
+    AddNode {
+        kind: NodeKind,
+        parent: Option<NodeId>,
+    },
+    RemoveNode {
+        id: NodeId,
+    },
+    SetTransform {
+        id: NodeId,
+        transform: Transform,
+    },
+    SetBlockColor {
+        id: NodeId,
+        color: Option<Color>,
+    },
+    /// Rewrite all sequencer indexes to match render (layer) order.
+    ResetSequenceToRenderOrder,
+    /// Replace the whole stitch sequence with a permutation of the
+    /// current blocks.
+    SetSequenceOrder { ids: Vec<NodeId> },
+    /// Insert a manual machine command before stitch `at_index` of a
+    /// shape's block.
+    InsertStitchCommand {
+        id: NodeId,
+        at_index: usize,
+        kind: crate::stitch::ManualCommandKind,
+    },
+    /// Remove the manual command anchored at `at_index` of a shape's block.
+    RemoveStitchCommand {
+        id: NodeId,
+        at_index: usize,
+    },
+    /// Run the geometry clean pass on a Path shape.
+    CleanPath {
+        id: NodeId,
+        epsilon_mm: f64,
+    },
+    /// Upgrade a running-stitch line to a satin column of `width_mm`.
+    RunningToSatin {
+        id: NodeId,
+        width_mm: f64,
+        density: f64,
+    },
+    /// Downgrade a satin column to a monoline running stitch.
+    SatinToRunning {
+        id: NodeId,
+    },
+}
+
+/// A fully cloned subtree plus where it was attached, so a removal can be
+/// undone (and redone) exactly.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubtreeSnapshot {
+    /// Nodes in depth-first order; the first entry is the subtree root.
+    pub nodes: Vec<Node>,
+    pub parent: Option<NodeId>,
+    /// Position within the parent's (or root's) child list.
+    pub index: usize,
+}
+
+/// An applied edit with the state needed to walk it in either direction.
+#[derive(Debug, Clone, PartialEq)]
+enum HistoryEntry {
+    /// A subtree now present in the scene; undo removes it.
+    Insert { snapshot: SubtreeSnapshot },
+    /// Several subtrees inserted as one edit (array replication); undo
+    /// removes them all.
+    InsertMany { snapshots: Vec<SubtreeSnapshot> },
+    /// Nodes swapped for other nodes in one step (boolean ops).
+    Replace {
+        removed: Vec<SubtreeSnapshot>,
+        inserted: Vec<SubtreeSnapshot>,
+    },
+    /// A subtree removed from the scene; undo restores it.
+    Remove { snapshot: SubtreeSnapshot },
+    Transform {
+        id: NodeId,
+        before: Transform,
+        after: Transform,
+    },
+    BlockColor {
+        id: NodeId,
+        before: Option<Color>,
+        after: Option<Color>,
+    },
+    SequenceIndexes {
+        before: Vec<(NodeId, Option<usize>)>,
+        after: Vec<(NodeId, Option<usize>)>,
+    },
+    ManualCommands {
+        id: NodeId,
+        before: Vec<crate::stitch::ManualStitchCommand>,
+        after: Vec<crate::stitch::ManualStitchCommand>,
+    },
+    ShapePath {
+        id: NodeId,
+        before: crate::path::VectorPath,
+        after: crate::path::VectorPath,
+    },
+    /// Stitch parameters and style rewritten together (technique
+    /// conversions).
+    StitchProfile {
+        id: NodeId,
+        before: Box<(crate::stitch::StitchParams, crate::shapes::ShapeStyle)>,
+        after: Box<(crate::stitch::StitchParams, crate::shapes::ShapeStyle)>,
+    },
+}
+
+/// Approximate heap bytes retained by one history entry.
+fn entry_memory(entry: &HistoryEntry) -> usize {
+    let base = std::mem::size_of::<HistoryEntry>();
+    base + match entry {
+        HistoryEntry::Insert { snapshot } | HistoryEntry::Remove { snapshot } => {
+            snapshot.nodes.iter().map(node_memory).sum()
+        }
+        HistoryEntry::InsertMany { snapshots } => snapshots
+            .iter()
+            .flat_map(|s| s.nodes.iter().map(node_memory))
+            .sum(),
+        HistoryEntry::Replace { removed, inserted } => removed
+            .iter()
+            .chain(inserted)
+            .flat_map(|s| s.nodes.iter().map(node_memory))
+            .sum(),
+        HistoryEntry::SequenceIndexes { before, after } => {
+            (before.len() + after.len()) * std::mem::size_of::<(NodeId, Option<usize>)>()
+        }
+        HistoryEntry::ManualCommands { before, after, .. } => {
+            (before.len() + after.len())
+                * std::mem::size_of::<crate::stitch::ManualStitchCommand>()
+        }
+        HistoryEntry::ShapePath { before, after, .. } => {
+            (before.commands.len() + after.commands.len())
+                * std::mem::size_of::<crate::path::PathCommand>()
+        }
+        HistoryEntry::StitchProfile { before, after, .. } => {
+            (before.0.manual_commands.len() + after.0.manual_commands.len())
+                * std::mem::size_of::<crate::stitch::ManualStitchCommand>()
+        }
+        HistoryEntry::Transform { .. } | HistoryEntry::BlockColor { .. } => 0,
+    }
+}
+
+/// Approximate bytes for one snapshotted node, counting path geometry.
+fn node_memory(node: &Node) -> usize {
+    let mut bytes = std::mem::size_of::<Node>() + node.children.len() * std::mem::size_of::<NodeId>();
+    if let NodeKind::Shape(shape) = &node.kind {
+        if let crate::shapes::ShapeData::Path(path) = &shape.data {
+            bytes += path.commands.len() * std::mem::size_of::<crate::path::PathCommand>();
+        }
+        bytes += shape.stitch.manual_commands.len()
+            * std::mem::size_of::<crate::stitch::ManualStitchCommand>();
+    }
+    bytes
+}
+
+pub type CheckpointId = u64;
+
+/// Default cap on retained history entries.
+pub const DEFAULT_MAX_HISTORY: usize = 1000;
+
+#[derive(Debug, Default, Clone)]
+pub struct CommandHistory {
+    undo_stack: Vec<HistoryEntry>,
+    redo_stack: Vec<HistoryEntry>,
+    /// `(id, undo_stack depth at creation)`, ascending by depth.
+    checkpoints: Vec<(CheckpointId, usize)>,
+    next_checkpoint: CheckpointId,
+    max_history: usize,
+}
+
+impl CommandHistory {
+    pub fn new() -> Self {
+        Self {
+            max_history: DEFAULT_MAX_HISTORY,
+            ..Self::default()
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    /// Apply a command, recording it for undo. Returns the new node ID for
+    /// `AddNode` commands.
+    pub fn apply(&mut self, scene: &mut Scene, command: Command) -> Result<Option<NodeId>, EngineError> {
+        let (entry, created) = match command {
+            Command::AddNode { kind, parent } => {
+                let id = scene.add_node(kind, parent)?;
+                let snapshot = scene.take_subtree_snapshot(id)?;
+                (HistoryEntry::Insert { snapshot }, Some(id))
+            }
+            Command::RemoveNode { id } => {
+                if scene.node(id)?.locked {
+                    return Err(EngineError::Locked(id));
+                }
+                let snapshot = scene.take_subtree_snapshot(id)?;
+                scene.remove_subtree_raw(id);
+                (HistoryEntry::Remove { snapshot }, None)
+            }
+            Command::SetTransform { id, transform } => {
+                let before = scene.node(id)?.transform;
+                scene.set_transform(id, transform)?;
+                (
+                    HistoryEntry::Transform {
+                        id,
+                        before,
+                        after: transform,
+                    },
+                    None,
+                )
+            }
+            Command::ResetSequenceToRenderOrder => {
+                let before = scene.reset_sequence_to_render_order();
+                let after = before
+                    .iter()
+                    .enumerate()
+                    .map(|(pos, (id, _))| (*id, Some(pos)))
+                    .collect();
+                (HistoryEntry::SequenceIndexes { before, after }, None)
+            }
+            Command::SetSequenceOrder { ref ids } => {
+                let before = scene.set_sequence_order(ids)?;
+                let after = ids.iter().enumerate().map(|(pos, id)| (*id, Some(pos))).collect();
+                (HistoryEntry::SequenceIndexes { before, after }, None)
+            }
+            Command::InsertStitchCommand { id, at_index, kind } => {
+                let before = scene.manual_commands(id)?;
+                scene.insert_stitch_command(id, at_index, kind)?;
+                let after = scene.manual_commands(id)?;
+                (HistoryEntry::ManualCommands { id, before, after }, None)
+            }
+            Command::RemoveStitchCommand { id, at_index } => {
+                let before = scene.manual_commands(id)?;
+                scene.remove_stitch_command(id, at_index)?;
+                let after = scene.manual_commands(id)?;
+                (HistoryEntry::ManualCommands { id, before, after }, None)
+            }
+            Command::CleanPath { id, epsilon_mm } => {
+                let before = scene.clean_path(id, epsilon_mm)?;
+                // `clean` is deterministic, so the new state is just the
+                // old one cleaned again.
+                let after = crate::path::clean(&before, epsilon_mm);
+                (HistoryEntry::ShapePath { id, before, after }, None)
+            }
+            Command::RunningToSatin {
+                id,
+                width_mm,
+                density,
+            } => {
+                let before = Box::new(scene.stitch_profile(id)?);
+                scene.running_to_satin(id, width_mm, density)?;
+                let after = Box::new(scene.stitch_profile(id)?);
+                (HistoryEntry::StitchProfile { id, before, after }, None)
+            }
+            Command::SatinToRunning { id } => {
+                let before = Box::new(scene.stitch_profile(id)?);
+                scene.satin_to_running(id)?;
+                let after = Box::new(scene.stitch_profile(id)?);
+                (HistoryEntry::StitchProfile { id, before, after }, None)
+            }
+            Command::SetBlockColor { id, color } => {
+                let before = scene.set_block_color(id, color)?;
+                (
+                    HistoryEntry::BlockColor {
+                        id,
+                        before,
+                        after: color,
+                    },
+                    None,
+                )
+            }
+        };
+
+        self.push_entry(entry);
+        Ok(created)
+    }
+
+    /// Replicate the subtree at `id` across a grid (see
+    /// [`Scene::array_replicate`]) as a single undoable entry. Returns the
+    /// clone roots in row-major order.
+    pub fn apply_array_replicate(
+        &mut self,
+        scene: &mut Scene,
+        id: NodeId,
+        rows: usize,
+        cols: usize,
+        dx: f64,
+        dy: f64,
+    ) -> Result<Vec<NodeId>, EngineError> {
+        let created = scene.array_replicate(id, rows, cols, dx, dy)?;
+        let snapshots = created
+            .iter()
+            .map(|&nid| scene.take_subtree_snapshot(nid))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.push_entry(HistoryEntry::InsertMany { snapshots });
+        Ok(created)
+    }
+
+    /// Replace nodes `a` and `b` with the boolean of their geometry (see
+    /// [`Scene::boolean_replace`]) as a single undoable entry. Returns the
+    /// created node IDs.
+    pub fn apply_boolean(
+        &mut self,
+        scene: &mut Scene,
+        a: NodeId,
+        b: NodeId,
+        op: crate::path::BooleanOp,
+    ) -> Result<Vec<NodeId>, EngineError> {
+        for id in [a, b] {
+            if scene.node(id)?.locked {
+                return Err(EngineError::Locked(id));
+            }
+        }
+        let removed = vec![
+            scene.take_subtree_snapshot(a)?,
+            scene.take_subtree_snapshot(b)?,
+        ];
+        let created = scene.boolean_replace(a, b, op)?;
+        let inserted = created
+            .iter()
+            .map(|&nid| scene.take_subtree_snapshot(nid))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.push_entry(HistoryEntry::Replace { removed, inserted });
+        Ok(created)
+    }
+
+    /// Record an applied entry, invalidating redo history and unreachable
+    /// checkpoints.
+    fn push_entry(&mut self, entry: HistoryEntry) {
+        self.redo_stack.clear();
+        // Redo history is gone, so checkpoints past the current depth can
+        // never be reached again.
+        let depth = self.undo_stack.len();
+        self.checkpoints.retain(|(_, pos)| *pos <= depth);
+        self.undo_stack.push(entry);
+        self.trim_to_max();
+    }
+
+    /// Cap retained history at `n` entries, dropping the oldest (and their
+    /// snapshots) immediately if the live history is already larger.
+    pub fn set_max_history(&mut self, n: usize) {
+        self.max_history = n;
+        self.trim_to_max();
+    }
+
+    /// Approximate bytes retained by the history, dominated by subtree
+    /// snapshots (full node clones, including path geometry).
+    pub fn memory_estimate(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + self
+                .undo_stack
+                .iter()
+                .chain(self.redo_stack.iter())
+                .map(entry_memory)
+                .sum::<usize>()
+    }
+
+    fn trim_to_max(&mut self) {
+        while self.max_history > 0 && self.undo_stack.len() > self.max_history {
+            self.undo_stack.remove(0);
+            // The pre-history state is unreachable now; shift checkpoint
+            // positions and drop the ones that pointed before it.
+            self.checkpoints.retain_mut(|(_, pos)| {
+                if *pos == 0 {
+                    false
+                } else {
+                    *pos -= 1;
+                    true
+                }
+            });
+        }
+    }
+
+    fn walk_back(scene: &mut Scene, entry: &HistoryEntry) -> Result<(), EngineError> {
+        match entry {
+            HistoryEntry::InsertMany { snapshots } => {
+                for snapshot in snapshots.iter().rev() {
+                    scene.remove_subtree_raw(snapshot.nodes[0].id);
+                }
+                Ok(())
+            }
+            HistoryEntry::Insert { snapshot } => {
+                scene.remove_subtree_raw(snapshot.nodes[0].id);
+                Ok(())
+            }
+            HistoryEntry::Remove { snapshot } => scene.restore_subtree(snapshot),
+            HistoryEntry::Transform { id, before, .. } => {
+                scene.node_mut(*id)?.transform = *before;
+                Ok(())
+            }
+            HistoryEntry::BlockColor { id, before, .. } => {
+                scene.set_block_color(*id, *before).map(|_| ())
+            }
+            HistoryEntry::SequenceIndexes { before, .. } => {
+                scene.apply_sequencer_indexes(before);
+                Ok(())
+            }
+            HistoryEntry::ManualCommands { id, before, .. } => {
+                scene.set_manual_commands(*id, before.clone());
+                Ok(())
+            }
+            HistoryEntry::ShapePath { id, before, .. } => {
+                scene.set_shape_path(*id, before.clone());
+                Ok(())
+            }
+            HistoryEntry::StitchProfile { id, before, .. } => {
+                scene.set_stitch_profile(*id, before);
+                Ok(())
+            }
+            HistoryEntry::Replace { removed, inserted } => {
+                for snapshot in inserted.iter().rev() {
+                    scene.remove_subtree_raw(snapshot.nodes[0].id);
+                }
+                for snapshot in removed {
+                    scene.restore_subtree(snapshot)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn walk_forward(scene: &mut Scene, entry: &HistoryEntry) -> Result<(), EngineError> {
+        match entry {
+            HistoryEntry::Insert { snapshot } => scene.restore_subtree(snapshot),
+            HistoryEntry::InsertMany { snapshots } => {
+                for snapshot in snapshots {
+                    scene.restore_subtree(snapshot)?;
+                }
+                Ok(())
+            }
+            HistoryEntry::Remove { snapshot } => {
+                scene.remove_subtree_raw(snapshot.nodes[0].id);
+                Ok(())
+            }
+            HistoryEntry::Transform { id, after, .. } => {
+                scene.node_mut(*id)?.transform = *after;
+                Ok(())
+            }
+            HistoryEntry::BlockColor { id, after, .. } => {
+                scene.set_block_color(*id, *after).map(|_| ())
+            }
+            HistoryEntry::SequenceIndexes { after, .. } => {
+                scene.apply_sequencer_indexes(after);
+                Ok(())
+            }
+            HistoryEntry::ManualCommands { id, after, .. } => {
+                scene.set_manual_commands(*id, after.clone());
+                Ok(())
+            }
+            HistoryEntry::Replace { removed, inserted } => {
+                for snapshot in removed.iter().rev() {
+                    scene.remove_subtree_raw(snapshot.nodes[0].id);
+                }
+                for snapshot in inserted {
+                    scene.restore_subtree(snapshot)?;
+                }
+                Ok(())
+            }
+            HistoryEntry::ShapePath { id, after, .. } => {
+                scene.set_shape_path(*id, after.clone());
+                Ok(())
+            }
+            HistoryEntry::StitchProfile { id, after, .. } => {
+                scene.set_stitch_profile(*id, after);
+                Ok(())
+            }
+        }
+    }
+
+    /// Undo one entry. Returns `false` when there is nothing to undo.
+    pub fn undo(&mut self, scene: &mut Scene) -> Result<bool, EngineError> {
+        let Some(entry) = self.undo_stack.pop() else {
+            return Ok(false);
+        };
+        Self::walk_back(scene, &entry)?;
+        self.redo_stack.push(entry);
+        Ok(true)
+    }
+
+    /// Redo one entry. Returns `false` when there is nothing to redo.
+    pub fn redo(&mut self, scene: &mut Scene) -> Result<bool, EngineError> {
+        let Some(entry) = self.redo_stack.pop() else {
+            return Ok(false);
+        };
+        Self::walk_forward(scene, &entry)?;
+        self.undo_stack.push(entry);
+        Ok(true)
+    }
+
+    /// Mark the current state so it can be reverted to later.
+    pub fn checkpoint(&mut self) -> CheckpointId {
+        let id = self.next_checkpoint;
+        self.next_checkpoint += 1;
+        self.checkpoints.push((id, self.undo_stack.len()));
+        id
+    }
+
+    /// Replay undos until the history depth matches `checkpoint`. Errors if
+    /// the checkpoint was invalidated (dropped by an intervening edit after
+    /// undos, or trimmed out of history).
+    pub fn undo_to(&mut self, scene: &mut Scene, checkpoint: CheckpointId) -> Result<(), EngineError> {
+        let Some(&(_, depth)) = self.checkpoints.iter().find(|(id, _)| *id == checkpoint) else {
+            return Err(EngineError::InvalidInput(format!(
+                "checkpoint {checkpoint} is no longer valid"
+            )));
+        };
+        if depth > self.undo_stack.len() {
+            return Err(EngineError::InvalidInput(format!(
+                "checkpoint {checkpoint} is ahead of the current state"
+            )));
+        }
+        while self.undo_stack.len() > depth {
+            self.undo(scene)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::ShapeNode;
+    use crate::shapes::{RectShape, ShapeData, ShapeStyle};
+    use crate::stitch::StitchParams;
+
+    fn rect_kind(w: f64) -> NodeKind {
+        NodeKind::Shape(Box::new(ShapeNode {
+            data: ShapeData::Rect(RectShape {
+                width: w,
+                height: w,
+            }),
+            style: ShapeStyle::default(),
+            stitch: StitchParams::default(),
+            sequencer: Default::default(),
+        }))
+    }
+
+    #[test]
+    fn array_replicate_is_one_undo_step() {
+        let mut scene = Scene::new();
+        let mut history = CommandHistory::new();
+        let id = history
+            .apply(
+                &mut scene,
+                Command::AddNode {
+                    kind: rect_kind(4.0),
+                    parent: None,
+                },
+            )
+            .unwrap()
+            .unwrap();
+
+        let clones = history
+            .apply_array_replicate(&mut scene, id, 2, 3, 10.0, 8.0)
+            .unwrap();
+        assert_eq!(clones.len(), 6);
+        assert_eq!(scene.nodes.len(), 7);
+        for (i, &clone) in clones.iter().enumerate() {
+            let t = scene.node(clone).unwrap().transform;
+            let (row, col) = (i / 3, i % 3);
+            assert_eq!((t.tx, t.ty), (col as f64 * 10.0, row as f64 * 8.0));
+        }
+
+        assert!(history.undo(&mut scene).unwrap());
+        assert_eq!(scene.nodes.len(), 1);
+        assert!(scene.node(id).is_ok());
+
+        assert!(history.redo(&mut scene).unwrap());
+        assert_eq!(scene.nodes.len(), 7);
+    }
+
+    #[test]
+    fn boolean_union_merges_and_undoes() {
+        let mut scene = Scene::new();
+        let mut history = CommandHistory::new();
+        let mut ids = Vec::new();
+        // Offset on both axes so no edges are collinear (edge-overlap is
+        // out of scope for the clipper).
+        for (x, y) in [(0.0, 0.0), (5.0, 3.0)] {
+            let id = history
+                .apply(
+                    &mut scene,
+                    Command::AddNode {
+                        kind: rect_kind(10.0),
+                        parent: None,
+                    },
+                )
+                .unwrap()
+                .unwrap();
+            history
+                .apply(
+                    &mut scene,
+                    Command::SetTransform {
+                        id,
+                        transform: crate::geometry::Transform::translation(x, y),
+                    },
+                )
+                .unwrap();
+            ids.push(id);
+        }
+
+        let created = history
+            .apply_boolean(&mut scene, ids[0], ids[1], crate::path::BooleanOp::Union)
+            .unwrap();
+        assert_eq!(created.len(), 1);
+        assert!(scene.node(ids[0]).is_err());
+        assert!(scene.node(ids[1]).is_err());
+
+        // Shoelace area of the merged outline: two 10×10 rects with a
+        // 5×7 overlap cover 165mm², not 200.
+        let node = scene.node(created[0]).unwrap();
+        let NodeKind::Shape(shape) = &node.kind else {
+            panic!("result is a shape");
+        };
+        let area: f64 = shape
+            .data
+            .to_path()
+            .flatten(0.1)
+            .iter()
+            .map(|ring| {
+                ring.windows(2)
+                    .map(|w| w[0].x * w[1].y - w[1].x * w[0].y)
+                    .sum::<f64>()
+                    .abs()
+                    * 0.5
+            })
+            .sum();
+        assert!((area - 165.0).abs() < 1.0, "area {area}");
+
+        assert!(history.undo(&mut scene).unwrap());
+        assert!(scene.node(ids[0]).is_ok());
+        assert!(scene.node(ids[1]).is_ok());
+        assert!(scene.node(created[0]).is_err());
+
+        assert!(history.redo(&mut scene).unwrap());
+        assert!(scene.node(created[0]).is_ok());
+    }
+
+    #[test]
+    fn bulk_sequence_reorder_validates_and_undoes() {
+        let mut scene = Scene::new();
+        let mut history = CommandHistory::new();
+        let mut ids = Vec::new();
+        for _ in 0..3 {
+            ids.push(
+                history
+                    .apply(
+                        &mut scene,
+                        Command::AddNode {
+                            kind: rect_kind(4.0),
+                            parent: None,
+                        },
+                    )
+                    .unwrap()
+                    .unwrap(),
+            );
+        }
+
+        // An incomplete list is rejected without touching the track.
+        let short = vec![ids[2], ids[0]];
+        assert!(history
+            .apply(&mut scene, Command::SetSequenceOrder { ids: short })
+            .is_err());
+        assert_eq!(scene.sequencer_shape_ids(), ids);
+
+        let reversed = vec![ids[2], ids[1], ids[0]];
+        history
+            .apply(
+                &mut scene,
+                Command::SetSequenceOrder {
+                    ids: reversed.clone(),
+                },
+            )
+            .unwrap();
+        assert_eq!(scene.sequencer_shape_ids(), reversed);
+
+        assert!(history.undo(&mut scene).unwrap());
+        assert_eq!(scene.sequencer_shape_ids(), ids);
+    }
+
+    #[test]
+    fn undo_redo_round_trip() {
+        let mut scene = Scene::new();
+        let mut history = CommandHistory::new();
+        let id = history
+            .apply(
+                &mut scene,
+                Command::AddNode {
+                    kind: rect_kind(5.0),
+                    parent: None,
+                },
+            )
+            .unwrap()
+            .unwrap();
+        history
+            .apply(
+                &mut scene,
+                Command::SetTransform {
+                    id,
+                    transform: Transform::translation(3.0, 0.0),
+                },
+            )
+            .unwrap();
+
+        assert!(history.undo(&mut scene).unwrap());
+        assert_eq!(scene.node(id).unwrap().transform, Transform::identity());
+        assert!(history.undo(&mut scene).unwrap());
+        assert!(scene.node(id).is_err());
+        assert!(history.redo(&mut scene).unwrap());
+        assert!(history.redo(&mut scene).unwrap());
+        assert_eq!(
+            scene.node(id).unwrap().transform,
+            Transform::translation(3.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn remove_undo_restores_subtree_in_place() {
+        let mut scene = Scene::new();
+        let mut history = CommandHistory::new();
+        let first = scene.add_node(rect_kind(1.0), None).unwrap();
+        let victim = scene.add_node(rect_kind(2.0), None).unwrap();
+        let last = scene.add_node(rect_kind(3.0), None).unwrap();
+
+        history
+            .apply(&mut scene, Command::RemoveNode { id: victim })
+            .unwrap();
+        assert_eq!(scene.root_children, vec![first, last]);
+        history.undo(&mut scene).unwrap();
+        assert_eq!(scene.root_children, vec![first, victim, last]);
+    }
+
+    #[test]
+    fn revert_to_checkpoint_and_keep_undoing() {
+        let mut scene = Scene::new();
+        let mut history = CommandHistory::new();
+        let add = |history: &mut CommandHistory, scene: &mut Scene, w: f64| {
+            history
+                .apply(
+                    scene,
+                    Command::AddNode {
+                        kind: rect_kind(w),
+                        parent: None,
+                    },
+                )
+                .unwrap()
+                .unwrap()
+        };
+
+        add(&mut history, &mut scene, 1.0);
+        add(&mut history, &mut scene, 2.0);
+        add(&mut history, &mut scene, 3.0);
+        let three_edit_state = scene.clone();
+        let checkpoint = history.checkpoint();
+        add(&mut history, &mut scene, 4.0);
+        add(&mut history, &mut scene, 5.0);
+
+        history.undo_to(&mut scene, checkpoint).unwrap();
+        // The ID counter is monotonic, so compare document state.
+        assert_eq!(scene.nodes, three_edit_state.nodes);
+        assert_eq!(scene.root_children, three_edit_state.root_children);
+
+        // History before the checkpoint is still walkable.
+        assert!(history.undo(&mut scene).unwrap());
+        assert_eq!(scene.root_children.len(), 2);
+    }
+
+    #[test]
+    fn lowering_max_history_shrinks_the_memory_estimate() {
+        let mut scene = Scene::new();
+        let mut history = CommandHistory::new();
+        // Many removals of multi-node subtrees pile up full snapshots.
+        for _ in 0..20 {
+            let group = scene.add_node(NodeKind::Group, None).unwrap();
+            for w in 1..=5 {
+                scene.add_node(rect_kind(w as f64), Some(group)).unwrap();
+            }
+            history
+                .apply(&mut scene, Command::RemoveNode { id: group })
+                .unwrap();
+        }
+        let before = history.memory_estimate();
+        history.set_max_history(3);
+        let after = history.memory_estimate();
+        assert!(after < before, "estimate did not shrink: {before} -> {after}");
+        // The newest entries survive compaction.
+        assert!(history.undo(&mut scene).unwrap());
+    }
+
+    #[test]
+    fn checkpoint_is_invalidated_by_edit_after_undo() {
+        let mut scene = Scene::new();
+        let mut history = CommandHistory::new();
+        history
+            .apply(
+                &mut scene,
+                Command::AddNode {
+                    kind: rect_kind(1.0),
+                    parent: None,
+                },
+            )
+            .unwrap();
+        let checkpoint = history.checkpoint();
+        history
+            .apply(
+                &mut scene,
+                Command::AddNode {
+                    kind: rect_kind(2.0),
+                    parent: None,
+                },
+            )
+            .unwrap();
+
+        // Undo past the checkpoint, then branch with a new edit: the redo
+        // stack clears and the checkpoint depth becomes unreachable... but
+        // this checkpoint sits at depth 1, still within the new history.
+        history.undo(&mut scene).unwrap();
+        history.undo(&mut scene).unwrap();
+        history
+            .apply(
+                &mut scene,
+                Command::AddNode {
+                    kind: rect_kind(3.0),
+                    parent: None,
+                },
+            )
+            .unwrap();
+        assert!(history.undo_to(&mut scene, checkpoint).is_err());
+    }
+}
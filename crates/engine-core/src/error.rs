@@ -0,0 +1,42 @@
+//! The crate-wide error type.
+
+use crate::scene::NodeId;
+
+/// Error returned by scene, command, and export APIs. Carrying the kind
+/// (and offending node) as data lets native callers branch on it; display
+/// keeps the human-readable message the string errors used to carry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EngineError {
+    NodeNotFound(NodeId),
+    NotAShape(NodeId),
+    Locked(NodeId),
+    /// A cancel token was flagged mid-operation.
+    Cancelled,
+    /// Anything wrong with the caller's arguments or the operation's
+    /// preconditions that isn't one of the node cases above.
+    InvalidInput(String),
+    /// A (de)serialization failure at an API boundary.
+    Serialization(String),
+}
+
+impl std::fmt::Display for EngineError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EngineError::NodeNotFound(id) => write!(f, "node {id} not found"),
+            EngineError::NotAShape(id) => write!(f, "node {id} is not a shape"),
+            EngineError::Locked(id) => write!(f, "node {id} is locked"),
+            EngineError::Cancelled => f.write_str(crate::cancel::CANCELLED),
+            EngineError::InvalidInput(msg) | EngineError::Serialization(msg) => f.write_str(msg),
+        }
+    }
+}
+
+impl std::error::Error for EngineError {}
+
+/// Free-form messages (parser errors, legacy callers) fold into
+/// `InvalidInput` so they can cross `?` boundaries unchanged.
+impl From<String> for EngineError {
+    fn from(msg: String) -> Self {
+        EngineError::InvalidInput(msg)
+    }
+}
@@ -0,0 +1,3391 @@
+//! Export assembly: turns a scene into a flat machine-ready stitch program.
+//!
+//! The pipeline has three stages: per-shape stitch generation (blocks),
+//! block ordering (color grouping / routing), and assembly into a single
+//! `ExportDesign` with jumps, trims, and color changes.
+
+use crate::cancel::CancelToken;
+use crate::error::EngineError;
+use crate::geometry::{BoundingBox, Point};
+use crate::path::DEFAULT_FLATTEN_TOLERANCE;
+use crate::scene::{NodeId, NodeKind, Scene};
+use crate::shapes::Color;
+use crate::stitch::running::generate_running_stitches;
+use crate::stitch::satin::generate_satin_shape_stitches;
+use crate::stitch::{Stitch, StitchType};
+use serde::{Deserialize, Serialize};
+
+/// The kind of a flat export stitch record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportStitchType {
+    Normal,
+    Jump,
+    Trim,
+    ColorChange,
+    Stop,
+    End,
+}
+
+impl From<crate::stitch::ManualCommandKind> for ExportStitchType {
+    fn from(kind: crate::stitch::ManualCommandKind) -> Self {
+        match kind {
+            crate::stitch::ManualCommandKind::Jump => Self::Jump,
+            crate::stitch::ManualCommandKind::Trim => Self::Trim,
+            crate::stitch::ManualCommandKind::Stop => Self::Stop,
+            crate::stitch::ManualCommandKind::ColorChange => Self::ColorChange,
+        }
+    }
+}
+
+/// Y-axis orientation of a coordinate space. The design space is Y-down
+/// (screen/SVG convention); most machine formats are Y-up. Exporters convert
+/// between the two in exactly one place (`format::stitches_in_units`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CoordinateSystem {
+    #[default]
+    YDown,
+    YUp,
+}
+
+/// How `format::stitches_in_units` snaps mm coordinates to a format's
+/// integer grid. Whatever the mode, the conversion carries its rounding
+/// error forward Bresenham-style, so a long run of sub-unit steps still
+/// lands its endpoint on the true coordinate instead of drifting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Quantization {
+    /// Nearest machine unit (default).
+    #[default]
+    Round,
+    /// Truncate toward negative infinity; the carry keeps the bias bounded.
+    Floor,
+    /// Snap to a 0.1 mm grid regardless of the format's unit size.
+    Nearest0_1,
+    /// Snap to a 0.01 mm grid regardless of the format's unit size.
+    Nearest0_01,
+}
+
+impl Quantization {
+    /// Snap a step expressed in format units to this mode's grid.
+    pub(crate) fn snap(self, units: f64, units_per_mm: f64) -> f64 {
+        match self {
+            Quantization::Round => units.round(),
+            Quantization::Floor => units.floor(),
+            Quantization::Nearest0_1 => {
+                let grid = 0.1 * units_per_mm;
+                (units / grid).round() * grid
+            }
+            Quantization::Nearest0_01 => {
+                let grid = 0.01 * units_per_mm;
+                (units / grid).round() * grid
+            }
+        }
+    }
+}
+
+/// One record of the flat stitch program, in design-space mm.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ExportStitch {
+    pub x: f64,
+    pub y: f64,
+    pub kind: ExportStitchType,
+}
+
+impl ExportStitch {
+    pub fn new(x: f64, y: f64, kind: ExportStitchType) -> Self {
+        Self { x, y, kind }
+    }
+}
+
+/// A fully assembled design ready for format encoding.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportDesign {
+    pub name: String,
+    pub stitches: Vec<ExportStitch>,
+    /// The thread **change sequence**, not a unique palette: `colors[0]`
+    /// is the starting thread and every `ColorChange` stitch advances to
+    /// the next entry, so `colors.len()` is always the change count plus
+    /// one. A color revisited after an intervening color appears again
+    /// (travel-optimized orderings interleave colors); use
+    /// [`distinct_color_count`](Self::distinct_color_count) for the
+    /// palette size.
+    pub colors: Vec<Color>,
+    /// Y-axis convention of `stitches`.
+    #[serde(default)]
+    pub coordinate_system: CoordinateSystem,
+    /// Unit-grid snapping applied when encoding to a machine format.
+    #[serde(default)]
+    pub quantization: Quantization,
+}
+
+impl ExportDesign {
+    /// Bounding box over every positioned record (jumps included — the
+    /// machine travels there even without a penetration).
+    pub fn extents(&self) -> BoundingBox {
+        let mut bbox = BoundingBox::empty();
+        for s in &self.stitches {
+            bbox.include(Point::new(s.x, s.y));
+        }
+        bbox
+    }
+
+    /// Extents in the flat JSON shape the UI consumes.
+    pub fn export_extents(&self) -> ExportExtents {
+        ExportExtents::from(self.extents())
+    }
+
+    pub fn normal_stitch_count(&self) -> usize {
+        self.stitches
+            .iter()
+            .filter(|s| s.kind == ExportStitchType::Normal)
+            .count()
+    }
+
+    /// Number of unique threads across the change sequence — what the
+    /// operator loads, as opposed to how often the machine swaps.
+    pub fn distinct_color_count(&self) -> usize {
+        let mut seen: Vec<Color> = Vec::new();
+        for &c in &self.colors {
+            if !seen.contains(&c) {
+                seen.push(c);
+            }
+        }
+        seen.len()
+    }
+}
+
+/// Serializable extents of an assembled design. Distinct from the scene's
+/// geometric content bounds: pull compensation and underlay can push stitches
+/// beyond the geometry.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ExportExtents {
+    pub min_x: f64,
+    pub min_y: f64,
+    pub max_x: f64,
+    pub max_y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl From<BoundingBox> for ExportExtents {
+    fn from(b: BoundingBox) -> Self {
+        Self {
+            min_x: b.min_x,
+            min_y: b.min_y,
+            max_x: b.max_x,
+            max_y: b.max_y,
+            width: b.width(),
+            height: b.height(),
+        }
+    }
+}
+
+/// What to emit between two color blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(tag = "mode", content = "value", rename_all = "snake_case")]
+pub enum ColorChangeTravel {
+    /// Always trim before the color change (safe default).
+    #[default]
+    AlwaysTrim,
+    /// Trim only when the travel to the next block exceeds this distance (mm).
+    TrimIfFar(f64),
+    /// No trim: secure with tie-off lock stitches and let the change handle it.
+    TieOff,
+}
+
+/// When outline (stroke-technique) blocks stitch relative to fills.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutlinePhase {
+    /// Blocks stitch in sequence order regardless of technique.
+    #[default]
+    PerObject,
+    /// All fill blocks stitch first, then all outline blocks — the
+    /// convention that keeps outlines crisp on top of every fill.
+    AllFillsThenOutlines,
+}
+
+/// Options controlling block ordering and travel between blocks.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RoutingOptions {
+    /// Keep blocks grouped by color in first-appearance order.
+    pub preserve_color_order: bool,
+    /// Same-color travel longer than this (mm) gets a trim before the jump.
+    pub trim_threshold_mm: f64,
+    /// Policy for the travel emitted at a color boundary.
+    pub color_change_travel: ColorChangeTravel,
+    /// Return a minimal placeholder design (a single `End`, one default
+    /// color) instead of erroring when the scene has no stitchable shapes.
+    /// Off by default so interactive exports still surface the problem.
+    pub allow_empty: bool,
+    /// Machine start position (design mm). Ordering leads with the block
+    /// whose entry stitch is closest to this point (and, with color
+    /// grouping, that block's color); `None` keeps pure source order.
+    pub start_near: Option<Point>,
+    /// Coordinate snapping mode used when the design is encoded to a
+    /// machine format.
+    pub quantization: Quantization,
+    /// Secure every trim with lock stitches: a tie-off just before the
+    /// trim and a tie-in at the first penetration after it. Covers manual
+    /// trims too, which block-boundary ties never see.
+    pub tie_at_trims: bool,
+    /// Whether outlines stitch in place or after every fill.
+    pub outline_phase: OutlinePhase,
+    /// Abort generation once the design would exceed this many stitch
+    /// records (`0` = unlimited). A runaway density setting then fails
+    /// fast with a budget error instead of exhausting the worker's memory.
+    pub max_total_stitches: usize,
+}
+
+impl Default for RoutingOptions {
+    fn default() -> Self {
+        Self {
+            preserve_color_order: true,
+            trim_threshold_mm: 5.0,
+            color_change_travel: ColorChangeTravel::default(),
+            allow_empty: false,
+            start_near: None,
+            quantization: Quantization::default(),
+            tie_at_trims: false,
+            outline_phase: OutlinePhase::default(),
+            max_total_stitches: 0,
+        }
+    }
+}
+
+/// An ordered run of stitches for one shape, pre-assembly.
+#[derive(Debug, Clone)]
+pub(crate) struct StitchBlock {
+    /// Thread color derived from the shape's style.
+    pub color: Color,
+    /// User-assigned thread color taking precedence over `color`.
+    pub color_override: Option<Color>,
+    pub stitches: Vec<Stitch>,
+    /// Hand-inserted machine commands, spliced in at their indexes during
+    /// assembly.
+    pub manual_commands: Vec<crate::stitch::ManualStitchCommand>,
+    /// Position in scene traversal order; ties are broken by this.
+    pub source_order: usize,
+    /// Stroke techniques (running, satin, chain) count as outlines for
+    /// phase separation; area techniques are fills.
+    pub is_outline: bool,
+}
+
+impl StitchBlock {
+    fn entry(&self) -> Point {
+        let s = &self.stitches[0];
+        Point::new(s.x, s.y)
+    }
+
+    /// The color actually stitched.
+    pub fn thread_color(&self) -> Color {
+        self.color_override.unwrap_or(self.color)
+    }
+}
+
+/// A non-fatal issue generation noticed and worked around, tied to the
+/// shape it came from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportWarning {
+    pub node_id: NodeId,
+    pub message: String,
+}
+
+/// A bundle of quality knobs for one generation pass. Interactive editing
+/// wants fast feedback; the final export wants every refinement on. The
+/// profile is a convenience layer — every setting it drives is reachable
+/// individually.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GenerationProfile {
+    /// Coarse curves, longer stitches, no underlay: fast preview quality.
+    Draft,
+    #[default]
+    Normal,
+    /// Fine curves and shorter stitches for the final run.
+    High,
+}
+
+impl GenerationProfile {
+    /// Curve flattening tolerance (mm) for this profile.
+    pub fn flatten_tolerance(self) -> f64 {
+        match self {
+            GenerationProfile::Draft => 0.5,
+            GenerationProfile::Normal => DEFAULT_FLATTEN_TOLERANCE,
+            GenerationProfile::High => 0.02,
+        }
+    }
+
+    /// Multiplier applied to the requested stitch length.
+    pub fn stitch_length_scale(self) -> f64 {
+        match self {
+            GenerationProfile::Draft => 1.5,
+            GenerationProfile::Normal => 1.0,
+            GenerationProfile::High => 0.8,
+        }
+    }
+
+    /// Whether underlay passes are worth generating at this quality.
+    pub fn include_underlay(self) -> bool {
+        !matches!(self, GenerationProfile::Draft)
+    }
+}
+
+/// Generate the stitch block for a single shape, in world space.
+#[allow(clippy::too_many_arguments)]
+fn generate_shape_block(
+    scene: &Scene,
+    node_id: NodeId,
+    source_order: usize,
+    stitch_length: f64,
+    profile: GenerationProfile,
+    budget_remaining: Option<usize>,
+    cancel: &CancelToken,
+    warnings: &mut Vec<ExportWarning>,
+) -> Result<Option<StitchBlock>, EngineError> {
+    let node = scene.node(node_id)?;
+    if node.export_excluded {
+        return Ok(None);
+    }
+    let NodeKind::Shape(shape) = &node.kind else {
+        return Ok(None);
+    };
+    let stitch_length = stitch_length * profile.stitch_length_scale();
+    let world = scene.world_transform(node_id)?;
+    let path = shape.data.to_path().transformed(&world);
+    let subpaths = path.flatten(profile.flatten_tolerance());
+    let density = shape.stitch.effective_density(world.scale_factor());
+
+    // Fills are where counts explode, so they get a cheap bounding-box
+    // estimate up front — erroring before the giant vector is allocated,
+    // not after.
+    if let (Some(remaining), StitchType::Tatami | StitchType::Satin) =
+        (budget_remaining, shape.stitch.stitch_type)
+    {
+        let mut bounds = BoundingBox::empty();
+        for subpath in &subpaths {
+            for p in subpath {
+                bounds.include(*p);
+            }
+        }
+        if !bounds.is_empty() && density > 0.0 && stitch_length > 0.0 {
+            let rows = bounds.height() / density;
+            let per_row = bounds.width() / stitch_length + 2.0;
+            if (rows * per_row) as usize > remaining.saturating_mul(2) {
+                return Err(stitch_budget_error(0, remaining));
+            }
+        }
+    }
+
+    let mut stitches: Vec<Stitch> = Vec::new();
+    let append = |stitches: &mut Vec<Stitch>, run: Vec<Stitch>| {
+        if run.is_empty() {
+            return;
+        }
+        if let Some(first) = run.first() {
+            if !stitches.is_empty() {
+                stitches.push(Stitch::jump(first.x, first.y));
+            }
+        }
+        stitches.extend(run);
+    };
+    match shape.stitch.stitch_type {
+        StitchType::Running => {
+            for subpath in &subpaths {
+                let run = match shape.stitch.dash {
+                    Some((dash_mm, gap_mm)) => crate::stitch::running::generate_dashed_run(
+                        subpath,
+                        dash_mm,
+                        gap_mm,
+                        stitch_length,
+                    ),
+                    None => generate_running_stitches(subpath, stitch_length),
+                };
+                append(&mut stitches, run);
+            }
+        }
+        StitchType::Tatami if fill_below_min_area(&shape.stitch, &subpaths) => {
+            // Tatami rows can't resolve shapes this small: narrow ones
+            // become a satin bar across the long axis, the rest a single
+            // outline run.
+            let mut bounds = BoundingBox::empty();
+            for subpath in &subpaths {
+                for p in subpath {
+                    bounds.include(*p);
+                }
+            }
+            if bounds.width().min(bounds.height()) <= SMALL_FILL_SATIN_MAX_WIDTH_MM {
+                let (centerline, half_width) = small_fill_satin_bar(&bounds);
+                let run =
+                    generate_satin_shape_stitches(&centerline, half_width, density);
+                append(&mut stitches, run);
+            } else {
+                for subpath in &subpaths {
+                    let run = generate_running_stitches(subpath, stitch_length);
+                    append(&mut stitches, run);
+                }
+            }
+        }
+        StitchType::Tatami => {
+            // Underlay passes go down first, sparse and raw-edged — they
+            // exist to stabilize, not to show.
+            let underlay_spacing = (density * UNDERLAY_SPACING_FACTOR).max(density);
+            let underlay = if profile.include_underlay() {
+                shape.stitch.fill_underlay
+            } else {
+                crate::stitch::fill::FillUnderlay::None
+            };
+            if underlay == crate::stitch::fill::FillUnderlay::EdgeRun {
+                for subpath in &subpaths {
+                    let run = generate_running_stitches(subpath, stitch_length);
+                    append(&mut stitches, run);
+                }
+            }
+            for pass_angle in underlay.pass_angles(shape.stitch.angle_degrees) {
+                let run = crate::stitch::fill::generate_tatami_fill_cancellable(
+                    &subpaths,
+                    pass_angle,
+                    underlay_spacing,
+                    stitch_length,
+                    crate::stitch::fill::FillEdgeStyle::Raw,
+                    cancel,
+                    &mut |_| {},
+                )?;
+                append(&mut stitches, run);
+            }
+            // True axis-aligned ellipses get the exact analytic scanline;
+            // everything else goes through the flattened polygon path.
+            if let Some(focus) = shape.stitch.fill_focus {
+                let run = crate::stitch::fill::generate_radial_fill(
+                    &subpaths,
+                    world.apply(focus),
+                    density,
+                    stitch_length,
+                );
+                append(&mut stitches, run);
+            } else if let (crate::shapes::ShapeData::Ellipse(e), true) =
+                (&shape.data, world.b.abs() < 1e-12 && world.c.abs() < 1e-12)
+            {
+                let run = crate::stitch::fill::generate_ellipse_tatami_fill(
+                    Point::new(world.tx, world.ty),
+                    e.rx * world.a.abs(),
+                    e.ry * world.d.abs(),
+                    shape.stitch.angle_degrees,
+                    density,
+                    stitch_length,
+                    shape.stitch.fill_edge_style,
+                );
+                append(&mut stitches, run);
+            } else if path.is_closed() {
+                let run = crate::stitch::fill::generate_tatami_fill_cancellable(
+                    &subpaths,
+                    shape.stitch.angle_degrees,
+                    density,
+                    stitch_length,
+                    shape.stitch.fill_edge_style,
+                    cancel,
+                    &mut |_| {},
+                )?;
+                append(&mut stitches, run);
+            } else {
+                // Fills need closed rings; close the gap (last→first) and
+                // say so rather than silently stitching nothing useful.
+                let closed: Vec<Vec<Point>> = subpaths
+                    .iter()
+                    .map(|subpath| {
+                        let mut ring = subpath.clone();
+                        if ring.first() != ring.last() {
+                            if let Some(&first) = ring.first() {
+                                ring.push(first);
+                            }
+                        }
+                        ring
+                    })
+                    .collect();
+                warnings.push(ExportWarning {
+                    node_id,
+                    message: "open path auto-closed for fill".to_string(),
+                });
+                let run = crate::stitch::fill::generate_tatami_fill_cancellable(
+                    &closed,
+                    shape.stitch.angle_degrees,
+                    density,
+                    stitch_length,
+                    shape.stitch.fill_edge_style,
+                    cancel,
+                    &mut |_| {},
+                )?;
+                append(&mut stitches, run);
+            }
+        }
+        StitchType::Bean => {
+            for subpath in &subpaths {
+                let run = crate::stitch::running::generate_bean_stitches(
+                    subpath,
+                    stitch_length,
+                    shape.stitch.bean_repeats,
+                );
+                append(&mut stitches, run);
+            }
+        }
+        StitchType::GradientFill => {
+            let run = crate::stitch::fill::generate_gradient_tatami_fill(
+                &subpaths,
+                shape.stitch.gradient_angle,
+                density,
+                shape.stitch.density_end_mm,
+                stitch_length,
+                shape.stitch.fill_edge_style,
+            );
+            append(&mut stitches, run);
+        }
+        StitchType::CrossStitch => {
+            let run = crate::stitch::fill::generate_cross_stitch_fill(
+                &subpaths,
+                shape.stitch.cell_size_mm,
+                shape.stitch.angle_degrees,
+            );
+            append(&mut stitches, run);
+        }
+        StitchType::Chain => {
+            for subpath in &subpaths {
+                let run = crate::stitch::chain::generate_chain_stitch(
+                    subpath,
+                    shape.stitch.chain_loop_mm,
+                    stitch_length,
+                );
+                append(&mut stitches, run);
+            }
+        }
+        StitchType::Satin => {
+            let band_width = shape.style.stroke_width * world.scale_factor();
+            let half_width =
+                band_width * 0.5 + shape.stitch.effective_pull_compensation(band_width);
+            for subpath in &subpaths {
+                // Closed outlines (primitives, closed paths) get true
+                // inner/outer rails from a ring offset — wrap-around
+                // normals, no seam artifacts. Open centerlines keep the
+                // repaired centerline-offset column.
+                let run = if subpath.len() > 3 && subpath.first() == subpath.last() {
+                    // `offset_ring` moves inward for a positive (shoelace)
+                    // winding and outward for a negative one; resolve the
+                    // sign so stroke alignment means true inside/outside.
+                    let signed_area: f64 = subpath
+                        .windows(2)
+                        .map(|w| w[0].x * w[1].y - w[1].x * w[0].y)
+                        .sum();
+                    let inward = half_width.copysign(signed_area);
+                    let (d1, d2) = match shape.stitch.stroke_align {
+                        crate::stitch::StrokeAlign::Center => (half_width, -half_width),
+                        crate::stitch::StrokeAlign::Inside => (0.0, 2.0 * inward),
+                        crate::stitch::StrokeAlign::Outside => (0.0, -2.0 * inward),
+                    };
+                    let rail1 = crate::path::offset_ring(subpath, d1);
+                    let rail2 = crate::path::offset_ring(subpath, d2);
+                    if shape.stitch.short_stitch_enabled {
+                        crate::stitch::satin::generate_satin_stitches_short(
+                            &rail1,
+                            &rail2,
+                            density,
+                            shape.stitch.short_stitch_angle_deg,
+                        )
+                    } else {
+                        crate::stitch::satin::generate_satin_stitches(
+                            &rail1,
+                            &rail2,
+                            density,
+                        )
+                    }
+                } else {
+                    crate::stitch::satin::generate_satin_shape_stitches_aligned(
+                        subpath,
+                        half_width,
+                        density,
+                        shape.stitch.stroke_align,
+                    )
+                };
+                // A bare jump between satin segments lets registration
+                // drift; travel there as a secured running connector
+                // instead.
+                if let (Some(last), Some(first)) = (stitches.last(), run.first()) {
+                    let connector = generate_running_stitches(
+                        &[Point::new(last.x, last.y), Point::new(first.x, first.y)],
+                        stitch_length,
+                    );
+                    stitches.extend(connector.into_iter().skip(1));
+                    stitches.extend(run);
+                } else {
+                    append(&mut stitches, run);
+                }
+            }
+        }
+        StitchType::EStitch => {
+            // Legs span the stroke band; the baseline runs on the outline
+            // itself and the legs reach onto the appliqué (inner) side.
+            let leg_length = shape.style.stroke_width * world.scale_factor();
+            for subpath in &subpaths {
+                let (_, inner) = crate::stitch::satin::build_satin_rails(subpath, leg_length);
+                let run = crate::stitch::satin::generate_e_stitch(
+                    subpath,
+                    &inner,
+                    density,
+                    leg_length,
+                );
+                append(&mut stitches, run);
+            }
+        }
+    }
+
+    crate::stitch::apply_jitter(&mut stitches, shape.stitch.jitter_mm, node_id);
+
+    if stitches.is_empty() {
+        return Ok(None);
+    }
+    if let Some(remaining) = budget_remaining {
+        if stitches.len() > remaining {
+            return Err(stitch_budget_error(stitches.len(), remaining));
+        }
+    }
+    Ok(Some(StitchBlock {
+        color: shape.style.thread_color(),
+        color_override: shape.stitch.color_override,
+        stitches,
+        manual_commands: shape.stitch.manual_commands.clone(),
+        source_order,
+        is_outline: !matches!(
+            shape.stitch.stitch_type,
+            StitchType::Tatami | StitchType::CrossStitch | StitchType::GradientFill
+        ),
+    }))
+}
+
+/// Underlay rows are spaced this many times wider than the top fill's.
+const UNDERLAY_SPACING_FACTOR: f64 = 4.0;
+
+/// Widest shape the small-fill fallback will cover with a satin bar;
+/// anything wider degrades to an outline run instead.
+const SMALL_FILL_SATIN_MAX_WIDTH_MM: f64 = 3.0;
+
+/// Whether a fill shape is below its `min_fill_area_mm2` threshold (bounding
+/// box area, world space). Always false when the threshold is disabled.
+fn fill_below_min_area(params: &crate::stitch::StitchParams, subpaths: &[Vec<Point>]) -> bool {
+    if params.min_fill_area_mm2 <= 0.0 {
+        return false;
+    }
+    let mut bounds = BoundingBox::empty();
+    for subpath in subpaths {
+        for p in subpath {
+            bounds.include(*p);
+        }
+    }
+    bounds.width() * bounds.height() < params.min_fill_area_mm2
+}
+
+/// A satin centerline along the long axis of a tiny fill's bounding box,
+/// with the half width covering the narrow axis.
+fn small_fill_satin_bar(bounds: &BoundingBox) -> (Vec<Point>, f64) {
+    let c = bounds.center();
+    if bounds.width() >= bounds.height() {
+        (
+            vec![Point::new(bounds.min_x, c.y), Point::new(bounds.max_x, c.y)],
+            bounds.height() * 0.5,
+        )
+    } else {
+        (
+            vec![Point::new(c.x, bounds.min_y), Point::new(c.x, bounds.max_y)],
+            bounds.width() * 0.5,
+        )
+    }
+}
+
+/// Collect stitch blocks for every visible shape, in scene traversal order.
+/// The graceful failure for [`RoutingOptions::max_total_stitches`],
+/// carrying how far generation got.
+fn stitch_budget_error(generated: usize, budget: usize) -> EngineError {
+    EngineError::InvalidInput(format!(
+        "stitch budget exceeded: {generated} stitches generated against a budget of {budget}"
+    ))
+}
+
+pub(crate) fn collect_blocks(
+    scene: &Scene,
+    stitch_length: f64,
+    profile: GenerationProfile,
+    max_total_stitches: usize,
+    cancel: &CancelToken,
+    warnings: &mut Vec<ExportWarning>,
+) -> Result<Vec<StitchBlock>, EngineError> {
+    let mut blocks = Vec::new();
+    let mut total = 0usize;
+    for (order, item) in scene.render_list().iter().enumerate() {
+        cancel.check()?;
+        let remaining = (max_total_stitches > 0).then(|| max_total_stitches - total);
+        if let Some(block) = generate_shape_block(
+            scene,
+            item.node_id,
+            order,
+            stitch_length,
+            profile,
+            remaining,
+            cancel,
+            warnings,
+        )? {
+            total += block.stitches.len();
+            blocks.push(block);
+        }
+    }
+    Ok(blocks)
+}
+
+/// Order blocks for stitching. With `preserve_color_order`, colors stay in
+/// first-appearance order and blocks keep their source order within a color.
+/// A `start_near` origin promotes the block with the closest entry (and its
+/// color) to the front.
+pub(crate) fn order_blocks(blocks: Vec<StitchBlock>, routing: &RoutingOptions) -> Vec<StitchBlock> {
+    if routing.outline_phase == OutlinePhase::AllFillsThenOutlines {
+        // Each phase orders independently; outlines always follow fills
+        // even when that costs extra color changes.
+        let (fills, outlines): (Vec<_>, Vec<_>) =
+            blocks.into_iter().partition(|b| !b.is_outline);
+        let mut out = order_blocks_single(fills, routing);
+        out.extend(order_blocks_single(outlines, routing));
+        return out;
+    }
+    order_blocks_single(blocks, routing)
+}
+
+fn order_blocks_single(mut blocks: Vec<StitchBlock>, routing: &RoutingOptions) -> Vec<StitchBlock> {
+    // `source_order` is unique per block, so it identifies the start block.
+    let start_order: Option<usize> = routing.start_near.and_then(|origin| {
+        blocks
+            .iter()
+            .min_by(|a, b| {
+                a.entry()
+                    .distance_to(origin)
+                    .total_cmp(&b.entry().distance_to(origin))
+            })
+            .map(|b| b.source_order)
+    });
+    let leads = |b: &StitchBlock| Some(b.source_order) == start_order;
+    if !routing.preserve_color_order {
+        blocks.sort_by_key(|b| (!leads(b), b.source_order));
+        return blocks;
+    }
+    let mut palette: Vec<Color> = Vec::new();
+    if let Some(start) = blocks.iter().find(|b| leads(b)) {
+        palette.push(start.thread_color());
+    }
+    for b in &blocks {
+        if !palette.contains(&b.thread_color()) {
+            palette.push(b.thread_color());
+        }
+    }
+    blocks.sort_by_key(|b| {
+        let color_rank = palette
+            .iter()
+            .position(|c| *c == b.thread_color())
+            .unwrap_or(0);
+        (color_rank, !leads(b), b.source_order)
+    });
+    blocks
+}
+
+/// Lock-stitch pattern used for tie-offs: short back-and-forth penetrations
+/// around the anchor so the thread cannot pull out.
+fn push_tie_off(stitches: &mut Vec<ExportStitch>, at: Point) {
+    const LOCK: f64 = 0.6;
+    for offset in [LOCK, -LOCK, LOCK * 0.5, 0.0] {
+        stitches.push(ExportStitch::new(
+            at.x + offset,
+            at.y,
+            ExportStitchType::Normal,
+        ));
+    }
+}
+
+/// Rewrite the program so every trim is locked: tie-off stitches before
+/// each `Trim` and tie-in stitches at the first `Normal` after it. Runs
+/// on the assembled program so manual mid-block trims are covered.
+fn secure_trims(stitches: Vec<ExportStitch>) -> Vec<ExportStitch> {
+    let mut out = Vec::with_capacity(stitches.len());
+    let mut pending_tie_in = false;
+    for s in stitches {
+        match s.kind {
+            ExportStitchType::Trim => {
+                push_tie_off(&mut out, Point::new(s.x, s.y));
+                out.push(s);
+                pending_tie_in = true;
+            }
+            ExportStitchType::Normal if pending_tie_in => {
+                push_tie_off(&mut out, Point::new(s.x, s.y));
+                out.push(s);
+                pending_tie_in = false;
+            }
+            _ => out.push(s),
+        }
+    }
+    out
+}
+
+/// Assemble ordered blocks into the flat stitch program.
+fn assemble(blocks: Vec<StitchBlock>, routing: &RoutingOptions, name: &str) -> ExportDesign {
+    let mut stitches: Vec<ExportStitch> = Vec::new();
+    let mut colors: Vec<Color> = Vec::new();
+    let mut current_color: Option<Color> = None;
+    let mut position: Option<Point> = None;
+
+    for block in &blocks {
+        let entry = block.entry();
+
+        if current_color != Some(block.thread_color()) {
+            if let Some(pos) = position {
+                // Color-change branch: what travels between colors is
+                // governed by the routing policy.
+                let travel = pos.distance_to(entry);
+                match routing.color_change_travel {
+                    ColorChangeTravel::AlwaysTrim => {
+                        stitches.push(ExportStitch::new(pos.x, pos.y, ExportStitchType::Trim));
+                    }
+                    ColorChangeTravel::TrimIfFar(threshold) => {
+                        if travel > threshold {
+                            stitches.push(ExportStitch::new(pos.x, pos.y, ExportStitchType::Trim));
+                        }
+                    }
+                    ColorChangeTravel::TieOff => {
+                        push_tie_off(&mut stitches, pos);
+                    }
+                }
+                stitches.push(ExportStitch::new(pos.x, pos.y, ExportStitchType::ColorChange));
+            }
+            colors.push(block.thread_color());
+            current_color = Some(block.thread_color());
+            stitches.push(ExportStitch::new(entry.x, entry.y, ExportStitchType::Jump));
+        } else if let Some(pos) = position {
+            // Same-color travel between blocks.
+            if pos.distance_to(entry) > routing.trim_threshold_mm {
+                stitches.push(ExportStitch::new(pos.x, pos.y, ExportStitchType::Trim));
+            }
+            stitches.push(ExportStitch::new(entry.x, entry.y, ExportStitchType::Jump));
+        } else {
+            stitches.push(ExportStitch::new(entry.x, entry.y, ExportStitchType::Jump));
+        }
+
+        let mut anchor = entry;
+        for (i, s) in block.stitches.iter().enumerate() {
+            // Manual commands anchor at the position just before their
+            // stitch index. A manual color change keeps the same thread (it
+            // pauses the machine for a swap), so the palette entry repeats.
+            for cmd in block.manual_commands.iter().filter(|c| c.at_index == i) {
+                stitches.push(ExportStitch::new(anchor.x, anchor.y, cmd.kind.into()));
+                if cmd.kind == crate::stitch::ManualCommandKind::ColorChange {
+                    colors.push(block.thread_color());
+                }
+            }
+            let kind = if s.is_jump {
+                ExportStitchType::Jump
+            } else {
+                ExportStitchType::Normal
+            };
+            stitches.push(ExportStitch::new(s.x, s.y, kind));
+            anchor = Point::new(s.x, s.y);
+        }
+        for cmd in block
+            .manual_commands
+            .iter()
+            .filter(|c| c.at_index >= block.stitches.len())
+        {
+            stitches.push(ExportStitch::new(anchor.x, anchor.y, cmd.kind.into()));
+            if cmd.kind == crate::stitch::ManualCommandKind::ColorChange {
+                colors.push(block.thread_color());
+            }
+        }
+        position = Some(anchor);
+    }
+
+    if let Some(pos) = position {
+        stitches.push(ExportStitch::new(pos.x, pos.y, ExportStitchType::End));
+    }
+    if routing.tie_at_trims {
+        stitches = secure_trims(stitches);
+    }
+
+    let mut design = ExportDesign {
+        name: name.to_string(),
+        stitches,
+        colors,
+        coordinate_system: CoordinateSystem::YDown,
+        quantization: routing.quantization,
+    };
+    dedupe_color_changes(&mut design);
+    design
+}
+
+/// A shape's world-space polylines: the exact geometry fed to stitch
+/// generation, exposed so the UI can visualize what will be stitched
+/// before generating.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FlattenedShape {
+    pub node_id: NodeId,
+    /// One polyline per subpath; closed subpaths repeat their first point.
+    pub subpaths: Vec<Vec<Point>>,
+    /// The thread color the shape will stitch with.
+    pub color: Color,
+}
+
+/// Flatten every visible shape to world-space polylines, in scene
+/// traversal order.
+pub fn scene_to_flattened_paths(scene: &Scene, tolerance: f64) -> Vec<FlattenedShape> {
+    let mut out = Vec::new();
+    for item in scene.render_list() {
+        let Ok(node) = scene.node(item.node_id) else {
+            continue;
+        };
+        let NodeKind::Shape(shape) = &node.kind else {
+            continue;
+        };
+        let Ok(world) = scene.world_transform(item.node_id) else {
+            continue;
+        };
+        out.push(FlattenedShape {
+            node_id: item.node_id,
+            subpaths: shape.data.to_path().transformed(&world).flatten(tolerance),
+            color: shape
+                .stitch
+                .color_override
+                .unwrap_or_else(|| shape.style.thread_color()),
+        });
+    }
+    out
+}
+
+/// One shape's outline and live stitch preview, bundled so the canvas can
+/// draw both from a single call.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShapePreview {
+    pub node_id: NodeId,
+    /// World-space outline polylines, one per subpath.
+    pub subpaths: Vec<Vec<Point>>,
+    /// The thread color the shape will stitch with.
+    pub color: Color,
+    /// The shape's generated stitches in world space; `is_jump` marks
+    /// needle-up travel so the preview can break the polyline there.
+    pub stitches: Vec<Stitch>,
+}
+
+/// Outline plus generated stitches for every visible shape, in render
+/// order. This is [`scene_to_flattened_paths`] and the per-shape half of
+/// export in one payload — the UI draws geometry and stitch preview
+/// together without a second round trip.
+pub fn scene_to_preview(
+    scene: &Scene,
+    stitch_length: f64,
+    tolerance: f64,
+) -> Result<Vec<ShapePreview>, EngineError> {
+    let cancel = CancelToken::new();
+    let mut warnings = Vec::new();
+    let mut out = Vec::new();
+    for (order, item) in scene.render_list().iter().enumerate() {
+        let Some(block) = generate_shape_block(
+            scene,
+            item.node_id,
+            order,
+            stitch_length,
+            GenerationProfile::Normal,
+            None,
+            &cancel,
+            &mut warnings,
+        )?
+        else {
+            continue;
+        };
+        let node = scene.node(item.node_id)?;
+        let NodeKind::Shape(shape) = &node.kind else {
+            continue;
+        };
+        let world = scene.world_transform(item.node_id)?;
+        out.push(ShapePreview {
+            node_id: item.node_id,
+            subpaths: shape.data.to_path().transformed(&world).flatten(tolerance),
+            color: block.thread_color(),
+            stitches: block.stitches,
+        });
+    }
+    Ok(out)
+}
+
+/// Aggregate stitch statistics for a design.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QualityMetrics {
+    /// Total records in the stitch program (jumps, trims, etc. included).
+    pub stitch_count: usize,
+    pub normal_count: usize,
+    pub jump_count: usize,
+    pub trim_count: usize,
+    pub color_change_count: usize,
+    /// Thread consumed by normal stitches (mm, flat — no loft allowance).
+    pub thread_length_mm: f64,
+    /// Longest single normal stitch (mm).
+    pub max_stitch_mm: f64,
+    /// Normal stitches shorter than 0.3 mm, which risk thread breaks.
+    pub tiny_stitch_count: usize,
+}
+
+/// Compute aggregate statistics over a design's stitch program.
+pub fn quality_metrics(design: &ExportDesign) -> QualityMetrics {
+    let mut metrics = QualityMetrics {
+        stitch_count: design.stitches.len(),
+        normal_count: 0,
+        jump_count: 0,
+        trim_count: 0,
+        color_change_count: 0,
+        thread_length_mm: 0.0,
+        max_stitch_mm: 0.0,
+        tiny_stitch_count: 0,
+    };
+    let mut position: Option<Point> = None;
+    for s in &design.stitches {
+        match s.kind {
+            ExportStitchType::Normal => {
+                metrics.normal_count += 1;
+                if let Some(pos) = position {
+                    let len = pos.distance_to(Point::new(s.x, s.y));
+                    metrics.thread_length_mm += len;
+                    metrics.max_stitch_mm = metrics.max_stitch_mm.max(len);
+                    if len < 0.3 {
+                        metrics.tiny_stitch_count += 1;
+                    }
+                }
+            }
+            ExportStitchType::Jump => metrics.jump_count += 1,
+            ExportStitchType::Trim => metrics.trim_count += 1,
+            ExportStitchType::ColorChange => metrics.color_change_count += 1,
+            ExportStitchType::Stop | ExportStitchType::End => {}
+        }
+        position = Some(Point::new(s.x, s.y));
+    }
+    metrics
+}
+
+/// Per-color-block stitch counts, in stitching order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColorBlockStats {
+    pub color: Color,
+    pub normal_count: usize,
+}
+
+/// Machine-time estimate at a given speed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProductionEstimate {
+    pub stitches_per_minute: f64,
+    /// Running time plus a 1.5-minute handling allowance per color change.
+    pub runtime_minutes: f64,
+}
+
+/// Whether the design fits a hoop, with the dimensions that decided it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HoopFit {
+    pub hoop: String,
+    pub hoop_width_mm: f64,
+    pub hoop_height_mm: f64,
+    pub design_width_mm: f64,
+    pub design_height_mm: f64,
+    pub fits: bool,
+}
+
+/// Stitchable area of a common hoop by its catalog name, in mm.
+pub fn hoop_size(name: &str) -> Option<(f64, f64)> {
+    match name {
+        "100x100" => Some((100.0, 100.0)),
+        "130x180" => Some((130.0, 180.0)),
+        "160x260" => Some((160.0, 260.0)),
+        "200x200" => Some((200.0, 200.0)),
+        "200x360" => Some((200.0, 360.0)),
+        _ => None,
+    }
+}
+
+/// Estimated thread used by one color, in physical mm.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ThreadUse {
+    pub color: Color,
+    /// Top (needle) thread, including the take-up for loft over the fabric.
+    pub top_thread_mm: f64,
+    /// Bobbin thread, estimated as a ratio of the top thread.
+    pub bobbin_mm: f64,
+}
+
+/// Estimate thread consumption per distinct color, for cone/bobbin billing.
+/// The stitched path length is multiplied by `take_up_factor` (thread is
+/// longer than the flat path — typical values run 1.2..1.5 depending on
+/// fabric loft) and bobbin use is `bobbin_ratio` of the top thread. Jumps
+/// and trims consume nothing; repeated colors aggregate onto one entry in
+/// first-appearance order.
+pub fn thread_consumption(
+    design: &ExportDesign,
+    take_up_factor: f64,
+    bobbin_ratio: f64,
+) -> Vec<ThreadUse> {
+    let mut per_run = vec![0.0f64; design.colors.len().max(1)];
+    let mut run = 0usize;
+    let mut prev: Option<Point> = None;
+    for s in &design.stitches {
+        match s.kind {
+            ExportStitchType::Normal => {
+                if let Some(p) = prev {
+                    let slot = run.min(per_run.len() - 1);
+                    per_run[slot] += p.distance_to(Point::new(s.x, s.y));
+                }
+                prev = Some(Point::new(s.x, s.y));
+            }
+            ExportStitchType::ColorChange => {
+                run += 1;
+                prev = None;
+            }
+            // The needle is up: no thread laid down, and the next normal
+            // stitch starts a fresh segment.
+            _ => prev = None,
+        }
+    }
+    let mut out: Vec<ThreadUse> = Vec::new();
+    for (i, &length) in per_run.iter().enumerate() {
+        let color = design.colors.get(i).copied().unwrap_or_default();
+        let top = length * take_up_factor;
+        match out.iter_mut().find(|u| u.color == color) {
+            Some(existing) => {
+                existing.top_thread_mm += top;
+                existing.bobbin_mm += top * bobbin_ratio;
+            }
+            None => out.push(ThreadUse {
+                color,
+                top_thread_mm: top,
+                bobbin_mm: top * bobbin_ratio,
+            }),
+        }
+    }
+    out
+}
+
+/// Where in the hoop a positioned design sits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HoopAnchor {
+    #[default]
+    Center,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+/// Translate the design so its stitched bbox sits in the given region of a
+/// `hoop_w` × `hoop_h` mm hoop centered on the origin, `margin_mm` in from
+/// the hoop edges. Design space is screen-oriented (Y grows downward), so
+/// "top" anchors align to negative Y. Errs when the design plus margins
+/// cannot fit the hoop.
+pub fn position_in_hoop(
+    design: &mut ExportDesign,
+    hoop_w: f64,
+    hoop_h: f64,
+    anchor: HoopAnchor,
+    margin_mm: f64,
+) -> Result<(), EngineError> {
+    if margin_mm < 0.0 {
+        return Err(EngineError::InvalidInput(
+            "hoop margin cannot be negative".to_string(),
+        ));
+    }
+    let bbox = design.extents();
+    if bbox.is_empty() {
+        return Ok(());
+    }
+    let (w, h) = (bbox.max_x - bbox.min_x, bbox.max_y - bbox.min_y);
+    if w > hoop_w - 2.0 * margin_mm || h > hoop_h - 2.0 * margin_mm {
+        return Err(EngineError::InvalidInput(format!(
+            "design {w:.1}x{h:.1} mm does not fit a {hoop_w:.0}x{hoop_h:.0} hoop with {margin_mm} mm margin"
+        )));
+    }
+    let (left, top) = (-hoop_w * 0.5 + margin_mm, -hoop_h * 0.5 + margin_mm);
+    let (right, bottom) = (hoop_w * 0.5 - margin_mm, hoop_h * 0.5 - margin_mm);
+    let (target_x, target_y) = match anchor {
+        HoopAnchor::Center => (-w * 0.5, -h * 0.5),
+        HoopAnchor::TopLeft => (left, top),
+        HoopAnchor::TopRight => (right - w, top),
+        HoopAnchor::BottomLeft => (left, bottom - h),
+        HoopAnchor::BottomRight => (right - w, bottom - h),
+    };
+    let (dx, dy) = (target_x - bbox.min_x, target_y - bbox.min_y);
+    for s in design.stitches.iter_mut() {
+        s.x += dx;
+        s.y += dy;
+    }
+    Ok(())
+}
+
+/// A production concern found while inspecting the design.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LintFinding {
+    pub code: String,
+    pub message: String,
+}
+
+/// The job-ticket bundle: everything the UI's info panel shows, in one
+/// deterministic serializable struct.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DesignReport {
+    pub name: String,
+    pub quality: QualityMetrics,
+    pub color_blocks: Vec<ColorBlockStats>,
+    pub production: ProductionEstimate,
+    pub hoop: HoopFit,
+    pub lints: Vec<LintFinding>,
+}
+
+/// Longest jump considered routine; anything farther gets a lint.
+const LONG_JUMP_LINT_MM: f64 = 30.0;
+
+/// Export the scene and assemble the full report for a hoop and machine
+/// speed (`spm`, stitches per minute).
+pub fn design_report(
+    scene: &Scene,
+    stitch_length: f64,
+    routing: &RoutingOptions,
+    hoop_name: &str,
+    spm: f64,
+) -> Result<DesignReport, EngineError> {
+    let (hoop_width, hoop_height) =
+        hoop_size(hoop_name).ok_or_else(|| format!("unknown hoop {hoop_name:?}"))?;
+    if spm <= 0.0 {
+        return Err(EngineError::InvalidInput(
+            "stitches per minute must be positive".to_string(),
+        ));
+    }
+    let design = scene_to_export_design_with_routing(scene, stitch_length, routing)?;
+    let quality = quality_metrics(&design);
+
+    let mut color_iter = design.colors.iter().copied();
+    let mut color_blocks = vec![ColorBlockStats {
+        color: color_iter.next().unwrap_or_default(),
+        normal_count: 0,
+    }];
+    for s in &design.stitches {
+        match s.kind {
+            ExportStitchType::ColorChange => color_blocks.push(ColorBlockStats {
+                color: color_iter.next().unwrap_or_default(),
+                normal_count: 0,
+            }),
+            ExportStitchType::Normal => {
+                color_blocks.last_mut().expect("non-empty").normal_count += 1;
+            }
+            _ => {}
+        }
+    }
+
+    let production = ProductionEstimate {
+        stitches_per_minute: spm,
+        runtime_minutes: quality.normal_count as f64 / spm
+            + quality.color_change_count as f64 * 1.5,
+    };
+
+    let bounds = design.extents();
+    let hoop = HoopFit {
+        hoop: hoop_name.to_string(),
+        hoop_width_mm: hoop_width,
+        hoop_height_mm: hoop_height,
+        design_width_mm: bounds.width(),
+        design_height_mm: bounds.height(),
+        fits: bounds.width() <= hoop_width && bounds.height() <= hoop_height,
+    };
+
+    let mut lints = Vec::new();
+    if !hoop.fits {
+        lints.push(LintFinding {
+            code: "hoop_overflow".to_string(),
+            message: format!(
+                "design is {:.1}×{:.1} mm but the {hoop_name} hoop stitches {hoop_width:.0}×{hoop_height:.0} mm",
+                bounds.width(),
+                bounds.height()
+            ),
+        });
+    }
+    if quality.tiny_stitch_count > 0 {
+        lints.push(LintFinding {
+            code: "tiny_stitches".to_string(),
+            message: format!(
+                "{} stitches are shorter than 0.3 mm and may break thread",
+                quality.tiny_stitch_count
+            ),
+        });
+    }
+    let mut position: Option<Point> = None;
+    for s in &design.stitches {
+        if s.kind == ExportStitchType::Jump {
+            if let Some(pos) = position {
+                let travel = pos.distance_to(Point::new(s.x, s.y));
+                if travel > LONG_JUMP_LINT_MM {
+                    lints.push(LintFinding {
+                        code: "long_jump".to_string(),
+                        message: format!("{travel:.1} mm jump near ({:.1}, {:.1})", s.x, s.y),
+                    });
+                }
+            }
+        }
+        position = Some(Point::new(s.x, s.y));
+    }
+
+    Ok(DesignReport {
+        name: design.name,
+        quality,
+        color_blocks,
+        production,
+        hoop,
+        lints,
+    })
+}
+
+/// Grid of needle-penetration counts over the design extents, used for
+/// density heatmaps and safety checks.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DensityGrid {
+    pub min_x: f64,
+    pub min_y: f64,
+    pub cell_mm: f64,
+    pub cols: usize,
+    pub rows: usize,
+    /// Row-major penetration counts (`counts[row * cols + col]`).
+    pub counts: Vec<u32>,
+}
+
+impl DensityGrid {
+    pub fn count(&self, col: usize, row: usize) -> u32 {
+        self.counts[row * self.cols + col]
+    }
+}
+
+/// Bucket the design's normal penetrations into `cell_mm` square cells.
+pub fn density_grid(design: &ExportDesign, cell_mm: f64) -> DensityGrid {
+    let bounds = design.extents();
+    let (min_x, min_y) = (bounds.min_x, bounds.min_y);
+    let cols = ((bounds.width() / cell_mm).ceil() as usize).max(1);
+    let rows = ((bounds.height() / cell_mm).ceil() as usize).max(1);
+    let mut counts = vec![0u32; cols * rows];
+    for s in &design.stitches {
+        if s.kind != ExportStitchType::Normal {
+            continue;
+        }
+        let col = (((s.x - min_x) / cell_mm) as usize).min(cols - 1);
+        let row = (((s.y - min_y) / cell_mm) as usize).min(rows - 1);
+        counts[row * cols + col] += 1;
+    }
+    DensityGrid {
+        min_x,
+        min_y,
+        cell_mm,
+        cols,
+        rows,
+        counts,
+    }
+}
+
+/// Cell size used for density safety checks.
+const DENSITY_WARNING_CELL_MM: f64 = 1.0;
+
+/// A grid cell whose penetration density exceeds the safe limit.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DensityWarning {
+    pub cell_col: usize,
+    pub cell_row: usize,
+    /// Cell center in design mm.
+    pub x_mm: f64,
+    pub y_mm: f64,
+    pub stitches_per_mm2: f64,
+    pub limit: f64,
+}
+
+/// Safe penetration density (stitches/mm²) for a technique on a fabric.
+/// Satin tolerates more because penetrations concentrate on two rails;
+/// fragile substrates scale the limit down.
+pub fn max_safe_density(stitch_type: StitchType, fabric: Option<crate::fabric::Fabric>) -> f64 {
+    let base = match stitch_type {
+        StitchType::Satin => 10.0,
+        // Chain re-penetrates each link's anchor by design.
+        StitchType::Chain => 8.0,
+        StitchType::Tatami | StitchType::GradientFill => 6.0,
+        // Bean re-enters the same holes, so extra passes don't open new
+        // penetrations the way a denser fill would.
+        StitchType::Bean => 6.0,
+        // Crosses concentrate four penetrations per cell corner shared by
+        // neighbors.
+        StitchType::CrossStitch => 6.0,
+        // Each leg returns through its base point, like chain anchors.
+        StitchType::EStitch => 8.0,
+        StitchType::Running => 4.0,
+    };
+    let factor = match fabric {
+        None | Some(crate::fabric::Fabric::Stable) => 1.0,
+        Some(crate::fabric::Fabric::Terry) => 0.9,
+        Some(crate::fabric::Fabric::Knit) => 0.8,
+        Some(crate::fabric::Fabric::Stretchy) => 0.7,
+        Some(crate::fabric::Fabric::Leather) => 0.6,
+    };
+    base * factor
+}
+
+/// Flag grid cells stitched denser than is safe for the technique/fabric
+/// combination.
+pub fn density_warnings(
+    design: &ExportDesign,
+    stitch_type: StitchType,
+    fabric: Option<crate::fabric::Fabric>,
+) -> Vec<DensityWarning> {
+    let limit = max_safe_density(stitch_type, fabric);
+    let grid = density_grid(design, DENSITY_WARNING_CELL_MM);
+    let cell_area = grid.cell_mm * grid.cell_mm;
+    let mut warnings = Vec::new();
+    for row in 0..grid.rows {
+        for col in 0..grid.cols {
+            let density = f64::from(grid.count(col, row)) / cell_area;
+            if density > limit {
+                warnings.push(DensityWarning {
+                    cell_col: col,
+                    cell_row: row,
+                    x_mm: grid.min_x + (col as f64 + 0.5) * grid.cell_mm,
+                    y_mm: grid.min_y + (row as f64 + 0.5) * grid.cell_mm,
+                    stitches_per_mm2: density,
+                    limit,
+                });
+            }
+        }
+    }
+    warnings
+}
+
+/// Export the scene and check it against the most restrictive stitch type
+/// among its visible shapes (so mixed designs get the conservative limit).
+pub fn scene_density_warnings(
+    scene: &Scene,
+    stitch_length: f64,
+    fabric: Option<crate::fabric::Fabric>,
+) -> Result<Vec<DensityWarning>, EngineError> {
+    let design = scene_to_export_design(scene, stitch_length)?;
+    let stitch_type = scene
+        .render_list()
+        .iter()
+        .filter_map(|item| match &scene.node(item.node_id).ok()?.kind {
+            NodeKind::Shape(s) => Some(s.stitch.stitch_type),
+            _ => None,
+        })
+        .min_by(|a, b| max_safe_density(*a, fabric).total_cmp(&max_safe_density(*b, fabric)))
+        .unwrap_or_default();
+    Ok(density_warnings(&design, stitch_type, fabric))
+}
+
+/// Split an assembled design into one design per color block for multi-file
+/// delivery. Each piece keeps its block's stitches (trims and jumps
+/// included), carries a single color, is recentered on the origin, and ends
+/// with its own `End` record.
+pub fn explode_by_color(design: &ExportDesign) -> Vec<ExportDesign> {
+    let mut color_iter = design.colors.iter().copied();
+    let mut current = color_iter.next().unwrap_or_default();
+    let mut runs: Vec<(Color, Vec<ExportStitch>)> = vec![(current, Vec::new())];
+    for s in &design.stitches {
+        match s.kind {
+            ExportStitchType::ColorChange => {
+                current = color_iter.next().unwrap_or(current);
+                runs.push((current, Vec::new()));
+            }
+            // Each piece gets its own terminator.
+            ExportStitchType::End => {}
+            _ => runs.last_mut().expect("runs is non-empty").1.push(*s),
+        }
+    }
+    runs.retain(|(_, r)| r.iter().any(|s| s.kind == ExportStitchType::Normal));
+
+    runs.into_iter()
+        .enumerate()
+        .map(|(i, (color, mut stitches))| {
+            let last = *stitches.last().expect("run has stitches");
+            stitches.push(ExportStitch::new(last.x, last.y, ExportStitchType::End));
+            let mut piece = ExportDesign {
+                name: format!("{}-{}", design.name, i + 1),
+                stitches,
+                colors: vec![color],
+                coordinate_system: design.coordinate_system,
+                quantization: design.quantization,
+            };
+            let center = piece.extents().center();
+            for s in &mut piece.stitches {
+                s.x -= center.x;
+                s.y -= center.y;
+            }
+            piece
+        })
+        .collect()
+}
+
+/// Drop color-change records that do not actually change the active thread
+/// — a by-product of reordering and palette merging — and the matching
+/// palette entries. The jump/travel around a dropped change stays; only the
+/// pointless machine stop goes.
+pub fn dedupe_color_changes(design: &mut ExportDesign) {
+    let mut palette = design.colors.iter().copied();
+    let Some(mut current) = palette.next() else {
+        return;
+    };
+    let mut new_colors = vec![current];
+    let mut stitches = Vec::with_capacity(design.stitches.len());
+    for s in design.stitches.drain(..) {
+        if s.kind == ExportStitchType::ColorChange {
+            let next = palette.next().unwrap_or(current);
+            if next == current {
+                continue;
+            }
+            current = next;
+            new_colors.push(next);
+        }
+        stitches.push(s);
+    }
+    design.stitches = stitches;
+    design.colors = new_colors;
+}
+
+/// Insert a basting rectangle at the very start of a design: running
+/// stitches `inset_mm` in from the edge of a `hoop_w` × `hoop_h` mm hoop
+/// centered on the design's extents, followed by a trim and a color change.
+/// The basting run gets its own first palette entry in a color distinct
+/// from the design's starting thread, so the operator can load a throwaway
+/// thread and pull the whole run out after stitching.
+pub fn prepend_basting(
+    design: &mut ExportDesign,
+    hoop_w: f64,
+    hoop_h: f64,
+    inset_mm: f64,
+    stitch_length: f64,
+) -> Result<(), EngineError> {
+    if stitch_length <= 0.0 {
+        return Err(EngineError::InvalidInput(
+            "stitch_length must be positive".to_string(),
+        ));
+    }
+    let (half_w, half_h) = (hoop_w * 0.5 - inset_mm, hoop_h * 0.5 - inset_mm);
+    if half_w <= 0.0 || half_h <= 0.0 {
+        return Err(EngineError::InvalidInput(format!(
+            "inset {inset_mm} mm leaves no basting rectangle in a {hoop_w}x{hoop_h} hoop"
+        )));
+    }
+    let center = design.extents().center();
+    let ring = [
+        Point::new(center.x - half_w, center.y - half_h),
+        Point::new(center.x + half_w, center.y - half_h),
+        Point::new(center.x + half_w, center.y + half_h),
+        Point::new(center.x - half_w, center.y + half_h),
+        Point::new(center.x - half_w, center.y - half_h),
+    ];
+    let run = crate::stitch::running::generate_running_stitches(&ring, stitch_length);
+    let last = *run.last().expect("basting ring is non-degenerate");
+
+    let mut prefix: Vec<ExportStitch> = run
+        .iter()
+        .map(|s| ExportStitch::new(s.x, s.y, ExportStitchType::Normal))
+        .collect();
+    prefix.push(ExportStitch::new(last.x, last.y, ExportStitchType::Trim));
+    prefix.push(ExportStitch::new(last.x, last.y, ExportStitchType::ColorChange));
+    design.stitches.splice(0..0, prefix);
+
+    // Any thread visibly unlike the real first color will do for basting.
+    let baste = if design.colors.first() == Some(&Color::BLACK) {
+        Color::rgb(0xff, 0xff, 0xff)
+    } else {
+        Color::BLACK
+    };
+    design.colors.insert(0, baste);
+    Ok(())
+}
+
+/// CIE76 color difference between two thread colors (alpha ignored; threads
+/// are opaque). Good enough for merge decisions — a ΔE under ~2 is barely
+/// distinguishable, under ~10 reads as "the same thread".
+fn color_delta_e(a: Color, b: Color) -> f64 {
+    let la = srgb_to_lab(a);
+    let lb = srgb_to_lab(b);
+    ((la.0 - lb.0).powi(2) + (la.1 - lb.1).powi(2) + (la.2 - lb.2).powi(2)).sqrt()
+}
+
+/// sRGB (8-bit) to CIELAB under D65.
+fn srgb_to_lab(c: Color) -> (f64, f64, f64) {
+    fn linearize(v: u8) -> f64 {
+        let v = v as f64 / 255.0;
+        if v <= 0.04045 {
+            v / 12.92
+        } else {
+            ((v + 0.055) / 1.055).powf(2.4)
+        }
+    }
+    let (r, g, b) = (linearize(c.r), linearize(c.g), linearize(c.b));
+    let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.95047;
+    let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.08883;
+    fn f(t: f64) -> f64 {
+        if t > 0.008856 {
+            t.cbrt()
+        } else {
+            7.787 * t + 16.0 / 116.0
+        }
+    }
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+    (116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz))
+}
+
+/// One spool the user actually owns.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ThreadEntry {
+    pub name: String,
+    pub color: Color,
+}
+
+/// A design color the inventory could not cover: kept as-is in the design,
+/// reported with its nearest spool so the user knows what to buy.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UnfulfilledColor {
+    pub requested: Color,
+    /// Closest spool name, or `None` for an empty inventory.
+    pub nearest: Option<String>,
+    pub delta_e: f64,
+}
+
+/// Any spool further than this ΔE from a requested color counts as missing
+/// rather than a substitute — beyond it the swap is plainly visible.
+pub const INVENTORY_MATCH_THRESHOLD_DELTA_E: f64 = 20.0;
+
+/// Remap each design color to the nearest spool in `inventory` (ΔE, like
+/// [`reduce_colors`]). Colors with no spool within
+/// [`INVENTORY_MATCH_THRESHOLD_DELTA_E`] are left untouched and returned as
+/// unfulfilled requirements. Changes that become no-ops after remapping are
+/// dropped.
+pub fn assign_from_inventory(
+    design: &mut ExportDesign,
+    inventory: &[ThreadEntry],
+) -> Vec<UnfulfilledColor> {
+    let mut unfulfilled: Vec<UnfulfilledColor> = Vec::new();
+    for color in design.colors.iter_mut() {
+        let nearest = inventory
+            .iter()
+            .map(|e| (e, color_delta_e(*color, e.color)))
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        match nearest {
+            Some((entry, d)) if d <= INVENTORY_MATCH_THRESHOLD_DELTA_E => {
+                *color = entry.color;
+            }
+            _ => {
+                if !unfulfilled.iter().any(|u| u.requested == *color) {
+                    unfulfilled.push(UnfulfilledColor {
+                        requested: *color,
+                        nearest: nearest.map(|(e, _)| e.name.clone()),
+                        delta_e: nearest.map_or(f64::INFINITY, |(_, d)| d),
+                    });
+                }
+            }
+        }
+    }
+    dedupe_color_changes(design);
+    unfulfilled
+}
+
+/// Merge near-identical thread colors in an assembled design. Colors within
+/// `merge_threshold_delta_e` of an earlier color collapse onto it, and the
+/// count is forced down to `max_colors` by merging the closest remaining
+/// pair. Color runs are remapped to their cluster representative and color
+/// changes between now-identical runs are dropped (the jump stays — the
+/// machine still travels).
+pub fn reduce_colors(design: &mut ExportDesign, max_colors: usize, merge_threshold_delta_e: f64) {
+    if design.colors.len() <= 1 || max_colors == 0 {
+        return;
+    }
+    // Greedy clustering in first-use order: the earliest color wins as the
+    // representative, which keeps the starting thread stable.
+    let mut reps: Vec<Color> = Vec::new();
+    let mut assign: Vec<usize> = Vec::with_capacity(design.colors.len());
+    for &c in &design.colors {
+        let nearest = reps
+            .iter()
+            .enumerate()
+            .map(|(i, r)| (i, color_delta_e(c, *r)))
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        match nearest {
+            Some((i, d)) if d <= merge_threshold_delta_e => assign.push(i),
+            _ => {
+                reps.push(c);
+                assign.push(reps.len() - 1);
+            }
+        }
+    }
+    // Enforce the hard cap by merging the closest pair until within budget.
+    while reps.len() > max_colors {
+        let mut best = (0usize, 1usize, f64::INFINITY);
+        for i in 0..reps.len() {
+            for j in i + 1..reps.len() {
+                let d = color_delta_e(reps[i], reps[j]);
+                if d < best.2 {
+                    best = (i, j, d);
+                }
+            }
+        }
+        let (keep, drop, _) = best;
+        for a in assign.iter_mut() {
+            if *a == drop {
+                *a = keep;
+            } else if *a > drop {
+                *a -= 1;
+            }
+        }
+        reps.remove(drop);
+    }
+    // Rewrite the program: advance through runs at each color change and
+    // drop changes whose two sides merged into the same representative.
+    let mut new_colors = vec![reps[assign[0]]];
+    let mut run = 0usize;
+    let mut stitches = Vec::with_capacity(design.stitches.len());
+    for s in design.stitches.drain(..) {
+        if s.kind == ExportStitchType::ColorChange {
+            run += 1;
+            let rep = reps[assign[run.min(assign.len() - 1)]];
+            if rep == *new_colors.last().unwrap() {
+                continue;
+            }
+            new_colors.push(rep);
+        }
+        stitches.push(s);
+    }
+    design.stitches = stitches;
+    design.colors = new_colors;
+}
+
+/// Export the scene with explicit routing options and a cancel token polled
+/// throughout generation.
+pub fn scene_to_export_design_cancellable(
+    scene: &Scene,
+    stitch_length: f64,
+    routing: &RoutingOptions,
+    cancel: &CancelToken,
+) -> Result<ExportDesign, EngineError> {
+    let mut warnings = Vec::new();
+    scene_export_cancellable_with_warnings(scene, stitch_length, routing, cancel, &mut warnings)
+}
+
+/// Export the scene and also report the non-fatal issues generation worked
+/// around (auto-closed fills and the like).
+pub fn scene_to_export_design_with_warnings(
+    scene: &Scene,
+    stitch_length: f64,
+    routing: &RoutingOptions,
+) -> Result<(ExportDesign, Vec<ExportWarning>), EngineError> {
+    let mut warnings = Vec::new();
+    let design = scene_export_cancellable_with_warnings(
+        scene,
+        stitch_length,
+        routing,
+        &CancelToken::default(),
+        &mut warnings,
+    )?;
+    Ok((design, warnings))
+}
+
+fn scene_export_cancellable_with_warnings(
+    scene: &Scene,
+    stitch_length: f64,
+    routing: &RoutingOptions,
+    cancel: &CancelToken,
+    warnings: &mut Vec<ExportWarning>,
+) -> Result<ExportDesign, EngineError> {
+    scene_export_profiled(
+        scene,
+        stitch_length,
+        GenerationProfile::Normal,
+        routing,
+        cancel,
+        warnings,
+    )
+}
+
+fn scene_export_profiled(
+    scene: &Scene,
+    stitch_length: f64,
+    profile: GenerationProfile,
+    routing: &RoutingOptions,
+    cancel: &CancelToken,
+    warnings: &mut Vec<ExportWarning>,
+) -> Result<ExportDesign, EngineError> {
+    if stitch_length <= 0.0 {
+        return Err(EngineError::InvalidInput(
+            "stitch_length must be positive".to_string(),
+        ));
+    }
+    let blocks = collect_blocks(
+        scene,
+        stitch_length,
+        profile,
+        routing.max_total_stitches,
+        cancel,
+        warnings,
+    )?;
+    if blocks.is_empty() {
+        if routing.allow_empty {
+            return Ok(ExportDesign {
+                name: scene.design_name.clone(),
+                stitches: vec![ExportStitch::new(0.0, 0.0, ExportStitchType::End)],
+                colors: vec![Color::default()],
+                coordinate_system: CoordinateSystem::YDown,
+                quantization: routing.quantization,
+            });
+        }
+        return Err(EngineError::InvalidInput(
+            "no stitchable shapes in scene".to_string(),
+        ));
+    }
+    cancel.check()?;
+    let ordered = order_blocks(blocks, routing);
+    Ok(assemble(ordered, routing, &scene.design_name))
+}
+
+/// Per-node stitch blocks kept between exports so a small edit does not
+/// regenerate the whole design. Blocks are world-space and independent of
+/// routing, so the cache survives reordering options; it is keyed to one
+/// stitch length and clears itself when that changes.
+#[derive(Debug, Default)]
+pub struct ExportCache {
+    stitch_length: f64,
+    blocks: std::collections::HashMap<NodeId, StitchBlock>,
+}
+
+impl ExportCache {
+    /// Drop every cached block.
+    pub fn clear(&mut self) {
+        self.blocks.clear();
+    }
+}
+
+/// Export the scene, regenerating blocks only for `changed_nodes` (and
+/// nodes the cache has not seen); everything else splices in from `cache`.
+/// Ordering and assembly always re-run — they are cheap and depend on the
+/// whole block set. The result is identical to a full export.
+pub fn scene_to_export_design_incremental(
+    scene: &Scene,
+    stitch_length: f64,
+    routing: &RoutingOptions,
+    cache: &mut ExportCache,
+    changed_nodes: &[NodeId],
+) -> Result<ExportDesign, EngineError> {
+    if stitch_length <= 0.0 {
+        return Err(EngineError::InvalidInput(
+            "stitch_length must be positive".to_string(),
+        ));
+    }
+    if cache.stitch_length != stitch_length {
+        cache.blocks.clear();
+        cache.stitch_length = stitch_length;
+    }
+    for id in changed_nodes {
+        cache.blocks.remove(id);
+    }
+
+    let cancel = CancelToken::default();
+    let mut warnings = Vec::new();
+    let render = scene.render_list();
+    let mut blocks = Vec::new();
+    for (order, item) in render.iter().enumerate() {
+        if let Some(cached) = cache.blocks.get(&item.node_id) {
+            let mut block = cached.clone();
+            block.source_order = order;
+            blocks.push(block);
+        } else if let Some(block) = generate_shape_block(
+            scene,
+            item.node_id,
+            order,
+            stitch_length,
+            GenerationProfile::Normal,
+            (routing.max_total_stitches > 0).then_some(routing.max_total_stitches),
+            &cancel,
+            &mut warnings,
+        )? {
+            cache.blocks.insert(item.node_id, block.clone());
+            blocks.push(block);
+        }
+    }
+    // Deleted nodes must not linger and resurface on a later splice.
+    let live: std::collections::HashSet<NodeId> = render.iter().map(|i| i.node_id).collect();
+    cache.blocks.retain(|id, _| live.contains(id));
+
+    if blocks.is_empty() {
+        return Err(EngineError::InvalidInput(
+            "no stitchable shapes in scene".to_string(),
+        ));
+    }
+    let ordered = order_blocks(blocks, routing);
+    Ok(assemble(ordered, routing, &scene.design_name))
+}
+
+/// Export the scene at a [`GenerationProfile`] with default routing.
+pub fn scene_to_export_design_profile(
+    scene: &Scene,
+    stitch_length: f64,
+    profile: GenerationProfile,
+) -> Result<ExportDesign, EngineError> {
+    let mut warnings = Vec::new();
+    scene_export_profiled(
+        scene,
+        stitch_length,
+        profile,
+        &RoutingOptions::default(),
+        &CancelToken::default(),
+        &mut warnings,
+    )
+}
+
+/// Export the scene with explicit routing options.
+pub fn scene_to_export_design_with_routing(
+    scene: &Scene,
+    stitch_length: f64,
+    routing: &RoutingOptions,
+) -> Result<ExportDesign, EngineError> {
+    scene_to_export_design_cancellable(scene, stitch_length, routing, &CancelToken::default())
+}
+
+/// Export the scene with default routing.
+pub fn scene_to_export_design(scene: &Scene, stitch_length: f64) -> Result<ExportDesign, EngineError> {
+    scene_to_export_design_with_routing(scene, stitch_length, &RoutingOptions::default())
+}
+
+/// One jump in an assembled design, for the pre-cut worst-jump review.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct JumpInfo {
+    /// Index of the jump stitch within `design.stitches`.
+    pub index: usize,
+    pub from: Point,
+    pub to: Point,
+    pub length_mm: f64,
+    /// A trim directly precedes the jump, so no thread is dragged.
+    pub has_trim_before: bool,
+}
+
+/// Every jump in the design, longest first, so operators can spot
+/// un-trimmed long jumps that would drag thread across the design and add
+/// a manual trim before cutting.
+pub fn jump_report(design: &ExportDesign) -> Vec<JumpInfo> {
+    let mut jumps = Vec::new();
+    let mut position: Option<Point> = None;
+    let mut trim_pending = false;
+    for (index, s) in design.stitches.iter().enumerate() {
+        match s.kind {
+            ExportStitchType::Jump => {
+                let to = Point::new(s.x, s.y);
+                if let Some(from) = position {
+                    jumps.push(JumpInfo {
+                        index,
+                        from,
+                        to,
+                        length_mm: from.distance_to(to),
+                        has_trim_before: trim_pending,
+                    });
+                }
+                position = Some(to);
+                trim_pending = false;
+            }
+            ExportStitchType::Trim => trim_pending = true,
+            ExportStitchType::Normal => {
+                position = Some(Point::new(s.x, s.y));
+                trim_pending = false;
+            }
+            // Color changes imply a thread cut at the machine.
+            ExportStitchType::ColorChange => trim_pending = true,
+            ExportStitchType::Stop | ExportStitchType::End => {}
+        }
+    }
+    jumps.sort_by(|a, b| b.length_mm.partial_cmp(&a.length_mm).unwrap());
+    jumps
+}
+
+/// Render a design as a self-contained SVG whose stitch path draws itself
+/// incrementally — one `<path>` per color block animated via
+/// `stroke-dashoffset`. The preview advances `stitches_per_frame` stitches
+/// per frame at a nominal 60 fps. Output is deterministic, so CI can
+/// snapshot it for visual regression.
+pub fn design_to_animated_svg(design: &ExportDesign, stitches_per_frame: f64) -> String {
+    let ext = design.export_extents();
+    let margin = 2.0;
+    let mut svg = format!(
+        concat!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" "#,
+            r#"viewBox="{:.3} {:.3} {:.3} {:.3}">"#,
+            "\n"
+        ),
+        ext.min_x - margin,
+        ext.min_y - margin,
+        (ext.max_x - ext.min_x) + margin * 2.0,
+        (ext.max_y - ext.min_y) + margin * 2.0,
+    );
+
+    // Split into color blocks; jumps and trims break the drawn polyline.
+    let mut blocks: Vec<(String, f64, usize)> = Vec::new();
+    let mut d = String::new();
+    let mut length = 0.0_f64;
+    let mut count = 0usize;
+    let mut pen: Option<Point> = None;
+    let flush =
+        |d: &mut String, length: &mut f64, count: &mut usize, blocks: &mut Vec<(String, f64, usize)>| {
+            blocks.push((std::mem::take(d), *length, *count));
+            *length = 0.0;
+            *count = 0;
+        };
+    for s in &design.stitches {
+        match s.kind {
+            ExportStitchType::Normal => {
+                match pen {
+                    Some(p) => {
+                        d.push_str(&format!(" L {:.3} {:.3}", s.x, s.y));
+                        length += p.distance_to(Point::new(s.x, s.y));
+                    }
+                    None => d.push_str(&format!("M {:.3} {:.3}", s.x, s.y)),
+                }
+                pen = Some(Point::new(s.x, s.y));
+                count += 1;
+            }
+            ExportStitchType::Jump | ExportStitchType::Trim => pen = None,
+            ExportStitchType::ColorChange => {
+                flush(&mut d, &mut length, &mut count, &mut blocks);
+                pen = None;
+            }
+            ExportStitchType::Stop | ExportStitchType::End => {}
+        }
+    }
+    flush(&mut d, &mut length, &mut count, &mut blocks);
+
+    let spf = stitches_per_frame.max(1e-6);
+    let mut begin_s = 0.0_f64;
+    for (i, (d, length, count)) in blocks.iter().enumerate() {
+        let color = design.colors.get(i).copied().unwrap_or(Color::BLACK);
+        let dur_s = (*count as f64 / (spf * 60.0)).max(1e-3);
+        svg.push_str(&format!(
+            concat!(
+                r##"<path d="{}" fill="none" stroke="#{:02x}{:02x}{:02x}" "##,
+                r#"stroke-width="0.3" stroke-dasharray="{len:.3}" "#,
+                r#"stroke-dashoffset="{len:.3}">"#,
+                "\n",
+                r#"<animate attributeName="stroke-dashoffset" from="{len:.3}" to="0" "#,
+                r#"begin="{:.3}s" dur="{:.3}s" fill="freeze"/>"#,
+                "\n</path>\n"
+            ),
+            d,
+            color.r,
+            color.g,
+            color.b,
+            begin_s,
+            dur_s,
+            len = length,
+        ));
+        begin_s += dur_s;
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Incremental cursor over an assembled design, handing out stitches in
+/// batches so large exports can be streamed to the UI without copying the
+/// whole list at once.
+#[derive(Debug, Clone)]
+pub struct DesignStreamer {
+    design: ExportDesign,
+    cursor: usize,
+}
+
+impl DesignStreamer {
+    pub fn new(design: ExportDesign) -> Self {
+        Self { design, cursor: 0 }
+    }
+
+    /// The design being streamed.
+    pub fn design(&self) -> &ExportDesign {
+        &self.design
+    }
+
+    /// The next batch of up to `max` stitches, or `None` once every stitch
+    /// has been handed out. A `max` of zero is treated as exhaustion so
+    /// callers looping on `Some` always terminate.
+    pub fn next_batch(&mut self, max: usize) -> Option<Vec<ExportStitch>> {
+        if max == 0 || self.cursor >= self.design.stitches.len() {
+            return None;
+        }
+        let end = (self.cursor + max).min(self.design.stitches.len());
+        let batch = self.design.stitches[self.cursor..end].to_vec();
+        self.cursor = end;
+        Some(batch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::Transform;
+    use crate::scene::ShapeNode;
+    use crate::shapes::{RectShape, ShapeData, ShapeStyle};
+    use crate::stitch::StitchParams;
+
+    /// Two rects in different colors, entry-to-exit transition distance
+    /// controlled by `gap_mm`.
+    fn two_color_scene(gap_mm: f64) -> Scene {
+        let mut scene = Scene::new();
+        for (i, color) in [Color::rgb(255, 0, 0), Color::rgb(0, 0, 255)]
+            .into_iter()
+            .enumerate()
+        {
+            let id = scene
+                .add_node(
+                    NodeKind::Shape(Box::new(ShapeNode {
+                        data: ShapeData::Rect(RectShape {
+                            width: 10.0,
+                            height: 10.0,
+                        }),
+                        style: ShapeStyle {
+                            stroke: Some(color),
+                            ..ShapeStyle::default()
+                        },
+                        stitch: StitchParams::default(),
+                        sequencer: Default::default(),
+                    })),
+                    None,
+                )
+                .unwrap();
+            scene
+                .set_transform(id, Transform::translation(i as f64 * (10.0 + gap_mm), 0.0))
+                .unwrap();
+        }
+        scene
+    }
+
+    fn export_with(scene: &Scene, travel: ColorChangeTravel) -> ExportDesign {
+        let routing = RoutingOptions {
+            color_change_travel: travel,
+            ..RoutingOptions::default()
+        };
+        scene_to_export_design_with_routing(scene, 2.0, &routing).unwrap()
+    }
+
+    fn trim_count(design: &ExportDesign) -> usize {
+        design
+            .stitches
+            .iter()
+            .filter(|s| s.kind == ExportStitchType::Trim)
+            .count()
+    }
+
+    #[test]
+    fn same_color_change_is_dropped_and_real_change_kept() {
+        let red = Color::rgb(255, 0, 0);
+        let blue = Color::rgb(0, 0, 255);
+        let mut design = ExportDesign {
+            name: "dedupe".to_string(),
+            stitches: vec![
+                ExportStitch::new(0.0, 0.0, ExportStitchType::Normal),
+                ExportStitch::new(0.0, 0.0, ExportStitchType::ColorChange),
+                ExportStitch::new(5.0, 0.0, ExportStitchType::Normal),
+                ExportStitch::new(5.0, 0.0, ExportStitchType::ColorChange),
+                ExportStitch::new(10.0, 0.0, ExportStitchType::Normal),
+                ExportStitch::new(10.0, 0.0, ExportStitchType::End),
+            ],
+            colors: vec![red, red, blue],
+            coordinate_system: CoordinateSystem::YDown,
+            quantization: Quantization::default(),
+        };
+        dedupe_color_changes(&mut design);
+        let changes: Vec<usize> = design
+            .stitches
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| s.kind == ExportStitchType::ColorChange)
+            .map(|(i, _)| i)
+            .collect();
+        // The red→red change is gone; the red→blue one survives in place.
+        assert_eq!(changes, vec![2]);
+        assert_eq!(design.colors, vec![red, blue]);
+        assert_eq!(design.stitches.len(), 5);
+    }
+
+    #[test]
+    fn basting_ring_comes_first_and_trims_before_the_design() {
+        let mut design = ExportDesign {
+            name: "basted".to_string(),
+            stitches: vec![
+                ExportStitch::new(0.0, 0.0, ExportStitchType::Normal),
+                ExportStitch::new(10.0, 0.0, ExportStitchType::Normal),
+                ExportStitch::new(10.0, 0.0, ExportStitchType::End),
+            ],
+            colors: vec![Color::rgb(255, 0, 0)],
+            coordinate_system: CoordinateSystem::YDown,
+            quantization: Quantization::default(),
+        };
+        prepend_basting(&mut design, 100.0, 100.0, 10.0, 5.0).unwrap();
+
+        // Everything up to the first trim is the basting run, and it visits
+        // all four corners of the 80×80 rectangle centered on the design.
+        let trim_at = design
+            .stitches
+            .iter()
+            .position(|s| s.kind == ExportStitchType::Trim)
+            .unwrap();
+        let run = &design.stitches[..trim_at];
+        assert!(run.iter().all(|s| s.kind == ExportStitchType::Normal));
+        for (cx, cy) in [(-35.0, -40.0), (45.0, -40.0), (45.0, 40.0), (-35.0, 40.0)] {
+            assert!(run.iter().any(|s| s.x == cx && s.y == cy));
+        }
+        // A color change follows the trim, then the real design resumes.
+        assert_eq!(
+            design.stitches[trim_at + 1].kind,
+            ExportStitchType::ColorChange
+        );
+        let first_real = design.stitches[trim_at + 2];
+        assert_eq!((first_real.x, first_real.y), (0.0, 0.0));
+        assert_eq!(design.colors.len(), 2);
+        assert_ne!(design.colors[0], design.colors[1]);
+        // An inset past the hoop's half-extent leaves nothing to baste.
+        assert!(prepend_basting(&mut design, 100.0, 100.0, 60.0, 5.0).is_err());
+    }
+
+    #[test]
+    fn always_trim_emits_trim_at_color_boundary() {
+        let scene = two_color_scene(2.0);
+        let design = export_with(&scene, ColorChangeTravel::AlwaysTrim);
+        assert_eq!(trim_count(&design), 1);
+        assert_eq!(design.colors.len(), 2);
+        // Trim comes right before the color change.
+        let change_idx = design
+            .stitches
+            .iter()
+            .position(|s| s.kind == ExportStitchType::ColorChange)
+            .unwrap();
+        assert_eq!(design.stitches[change_idx - 1].kind, ExportStitchType::Trim);
+    }
+
+    #[test]
+    fn trim_if_far_skips_trim_on_short_transition() {
+        let scene = two_color_scene(2.0);
+        let design = export_with(&scene, ColorChangeTravel::TrimIfFar(50.0));
+        assert_eq!(trim_count(&design), 0);
+        assert_eq!(design.colors.len(), 2);
+    }
+
+    #[test]
+    fn trim_if_far_trims_on_long_transition() {
+        let scene = two_color_scene(80.0);
+        let design = export_with(&scene, ColorChangeTravel::TrimIfFar(50.0));
+        assert_eq!(trim_count(&design), 1);
+    }
+
+    #[test]
+    fn tie_off_replaces_trim_with_lock_stitches() {
+        let scene = two_color_scene(2.0);
+        let design = export_with(&scene, ColorChangeTravel::TieOff);
+        assert_eq!(trim_count(&design), 0);
+        let change_idx = design
+            .stitches
+            .iter()
+            .position(|s| s.kind == ExportStitchType::ColorChange)
+            .unwrap();
+        // Lock stitches directly precede the change, anchored at the exit.
+        let exit = design.stitches[change_idx];
+        for s in &design.stitches[change_idx - 4..change_idx] {
+            assert_eq!(s.kind, ExportStitchType::Normal);
+            assert!((s.x - exit.x).abs() <= 1.0 && (s.y - exit.y).abs() <= 1.0);
+        }
+    }
+
+    #[test]
+    fn satin_pull_compensation_grows_export_extents() {
+        let build = |pull: f64| {
+            let mut scene = Scene::new();
+            scene
+                .add_node(
+                    NodeKind::Shape(Box::new(ShapeNode {
+                        data: ShapeData::Rect(RectShape {
+                            width: 20.0,
+                            height: 10.0,
+                        }),
+                        style: ShapeStyle {
+                            stroke_width: 2.0,
+                            ..ShapeStyle::default()
+                        },
+                        stitch: StitchParams {
+                            stitch_type: crate::stitch::StitchType::Satin,
+                            pull_compensation: pull,
+                            ..StitchParams::default()
+                        },
+                        sequencer: Default::default(),
+                    })),
+                    None,
+                )
+                .unwrap();
+            scene
+        };
+
+        let plain = build(0.0);
+        let compensated = build(0.4);
+        let geom = plain.content_bounds().unwrap();
+        let plain_ext = scene_to_export_design(&plain, 2.0).unwrap().export_extents();
+        let comp_ext = scene_to_export_design(&compensated, 2.0)
+            .unwrap()
+            .export_extents();
+
+        // The satin band straddles the outline, so stitched extents exceed
+        // the geometric bbox; pull compensation widens them further.
+        assert!(plain_ext.width > geom.width());
+        assert!(comp_ext.width > plain_ext.width + 0.3);
+    }
+
+    #[test]
+    fn block_color_override_wins_in_export_without_touching_style() {
+        let mut scene = two_color_scene(2.0);
+        // Override the red shape's block to blue; its style stays red.
+        let first = scene.render_list()[0].node_id;
+        scene
+            .set_block_color(first, Some(Color::rgb(0, 0, 255)))
+            .unwrap();
+        let design = scene_to_export_design(&scene, 2.0).unwrap();
+        // Both blocks now stitch blue: one color, no change.
+        assert_eq!(design.colors, vec![Color::rgb(0, 0, 255)]);
+        let NodeKind::Shape(shape) = &scene.node(first).unwrap().kind else {
+            panic!("expected a shape");
+        };
+        assert_eq!(shape.style.thread_color(), Color::rgb(255, 0, 0));
+    }
+
+    #[test]
+    fn explode_by_color_yields_recentered_single_color_pieces() {
+        let design = scene_to_export_design(&two_color_scene(2.0), 2.0).unwrap();
+        let pieces = explode_by_color(&design);
+        assert_eq!(pieces.len(), 2);
+        let mut total_normals = 0;
+        for piece in &pieces {
+            assert_eq!(piece.colors.len(), 1);
+            assert_eq!(piece.stitches.last().unwrap().kind, ExportStitchType::End);
+            assert!(!piece
+                .stitches
+                .iter()
+                .any(|s| s.kind == ExportStitchType::ColorChange));
+            let center = piece.extents().center();
+            assert!(center.x.abs() < 1e-9 && center.y.abs() < 1e-9);
+            total_normals += piece.normal_stitch_count();
+        }
+        assert_eq!(pieces[0].colors[0], design.colors[0]);
+        assert_eq!(pieces[1].colors[0], design.colors[1]);
+        assert_eq!(total_normals, design.normal_stitch_count());
+    }
+
+    #[test]
+    fn reduce_colors_merges_near_identical_threads() {
+        let mut scene = two_color_scene(2.0);
+        // Make the second rect a near-duplicate red instead of blue.
+        let second = scene.render_list()[1].node_id;
+        if let NodeKind::Shape(shape) = &mut scene.node_mut(second).unwrap().kind {
+            shape.style.stroke = Some(Color::rgb(250, 8, 4));
+        }
+        let mut design = scene_to_export_design(&scene, 2.0).unwrap();
+        assert_eq!(design.colors.len(), 2);
+        let changes_before = design
+            .stitches
+            .iter()
+            .filter(|s| s.kind == ExportStitchType::ColorChange)
+            .count();
+        assert_eq!(changes_before, 1);
+
+        reduce_colors(&mut design, 8, 10.0);
+        assert_eq!(design.colors, vec![Color::rgb(255, 0, 0)]);
+        let changes_after = design
+            .stitches
+            .iter()
+            .filter(|s| s.kind == ExportStitchType::ColorChange)
+            .count();
+        assert_eq!(changes_after, 0);
+    }
+
+    #[test]
+    fn reduce_colors_respects_hard_cap() {
+        let mut design = scene_to_export_design(&two_color_scene(2.0), 2.0).unwrap();
+        // Red and blue are far beyond any sane threshold, but the cap wins.
+        reduce_colors(&mut design, 1, 1.0);
+        assert_eq!(design.colors.len(), 1);
+    }
+
+    #[test]
+    fn reduce_colors_keeps_distinct_threads() {
+        let mut design = scene_to_export_design(&two_color_scene(2.0), 2.0).unwrap();
+        let before = design.clone();
+        reduce_colors(&mut design, 8, 2.0);
+        assert_eq!(design, before);
+    }
+
+    #[test]
+    fn routing_options_parse_from_json() {
+        let routing: RoutingOptions = serde_json::from_str(
+            r#"{"color_change_travel":{"mode":"trim_if_far","value":12.5}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            routing.color_change_travel,
+            ColorChangeTravel::TrimIfFar(12.5)
+        );
+        assert!(routing.preserve_color_order);
+    }
+
+    #[test]
+    fn cancelled_token_aborts_export() {
+        let scene = two_color_scene(2.0);
+        let token = CancelToken::new();
+        token.cancel();
+        let err = scene_to_export_design_cancellable(
+            &scene,
+            2.0,
+            &RoutingOptions::default(),
+            &token,
+        )
+        .unwrap_err();
+        assert_eq!(err, EngineError::Cancelled);
+    }
+
+    #[test]
+    fn empty_scene_errors() {
+        let scene = Scene::new();
+        assert!(scene_to_export_design(&scene, 2.0).is_err());
+    }
+
+    #[test]
+    fn allow_empty_yields_a_placeholder_design() {
+        let scene = Scene::new();
+        let routing = RoutingOptions {
+            allow_empty: true,
+            ..RoutingOptions::default()
+        };
+        let design = scene_to_export_design_with_routing(&scene, 2.0, &routing).unwrap();
+        assert_eq!(design.stitches.len(), 1);
+        assert_eq!(design.stitches[0].kind, ExportStitchType::End);
+        assert_eq!(design.colors.len(), 1);
+    }
+
+    #[test]
+    fn satin_rect_zigzags_between_inner_and_outer_rails() {
+        let mut scene = Scene::new();
+        scene
+            .add_node(
+                NodeKind::Shape(Box::new(ShapeNode {
+                    data: ShapeData::Rect(RectShape {
+                        width: 10.0,
+                        height: 10.0,
+                    }),
+                    style: ShapeStyle {
+                        stroke_width: 2.0,
+                        ..ShapeStyle::default()
+                    },
+                    stitch: StitchParams {
+                        stitch_type: StitchType::Satin,
+                        ..StitchParams::default()
+                    },
+                    sequencer: Default::default(),
+                })),
+                None,
+            )
+            .unwrap();
+        let design = scene_to_export_design(&scene, 2.0).unwrap();
+        // A running outline would keep every penetration on the rect
+        // boundary (Chebyshev radius 5); a satin band straddles it.
+        let radii: Vec<f64> = design
+            .stitches
+            .iter()
+            .filter(|s| s.kind == ExportStitchType::Normal)
+            .map(|s| s.x.abs().max(s.y.abs()))
+            .collect();
+        let inside = radii.iter().filter(|r| **r < 4.95).count();
+        let outside = radii.iter().filter(|r| **r > 5.05).count();
+        assert!(
+            inside * 10 >= radii.len() * 4 && outside * 10 >= radii.len() * 4,
+            "not an alternating band: {inside} inside / {outside} outside of {}",
+            radii.len()
+        );
+    }
+
+    #[test]
+    fn disconnected_satin_segments_are_linked_by_a_running_connector() {
+        // Two horizontal strokes 20 mm apart, as one multi-subpath path.
+        let mut path = crate::path::VectorPath::from_polyline(&[
+            Point::new(0.0, 0.0),
+            Point::new(10.0, 0.0),
+        ]);
+        path.commands.extend(
+            crate::path::VectorPath::from_polyline(&[
+                Point::new(0.0, 20.0),
+                Point::new(10.0, 20.0),
+            ])
+            .commands,
+        );
+        let mut scene = Scene::new();
+        scene
+            .add_node(
+                NodeKind::Shape(Box::new(ShapeNode {
+                    data: ShapeData::Path(path),
+                    style: ShapeStyle {
+                        stroke_width: 2.0,
+                        ..ShapeStyle::default()
+                    },
+                    stitch: StitchParams {
+                        stitch_type: StitchType::Satin,
+                        ..StitchParams::default()
+                    },
+                    sequencer: Default::default(),
+                })),
+                None,
+            )
+            .unwrap();
+        let design = scene_to_export_design(&scene, 2.0).unwrap();
+        // The travel between the bands must be secured running stitches,
+        // not a jump: only the block-entry positioning jump may remain.
+        let gap: Vec<_> = design
+            .stitches
+            .iter()
+            .filter(|s| s.y > 2.0 && s.y < 18.0)
+            .collect();
+        assert!(gap.iter().all(|s| s.kind == ExportStitchType::Normal));
+        assert!(gap.len() >= 5, "no running connector across the gap");
+    }
+
+    #[test]
+    fn stitch_budget_fails_fast_on_runaway_density() {
+        let mut scene = Scene::new();
+        scene
+            .add_node(
+                NodeKind::Shape(Box::new(ShapeNode {
+                    data: ShapeData::Rect(RectShape {
+                        width: 100.0,
+                        height: 100.0,
+                    }),
+                    style: ShapeStyle::default(),
+                    stitch: StitchParams {
+                        stitch_type: StitchType::Tatami,
+                        density: 0.05,
+                        ..StitchParams::default()
+                    },
+                    sequencer: Default::default(),
+                })),
+                None,
+            )
+            .unwrap();
+        let routing = RoutingOptions {
+            max_total_stitches: 10_000,
+            ..RoutingOptions::default()
+        };
+        let err = scene_to_export_design_with_routing(&scene, 0.5, &routing).unwrap_err();
+        assert!(err.to_string().contains("stitch budget exceeded"), "{err}");
+        // The same scene still exports when the budget allows it.
+        let routing = RoutingOptions {
+            max_total_stitches: 10_000_000,
+            ..RoutingOptions::default()
+        };
+        assert!(scene_to_export_design_with_routing(&scene, 0.5, &routing).is_ok());
+    }
+
+    #[test]
+    fn incremental_export_reuses_unchanged_blocks() {
+        let mut scene = two_color_scene(2.0);
+        let routing = RoutingOptions::default();
+        let mut cache = ExportCache::default();
+
+        let cold = scene_to_export_design_incremental(&scene, 2.0, &routing, &mut cache, &[])
+            .unwrap();
+        let full = scene_to_export_design_with_routing(&scene, 2.0, &routing).unwrap();
+        assert_eq!(cold, full);
+
+        // Recolor the first shape behind the cache's back. With no change
+        // notice the stale block is spliced in untouched — proof the cache
+        // is being reused, not regenerated.
+        let red_id = scene.sequencer_shape_ids()[0];
+        {
+            let node = scene.node_mut(red_id).unwrap();
+            let NodeKind::Shape(shape) = &mut node.kind else {
+                panic!("shape node");
+            };
+            shape.style.stroke = Some(Color::rgb(0, 255, 0));
+        }
+        let stale = scene_to_export_design_incremental(&scene, 2.0, &routing, &mut cache, &[])
+            .unwrap();
+        assert_eq!(stale.colors[0], Color::rgb(255, 0, 0));
+
+        // Flagging the node regenerates just that block; the result matches
+        // a full re-export, stitch for stitch.
+        let fresh =
+            scene_to_export_design_incremental(&scene, 2.0, &routing, &mut cache, &[red_id])
+                .unwrap();
+        let full = scene_to_export_design_with_routing(&scene, 2.0, &routing).unwrap();
+        assert_eq!(fresh, full);
+        assert_eq!(fresh.colors[0], Color::rgb(0, 255, 0));
+        // The untouched block's geometry is bit-identical.
+        assert_eq!(fresh.stitches, stale.stitches);
+    }
+
+    #[test]
+    fn draft_profile_is_coarser_than_high() {
+        // A curve-heavy scene: two ellipses, one filled, one outlined.
+        let mut scene = Scene::new();
+        for stitch_type in [StitchType::Running, StitchType::Tatami] {
+            scene
+                .add_node(
+                    NodeKind::Shape(Box::new(ShapeNode {
+                        data: ShapeData::Ellipse(crate::shapes::EllipseShape {
+                            rx: 12.0,
+                            ry: 8.0,
+                        }),
+                        style: ShapeStyle::default(),
+                        stitch: StitchParams {
+                            stitch_type,
+                            fill_underlay: crate::stitch::fill::FillUnderlay::Perpendicular,
+                            ..StitchParams::default()
+                        },
+                        sequencer: Default::default(),
+                    })),
+                    None,
+                )
+                .unwrap();
+        }
+        let draft =
+            scene_to_export_design_profile(&scene, 2.0, GenerationProfile::Draft).unwrap();
+        let high = scene_to_export_design_profile(&scene, 2.0, GenerationProfile::High).unwrap();
+        assert!(
+            draft.stitches.len() * 2 < high.stitches.len(),
+            "draft {} vs high {}",
+            draft.stitches.len(),
+            high.stitches.len()
+        );
+    }
+
+    #[test]
+    fn perpendicular_underlay_runs_across_the_top_fill() {
+        let fill_scene = |underlay: crate::stitch::fill::FillUnderlay| {
+            let mut scene = Scene::new();
+            scene
+                .add_node(
+                    NodeKind::Shape(Box::new(ShapeNode {
+                        data: ShapeData::Rect(RectShape {
+                            width: 20.0,
+                            height: 20.0,
+                        }),
+                        style: ShapeStyle::default(),
+                        stitch: StitchParams {
+                            stitch_type: StitchType::Tatami,
+                            fill_underlay: underlay,
+                            ..StitchParams::default()
+                        },
+                        sequencer: Default::default(),
+                    })),
+                    None,
+                )
+                .unwrap();
+            scene
+        };
+        let plain = scene_to_export_design(&fill_scene(crate::stitch::fill::FillUnderlay::None), 2.0)
+            .unwrap();
+        let under = scene_to_export_design(
+            &fill_scene(crate::stitch::fill::FillUnderlay::Perpendicular),
+            2.0,
+        )
+        .unwrap();
+        let extra = under.stitches.len() - plain.stitches.len();
+        assert!(extra > 0);
+
+        // Dominant travel direction of a run: total |dx| vs |dy| over
+        // normal-to-normal segments.
+        let travel = |stitches: &[ExportStitch]| -> (f64, f64) {
+            let mut sums = (0.0, 0.0);
+            for w in stitches.windows(2) {
+                if w[0].kind == ExportStitchType::Normal && w[1].kind == ExportStitchType::Normal {
+                    sums.0 += (w[1].x - w[0].x).abs();
+                    sums.1 += (w[1].y - w[0].y).abs();
+                }
+            }
+            sums
+        };
+        // The underlay is stitched first; the top fill follows it.
+        let (ux, uy) = travel(&under.stitches[..extra]);
+        let (tx, ty) = travel(&under.stitches[extra..]);
+        // Top rows run along X (angle 0); the underlay runs along Y.
+        assert!(tx > ty * 3.0, "top fill travel ({tx}, {ty})");
+        assert!(uy > ux * 3.0, "underlay travel ({ux}, {uy})");
+    }
+
+    #[test]
+    fn edge_run_underlay_traces_the_boundary_before_the_rows() {
+        let fill_scene = |underlay: crate::stitch::fill::FillUnderlay| {
+            let mut scene = Scene::new();
+            scene
+                .add_node(
+                    NodeKind::Shape(Box::new(ShapeNode {
+                        data: ShapeData::Rect(RectShape {
+                            width: 20.0,
+                            height: 20.0,
+                        }),
+                        style: ShapeStyle::default(),
+                        stitch: StitchParams {
+                            stitch_type: StitchType::Tatami,
+                            fill_underlay: underlay,
+                            ..StitchParams::default()
+                        },
+                        sequencer: Default::default(),
+                    })),
+                    None,
+                )
+                .unwrap();
+            scene
+        };
+        let plain = scene_to_export_design(&fill_scene(crate::stitch::fill::FillUnderlay::None), 2.0)
+            .unwrap();
+        let under =
+            scene_to_export_design(&fill_scene(crate::stitch::fill::FillUnderlay::EdgeRun), 2.0)
+                .unwrap();
+        let extra = under.stitches.len() - plain.stitches.len();
+        assert!(extra > 0);
+        // The edge run comes first and hugs the rect boundary (|x| or |y|
+        // at 10); fill rows would put most penetrations in the interior.
+        for s in under.stitches[..extra]
+            .iter()
+            .filter(|s| s.kind == ExportStitchType::Normal)
+        {
+            let edge_dist = (s.x.abs() - 10.0).abs().min((s.y.abs() - 10.0).abs());
+            assert!(edge_dist < 1e-6, "underlay stitch off the edge: ({}, {})", s.x, s.y);
+        }
+    }
+
+    #[test]
+    fn fills_phase_stitches_before_every_outline() {
+        // Two objects, each a fill block and an outline block on top, in
+        // per-object source order: fillA, outlineA, fillB, outlineB.
+        let block = |source_order: usize, is_outline: bool, x: f64| StitchBlock {
+            color: Color::BLACK,
+            color_override: None,
+            stitches: vec![Stitch::normal(x, 0.0), Stitch::normal(x + 1.0, 0.0)],
+            manual_commands: Vec::new(),
+            source_order,
+            is_outline,
+        };
+        let blocks = vec![
+            block(0, false, 0.0),
+            block(1, true, 0.0),
+            block(2, false, 20.0),
+            block(3, true, 20.0),
+        ];
+
+        let per_object = order_blocks(blocks.clone(), &RoutingOptions::default());
+        let order: Vec<usize> = per_object.iter().map(|b| b.source_order).collect();
+        assert_eq!(order, vec![0, 1, 2, 3]);
+
+        let routing = RoutingOptions {
+            outline_phase: OutlinePhase::AllFillsThenOutlines,
+            ..RoutingOptions::default()
+        };
+        let phased = order_blocks(blocks, &routing);
+        let order: Vec<usize> = phased.iter().map(|b| b.source_order).collect();
+        assert_eq!(order, vec![0, 2, 1, 3]);
+    }
+
+    #[test]
+    fn preview_bundles_outline_and_stitches() {
+        let scene = two_color_scene(2.0);
+        let previews = scene_to_preview(&scene, 2.0, 0.1).expect("preview succeeds");
+        assert_eq!(previews.len(), 2);
+        for p in &previews {
+            assert!(!p.subpaths.is_empty());
+            assert!(!p.stitches.is_empty());
+            // Outline and stitches agree on where the shape is.
+            let outline_x = p.subpaths[0][0].x;
+            assert!(p.stitches.iter().any(|s| (s.x - outline_x).abs() < 20.0));
+        }
+    }
+
+    #[test]
+    fn tie_at_trims_locks_every_trim() {
+        let scene = two_color_scene(20.0);
+        let routing = RoutingOptions {
+            tie_at_trims: true,
+            ..RoutingOptions::default()
+        };
+        let design =
+            scene_to_export_design_with_routing(&scene, 2.0, &routing).expect("export succeeds");
+        let trims: Vec<usize> = design
+            .stitches
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| s.kind == ExportStitchType::Trim)
+            .map(|(i, _)| i)
+            .collect();
+        assert!(!trims.is_empty());
+        for i in trims {
+            assert!(i >= 4);
+            for s in &design.stitches[i - 4..i] {
+                assert_eq!(s.kind, ExportStitchType::Normal);
+            }
+            // The tie-off ends on the trim's own anchor.
+            assert_eq!(design.stitches[i - 1].x, design.stitches[i].x);
+            assert_eq!(design.stitches[i - 1].y, design.stitches[i].y);
+        }
+    }
+
+    #[test]
+    fn thread_consumption_scales_path_length_by_take_up() {
+        let design = ExportDesign {
+            name: "line".to_string(),
+            stitches: vec![
+                ExportStitch::new(0.0, 0.0, ExportStitchType::Normal),
+                ExportStitch::new(4.0, 0.0, ExportStitchType::Normal),
+                ExportStitch::new(10.0, 0.0, ExportStitchType::Normal),
+                ExportStitch::new(10.0, 0.0, ExportStitchType::End),
+            ],
+            colors: vec![Color::BLACK],
+            coordinate_system: CoordinateSystem::YDown,
+            quantization: Quantization::default(),
+        };
+        let use_per_color = thread_consumption(&design, 1.3, 0.6);
+        assert_eq!(use_per_color.len(), 1);
+        assert!((use_per_color[0].top_thread_mm - 13.0).abs() < 1e-9);
+        assert!((use_per_color[0].bobbin_mm - 7.8).abs() < 1e-9);
+    }
+
+    #[test]
+    fn export_excluded_shapes_render_but_never_stitch() {
+        let mut scene = two_color_scene(2.0);
+        let excluded = scene.render_list()[0].node_id;
+        scene.set_export_excluded(excluded, true).unwrap();
+
+        // Still part of the render traversal...
+        assert!(scene
+            .render_list()
+            .iter()
+            .any(|item| item.node_id == excluded));
+
+        // ...but only the other shape's color reaches the export.
+        let design = scene_to_export_design(&scene, 2.0).unwrap();
+        assert_eq!(design.colors, vec![Color::rgb(0, 0, 255)]);
+    }
+
+    #[test]
+    fn hoop_positioning_pins_the_bbox_to_the_anchor() {
+        let mut design = scene_to_export_design(&two_color_scene(2.0), 2.0).unwrap();
+        position_in_hoop(&mut design, 100.0, 100.0, HoopAnchor::TopLeft, 5.0).unwrap();
+        let bbox = design.extents();
+        // Screen convention: top-left is the minimum corner.
+        assert!((bbox.min_x - (-45.0)).abs() < 1e-9);
+        assert!((bbox.min_y - (-45.0)).abs() < 1e-9);
+
+        position_in_hoop(&mut design, 100.0, 100.0, HoopAnchor::Center, 5.0).unwrap();
+        let bbox = design.extents();
+        assert!((bbox.min_x + bbox.max_x).abs() < 1e-9);
+        assert!((bbox.min_y + bbox.max_y).abs() < 1e-9);
+
+        // A 100 mm hoop with margins cannot take a 22 mm design at 45 mm.
+        assert!(position_in_hoop(&mut design, 100.0, 100.0, HoopAnchor::Center, 45.0).is_err());
+    }
+
+    #[test]
+    fn stroke_alignment_places_the_satin_band_on_the_right_side() {
+        let satin_design = |align: crate::stitch::StrokeAlign| {
+            let mut scene = Scene::new();
+            scene
+                .add_node(
+                    NodeKind::Shape(Box::new(ShapeNode {
+                        data: ShapeData::Rect(RectShape {
+                            width: 20.0,
+                            height: 20.0,
+                        }),
+                        style: ShapeStyle {
+                            stroke_width: 2.0,
+                            ..ShapeStyle::default()
+                        },
+                        stitch: StitchParams {
+                            stitch_type: crate::stitch::StitchType::Satin,
+                            stroke_align: align,
+                            ..StitchParams::default()
+                        },
+                        sequencer: Default::default(),
+                    })),
+                    None,
+                )
+                .unwrap();
+            scene_to_export_design(&scene, 2.0).unwrap()
+        };
+
+        let inside = satin_design(crate::stitch::StrokeAlign::Inside);
+        for s in inside.stitches.iter().filter(|s| s.kind == ExportStitchType::Normal) {
+            assert!(
+                s.x.abs() <= 10.0 + 1e-6 && s.y.abs() <= 10.0 + 1e-6,
+                "inside stitch escaped the region: ({}, {})",
+                s.x,
+                s.y
+            );
+        }
+
+        let outside = satin_design(crate::stitch::StrokeAlign::Outside);
+        for s in outside.stitches.iter().filter(|s| s.kind == ExportStitchType::Normal) {
+            assert!(
+                s.x.abs() >= 10.0 - 1e-6 || s.y.abs() >= 10.0 - 1e-6,
+                "outside stitch inside the region: ({}, {})",
+                s.x,
+                s.y
+            );
+        }
+    }
+
+    #[test]
+    fn running_to_satin_upgrade_widens_output_and_undoes() {
+        use crate::command::{Command, CommandHistory};
+
+        let mut scene = Scene::new();
+        let mut history = CommandHistory::new();
+        let id = history
+            .apply(
+                &mut scene,
+                Command::AddNode {
+                    kind: NodeKind::Shape(Box::new(ShapeNode {
+                        data: ShapeData::Path(crate::path::VectorPath::from_polyline(&[
+                            Point::new(0.0, 0.0),
+                            Point::new(30.0, 0.0),
+                        ])),
+                        style: ShapeStyle::default(),
+                        stitch: StitchParams::default(),
+                        sequencer: Default::default(),
+                    })),
+                    parent: None,
+                },
+            )
+            .unwrap()
+            .unwrap();
+
+        // Running output hugs the centerline.
+        let design = scene_to_export_design(&scene, 2.0).unwrap();
+        let b = design.extents();
+        let running_span = b.max_y - b.min_y;
+        assert!(running_span < 0.5, "running span {running_span}");
+
+        history
+            .apply(
+                &mut scene,
+                Command::RunningToSatin {
+                    id,
+                    width_mm: 3.0,
+                    density: 0.5,
+                },
+            )
+            .unwrap();
+        let design = scene_to_export_design(&scene, 2.0).unwrap();
+        let b = design.extents();
+        let satin_span = b.max_y - b.min_y;
+        assert!(
+            (satin_span - 3.0).abs() < 0.6,
+            "satin span {satin_span} should be about the requested width"
+        );
+
+        history.undo(&mut scene).unwrap();
+        let design = scene_to_export_design(&scene, 2.0).unwrap();
+        let b = design.extents();
+        let undone_span = b.max_y - b.min_y;
+        assert!(undone_span < 0.5, "undone span {undone_span}");
+    }
+
+    #[test]
+    fn inventory_assignment_remaps_matches_and_flags_misses() {
+        let mut design = scene_to_export_design(&two_color_scene(2.0), 2.0).unwrap();
+        assert_eq!(design.colors[0], Color::rgb(255, 0, 0));
+        let inventory = vec![
+            ThreadEntry {
+                name: "Poppy".to_string(),
+                color: Color::rgb(250, 10, 5),
+            },
+            ThreadEntry {
+                name: "Leaf".to_string(),
+                color: Color::rgb(40, 160, 40),
+            },
+        ];
+        let unfulfilled = assign_from_inventory(&mut design, &inventory);
+
+        // Red snaps to the near-exact Poppy spool; blue has nothing close.
+        assert_eq!(design.colors[0], Color::rgb(250, 10, 5));
+        assert_eq!(design.colors[1], Color::rgb(0, 0, 255));
+        assert_eq!(unfulfilled.len(), 1);
+        assert_eq!(unfulfilled[0].requested, Color::rgb(0, 0, 255));
+        assert!(unfulfilled[0].nearest.is_some());
+        assert!(unfulfilled[0].delta_e > INVENTORY_MATCH_THRESHOLD_DELTA_E);
+    }
+
+    #[test]
+    fn design_name_flows_through_to_the_export() {
+        let mut scene = two_color_scene(2.0);
+        scene.set_design_name("Rosebud");
+        let design = scene_to_export_design(&scene, 2.0).unwrap();
+        assert_eq!(design.name, "Rosebud");
+
+        scene.set_design_name("   ");
+        let design = scene_to_export_design(&scene, 2.0).unwrap();
+        assert_eq!(design.name, "design");
+    }
+
+    #[test]
+    fn revisited_color_repeats_in_the_change_sequence() {
+        // Red, blue, red in source order; without color-order preservation
+        // the blocks stay interleaved, so red is revisited.
+        let red = Color::rgb(220, 30, 30);
+        let blue = Color::rgb(30, 30, 220);
+        let mut scene = Scene::new();
+        for (i, color) in [red, blue, red].into_iter().enumerate() {
+            let id = scene
+                .add_node(
+                    NodeKind::Shape(Box::new(ShapeNode {
+                        data: ShapeData::Rect(RectShape {
+                            width: 4.0,
+                            height: 4.0,
+                        }),
+                        style: ShapeStyle {
+                            stroke: Some(color),
+                            ..ShapeStyle::default()
+                        },
+                        stitch: StitchParams::default(),
+                        sequencer: Default::default(),
+                    })),
+                    None,
+                )
+                .unwrap();
+            scene
+                .set_transform(id, Transform::translation(i as f64 * 10.0, 0.0))
+                .unwrap();
+        }
+        let routing = RoutingOptions {
+            preserve_color_order: false,
+            ..RoutingOptions::default()
+        };
+        let design = scene_to_export_design_with_routing(&scene, 2.0, &routing).unwrap();
+
+        // `colors` is the change sequence: the revisit is a fresh entry.
+        assert_eq!(design.colors, vec![red, blue, red]);
+        assert_eq!(design.distinct_color_count(), 2);
+        let changes = design
+            .stitches
+            .iter()
+            .filter(|s| s.kind == ExportStitchType::ColorChange)
+            .count();
+        assert_eq!(changes + 1, design.colors.len());
+    }
+
+    #[test]
+    fn jump_report_puts_the_longest_untrimmed_jump_first() {
+        let design = ExportDesign {
+            name: "jumps".to_string(),
+            stitches: vec![
+                ExportStitch::new(0.0, 0.0, ExportStitchType::Normal),
+                // A 3-4-5 triangle: 50 mm, no trim before it.
+                ExportStitch::new(30.0, 40.0, ExportStitchType::Jump),
+                ExportStitch::new(31.0, 40.0, ExportStitchType::Normal),
+                ExportStitch::new(31.0, 40.0, ExportStitchType::Trim),
+                ExportStitch::new(31.0, 45.0, ExportStitchType::Jump),
+                ExportStitch::new(31.0, 45.0, ExportStitchType::End),
+            ],
+            colors: vec![Color::BLACK],
+            coordinate_system: CoordinateSystem::YDown,
+            quantization: Quantization::default(),
+        };
+        let report = jump_report(&design);
+        assert_eq!(report.len(), 2);
+        assert!((report[0].length_mm - 50.0).abs() < 1e-9);
+        assert_eq!(report[0].index, 1);
+        assert!(!report[0].has_trim_before);
+        assert!((report[1].length_mm - 5.0).abs() < 1e-9);
+        assert!(report[1].has_trim_before);
+    }
+
+    #[test]
+    fn animated_svg_has_one_path_per_color_and_balanced_tags() {
+        let scene = two_color_scene(3.0);
+        let design = scene_to_export_design(&scene, 2.0).unwrap();
+        let svg = design_to_animated_svg(&design, 30.0);
+
+        assert_eq!(svg.matches("<path ").count(), design.colors.len());
+        assert_eq!(
+            svg.matches("<path ").count(),
+            svg.matches("</path>").count()
+        );
+        assert_eq!(svg.matches("<animate ").count(), design.colors.len());
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.trim_end().ends_with("</svg>"));
+        // Deterministic artifact: a second render is byte-identical.
+        assert_eq!(svg, design_to_animated_svg(&design, 30.0));
+    }
+
+    #[test]
+    fn flattened_paths_expose_world_space_rings() {
+        let mut scene = Scene::new();
+        let id = scene
+            .add_node(
+                NodeKind::Shape(Box::new(ShapeNode {
+                    data: ShapeData::Rect(RectShape {
+                        width: 10.0,
+                        height: 4.0,
+                    }),
+                    style: ShapeStyle::default(),
+                    stitch: StitchParams::default(),
+                    sequencer: Default::default(),
+                })),
+                None,
+            )
+            .unwrap();
+        scene
+            .set_transform(id, Transform::translation(5.0, 5.0))
+            .unwrap();
+
+        let shapes = scene_to_flattened_paths(&scene, 0.1);
+        assert_eq!(shapes.len(), 1);
+        assert_eq!(shapes[0].node_id, id);
+        assert_eq!(shapes[0].subpaths.len(), 1);
+        let ring = &shapes[0].subpaths[0];
+        assert_eq!(ring.first(), ring.last(), "rect ring is closed");
+        assert_eq!(ring[0], Point::new(0.0, 3.0));
+        assert!(ring.contains(&Point::new(10.0, 7.0)));
+    }
+
+    #[test]
+    fn design_report_matches_the_exported_design() {
+        let scene = two_color_scene(5.0);
+        let routing = RoutingOptions::default();
+        let design = scene_to_export_design_with_routing(&scene, 2.0, &routing).unwrap();
+        let report = design_report(&scene, 2.0, &routing, "130x180", 800.0).unwrap();
+
+        assert_eq!(report.quality.stitch_count, design.stitches.len());
+        assert_eq!(report.color_blocks.len(), 2);
+        assert_eq!(
+            report.color_blocks.iter().map(|b| b.normal_count).sum::<usize>(),
+            report.quality.normal_count
+        );
+        assert!(report.hoop.fits);
+        assert!(report.production.runtime_minutes > 0.0);
+        assert!(design_report(&scene, 2.0, &routing, "9x9", 800.0).is_err());
+    }
+
+    #[test]
+    fn overly_dense_fill_triggers_density_warnings() {
+        fn fill_scene(density: f64) -> Scene {
+            let mut scene = Scene::new();
+            scene
+                .add_node(
+                    NodeKind::Shape(Box::new(ShapeNode {
+                        data: ShapeData::Rect(RectShape {
+                            width: 5.0,
+                            height: 5.0,
+                        }),
+                        style: ShapeStyle::default(),
+                        stitch: StitchParams {
+                            stitch_type: StitchType::Tatami,
+                            density,
+                            ..StitchParams::default()
+                        },
+                        sequencer: Default::default(),
+                    })),
+                    None,
+                )
+                .unwrap();
+            scene
+        }
+        let dense = scene_density_warnings(&fill_scene(0.05), 0.5, None).unwrap();
+        assert!(!dense.is_empty());
+        let warning = &dense[0];
+        assert!(warning.stitches_per_mm2 > warning.limit);
+
+        let normal = scene_density_warnings(&fill_scene(0.4), 2.0, None).unwrap();
+        assert!(normal.is_empty(), "unexpected warnings: {normal:?}");
+    }
+
+    #[test]
+    fn scale_invariant_density_keeps_coverage_constant() {
+        fn filled_rect(scale: f64, density_follows_scale: bool) -> Scene {
+            let mut scene = Scene::new();
+            let id = scene
+                .add_node(
+                    NodeKind::Shape(Box::new(ShapeNode {
+                        data: ShapeData::Rect(RectShape {
+                            width: 10.0,
+                            height: 10.0,
+                        }),
+                        style: ShapeStyle::default(),
+                        stitch: StitchParams {
+                            stitch_type: StitchType::Tatami,
+                            density: 0.5,
+                            density_follows_scale,
+                            ..StitchParams::default()
+                        },
+                        sequencer: Default::default(),
+                    })),
+                    None,
+                )
+                .unwrap();
+            scene.set_transform(id, Transform::scale(scale, scale)).unwrap();
+            scene
+        }
+        fn coverage(scene: &Scene, area: f64) -> f64 {
+            let design = scene_to_export_design(scene, 2.0).unwrap();
+            design.normal_stitch_count() as f64 / area
+        }
+        let base = coverage(&filled_rect(1.0, true), 100.0);
+        let scaled = coverage(&filled_rect(2.0, true), 400.0);
+        assert!(
+            (scaled / base - 1.0).abs() < 0.25,
+            "coverage drifted: {base} vs {scaled}"
+        );
+        // Without the flag the spacing follows the scale and coverage drops.
+        let local = coverage(&filled_rect(2.0, false), 400.0);
+        assert!(local < base * 0.7, "expected coarser fill, got {local} vs {base}");
+    }
+
+    #[test]
+    fn open_fill_path_is_auto_closed_with_a_warning() {
+        fn tatami_path_scene(path: crate::path::VectorPath) -> Scene {
+            let mut scene = Scene::new();
+            scene
+                .add_node(
+                    NodeKind::Shape(Box::new(ShapeNode {
+                        data: ShapeData::Path(path),
+                        style: ShapeStyle::default(),
+                        stitch: StitchParams {
+                            stitch_type: StitchType::Tatami,
+                            ..StitchParams::default()
+                        },
+                        sequencer: Default::default(),
+                    })),
+                    None,
+                )
+                .unwrap();
+            scene
+        }
+        let triangle = [
+            Point::new(0.0, 0.0),
+            Point::new(20.0, 0.0),
+            Point::new(10.0, 15.0),
+        ];
+        // An open triangle: three vertices, no closing segment.
+        let open = tatami_path_scene(crate::path::VectorPath::from_polyline(&triangle));
+        let (design, warnings) =
+            scene_to_export_design_with_warnings(&open, 2.0, &RoutingOptions::default()).unwrap();
+        assert!(design.normal_stitch_count() > 20, "expected fill stitches");
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].message.contains("auto-closed"));
+
+        // The same triangle explicitly closed fills without complaint.
+        let closed = tatami_path_scene(crate::path::VectorPath::from_polygon(&triangle));
+        let (_, warnings) =
+            scene_to_export_design_with_warnings(&closed, 2.0, &RoutingOptions::default()).unwrap();
+        assert!(warnings.is_empty(), "unexpected warnings: {warnings:?}");
+    }
+
+    #[test]
+    fn start_near_leads_with_the_closest_block() {
+        let scene = two_color_scene(20.0);
+        let default_design = scene_to_export_design(&scene, 2.0).unwrap();
+        assert_eq!(default_design.colors[0], Color::rgb(255, 0, 0));
+
+        // An origin by the far (blue) rect makes it stitch first.
+        let routing = RoutingOptions {
+            start_near: Some(Point::new(40.0, 0.0)),
+            ..RoutingOptions::default()
+        };
+        let design = scene_to_export_design_with_routing(&scene, 2.0, &routing).unwrap();
+        assert_eq!(design.colors[0], Color::rgb(0, 0, 255));
+    }
+
+    #[test]
+    fn manual_trim_lands_at_its_stitch_index() {
+        let mut scene = Scene::new();
+        let id = scene
+            .add_node(
+                NodeKind::Shape(Box::new(ShapeNode {
+                    data: ShapeData::Path(crate::path::VectorPath::from_polyline(&[
+                        Point::new(0.0, 0.0),
+                        Point::new(10.0, 0.0),
+                    ])),
+                    style: ShapeStyle::default(),
+                    stitch: StitchParams::default(),
+                    sequencer: Default::default(),
+                })),
+                None,
+            )
+            .unwrap();
+        scene
+            .insert_stitch_command(id, 3, crate::stitch::ManualCommandKind::Trim)
+            .unwrap();
+
+        // A 10 mm line at 2 mm spacing is a six-stitch block; the program is
+        // the entry jump, three stitches, the trim, the rest, and the end.
+        let design = scene_to_export_design(&scene, 2.0).unwrap();
+        assert_eq!(design.stitches[4].kind, ExportStitchType::Trim);
+        assert_eq!(design.stitches[4].x, 4.0);
+        assert_eq!(
+            design
+                .stitches
+                .iter()
+                .filter(|s| s.kind == ExportStitchType::Normal)
+                .count(),
+            6
+        );
+    }
+
+    #[test]
+    fn tiny_tatami_fill_falls_back_below_min_area() {
+        fn fill_scene(size: f64) -> Scene {
+            let mut scene = Scene::new();
+            scene
+                .add_node(
+                    NodeKind::Shape(Box::new(ShapeNode {
+                        data: ShapeData::Rect(RectShape {
+                            width: size,
+                            height: size,
+                        }),
+                        style: ShapeStyle::default(),
+                        stitch: StitchParams {
+                            stitch_type: StitchType::Tatami,
+                            min_fill_area_mm2: 4.0,
+                            ..StitchParams::default()
+                        },
+                        sequencer: Default::default(),
+                    })),
+                    None,
+                )
+                .unwrap();
+            scene
+        }
+        fn row_count(design: &ExportDesign) -> usize {
+            let rows: std::collections::BTreeSet<i64> = design
+                .stitches
+                .iter()
+                .filter(|s| s.kind == ExportStitchType::Normal)
+                .map(|s| (s.y * 1000.0).round() as i64)
+                .collect();
+            rows.len()
+        }
+        // 1 mm² is under the 4 mm² threshold: a satin bar zigzagging
+        // between two rails, not tatami rows.
+        let tiny = scene_to_export_design(&fill_scene(1.0), 2.0).unwrap();
+        assert_eq!(row_count(&tiny), 2);
+        // 400 mm² is over the threshold and still tatami-fills.
+        let big = scene_to_export_design(&fill_scene(20.0), 2.0).unwrap();
+        assert!(row_count(&big) > 10);
+    }
+
+    #[test]
+    fn streamed_batches_concatenate_to_the_full_design() {
+        let design = scene_to_export_design(&two_color_scene(5.0), 2.0).unwrap();
+        let mut streamer = DesignStreamer::new(design.clone());
+        let mut collected = Vec::new();
+        while let Some(batch) = streamer.next_batch(7) {
+            assert!(!batch.is_empty() && batch.len() <= 7);
+            collected.extend(batch);
+        }
+        assert_eq!(collected, design.stitches);
+        assert!(streamer.next_batch(7).is_none());
+    }
+}
@@ -0,0 +1,71 @@
+//! Fabric-dependent stitch compensation heuristics.
+//!
+//! Satin columns sink into the fabric and pull inward along the stitch
+//! direction; how much depends on the substrate. These are starting-point
+//! values in line with common digitizing guidance — per-object manual
+//! override always wins.
+
+use serde::{Deserialize, Serialize};
+
+/// The substrate a design is stitched on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Fabric {
+    /// Woven, stable fabrics (twill, canvas, denim).
+    #[default]
+    Stable,
+    /// Standard knits (jersey, piqué).
+    Knit,
+    /// High-stretch knits (lycra, performance wear).
+    Stretchy,
+    /// Looped pile (towels); stitches also sink into the pile.
+    Terry,
+    /// Leather and other non-stretch films; minimal pull, needle-sensitive.
+    Leather,
+}
+
+impl Fabric {
+    /// Pull compensation as a fraction of satin width, and a flat base (mm).
+    fn factors(self) -> (f64, f64) {
+        match self {
+            Fabric::Stable => (0.03, 0.10),
+            Fabric::Knit => (0.07, 0.15),
+            Fabric::Stretchy => (0.12, 0.20),
+            Fabric::Terry => (0.10, 0.25),
+            Fabric::Leather => (0.02, 0.05),
+        }
+    }
+}
+
+/// Recommended `(pull_mm, push_mm)` compensation for a satin column of the
+/// given width. Pull (added to each rail along the stitch direction) scales
+/// with width; push (trimmed from the column ends) is roughly half of it.
+/// Both are clamped so extreme widths do not produce absurd values.
+pub fn recommend_compensation(fabric: Fabric, satin_width_mm: f64) -> (f64, f64) {
+    let width = satin_width_mm.max(0.0);
+    let (fraction, base) = fabric.factors();
+    let pull = (width * fraction + base).min(1.5);
+    (pull, pull * 0.5)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stretchier_fabric_gets_more_compensation() {
+        let (stable, _) = recommend_compensation(Fabric::Stable, 4.0);
+        let (stretchy, _) = recommend_compensation(Fabric::Stretchy, 4.0);
+        assert!(stretchy > stable);
+    }
+
+    #[test]
+    fn compensation_grows_with_width_and_is_clamped() {
+        let (narrow, _) = recommend_compensation(Fabric::Knit, 2.0);
+        let (wide, _) = recommend_compensation(Fabric::Knit, 8.0);
+        assert!(wide > narrow);
+        let (huge, push) = recommend_compensation(Fabric::Stretchy, 500.0);
+        assert_eq!(huge, 1.5);
+        assert_eq!(push, 0.75);
+    }
+}
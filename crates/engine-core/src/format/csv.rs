@@ -0,0 +1,151 @@
+//! Human-readable stitch interchange: one `x,y,type` line per stitch.
+//!
+//! Not a machine format — this exists for golden-test fixtures, scripted
+//! imports, and eyeballing a stitch program in a diff. Metadata the line
+//! grammar cannot carry (design name, thread change sequence) rides in
+//! `#`-prefixed header comments so export → import is lossless; the
+//! coordinate system is always design space (mm, Y-down).
+
+use crate::export_pipeline::{
+    CoordinateSystem, ExportDesign, ExportStitch, ExportStitchType, Quantization,
+};
+use crate::shapes::Color;
+
+fn type_name(kind: ExportStitchType) -> &'static str {
+    match kind {
+        ExportStitchType::Normal => "normal",
+        ExportStitchType::Jump => "jump",
+        ExportStitchType::Trim => "trim",
+        ExportStitchType::ColorChange => "color",
+        ExportStitchType::Stop => "stop",
+        ExportStitchType::End => "end",
+    }
+}
+
+fn parse_type(name: &str) -> Result<ExportStitchType, String> {
+    match name {
+        "normal" => Ok(ExportStitchType::Normal),
+        "jump" => Ok(ExportStitchType::Jump),
+        "trim" => Ok(ExportStitchType::Trim),
+        "color" => Ok(ExportStitchType::ColorChange),
+        "stop" => Ok(ExportStitchType::Stop),
+        "end" => Ok(ExportStitchType::End),
+        other => Err(format!("unknown stitch type '{other}'")),
+    }
+}
+
+/// Serialize a design to the CSV fixture format. Coordinates use Rust's
+/// shortest round-trip float formatting, so re-importing reproduces the
+/// exact values.
+pub fn export_stitch_csv(design: &ExportDesign) -> String {
+    let mut out = String::new();
+    out.push_str(&format!("# name: {}\n", design.name));
+    for c in &design.colors {
+        out.push_str(&format!("# color: #{:02x}{:02x}{:02x}\n", c.r, c.g, c.b));
+    }
+    for s in &design.stitches {
+        out.push_str(&format!("{},{},{}\n", s.x, s.y, type_name(s.kind)));
+    }
+    out
+}
+
+/// Parse the CSV fixture format back into an [`ExportDesign`]. Blank lines
+/// are skipped; unknown header comments are ignored so fixtures can carry
+/// their own annotations. A file with no `# color:` headers gets a single
+/// black entry, matching the empty-scene placeholder convention.
+pub fn import_stitch_csv(text: &str) -> Result<ExportDesign, String> {
+    let mut name = "design".to_string();
+    let mut colors: Vec<Color> = Vec::new();
+    let mut stitches: Vec<ExportStitch> = Vec::new();
+
+    for (line_no, raw) in text.lines().enumerate() {
+        let line = raw.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(comment) = line.strip_prefix('#') {
+            let comment = comment.trim();
+            if let Some(v) = comment.strip_prefix("name:") {
+                name = v.trim().to_string();
+            } else if let Some(v) = comment.strip_prefix("color:") {
+                let hex = v.trim().trim_start_matches('#');
+                if hex.len() != 6 {
+                    return Err(format!("line {}: bad color '{v}'", line_no + 1));
+                }
+                let parse = |s: &str| {
+                    u8::from_str_radix(s, 16)
+                        .map_err(|_| format!("line {}: bad color '{v}'", line_no + 1))
+                };
+                colors.push(Color::rgb(
+                    parse(&hex[0..2])?,
+                    parse(&hex[2..4])?,
+                    parse(&hex[4..6])?,
+                ));
+            }
+            continue;
+        }
+        let mut fields = line.split(',');
+        let (Some(x), Some(y), Some(kind)) = (fields.next(), fields.next(), fields.next()) else {
+            return Err(format!("line {}: expected 'x,y,type'", line_no + 1));
+        };
+        if fields.next().is_some() {
+            return Err(format!("line {}: expected 'x,y,type'", line_no + 1));
+        }
+        let x: f64 = x
+            .trim()
+            .parse()
+            .map_err(|_| format!("line {}: bad x coordinate", line_no + 1))?;
+        let y: f64 = y
+            .trim()
+            .parse()
+            .map_err(|_| format!("line {}: bad y coordinate", line_no + 1))?;
+        stitches.push(ExportStitch::new(x, y, parse_type(kind.trim())?));
+    }
+
+    if colors.is_empty() {
+        colors.push(Color::BLACK);
+    }
+    Ok(ExportDesign {
+        name,
+        stitches,
+        colors,
+        coordinate_system: CoordinateSystem::YDown,
+        quantization: Quantization::default(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_round_trips_exactly() {
+        let design = ExportDesign {
+            name: "fixture".to_string(),
+            stitches: vec![
+                ExportStitch::new(0.0, 0.0, ExportStitchType::Jump),
+                ExportStitch::new(1.25, -3.7, ExportStitchType::Normal),
+                ExportStitch::new(10.333333333333334, 4.0, ExportStitchType::Normal),
+                ExportStitch::new(10.333333333333334, 4.0, ExportStitchType::Trim),
+                ExportStitch::new(10.333333333333334, 4.0, ExportStitchType::ColorChange),
+                ExportStitch::new(5.0, 5.0, ExportStitchType::Normal),
+                ExportStitch::new(5.0, 5.0, ExportStitchType::End),
+            ],
+            colors: vec![Color::rgb(255, 0, 0), Color::rgb(0, 0, 255)],
+            coordinate_system: CoordinateSystem::YDown,
+            quantization: Quantization::default(),
+        };
+        let text = export_stitch_csv(&design);
+        let back = import_stitch_csv(&text).expect("re-import succeeds");
+        assert_eq!(back, design);
+    }
+
+    #[test]
+    fn import_reports_the_offending_line() {
+        assert!(import_stitch_csv("1,2,normal\n3,4\n").is_err());
+        let err = import_stitch_csv("1,2,zigzag\n").unwrap_err();
+        assert!(err.contains("zigzag"));
+        let err = import_stitch_csv("# color: #12345\n").unwrap_err();
+        assert!(err.contains("line 1"));
+    }
+}
@@ -0,0 +1,365 @@
+//! DST (Tajima) reader and writer.
+//!
+//! DST is the lowest common denominator: a 512-byte ASCII header followed by
+//! 3-byte ternary-encoded relative stitch records in 0.1 mm units, Y **up**.
+//! The format embeds no thread colors — only the change count — and has no
+//! explicit trim record; trims are emitted as zero-motion jumps, which is
+//! what most machines infer a trim from.
+
+use crate::export_pipeline::{
+    CoordinateSystem, ExportDesign, ExportStitch, ExportStitchType, Quantization,
+};
+use crate::error::EngineError;
+use crate::format::{stitches_in_units, UnitStitch};
+use crate::shapes::Color;
+
+/// DST coordinate units per design-space millimetre.
+pub const DST_UNITS_PER_MM: f64 = 10.0;
+
+/// Largest movement one record can express on each axis.
+pub(crate) const MAX_DELTA: i32 = 121;
+
+/// Encode a design as a DST file.
+pub fn export_dst(design: &ExportDesign) -> Result<Vec<u8>, EngineError> {
+    if design.stitches.is_empty() {
+        return Err(EngineError::InvalidInput(
+            "cannot export an empty design".to_string(),
+        ));
+    }
+    let stitches = stitches_in_units(design, DST_UNITS_PER_MM, CoordinateSystem::YUp);
+    let records = encode_records(&stitches);
+    let mut out = header(design, &stitches, records.len());
+    for r in &records {
+        out.extend_from_slice(r);
+    }
+    Ok(out)
+}
+
+/// The 512-byte ASCII header: `XX:value\r` fields padded with spaces.
+fn header(design: &ExportDesign, stitches: &[UnitStitch], record_count: usize) -> Vec<u8> {
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (i32::MAX, i32::MAX, i32::MIN, i32::MIN);
+    for s in stitches {
+        min_x = min_x.min(s.x);
+        min_y = min_y.min(s.y);
+        max_x = max_x.max(s.x);
+        max_y = max_y.max(s.y);
+    }
+    // DST displays at most 8 characters; machines expect plain uppercase
+    // ASCII in the LA field.
+    let name: String = design
+        .name
+        .chars()
+        .filter(|c| c.is_ascii() && *c != '\r')
+        .map(|c| c.to_ascii_uppercase())
+        .take(8)
+        .collect();
+    let color_changes = design.colors.len().saturating_sub(1);
+    let mut text = format!("LA:{name:<16}\r");
+    text.push_str(&format!("ST:{record_count:7}\r"));
+    text.push_str(&format!("CO:{color_changes:3}\r"));
+    text.push_str(&format!("+X:{:5}\r", max_x.max(0)));
+    text.push_str(&format!("-X:{:5}\r", (-min_x).max(0)));
+    text.push_str(&format!("+Y:{:5}\r", max_y.max(0)));
+    text.push_str(&format!("-Y:{:5}\r", (-min_y).max(0)));
+    // End-point deltas and multi-design offsets: we always end in place.
+    text.push_str("AX:+    0\r");
+    text.push_str("AY:+    0\r");
+    text.push_str("MX:+    0\r");
+    text.push_str("MY:+    0\r");
+    text.push_str("PD:******\r");
+    let mut out = text.into_bytes();
+    out.push(0x1a); // Header terminator.
+    out.resize(512, b' ');
+    out
+}
+
+/// Turn unit stitches into 3-byte records, splitting moves longer than a
+/// record can carry into intermediate jumps.
+fn encode_records(stitches: &[UnitStitch]) -> Vec<[u8; 3]> {
+    let mut records = Vec::new();
+    let mut cursor = (0i32, 0i32);
+    let mut first = true;
+    for s in stitches {
+        match s.kind {
+            ExportStitchType::Normal | ExportStitchType::Jump => {
+                // The machine starts at the first stitch; no leading travel.
+                if first {
+                    cursor = (s.x, s.y);
+                    first = false;
+                    if s.kind == ExportStitchType::Jump {
+                        continue;
+                    }
+                }
+                let mut dx = s.x - cursor.0;
+                let mut dy = s.y - cursor.1;
+                while dx.abs() > MAX_DELTA || dy.abs() > MAX_DELTA {
+                    let step_x = dx.clamp(-MAX_DELTA, MAX_DELTA);
+                    let step_y = dy.clamp(-MAX_DELTA, MAX_DELTA);
+                    records.push(encode_record(step_x, step_y, true, false));
+                    dx -= step_x;
+                    dy -= step_y;
+                }
+                records.push(encode_record(
+                    dx,
+                    dy,
+                    s.kind == ExportStitchType::Jump,
+                    false,
+                ));
+                cursor = (s.x, s.y);
+            }
+            // No trim record exists; a zero-motion jump is the convention.
+            ExportStitchType::Trim => records.push(encode_record(0, 0, true, false)),
+            ExportStitchType::ColorChange => {
+                records.push(encode_record(0, 0, false, true));
+            }
+            // No stop record exists (see the capability table); dropped
+            // rather than downgraded to a spurious color change.
+            ExportStitchType::Stop => {}
+            ExportStitchType::End => records.push([0x00, 0x00, 0xf3]),
+        }
+    }
+    records
+}
+
+/// Ternary encoding: each axis decomposes into ±81, ±27, ±9, ±3, ±1 with one
+/// flag bit per term. Bits 0–1 of the third byte are always set; bit 7 marks
+/// a jump and bits 6–7 together a color change.
+fn encode_record(mut dx: i32, mut dy: i32, jump: bool, color_change: bool) -> [u8; 3] {
+    let mut b = [0u8, 0u8, 0b0000_0011];
+    let mut term = |v: &mut i32, magnitude: i32, byte: usize, pos_bit: u8, neg_bit: u8| {
+        let threshold = magnitude / 2 + 1;
+        if *v >= threshold {
+            b[byte] |= 1 << pos_bit;
+            *v -= magnitude;
+        } else if *v <= -threshold {
+            b[byte] |= 1 << neg_bit;
+            *v += magnitude;
+        }
+    };
+    term(&mut dx, 81, 2, 2, 3);
+    term(&mut dy, 81, 2, 5, 4);
+    term(&mut dx, 27, 1, 2, 3);
+    term(&mut dy, 27, 1, 5, 4);
+    term(&mut dx, 9, 0, 2, 3);
+    term(&mut dy, 9, 0, 5, 4);
+    term(&mut dx, 3, 1, 0, 1);
+    term(&mut dy, 3, 1, 7, 6);
+    term(&mut dx, 1, 0, 0, 1);
+    term(&mut dy, 1, 0, 7, 6);
+    debug_assert!(dx == 0 && dy == 0, "delta exceeded record range");
+    if color_change {
+        b[2] |= 0b1100_0000;
+    } else if jump {
+        b[2] |= 0b1000_0000;
+    }
+    b
+}
+
+/// Decode a DST file back into an [`ExportDesign`].
+///
+/// DST positions are relative, so the imported design is anchored at the
+/// first stitch; trims come back as the zero-motion jumps they were written
+/// as. The format carries no thread colors, so the change sequence is
+/// filled with black placeholders for the caller to re-color.
+pub fn import_dst(bytes: &[u8]) -> Result<ExportDesign, String> {
+    if bytes.len() < 512 {
+        return Err("file too short for a DST header".to_string());
+    }
+    let header = String::from_utf8_lossy(&bytes[..512]);
+    let name = header
+        .split('\r')
+        .find_map(|field| field.trim_start().strip_prefix("LA:"))
+        .map(|v| v.trim().to_string())
+        .unwrap_or_else(|| "design".to_string());
+
+    let mut stitches = Vec::new();
+    let (mut x, mut y) = (0i32, 0i32);
+    let mut color_changes = 0usize;
+    for (i, chunk) in bytes[512..].chunks(3).enumerate() {
+        let record: [u8; 3] = chunk
+            .try_into()
+            .map_err(|_| format!("record {i}: truncated"))?;
+        if record == [0x00, 0x00, 0xf3] {
+            stitches.push(unit_stitch(x, y, ExportStitchType::End));
+            break;
+        }
+        if record[2] & 0b0000_0011 != 0b0000_0011 {
+            return Err(format!("record {i}: not a stitch record"));
+        }
+        let (dx, dy) = decode_record(record);
+        x += dx;
+        y += dy;
+        let kind = if record[2] & 0b1100_0000 == 0b1100_0000 {
+            color_changes += 1;
+            ExportStitchType::ColorChange
+        } else if record[2] & 0b1000_0000 != 0 {
+            if dx == 0 && dy == 0 {
+                ExportStitchType::Trim
+            } else {
+                ExportStitchType::Jump
+            }
+        } else {
+            ExportStitchType::Normal
+        };
+        stitches.push(unit_stitch(x, y, kind));
+    }
+
+    Ok(ExportDesign {
+        name,
+        stitches,
+        colors: vec![Color::BLACK; color_changes + 1],
+        coordinate_system: CoordinateSystem::YDown,
+        quantization: Quantization::default(),
+    })
+}
+
+/// A unit-space position back in design space: mm, Y flipped back down.
+fn unit_stitch(x: i32, y: i32, kind: ExportStitchType) -> ExportStitch {
+    ExportStitch::new(x as f64 / DST_UNITS_PER_MM, -y as f64 / DST_UNITS_PER_MM, kind)
+}
+
+/// Inverse of `encode_record`.
+fn decode_record(r: [u8; 3]) -> (i32, i32) {
+    let bit = |byte: u8, i: u8| ((byte >> i) & 1) as i32;
+    let x = 81 * (bit(r[2], 2) - bit(r[2], 3))
+        + 27 * (bit(r[1], 2) - bit(r[1], 3))
+        + 9 * (bit(r[0], 2) - bit(r[0], 3))
+        + 3 * (bit(r[1], 0) - bit(r[1], 1))
+        + (bit(r[0], 0) - bit(r[0], 1));
+    let y = 81 * (bit(r[2], 5) - bit(r[2], 4))
+        + 27 * (bit(r[1], 5) - bit(r[1], 4))
+        + 9 * (bit(r[0], 5) - bit(r[0], 4))
+        + 3 * (bit(r[1], 7) - bit(r[1], 6))
+        + (bit(r[0], 7) - bit(r[0], 6));
+    (x, y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_round_trip_across_the_range() {
+        for v in [-121, -82, -81, -40, -1, 0, 1, 40, 81, 121] {
+            for w in [-121, -13, 0, 13, 121] {
+                assert_eq!(decode_record(encode_record(v, w, false, false)), (v, w));
+            }
+        }
+    }
+
+    #[test]
+    fn lower_on_screen_stitches_downward_in_dst() {
+        // Second stitch is lower on screen (larger design-space Y); DST is
+        // Y-up, so the encoded movement must be negative.
+        let design = ExportDesign {
+            name: "flip".to_string(),
+            stitches: vec![
+                ExportStitch::new(0.0, 0.0, ExportStitchType::Normal),
+                ExportStitch::new(0.0, 5.0, ExportStitchType::Normal),
+            ],
+            colors: vec![Color::BLACK],
+            coordinate_system: CoordinateSystem::YDown,
+            quantization: Quantization::default(),
+        };
+        let bytes = export_dst(&design).unwrap();
+        let record: [u8; 3] = bytes[512 + 3..512 + 6].try_into().unwrap();
+        assert_eq!(decode_record(record), (0, -50));
+    }
+
+    #[test]
+    fn header_fields_and_terminator() {
+        let design = ExportDesign {
+            name: "tester".to_string(),
+            stitches: vec![
+                ExportStitch::new(0.0, 0.0, ExportStitchType::Normal),
+                ExportStitch::new(2.0, 1.0, ExportStitchType::Normal),
+                ExportStitch::new(2.0, 1.0, ExportStitchType::End),
+            ],
+            colors: vec![Color::BLACK],
+            coordinate_system: CoordinateSystem::YDown,
+            quantization: Quantization::default(),
+        };
+        let bytes = export_dst(&design).unwrap();
+        assert_eq!(bytes.len(), 512 + 3 * 3);
+        let header = String::from_utf8_lossy(&bytes[..512]);
+        assert!(header.starts_with("LA:TESTER"));
+        assert!(header.contains("ST:      3"));
+        assert!(header.contains("CO:  0"));
+        assert_eq!(bytes[512 + 6..512 + 9], [0x00, 0x00, 0xf3]);
+    }
+
+    #[test]
+    fn header_name_is_truncated_and_uppercased() {
+        let design = ExportDesign {
+            name: "rosebud garden".to_string(),
+            stitches: vec![
+                ExportStitch::new(0.0, 0.0, ExportStitchType::Normal),
+                ExportStitch::new(1.0, 0.0, ExportStitchType::Normal),
+            ],
+            colors: vec![Color::BLACK],
+            coordinate_system: CoordinateSystem::YDown,
+            quantization: Quantization::default(),
+        };
+        let bytes = export_dst(&design).unwrap();
+        let header = String::from_utf8_lossy(&bytes[..512]);
+        assert!(header.starts_with("LA:ROSEBUD "));
+        assert!(!header.contains("GARDEN"));
+    }
+
+    #[test]
+    fn long_moves_split_into_jumps() {
+        let design = ExportDesign {
+            name: "long".to_string(),
+            stitches: vec![
+                ExportStitch::new(0.0, 0.0, ExportStitchType::Normal),
+                ExportStitch::new(30.0, 0.0, ExportStitchType::Normal),
+            ],
+            colors: vec![Color::BLACK],
+            coordinate_system: CoordinateSystem::YDown,
+            quantization: Quantization::default(),
+        };
+        let bytes = export_dst(&design).unwrap();
+        let records: Vec<[u8; 3]> = bytes[512..].chunks(3).map(|c| c.try_into().unwrap()).collect();
+        // 300 units needs two intermediate jumps plus the final stitch.
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[1][2] & 0b1100_0000, 0b1000_0000);
+        let total: i32 = records.iter().map(|r| decode_record(*r).0).sum();
+        assert_eq!(total, 300);
+    }
+
+    #[test]
+    fn export_then_import_is_stable() {
+        // Anchored at the origin, 0.1 mm-aligned coordinates: the parts of a
+        // design DST can actually carry must survive a round trip exactly.
+        let design = ExportDesign {
+            name: "RT".to_string(),
+            stitches: vec![
+                ExportStitch::new(0.0, 0.0, ExportStitchType::Normal),
+                ExportStitch::new(2.0, 1.5, ExportStitchType::Normal),
+                ExportStitch::new(2.0, 1.5, ExportStitchType::Trim),
+                ExportStitch::new(2.0, 1.5, ExportStitchType::ColorChange),
+                ExportStitch::new(-3.1, 4.0, ExportStitchType::Jump),
+                ExportStitch::new(-3.0, -2.5, ExportStitchType::Normal),
+                ExportStitch::new(-3.0, -2.5, ExportStitchType::End),
+            ],
+            colors: vec![Color::rgb(255, 0, 0), Color::rgb(0, 0, 255)],
+            coordinate_system: CoordinateSystem::YDown,
+            quantization: Quantization::default(),
+        };
+        let back = import_dst(&export_dst(&design).unwrap()).expect("import succeeds");
+        assert_eq!(back.name, "RT");
+        assert_eq!(back.stitches, design.stitches);
+        // Colors do not survive: DST records only the change count.
+        assert_eq!(back.colors.len(), 2);
+        let twice = import_dst(&export_dst(&back).unwrap()).unwrap();
+        assert_eq!(twice.stitches, back.stitches);
+    }
+
+    #[test]
+    fn import_rejects_garbage() {
+        assert!(import_dst(&[0u8; 100]).is_err());
+        let mut bytes = vec![b' '; 512];
+        bytes.extend_from_slice(&[0x00, 0x00, 0x00]); // Low bits unset.
+        assert!(import_dst(&bytes).is_err());
+    }
+}
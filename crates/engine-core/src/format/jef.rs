@@ -0,0 +1,264 @@
+//! JEF (Janome) writer.
+//!
+//! A 116-byte little-endian header (stitch offset, date, color and record
+//! counts, hoop code, extents/margin blocks), a thread index table, then
+//! 2-byte relative stitch records in 0.1 mm units, Y **up**. Control
+//! records escape through `0x80`: `0x80 0x01` is a color change,
+//! `0x80 0x02 dx dy` a needle-up move, `0x80 0x10` the end record.
+
+use crate::error::EngineError;
+use crate::export_pipeline::{CoordinateSystem, ExportDesign, ExportStitchType};
+use crate::format::{put_i32_le, put_u32_le, stitches_in_units, UnitStitch};
+use crate::shapes::Color;
+
+/// JEF coordinate units per design-space millimetre.
+pub const JEF_UNITS_PER_MM: f64 = 10.0;
+
+/// Largest movement one record can express on each axis (`-0x80` is the
+/// escape byte, so the negative range loses one step).
+pub(crate) const MAX_DELTA: i32 = 127;
+
+const HEADER_SIZE: usize = 116;
+
+/// Fixed timestamp written into the header's date field. The engine is
+/// deterministic — identical input must produce identical bytes — so the
+/// field does not track wall-clock time.
+const HEADER_DATE: &[u8; 14] = b"20200101000000";
+
+/// The Janome thread chart subset we match against, as `(name, rgb)`. The
+/// machine looks threads up by 1-based index into its full chart; this
+/// table keeps the commonly used colors at their catalog positions.
+pub(crate) const JANOME_THREADS: &[(&str, [u8; 3])] = &[
+    ("Black", [0x00, 0x00, 0x00]),
+    ("White", [0xff, 0xff, 0xff]),
+    ("Yellow", [0xff, 0xff, 0x17]),
+    ("Orange", [0xff, 0xa0, 0x17]),
+    ("Olive Green", [0x5c, 0x66, 0x0a]),
+    ("Green", [0x20, 0x81, 0x20]),
+    ("Sky Blue", [0x42, 0xc7, 0xdb]),
+    ("Purple", [0x85, 0x4e, 0xa5]),
+    ("Pink", [0xff, 0xb4, 0xc8]),
+    ("Red", [0xe1, 0x10, 0x17]),
+    ("Brown", [0x91, 0x56, 0x2b]),
+    ("Blue", [0x10, 0x38, 0xa0]),
+    ("Gold", [0xe3, 0xb0, 0x4c]),
+    ("Dark Brown", [0x4c, 0x28, 0x15]),
+    ("Pale Violet", [0xb3, 0x9f, 0xcf]),
+    ("Pale Yellow", [0xf5, 0xec, 0xa5]),
+    ("Pale Pink", [0xf8, 0xd5, 0xd5]),
+    ("Peach", [0xf5, 0xb0, 0x7a]),
+    ("Beige", [0xd1, 0xbd, 0x95]),
+    ("Wine Red", [0x78, 0x10, 0x2e]),
+    ("Pale Sky", [0xaf, 0xc8, 0xe1]),
+    ("Yellow Green", [0x9e, 0xc8, 0x31]),
+    ("Silver Gray", [0xc8, 0xc8, 0xc8]),
+    ("Gray", [0x80, 0x80, 0x80]),
+    ("Grass Green", [0x36, 0x8a, 0x22]),
+    ("Navy", [0x10, 0x18, 0x50]),
+    ("Light Blue", [0x62, 0x96, 0xd2]),
+    ("Deep Green", [0x0c, 0x50, 0x26]),
+];
+
+/// Nearest 1-based Janome chart index for a design color (RGB distance,
+/// like the Brother lookup — machine charts are coarse).
+pub fn nearest_janome_index(color: Color) -> u32 {
+    JANOME_THREADS
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, (_, rgb))| {
+            let dr = rgb[0] as i32 - color.r as i32;
+            let dg = rgb[1] as i32 - color.g as i32;
+            let db = rgb[2] as i32 - color.b as i32;
+            dr * dr + dg * dg + db * db
+        })
+        .map(|(i, _)| i as u32 + 1)
+        .expect("catalog is non-empty")
+}
+
+/// Encode a design as a JEF file.
+pub fn export_jef(design: &ExportDesign) -> Result<Vec<u8>, EngineError> {
+    if design.stitches.is_empty() {
+        return Err(EngineError::InvalidInput(
+            "cannot export an empty design".to_string(),
+        ));
+    }
+    let stitches = stitches_in_units(design, JEF_UNITS_PER_MM, CoordinateSystem::YUp);
+    let data = encode_records(&stitches);
+    let record_count = data.len() / 2;
+
+    let mut out = Vec::with_capacity(HEADER_SIZE + design.colors.len() * 4 + data.len());
+    put_u32_le(&mut out, (HEADER_SIZE + design.colors.len() * 4) as u32);
+    put_u32_le(&mut out, 0x14); // Format revision.
+    out.extend_from_slice(HEADER_DATE);
+    out.extend_from_slice(&[0x00, 0x00]);
+    put_u32_le(&mut out, design.colors.len() as u32);
+    put_u32_le(&mut out, record_count as u32);
+
+    let (min_x, min_y, max_x, max_y) = extents(&stitches);
+    put_u32_le(&mut out, hoop_code(max_x - min_x, max_y - min_y));
+    // Design extents from the center, then margin blocks for each standard
+    // hoop (`-1` throughout when the design does not fit that hoop).
+    for v in [-min_x, -min_y, max_x, max_y] {
+        put_i32_le(&mut out, v);
+    }
+    for (w, h) in [(110, 110), (50, 50), (140, 200), (200, 200)] {
+        let (hw, hh) = (w * 10 / 2, h * 10 / 2);
+        let margins = [hw + min_x, hh + min_y, hw - max_x, hh - max_y];
+        let fits = margins.iter().all(|m| *m >= 0);
+        for m in margins {
+            put_i32_le(&mut out, if fits { m } else { -1 });
+        }
+    }
+    debug_assert_eq!(out.len(), HEADER_SIZE);
+
+    for c in &design.colors {
+        put_u32_le(&mut out, nearest_janome_index(*c));
+    }
+    out.extend_from_slice(&data);
+    Ok(out)
+}
+
+fn extents(stitches: &[UnitStitch]) -> (i32, i32, i32, i32) {
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (0, 0, 0, 0);
+    for s in stitches {
+        min_x = min_x.min(s.x);
+        min_y = min_y.min(s.y);
+        max_x = max_x.max(s.x);
+        max_y = max_y.max(s.y);
+    }
+    (min_x, min_y, max_x, max_y)
+}
+
+/// Smallest standard Janome hoop the design fits, as the header code.
+fn hoop_code(width_units: i32, height_units: i32) -> u32 {
+    let fits = |w: i32, h: i32| width_units <= w * 10 && height_units <= h * 10;
+    if fits(50, 50) {
+        1
+    } else if fits(110, 110) {
+        0
+    } else if fits(126, 110) {
+        3
+    } else if fits(140, 200) {
+        2
+    } else {
+        4 // 200×200, and the fallback for anything larger.
+    }
+}
+
+/// Turn unit stitches into 2-byte records, splitting moves longer than a
+/// record can carry into intermediate needle-up moves.
+fn encode_records(stitches: &[UnitStitch]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut cursor = (0i32, 0i32);
+    let mut first = true;
+    for s in stitches {
+        match s.kind {
+            ExportStitchType::Normal | ExportStitchType::Jump => {
+                // The machine starts at the first stitch; no leading travel.
+                if first {
+                    cursor = (s.x, s.y);
+                    first = false;
+                    if s.kind == ExportStitchType::Jump {
+                        continue;
+                    }
+                }
+                let mut dx = s.x - cursor.0;
+                let mut dy = s.y - cursor.1;
+                while dx.abs() > MAX_DELTA || dy.abs() > MAX_DELTA {
+                    let step_x = dx.clamp(-MAX_DELTA, MAX_DELTA);
+                    let step_y = dy.clamp(-MAX_DELTA, MAX_DELTA);
+                    out.extend_from_slice(&[0x80, 0x02, step_x as i8 as u8, step_y as i8 as u8]);
+                    dx -= step_x;
+                    dy -= step_y;
+                }
+                if s.kind == ExportStitchType::Jump {
+                    out.extend_from_slice(&[0x80, 0x02]);
+                }
+                out.extend_from_slice(&[dx as i8 as u8, dy as i8 as u8]);
+                cursor = (s.x, s.y);
+            }
+            // No dedicated trim record: a zero-motion needle-up move is the
+            // convention, as with DST.
+            ExportStitchType::Trim => out.extend_from_slice(&[0x80, 0x02, 0x00, 0x00]),
+            // A stop on Janome is a color change to the same thread, so
+            // both map to the same escape.
+            ExportStitchType::ColorChange | ExportStitchType::Stop => {
+                out.extend_from_slice(&[0x80, 0x01]);
+            }
+            ExportStitchType::End => out.extend_from_slice(&[0x80, 0x10]),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export_pipeline::{ExportStitch, Quantization};
+
+    fn two_color_design() -> ExportDesign {
+        ExportDesign {
+            name: "jef".to_string(),
+            stitches: vec![
+                ExportStitch::new(0.0, 0.0, ExportStitchType::Normal),
+                ExportStitch::new(5.0, 0.0, ExportStitchType::Normal),
+                ExportStitch::new(5.0, 0.0, ExportStitchType::ColorChange),
+                ExportStitch::new(5.0, 5.0, ExportStitchType::Normal),
+                ExportStitch::new(5.0, 5.0, ExportStitchType::End),
+            ],
+            colors: vec![Color::rgb(255, 0, 0), Color::rgb(0, 0, 255)],
+            coordinate_system: CoordinateSystem::YDown,
+            quantization: Quantization::default(),
+        }
+    }
+
+    #[test]
+    fn header_counts_and_stitch_offset() {
+        let design = two_color_design();
+        let bytes = export_jef(&design).unwrap();
+        let u32_at = |off: usize| u32::from_le_bytes(bytes[off..off + 4].try_into().unwrap());
+        // Stitch data begins after the header and two thread indexes.
+        assert_eq!(u32_at(0x00), (HEADER_SIZE + 8) as u32);
+        assert_eq!(&bytes[0x08..0x16], HEADER_DATE);
+        assert_eq!(u32_at(0x18), 2); // Color count.
+        // Records: stitch, stitch, color change, stitch, end = 5 pairs.
+        assert_eq!(u32_at(0x1c), 5);
+        assert_eq!(u32_at(0x20), 1); // Fits the 50×50 hoop.
+        let offset = u32_at(0x00) as usize;
+        assert_eq!(bytes.len() - offset, 10);
+        // A zero delta sets the first needle position, then 5mm right;
+        // JEF is Y-up so the second normal (lower on screen) moves negative.
+        assert_eq!(&bytes[offset..offset + 2], &[0, 0]);
+        assert_eq!(&bytes[offset + 2..offset + 4], &[50, 0]);
+        assert_eq!(bytes[bytes.len() - 2..], [0x80, 0x10]);
+    }
+
+    #[test]
+    fn thread_table_maps_to_janome_indexes() {
+        let design = two_color_design();
+        let bytes = export_jef(&design).unwrap();
+        let u32_at = |off: usize| u32::from_le_bytes(bytes[off..off + 4].try_into().unwrap());
+        assert_eq!(u32_at(HEADER_SIZE), nearest_janome_index(Color::rgb(255, 0, 0)));
+        assert_eq!(u32_at(HEADER_SIZE + 4), nearest_janome_index(Color::rgb(0, 0, 255)));
+        assert_eq!(nearest_janome_index(Color::BLACK), 1);
+    }
+
+    #[test]
+    fn long_moves_split_into_needle_up_moves() {
+        let design = ExportDesign {
+            name: "long".to_string(),
+            stitches: vec![
+                ExportStitch::new(0.0, 0.0, ExportStitchType::Normal),
+                ExportStitch::new(30.0, 0.0, ExportStitchType::Normal),
+            ],
+            colors: vec![Color::BLACK],
+            coordinate_system: CoordinateSystem::YDown,
+            quantization: Quantization::default(),
+        };
+        let bytes = export_jef(&design).unwrap();
+        let offset = u32::from_le_bytes(bytes[0..4].try_into().unwrap()) as usize;
+        let data = &bytes[offset..];
+        // 300 units: two 127-unit moves plus the 46-unit stitch.
+        assert_eq!(data, &[0, 0, 0x80, 0x02, 127, 0, 0x80, 0x02, 127, 0, 46, 0]);
+    }
+}
@@ -0,0 +1,245 @@
+//! Machine file format encoders.
+//!
+//! Each format module exposes an `export_*` function taking a finished
+//! [`ExportDesign`](crate::export_pipeline::ExportDesign) and returning the
+//! encoded bytes. Formats differ in units, coordinate orientation, and which
+//! control records they can express; those decisions live in the format
+//! module, never in the pipeline.
+
+pub mod csv;
+pub mod dst;
+pub mod jef;
+pub mod pes;
+pub mod vp3;
+
+pub use csv::{export_stitch_csv, import_stitch_csv};
+
+use crate::export_pipeline::{CoordinateSystem, ExportDesign, ExportStitchType};
+use serde::{Deserialize, Serialize};
+
+/// A machine file format this crate can write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    Dst,
+    Jef,
+    Pes,
+    Vp3,
+}
+
+/// What a format can express. The UI greys out options a format cannot
+/// honor, and exporters consult the same table for downgrade decisions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FormatCapabilities {
+    /// Thread colors are stored in the file (not just a change count).
+    pub embeds_colors: bool,
+    /// The file carries preview thumbnails.
+    pub embeds_thumbnail: bool,
+    /// A stop/pause can be expressed without faking a color change.
+    pub supports_stop: bool,
+    /// A real trim record exists (vs. the zero-motion-jump convention).
+    pub supports_trim: bool,
+    /// Largest single movement one record can express, in format units.
+    pub max_jump_units: i32,
+}
+
+/// The capability table for `fmt`.
+pub fn capabilities(fmt: ExportFormat) -> FormatCapabilities {
+    match fmt {
+        ExportFormat::Dst => FormatCapabilities {
+            embeds_colors: false,
+            embeds_thumbnail: false,
+            supports_stop: false,
+            supports_trim: false,
+            max_jump_units: dst::MAX_DELTA,
+        },
+        ExportFormat::Jef => FormatCapabilities {
+            embeds_colors: true,
+            embeds_thumbnail: false,
+            supports_stop: false,
+            supports_trim: false,
+            max_jump_units: jef::MAX_DELTA,
+        },
+        ExportFormat::Pes => FormatCapabilities {
+            embeds_colors: true,
+            embeds_thumbnail: true,
+            supports_stop: true,
+            supports_trim: true,
+            max_jump_units: pes::PEC_MAX_DELTA,
+        },
+        ExportFormat::Vp3 => FormatCapabilities {
+            embeds_colors: true,
+            embeds_thumbnail: false,
+            supports_stop: true,
+            supports_trim: false,
+            max_jump_units: i16::MAX as i32,
+        },
+    }
+}
+
+/// One stitch record converted to a format's integer units.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnitStitch {
+    pub x: i32,
+    pub y: i32,
+    pub kind: ExportStitchType,
+}
+
+/// Convert a design's stitches to integer format units, flipping Y when the
+/// design and the target format disagree on orientation. Every exporter goes
+/// through this — it is the only place an axis flip may happen.
+///
+/// Movements are snapped to the grid chosen by the design's
+/// [`Quantization`](crate::export_pipeline::Quantization) with the
+/// residual carried into the next stitch, so a
+/// run of thousands of sub-grid steps ends within one unit of the true
+/// endpoint instead of drifting by the per-stitch bias.
+pub fn stitches_in_units(
+    design: &ExportDesign,
+    units_per_mm: f64,
+    target: CoordinateSystem,
+) -> Vec<UnitStitch> {
+    convert_stitches(design, units_per_mm, target, true)
+}
+
+/// The conversion behind [`stitches_in_units`], with the error carry made
+/// switchable so tests can measure the drift it prevents.
+fn convert_stitches(
+    design: &ExportDesign,
+    units_per_mm: f64,
+    target: CoordinateSystem,
+    carry: bool,
+) -> Vec<UnitStitch> {
+    let flip = design.coordinate_system != target;
+    let q = design.quantization;
+    // Emitted position so far, in (possibly fractional) format units.
+    let (mut ex, mut ey) = (0.0_f64, 0.0_f64);
+    let (mut px, mut py) = (0.0_f64, 0.0_f64);
+    design
+        .stitches
+        .iter()
+        .map(|s| {
+            let ty = if flip { -s.y } else { s.y };
+            let (tx, ty) = (s.x * units_per_mm, ty * units_per_mm);
+            let (from_x, from_y) = if carry { (ex, ey) } else { (px, py) };
+            ex += q.snap(tx - from_x, units_per_mm);
+            ey += q.snap(ty - from_y, units_per_mm);
+            (px, py) = (tx, ty);
+            UnitStitch {
+                x: ex.round() as i32,
+                y: ey.round() as i32,
+                kind: s.kind,
+            }
+        })
+        .collect()
+}
+
+/// Append a big-endian u16.
+pub(crate) fn put_u16_be(out: &mut Vec<u8>, v: u16) {
+    out.extend_from_slice(&v.to_be_bytes());
+}
+
+/// Append a big-endian i16.
+pub(crate) fn put_i16_be(out: &mut Vec<u8>, v: i16) {
+    out.extend_from_slice(&v.to_be_bytes());
+}
+
+/// Append a big-endian u32.
+pub(crate) fn put_u32_be(out: &mut Vec<u8>, v: u32) {
+    out.extend_from_slice(&v.to_be_bytes());
+}
+
+/// Append a big-endian i32.
+pub(crate) fn put_i32_be(out: &mut Vec<u8>, v: i32) {
+    out.extend_from_slice(&v.to_be_bytes());
+}
+
+/// Append a little-endian i32.
+pub(crate) fn put_i32_le(out: &mut Vec<u8>, v: i32) {
+    out.extend_from_slice(&v.to_le_bytes());
+}
+
+/// Append a little-endian u16.
+pub(crate) fn put_u16_le(out: &mut Vec<u8>, v: u16) {
+    out.extend_from_slice(&v.to_le_bytes());
+}
+
+/// Append a little-endian i16.
+pub(crate) fn put_i16_le(out: &mut Vec<u8>, v: i16) {
+    out.extend_from_slice(&v.to_le_bytes());
+}
+
+/// Append a little-endian u32.
+pub(crate) fn put_u32_le(out: &mut Vec<u8>, v: u32) {
+    out.extend_from_slice(&v.to_le_bytes());
+}
+
+/// Append a little-endian f32.
+pub(crate) fn put_f32_le(out: &mut Vec<u8>, v: f32) {
+    out.extend_from_slice(&v.to_le_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export_pipeline::{ExportStitch, Quantization};
+    use crate::shapes::Color;
+
+    #[test]
+    fn y_flips_only_when_conventions_differ() {
+        let design = ExportDesign {
+            name: "flip".to_string(),
+            stitches: vec![
+                ExportStitch::new(0.0, 0.0, ExportStitchType::Normal),
+                // Lower on screen = larger design-space Y.
+                ExportStitch::new(1.0, 5.0, ExportStitchType::Normal),
+            ],
+            colors: vec![Color::BLACK],
+            coordinate_system: CoordinateSystem::YDown,
+            quantization: Quantization::default(),
+        };
+        let up = stitches_in_units(&design, 10.0, CoordinateSystem::YUp);
+        assert_eq!((up[1].x, up[1].y), (10, -50));
+        let down = stitches_in_units(&design, 10.0, CoordinateSystem::YDown);
+        assert_eq!((down[1].x, down[1].y), (10, 50));
+    }
+
+    #[test]
+    fn capability_table_matches_the_writers() {
+        let dst = capabilities(ExportFormat::Dst);
+        assert!(!dst.embeds_colors);
+        assert!(!dst.supports_stop);
+        let pes = capabilities(ExportFormat::Pes);
+        assert!(pes.embeds_colors);
+        assert!(pes.embeds_thumbnail);
+    }
+
+    #[test]
+    fn error_carry_stops_long_runs_from_drifting() {
+        // 2000 diagonal steps of 0.13 x 0.07 mm — 1.3 x 0.7 units at DST
+        // resolution, so every step has a sub-unit fractional part.
+        let stitches: Vec<ExportStitch> = (1..=2000)
+            .map(|i| ExportStitch::new(i as f64 * 0.13, i as f64 * 0.07, ExportStitchType::Normal))
+            .collect();
+        let design = ExportDesign {
+            name: "drift".to_string(),
+            stitches,
+            colors: vec![Color::BLACK],
+            coordinate_system: CoordinateSystem::YDown,
+            quantization: Quantization::Floor,
+        };
+        let (true_x, true_y) = (2000.0 * 1.3, 2000.0 * 0.7);
+
+        let carried = stitches_in_units(&design, 10.0, CoordinateSystem::YDown);
+        let end = carried.last().unwrap();
+        assert!((end.x as f64 - true_x).abs() <= 1.0);
+        assert!((end.y as f64 - true_y).abs() <= 1.0);
+
+        // Snapping each movement independently floors away the fractional
+        // part of every step, and the loss compounds across the run.
+        let naive = convert_stitches(&design, 10.0, CoordinateSystem::YDown, false);
+        let end = naive.last().unwrap();
+        assert!((end.x as f64 - true_x).abs() > 100.0);
+        assert!((end.y as f64 - true_y).abs() > 100.0);
+    }
+}
@@ -0,0 +1,388 @@
+//! PES (Brother / Baby Lock) writer.
+//!
+//! A PES file carries two layers: the PES sections (a `CEmbOne` geometry
+//! header plus `CSewSeg` stitch segments) that editing software like
+//! PE-Design reads, and a trailing PEC block that the machine itself
+//! stitches. We emit the version-1 container — the `CEmbOne`/`CSewSeg`
+//! layout is identical across versions and v1 skips the long metadata
+//! header machines ignore.
+//!
+//! Units are 0.1 mm with Y **up**; `stitches_in_units` performs the flip
+//! from design space. `CEmbOne` extents are written in that flipped
+//! absolute space; `CSewSeg` coordinates are block-local (origin at the
+//! extents minimum) and the `CEmbOne` affine translates the block to the
+//! hoop center.
+
+use crate::export_pipeline::{CoordinateSystem, ExportDesign, ExportStitchType};
+use crate::error::EngineError;
+use crate::format::{put_f32_le, put_i16_le, put_u16_le, put_u32_le, stitches_in_units, UnitStitch};
+use crate::shapes::Color;
+
+/// PES/PEC coordinate units per design-space millimetre.
+pub const PES_UNITS_PER_MM: f64 = 10.0;
+
+/// Default 130×180 mm hoop, in PES units.
+const HOOP_WIDTH: f64 = 1300.0;
+const HOOP_HEIGHT: f64 = 1800.0;
+
+/// Byte offset of the `CEmbOne` extents within the file: magic (8) +
+/// PEC-offset u32 (4) + three u16 header fields (6) + section name length
+/// u16 (2) + "CEmbOne" (7).
+pub const CEMBONE_EXTENTS_OFFSET: usize = 27;
+
+/// Nearest PEC machine thread index for a design color. PEC palette
+/// indexes are Brother thread codes, so this is the catalog's inverse
+/// lookup narrowed to a byte.
+pub fn nearest_pec_index(color: Color) -> u8 {
+    crate::thread::brother_code_for(color) as u8
+}
+
+/// Encode a design as a PES file.
+pub fn export_pes(design: &ExportDesign) -> Result<Vec<u8>, EngineError> {
+    if design.stitches.is_empty() {
+        return Err(EngineError::InvalidInput(
+            "cannot export an empty design".to_string(),
+        ));
+    }
+    let stitches = stitches_in_units(design, PES_UNITS_PER_MM, CoordinateSystem::YUp);
+    let (min_x, min_y, max_x, max_y) = unit_extents(&stitches);
+    let mut out = Vec::new();
+    out.extend_from_slice(b"#PES0001");
+    let pec_offset_at = out.len();
+    put_u32_le(&mut out, 0); // PEC offset, backpatched below.
+    put_u16_le(&mut out, 1); // Scale to fit.
+    put_u16_le(&mut out, 1); // Hoop: 130×180.
+    put_u16_le(&mut out, 1); // One segment group.
+
+    write_cembone(&mut out, min_x, min_y, max_x, max_y);
+    write_csewseg(&mut out, design, &stitches, min_x, min_y);
+
+    let pec_offset = out.len() as u32;
+    out[pec_offset_at..pec_offset_at + 4].copy_from_slice(&pec_offset.to_le_bytes());
+    write_pec(&mut out, design, &stitches);
+    Ok(out)
+}
+
+/// PES-unit extents over every positioned record.
+fn unit_extents(stitches: &[UnitStitch]) -> (i16, i16, i16, i16) {
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (i32::MAX, i32::MAX, i32::MIN, i32::MIN);
+    for s in stitches {
+        min_x = min_x.min(s.x);
+        min_y = min_y.min(s.y);
+        max_x = max_x.max(s.x);
+        max_y = max_y.max(s.y);
+    }
+    (min_x as i16, min_y as i16, max_x as i16, max_y as i16)
+}
+
+/// The `CEmbOne` section: extents, placement affine, and block dimensions.
+fn write_cembone(out: &mut Vec<u8>, min_x: i16, min_y: i16, max_x: i16, max_y: i16) {
+    let width = max_x - min_x;
+    let height = max_y - min_y;
+    put_u16_le(out, 7);
+    out.extend_from_slice(b"CEmbOne");
+    // Extents appear twice: once for the design, once for the hoop clip.
+    for _ in 0..2 {
+        put_i16_le(out, min_x);
+        put_i16_le(out, min_y);
+        put_i16_le(out, max_x);
+        put_i16_le(out, max_y);
+    }
+    // Affine placing the block-local segment coordinates in the hoop:
+    // identity rotation/scale, translation centering the block.
+    put_f32_le(out, 1.0);
+    put_f32_le(out, 0.0);
+    put_f32_le(out, 0.0);
+    put_f32_le(out, 1.0);
+    put_f32_le(out, ((HOOP_WIDTH - width as f64) / 2.0) as f32);
+    put_f32_le(out, ((HOOP_HEIGHT - height as f64) / 2.0) as f32);
+    put_u16_le(out, 1);
+    put_i16_le(out, 0); // Block-local coordinate offset.
+    put_i16_le(out, 0);
+    put_i16_le(out, width);
+    put_i16_le(out, height);
+    out.extend_from_slice(&[0u8; 8]);
+}
+
+/// The `CSewSeg` section: runs of block-local coordinates, typed jump (0) or
+/// stitch (1), each tagged with its thread's PEC palette index, followed by
+/// the color log mapping segment index to palette index at each change.
+fn write_csewseg(
+    out: &mut Vec<u8>,
+    design: &ExportDesign,
+    stitches: &[UnitStitch],
+    min_x: i16,
+    min_y: i16,
+) {
+    /// One run of connected coordinates sharing a type and thread.
+    struct Segment {
+        is_jump: bool,
+        color: u8,
+        points: Vec<(i16, i16)>,
+    }
+    // Split the flat program into segments.
+    let mut segments: Vec<Segment> = Vec::new();
+    let mut color_iter = design.colors.iter();
+    let mut current = color_iter
+        .next()
+        .map(|c| nearest_pec_index(*c))
+        .unwrap_or(20);
+    let mut run: Vec<(i16, i16)> = Vec::new();
+    let mut run_is_jump = false;
+    let mut last_point: Option<(i16, i16)> = None;
+    for s in stitches {
+        let point = (s.x as i16 - min_x, s.y as i16 - min_y);
+        match s.kind {
+            ExportStitchType::Normal | ExportStitchType::Jump => {
+                let is_jump = s.kind == ExportStitchType::Jump;
+                if is_jump != run_is_jump && !run.is_empty() {
+                    segments.push(Segment {
+                        is_jump: run_is_jump,
+                        color: current,
+                        points: std::mem::take(&mut run),
+                    });
+                    // Segments connect: re-seed from the previous endpoint.
+                    if let Some(p) = last_point {
+                        run.push(p);
+                    }
+                }
+                run_is_jump = is_jump;
+                run.push(point);
+                last_point = Some(point);
+            }
+            ExportStitchType::ColorChange => {
+                if !run.is_empty() {
+                    segments.push(Segment {
+                        is_jump: run_is_jump,
+                        color: current,
+                        points: std::mem::take(&mut run),
+                    });
+                }
+                run_is_jump = false;
+                current = color_iter
+                    .next()
+                    .map(|c| nearest_pec_index(*c))
+                    .unwrap_or(current);
+            }
+            // Trims and stops have no CSewSeg form; the PEC block carries them.
+            ExportStitchType::Trim | ExportStitchType::Stop | ExportStitchType::End => {}
+        }
+    }
+    if run.len() > 1 || (!run.is_empty() && segments.is_empty()) {
+        segments.push(Segment {
+            is_jump: run_is_jump,
+            color: current,
+            points: run,
+        });
+    }
+
+    put_u16_le(out, 7);
+    out.extend_from_slice(b"CSewSeg");
+    for (i, seg) in segments.iter().enumerate() {
+        put_u16_le(out, if seg.is_jump { 0 } else { 1 });
+        put_u16_le(out, seg.color as u16);
+        put_u16_le(out, seg.points.len() as u16);
+        for (x, y) in &seg.points {
+            put_i16_le(out, *x);
+            put_i16_le(out, *y);
+        }
+        if i + 1 < segments.len() {
+            put_u16_le(out, 0x8003); // Continuation flag.
+        }
+    }
+    // Color log: (segment index, palette index) at each thread change.
+    let mut log: Vec<(u16, u16)> = Vec::new();
+    for (i, seg) in segments.iter().enumerate() {
+        if log.last().map(|(_, c)| *c) != Some(seg.color as u16) {
+            log.push((i as u16, seg.color as u16));
+        }
+    }
+    put_u16_le(out, log.len() as u16);
+    for (seg, color) in log {
+        put_u16_le(out, seg);
+        put_u16_le(out, color);
+    }
+    put_u16_le(out, 0);
+    put_u16_le(out, 0);
+}
+
+/// The PEC block the machine stitches: header with the thread palette, then
+/// delta-encoded stitches, then one blank 48×38 thumbnail per color plus an
+/// overview image (machines require their presence, not their content).
+fn write_pec(out: &mut Vec<u8>, design: &ExportDesign, stitches: &[UnitStitch]) {
+    let (min_x, min_y, max_x, max_y) = unit_extents(stitches);
+    let width = max_x - min_x;
+    let height = max_y - min_y;
+
+    // Header: "LA:" + 16-char padded name + CR, filler, thumbnail geometry.
+    let mut name: String = design.name.chars().take(8).collect();
+    while name.len() < 16 {
+        name.push(' ');
+    }
+    out.extend_from_slice(b"LA:");
+    out.extend_from_slice(name.as_bytes());
+    out.push(b'\r');
+    out.extend_from_slice(&[0x16; 12]);
+    out.extend_from_slice(&[0xff, 0x00, 0x06, 0x26]);
+    out.extend_from_slice(&[0x20; 12]);
+    let color_count = design.colors.len().max(1);
+    out.push((color_count - 1) as u8);
+    for c in &design.colors {
+        out.push(nearest_pec_index(*c));
+    }
+    // Pad the palette area to its fixed size.
+    out.extend_from_slice(&vec![0x20; 0x1cf - color_count]);
+    put_u16_le(out, 0);
+
+    put_u16_le(out, width as u16);
+    put_u16_le(out, height as u16);
+    put_u16_le(out, 0x1e0);
+    put_u16_le(out, 0x1b0);
+    write_pec_stitches(out, stitches, min_x, min_y);
+    // Blank thumbnails: 48×38 1-bit images, one per color plus the overview.
+    for _ in 0..=color_count {
+        out.extend_from_slice(&[0u8; 48 / 8 * 38]);
+    }
+}
+
+/// PEC stitch deltas: 7-bit short form for small moves, 12-bit long form
+/// (flagged for jump/trim) otherwise; `0xfe 0xb0` marks a color change and
+/// `0xff` ends the program. Coordinates are block-local.
+fn write_pec_stitches(out: &mut Vec<u8>, stitches: &[UnitStitch], min_x: i16, min_y: i16) {
+    let mut cursor = (0i32, 0i32);
+    let mut change_toggle = 2u8;
+    for s in stitches {
+        let x = s.x - min_x as i32;
+        let y = s.y - min_y as i32;
+        match s.kind {
+            ExportStitchType::Normal | ExportStitchType::Jump | ExportStitchType::Trim => {
+                let dx = x - cursor.0;
+                let dy = y - cursor.1;
+                let long = s.kind != ExportStitchType::Normal;
+                push_pec_delta(out, dx, long, s.kind == ExportStitchType::Trim);
+                push_pec_delta(out, dy, long, s.kind == ExportStitchType::Trim);
+                cursor = (x, y);
+            }
+            ExportStitchType::ColorChange | ExportStitchType::Stop => {
+                out.push(0xfe);
+                out.push(0xb0);
+                out.push(change_toggle);
+                change_toggle = if change_toggle == 2 { 1 } else { 2 };
+            }
+            ExportStitchType::End => {}
+        }
+    }
+    out.push(0xff);
+}
+
+/// Largest movement the 12-bit long form can express on each axis.
+pub(crate) const PEC_MAX_DELTA: i32 = 2047;
+
+fn push_pec_delta(out: &mut Vec<u8>, v: i32, force_long: bool, trim: bool) {
+    if !force_long && (-64..=63).contains(&v) {
+        out.push((v & 0x7f) as u8);
+    } else {
+        let v = v.clamp(-PEC_MAX_DELTA - 1, PEC_MAX_DELTA) & 0x0fff;
+        let mut high = 0x80 | ((v >> 8) as u8);
+        if force_long {
+            high |= if trim { 0x20 } else { 0x10 };
+        }
+        out.push(high);
+        out.push((v & 0xff) as u8);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export_pipeline::{ExportStitch, Quantization};
+
+    fn sample_design() -> ExportDesign {
+        // A diagonal run from (5, 10) to (25, 40) mm.
+        let mut stitches = vec![ExportStitch::new(5.0, 10.0, ExportStitchType::Jump)];
+        for i in 0..=10 {
+            let t = i as f64 / 10.0;
+            stitches.push(ExportStitch::new(
+                5.0 + 20.0 * t,
+                10.0 + 30.0 * t,
+                ExportStitchType::Normal,
+            ));
+        }
+        stitches.push(ExportStitch::new(25.0, 40.0, ExportStitchType::End));
+        ExportDesign {
+            name: "sample".to_string(),
+            stitches,
+            colors: vec![Color::BLACK],
+            coordinate_system: CoordinateSystem::YDown,
+            quantization: Quantization::default(),
+        }
+    }
+
+    fn i16_at(bytes: &[u8], at: usize) -> i16 {
+        i16::from_le_bytes([bytes[at], bytes[at + 1]])
+    }
+
+    fn f32_at(bytes: &[u8], at: usize) -> f32 {
+        f32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn cembone_extents_match_design_bounds() {
+        let design = sample_design();
+        let bytes = export_pes(&design).unwrap();
+        assert_eq!(&bytes[..8], b"#PES0001");
+
+        // Extents are in PES units (0.1 mm) with Y flipped, written twice.
+        let at = CEMBONE_EXTENTS_OFFSET;
+        assert_eq!(i16_at(&bytes, at), 50); // min_x = 5 mm
+        assert_eq!(i16_at(&bytes, at + 2), -400); // min_y = -max_y = -40 mm
+        assert_eq!(i16_at(&bytes, at + 4), 250); // max_x = 25 mm
+        assert_eq!(i16_at(&bytes, at + 6), -100); // max_y = -min_y = -10 mm
+        for i in 0..4 {
+            assert_eq!(i16_at(&bytes, at + i * 2), i16_at(&bytes, at + 8 + i * 2));
+        }
+    }
+
+    #[test]
+    fn cembone_affine_centers_block_in_hoop() {
+        let design = sample_design();
+        let bytes = export_pes(&design).unwrap();
+        let at = CEMBONE_EXTENTS_OFFSET + 16;
+        assert_eq!(f32_at(&bytes, at), 1.0);
+        assert_eq!(f32_at(&bytes, at + 4), 0.0);
+        assert_eq!(f32_at(&bytes, at + 8), 0.0);
+        assert_eq!(f32_at(&bytes, at + 12), 1.0);
+        // 20×30 mm block centered in the 130×180 mm hoop.
+        assert_eq!(f32_at(&bytes, at + 16), (1300.0 - 200.0) / 2.0);
+        assert_eq!(f32_at(&bytes, at + 20), (1800.0 - 300.0) / 2.0);
+    }
+
+    #[test]
+    fn pec_offset_points_at_pec_header() {
+        let design = sample_design();
+        let bytes = export_pes(&design).unwrap();
+        let offset = u32::from_le_bytes(bytes[8..12].try_into().unwrap()) as usize;
+        assert_eq!(&bytes[offset..offset + 3], b"LA:");
+        // The program terminator is present in the PEC block.
+        assert!(bytes[offset..].contains(&0xff));
+    }
+
+    #[test]
+    fn nearest_pec_index_picks_exact_matches() {
+        assert_eq!(nearest_pec_index(Color::BLACK), 20);
+        assert_eq!(nearest_pec_index(Color::rgb(0xec, 0x00, 0x00)), 5);
+        assert_eq!(nearest_pec_index(Color::rgb(0xf0, 0xf0, 0xf0)), 29);
+    }
+
+    #[test]
+    fn empty_design_errors() {
+        let design = ExportDesign {
+            name: "empty".to_string(),
+            stitches: Vec::new(),
+            colors: Vec::new(),
+            coordinate_system: CoordinateSystem::YDown,
+            quantization: Quantization::default(),
+        };
+        assert!(export_pes(&design).is_err());
+    }
+}
@@ -0,0 +1,217 @@
+//! VP3 (Husqvarna Viking / Pfaff) writer.
+//!
+//! VP3 carries full color information: each color runs as its own block
+//! with a thread name, an RGB value, and its own start position, so the
+//! machine needs no external color card. We write the `%vsm%` container
+//! with one block per palette entry. Integers are big-endian; units are
+//! 0.1 mm with Y **down**, matching design space, so no axis flip happens.
+//!
+//! Stitch data is relative: a pair of signed bytes per move, with an
+//! `0x80` escape for anything a byte can't carry — `0x01` a long move or
+//! jump (two big-endian i16 deltas), `0x02` a stop, `0x03` end of block.
+
+use crate::error::EngineError;
+use crate::export_pipeline::{CoordinateSystem, ExportDesign, ExportStitchType};
+use crate::format::{put_i16_be, put_i32_be, put_u16_be, put_u32_be, stitches_in_units, UnitStitch};
+use crate::shapes::Color;
+
+/// VP3 coordinate units per design-space millimetre.
+pub const VP3_UNITS_PER_MM: f64 = 10.0;
+
+/// Producer tag embedded in the header.
+const PRODUCER: &str = "vision";
+
+/// Largest movement a short (byte-pair) record can express on each axis.
+const MAX_SHORT_DELTA: i32 = 127;
+
+/// A representative slice of the Pfaff/Viking rayon chart, as
+/// `(name, rgb)`. Like the PEC table, coverage of the hue wheel is what
+/// matters — the machine shows the name, the RGB drives the preview.
+pub(crate) const VP3_THREADS: &[(&str, [u8; 3])] = &[
+    ("Black", [0x00, 0x00, 0x00]),
+    ("White", [0xf5, 0xf5, 0xf5]),
+    ("Poppy", [0xe3, 0x1d, 0x1d]),
+    ("Burgundy", [0x86, 0x1a, 0x33]),
+    ("Orange", [0xf8, 0x76, 0x10]),
+    ("Goldenrod", [0xe9, 0xb8, 0x24]),
+    ("Canary Yellow", [0xf9, 0xe8, 0x14]),
+    ("Moss Green", [0x55, 0x79, 0x2c]),
+    ("Emerald", [0x11, 0x86, 0x40]),
+    ("Teal", [0x17, 0x80, 0x80]),
+    ("Sky Blue", [0x63, 0xc1, 0xef]),
+    ("Royal Blue", [0x17, 0x2f, 0xa5]),
+    ("Navy", [0x0e, 0x1a, 0x4e]),
+    ("Lavender", [0xb5, 0x9d, 0xdc]),
+    ("Purple", [0x6a, 0x1c, 0x8e]),
+    ("Fuchsia", [0xd1, 0x24, 0x8e]),
+    ("Pink", [0xf4, 0xa6, 0xc8]),
+    ("Chocolate", [0x55, 0x32, 0x1a]),
+    ("Tan", [0xc8, 0xa1, 0x65]),
+    ("Gray", [0x8c, 0x8c, 0x8c]),
+];
+
+/// Nearest chart entry for a design color (RGB distance, same rationale as
+/// the PEC mapping).
+pub fn nearest_thread(color: Color) -> (&'static str, [u8; 3]) {
+    *VP3_THREADS
+        .iter()
+        .min_by_key(|(_, rgb)| {
+            let dr = rgb[0] as i32 - color.r as i32;
+            let dg = rgb[1] as i32 - color.g as i32;
+            let db = rgb[2] as i32 - color.b as i32;
+            dr * dr + dg * dg + db * db
+        })
+        .expect("thread chart is non-empty")
+}
+
+/// Encode a design as a VP3 file.
+pub fn export_vp3(design: &ExportDesign) -> Result<Vec<u8>, EngineError> {
+    if design.stitches.is_empty() {
+        return Err(EngineError::InvalidInput(
+            "cannot export an empty design".to_string(),
+        ));
+    }
+    let stitches = stitches_in_units(design, VP3_UNITS_PER_MM, CoordinateSystem::YDown);
+    let blocks = split_color_blocks(&stitches);
+
+    let mut out = Vec::new();
+    out.extend_from_slice(b"%vsm%\x00");
+    put_string(&mut out, PRODUCER);
+    let remaining_at = out.len();
+    put_u32_be(&mut out, 0); // Bytes after this field, backpatched below.
+
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (i32::MAX, i32::MAX, i32::MIN, i32::MIN);
+    for s in &stitches {
+        min_x = min_x.min(s.x);
+        min_y = min_y.min(s.y);
+        max_x = max_x.max(s.x);
+        max_y = max_y.max(s.y);
+    }
+    put_i32_be(&mut out, min_x);
+    put_i32_be(&mut out, min_y);
+    put_i32_be(&mut out, max_x);
+    put_i32_be(&mut out, max_y);
+
+    out.push(blocks.len() as u8);
+    for (i, block) in blocks.iter().enumerate() {
+        let color = design
+            .colors
+            .get(i)
+            .copied()
+            .unwrap_or(Color::BLACK);
+        write_color_block(&mut out, block, color);
+    }
+
+    let remaining = (out.len() - remaining_at - 4) as u32;
+    out[remaining_at..remaining_at + 4].copy_from_slice(&remaining.to_be_bytes());
+    Ok(out)
+}
+
+/// Split the unit stitches into one run per color, consuming the
+/// `ColorChange` markers. `End` terminates the final block.
+fn split_color_blocks(stitches: &[UnitStitch]) -> Vec<Vec<UnitStitch>> {
+    let mut blocks = vec![Vec::new()];
+    for s in stitches {
+        match s.kind {
+            ExportStitchType::ColorChange => blocks.push(Vec::new()),
+            ExportStitchType::End => break,
+            _ => blocks.last_mut().expect("starts non-empty").push(*s),
+        }
+    }
+    blocks.retain(|b| !b.is_empty());
+    blocks
+}
+
+/// One color block: marker, thread name, RGB, absolute start, stitch byte
+/// length, then the relative stitch data ending in `0x80 0x03`.
+fn write_color_block(out: &mut Vec<u8>, block: &[UnitStitch], color: Color) {
+    let (name, _) = nearest_thread(color);
+    out.extend_from_slice(&[0x00, 0x05, 0x00]);
+    put_string(out, name);
+    out.extend_from_slice(&[color.r, color.g, color.b]);
+
+    let start = block.first().expect("empty blocks are dropped");
+    put_i32_be(out, start.x);
+    put_i32_be(out, start.y);
+    let len_at = out.len();
+    put_u32_be(out, 0); // Stitch byte length, backpatched below.
+
+    let mut cursor = (start.x, start.y);
+    for s in block {
+        match s.kind {
+            ExportStitchType::Normal | ExportStitchType::Jump | ExportStitchType::Trim => {
+                let dx = s.x - cursor.0;
+                let dy = s.y - cursor.1;
+                // Jumps (and trims, which VP3 infers from jumps) always use
+                // the escaped long form so the machine lifts the needle.
+                if s.kind == ExportStitchType::Normal
+                    && dx.abs() <= MAX_SHORT_DELTA
+                    && dy.abs() <= MAX_SHORT_DELTA
+                    && dx as u8 != 0x80
+                {
+                    out.push(dx as u8);
+                    out.push(dy as u8);
+                } else {
+                    out.extend_from_slice(&[0x80, 0x01]);
+                    put_i16_be(out, dx.clamp(i16::MIN as i32, i16::MAX as i32) as i16);
+                    put_i16_be(out, dy.clamp(i16::MIN as i32, i16::MAX as i32) as i16);
+                }
+                cursor = (s.x, s.y);
+            }
+            ExportStitchType::Stop => out.extend_from_slice(&[0x80, 0x02]),
+            ExportStitchType::ColorChange | ExportStitchType::End => {
+                unreachable!("blocks are split on these")
+            }
+        }
+    }
+    out.extend_from_slice(&[0x80, 0x03]);
+
+    let len = (out.len() - len_at - 4) as u32;
+    out[len_at..len_at + 4].copy_from_slice(&len.to_be_bytes());
+}
+
+/// A big-endian u16 byte length followed by the UTF-8 bytes.
+fn put_string(out: &mut Vec<u8>, s: &str) {
+    put_u16_be(out, s.len() as u16);
+    out.extend_from_slice(s.as_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export_pipeline::{ExportStitch, Quantization};
+
+    fn two_color_design() -> ExportDesign {
+        ExportDesign {
+            name: "vp3".to_string(),
+            stitches: vec![
+                ExportStitch::new(0.0, 0.0, ExportStitchType::Normal),
+                ExportStitch::new(3.0, 0.0, ExportStitchType::Normal),
+                ExportStitch::new(3.0, 0.0, ExportStitchType::ColorChange),
+                ExportStitch::new(3.0, 4.0, ExportStitchType::Normal),
+                ExportStitch::new(0.0, 4.0, ExportStitchType::Normal),
+                ExportStitch::new(0.0, 4.0, ExportStitchType::End),
+            ],
+            colors: vec![Color::rgb(230, 20, 20), Color::rgb(20, 40, 200)],
+            coordinate_system: CoordinateSystem::YDown,
+            quantization: Quantization::default(),
+        }
+    }
+
+    #[test]
+    fn one_block_per_palette_color_and_magic_present() {
+        let design = two_color_design();
+        let bytes = export_vp3(&design).unwrap();
+        assert!(bytes.starts_with(b"%vsm%\x00"));
+        // Block count byte sits after magic, producer string, remaining
+        // length, and the four extents.
+        let count_at = 6 + 2 + PRODUCER.len() + 4 + 16;
+        assert_eq!(bytes[count_at], 2);
+    }
+
+    #[test]
+    fn thread_names_come_from_the_chart() {
+        assert_eq!(nearest_thread(Color::rgb(240, 15, 10)).0, "Poppy");
+        assert_eq!(nearest_thread(Color::BLACK).0, "Black");
+    }
+}
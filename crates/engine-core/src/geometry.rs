@@ -0,0 +1,285 @@
+//! Primitive geometry types shared across the engine.
+//!
+//! All coordinates are millimetres in a Y-down design space unless a caller
+//! explicitly converts (exporters own their format's unit and axis rules).
+
+use serde::{Deserialize, Serialize};
+
+/// A 2D point in millimetres.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Point {
+    pub fn new(x: f64, y: f64) -> Self {
+        Self { x, y }
+    }
+
+    pub fn distance_to(&self, other: Point) -> f64 {
+        ((self.x - other.x).powi(2) + (self.y - other.y).powi(2)).sqrt()
+    }
+
+    pub fn lerp(&self, other: Point, t: f64) -> Point {
+        Point::new(
+            self.x + (other.x - self.x) * t,
+            self.y + (other.y - self.y) * t,
+        )
+    }
+
+    pub fn length(&self) -> f64 {
+        (self.x * self.x + self.y * self.y).sqrt()
+    }
+
+    /// Unit vector in the same direction, or zero if degenerate.
+    pub fn normalized(&self) -> Point {
+        let len = self.length();
+        if len <= f64::EPSILON {
+            Point::default()
+        } else {
+            Point::new(self.x / len, self.y / len)
+        }
+    }
+
+    /// Perpendicular (rotated 90° counter-clockwise in Y-down space).
+    pub fn perp(&self) -> Point {
+        Point::new(-self.y, self.x)
+    }
+}
+
+impl std::ops::Add for Point {
+    type Output = Point;
+    fn add(self, rhs: Point) -> Point {
+        Point::new(self.x + rhs.x, self.y + rhs.y)
+    }
+}
+
+impl std::ops::Sub for Point {
+    type Output = Point;
+    fn sub(self, rhs: Point) -> Point {
+        Point::new(self.x - rhs.x, self.y - rhs.y)
+    }
+}
+
+impl std::ops::Mul<f64> for Point {
+    type Output = Point;
+    fn mul(self, rhs: f64) -> Point {
+        Point::new(self.x * rhs, self.y * rhs)
+    }
+}
+
+/// Display/interchange units for coordinate output. Design space is always
+/// mm internally; these only affect serialized payloads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Units {
+    #[default]
+    Mm,
+    Cm,
+    Inch,
+}
+
+impl Units {
+    /// Units per design-space millimetre.
+    pub fn per_mm(self) -> f64 {
+        match self {
+            Units::Mm => 1.0,
+            Units::Cm => 0.1,
+            Units::Inch => 1.0 / 25.4,
+        }
+    }
+}
+
+/// Round to `decimals` places, for trimming serialized payloads to what
+/// the UI displays anyway.
+pub fn round_to(v: f64, decimals: u32) -> f64 {
+    let scale = 10f64.powi(decimals as i32);
+    (v * scale).round() / scale
+}
+
+/// A 2D affine transform in column-major form:
+/// `x' = a*x + c*y + tx`, `y' = b*x + d*y + ty`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Transform {
+    pub a: f64,
+    pub b: f64,
+    pub c: f64,
+    pub d: f64,
+    pub tx: f64,
+    pub ty: f64,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Transform {
+    pub fn identity() -> Self {
+        Self {
+            a: 1.0,
+            b: 0.0,
+            c: 0.0,
+            d: 1.0,
+            tx: 0.0,
+            ty: 0.0,
+        }
+    }
+
+    pub fn translation(tx: f64, ty: f64) -> Self {
+        Self {
+            tx,
+            ty,
+            ..Self::identity()
+        }
+    }
+
+    pub fn scale(sx: f64, sy: f64) -> Self {
+        Self {
+            a: sx,
+            d: sy,
+            ..Self::identity()
+        }
+    }
+
+    pub fn rotation(radians: f64) -> Self {
+        let (s, c) = radians.sin_cos();
+        Self {
+            a: c,
+            b: s,
+            c: -s,
+            d: c,
+            tx: 0.0,
+            ty: 0.0,
+        }
+    }
+
+    pub fn apply(&self, p: Point) -> Point {
+        Point::new(
+            self.a * p.x + self.c * p.y + self.tx,
+            self.b * p.x + self.d * p.y + self.ty,
+        )
+    }
+
+    /// `self` applied after `other` (i.e. `self * other` as matrices).
+    pub fn compose(&self, other: &Transform) -> Transform {
+        Transform {
+            a: self.a * other.a + self.c * other.b,
+            b: self.b * other.a + self.d * other.b,
+            c: self.a * other.c + self.c * other.d,
+            d: self.b * other.c + self.d * other.d,
+            tx: self.a * other.tx + self.c * other.ty + self.tx,
+            ty: self.b * other.tx + self.d * other.ty + self.ty,
+        }
+    }
+
+    /// Average absolute scale factor, used to convert local lengths to world.
+    /// Inverse transform, or `None` when the matrix is singular (zero
+    /// scale collapses geometry and cannot be undone).
+    pub fn inverse(&self) -> Option<Transform> {
+        let det = self.a * self.d - self.b * self.c;
+        if det.abs() <= 1e-12 {
+            return None;
+        }
+        let (a, b, c, d) = (self.d / det, -self.b / det, -self.c / det, self.a / det);
+        Some(Transform {
+            a,
+            b,
+            c,
+            d,
+            tx: -(a * self.tx + c * self.ty),
+            ty: -(b * self.tx + d * self.ty),
+        })
+    }
+
+    pub fn scale_factor(&self) -> f64 {
+        let sx = (self.a * self.a + self.b * self.b).sqrt();
+        let sy = (self.c * self.c + self.d * self.d).sqrt();
+        (sx + sy) * 0.5
+    }
+}
+
+/// Axis-aligned bounding box.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BoundingBox {
+    pub min_x: f64,
+    pub min_y: f64,
+    pub max_x: f64,
+    pub max_y: f64,
+}
+
+impl BoundingBox {
+    /// An empty box that unions correctly with any point.
+    pub fn empty() -> Self {
+        Self {
+            min_x: f64::INFINITY,
+            min_y: f64::INFINITY,
+            max_x: f64::NEG_INFINITY,
+            max_y: f64::NEG_INFINITY,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min_x > self.max_x || self.min_y > self.max_y
+    }
+
+    pub fn include(&mut self, p: Point) {
+        self.min_x = self.min_x.min(p.x);
+        self.min_y = self.min_y.min(p.y);
+        self.max_x = self.max_x.max(p.x);
+        self.max_y = self.max_y.max(p.y);
+    }
+
+    pub fn union(&self, other: &BoundingBox) -> BoundingBox {
+        BoundingBox {
+            min_x: self.min_x.min(other.min_x),
+            min_y: self.min_y.min(other.min_y),
+            max_x: self.max_x.max(other.max_x),
+            max_y: self.max_y.max(other.max_y),
+        }
+    }
+
+    pub fn width(&self) -> f64 {
+        (self.max_x - self.min_x).max(0.0)
+    }
+
+    pub fn height(&self) -> f64 {
+        (self.max_y - self.min_y).max(0.0)
+    }
+
+    pub fn center(&self) -> Point {
+        Point::new(
+            (self.min_x + self.max_x) * 0.5,
+            (self.min_y + self.max_y) * 0.5,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transform_compose_matches_sequential_apply() {
+        let t = Transform::translation(10.0, 5.0);
+        let r = Transform::rotation(std::f64::consts::FRAC_PI_2);
+        let composed = t.compose(&r);
+        let p = Point::new(1.0, 0.0);
+        let expected = t.apply(r.apply(p));
+        let got = composed.apply(p);
+        assert!((got.x - expected.x).abs() < 1e-12);
+        assert!((got.y - expected.y).abs() < 1e-12);
+    }
+
+    #[test]
+    fn bounding_box_union_and_extents() {
+        let mut b = BoundingBox::empty();
+        assert!(b.is_empty());
+        b.include(Point::new(-1.0, 2.0));
+        b.include(Point::new(3.0, -4.0));
+        assert_eq!(b.width(), 4.0);
+        assert_eq!(b.height(), 6.0);
+    }
+}
@@ -0,0 +1,20 @@
+//! engine-core: deterministic embroidery algorithms.
+//!
+//! Geometry, pathing, stitch generation, and export assembly live here.
+//! IO and host concerns (workers, storage, bindings) stay outside this crate;
+//! `engine-wasm` is the only intended consumer of the public surface.
+
+pub mod cancel;
+pub mod command;
+pub mod error;
+pub mod export_pipeline;
+pub mod fabric;
+pub mod format;
+pub mod geometry;
+pub mod path;
+pub mod scene;
+pub mod shapes;
+pub mod stitch;
+pub mod stroke_font;
+pub mod svg;
+pub mod thread;
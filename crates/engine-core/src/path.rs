@@ -0,0 +1,1020 @@
+//! Vector paths: command lists, flattening, and polygon predicates.
+
+use crate::geometry::{BoundingBox, Point, Transform};
+use serde::{Deserialize, Serialize};
+
+/// Flatten tolerance (mm) used when no caller-specific tolerance applies.
+pub const DEFAULT_FLATTEN_TOLERANCE: f64 = 0.1;
+
+/// A single path command. Curves are cubic beziers; quadratic input should be
+/// promoted by importers.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum PathCommand {
+    MoveTo { to: Point },
+    LineTo { to: Point },
+    CurveTo { c1: Point, c2: Point, to: Point },
+    Close,
+}
+
+/// A multi-subpath vector path in local coordinates.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct VectorPath {
+    pub commands: Vec<PathCommand>,
+}
+
+impl VectorPath {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build a closed polygon path from a point loop.
+    pub fn from_polygon(points: &[Point]) -> Self {
+        let mut commands = Vec::with_capacity(points.len() + 1);
+        for (i, p) in points.iter().enumerate() {
+            if i == 0 {
+                commands.push(PathCommand::MoveTo { to: *p });
+            } else {
+                commands.push(PathCommand::LineTo { to: *p });
+            }
+        }
+        if !points.is_empty() {
+            commands.push(PathCommand::Close);
+        }
+        Self { commands }
+    }
+
+    /// Build an open polyline path.
+    pub fn from_polyline(points: &[Point]) -> Self {
+        let mut path = Self::from_polygon(points);
+        path.commands.pop();
+        path
+    }
+
+    /// True when every subpath ends with an explicit `Close`.
+    pub fn is_closed(&self) -> bool {
+        let mut has_subpath = false;
+        let mut closed = true;
+        for cmd in &self.commands {
+            match cmd {
+                PathCommand::MoveTo { .. } => {
+                    if has_subpath && !closed {
+                        return false;
+                    }
+                    has_subpath = true;
+                    closed = false;
+                }
+                PathCommand::Close => closed = true,
+                _ => {}
+            }
+        }
+        has_subpath && closed
+    }
+
+    /// Flatten to polylines (one per subpath). Closed subpaths repeat their
+    /// first point at the end so downstream code can treat them as rings.
+    pub fn flatten(&self, tolerance: f64) -> Vec<Vec<Point>> {
+        let mut subpaths: Vec<Vec<Point>> = Vec::new();
+        let mut current: Vec<Point> = Vec::new();
+        let mut start = Point::default();
+        let mut cursor = Point::default();
+
+        for cmd in &self.commands {
+            match *cmd {
+                PathCommand::MoveTo { to } => {
+                    if current.len() > 1 {
+                        subpaths.push(std::mem::take(&mut current));
+                    } else {
+                        current.clear();
+                    }
+                    current.push(to);
+                    start = to;
+                    cursor = to;
+                }
+                PathCommand::LineTo { to } => {
+                    current.push(to);
+                    cursor = to;
+                }
+                PathCommand::CurveTo { c1, c2, to } => {
+                    flatten_cubic(cursor, c1, c2, to, tolerance, &mut current);
+                    cursor = to;
+                }
+                PathCommand::Close => {
+                    if current.last() != Some(&start) {
+                        current.push(start);
+                    }
+                    if current.len() > 1 {
+                        subpaths.push(std::mem::take(&mut current));
+                    } else {
+                        current.clear();
+                    }
+                    cursor = start;
+                }
+            }
+        }
+        if current.len() > 1 {
+            subpaths.push(current);
+        }
+        subpaths
+    }
+
+    pub fn bounding_box(&self, tolerance: f64) -> BoundingBox {
+        let mut bbox = BoundingBox::empty();
+        for subpath in self.flatten(tolerance) {
+            for p in subpath {
+                bbox.include(p);
+            }
+        }
+        bbox
+    }
+
+    /// Signed area (shoelace over flattened subpaths, summed). Subpaths wound
+    /// opposite to the outer ring — holes — subtract from the total.
+    pub fn area(&self) -> f64 {
+        let mut total = 0.0;
+        for ring in self.flatten(DEFAULT_FLATTEN_TOLERANCE) {
+            let mut ring_area = 0.0;
+            for w in ring.windows(2) {
+                ring_area += w[0].x * w[1].y - w[1].x * w[0].y;
+            }
+            // Open subpaths close implicitly for the area sum.
+            if let (Some(first), Some(last)) = (ring.first(), ring.last()) {
+                if first != last {
+                    ring_area += last.x * first.y - first.x * last.y;
+                }
+            }
+            total += ring_area * 0.5;
+        }
+        total
+    }
+
+    /// Total length of all flattened subpaths.
+    pub fn perimeter(&self) -> f64 {
+        self.flatten(DEFAULT_FLATTEN_TOLERANCE)
+            .iter()
+            .map(|ring| ring.windows(2).map(|w| w[0].distance_to(w[1])).sum::<f64>())
+            .sum()
+    }
+
+    /// A copy with `transform` applied to every coordinate (including control
+    /// points; affine maps preserve bezier structure).
+    pub fn transformed(&self, transform: &Transform) -> VectorPath {
+        let commands = self
+            .commands
+            .iter()
+            .map(|cmd| match *cmd {
+                PathCommand::MoveTo { to } => PathCommand::MoveTo {
+                    to: transform.apply(to),
+                },
+                PathCommand::LineTo { to } => PathCommand::LineTo {
+                    to: transform.apply(to),
+                },
+                PathCommand::CurveTo { c1, c2, to } => PathCommand::CurveTo {
+                    c1: transform.apply(c1),
+                    c2: transform.apply(c2),
+                    to: transform.apply(to),
+                },
+                PathCommand::Close => PathCommand::Close,
+            })
+            .collect();
+        VectorPath { commands }
+    }
+}
+
+/// Adaptive cubic flattening: subdivide until the control polygon deviates
+/// from the chord by less than `tolerance`. Appends all points after `from`.
+fn flatten_cubic(from: Point, c1: Point, c2: Point, to: Point, tolerance: f64, out: &mut Vec<Point>) {
+    fn recurse(
+        p0: Point,
+        p1: Point,
+        p2: Point,
+        p3: Point,
+        tolerance: f64,
+        depth: u32,
+        out: &mut Vec<Point>,
+    ) {
+        let d1 = point_to_line_dist(p1, p0, p3);
+        let d2 = point_to_line_dist(p2, p0, p3);
+        if depth >= 16 || d1.max(d2) <= tolerance {
+            out.push(p3);
+            return;
+        }
+        // de Casteljau split at t = 0.5.
+        let p01 = p0.lerp(p1, 0.5);
+        let p12 = p1.lerp(p2, 0.5);
+        let p23 = p2.lerp(p3, 0.5);
+        let p012 = p01.lerp(p12, 0.5);
+        let p123 = p12.lerp(p23, 0.5);
+        let mid = p012.lerp(p123, 0.5);
+        recurse(p0, p01, p012, mid, tolerance, depth + 1, out);
+        recurse(mid, p123, p23, p3, tolerance, depth + 1, out);
+    }
+    recurse(from, c1, c2, to, tolerance, 0, out);
+}
+
+fn point_to_line_dist(p: Point, a: Point, b: Point) -> f64 {
+    let ab = b - a;
+    let len = ab.length();
+    if len <= f64::EPSILON {
+        return p.distance_to(a);
+    }
+    ((p.x - a.x) * ab.y - (p.y - a.y) * ab.x).abs() / len
+}
+
+/// Closest point on the flattened path to `query`, with its distance.
+/// Returns the query itself (at infinite distance) for an empty path.
+pub fn closest_point_on_path(path: &VectorPath, query: Point) -> (Point, f64) {
+    let mut best = (query, f64::INFINITY);
+    for subpath in path.flatten(DEFAULT_FLATTEN_TOLERANCE) {
+        for seg in subpath.windows(2) {
+            let d_sq = crate::scene::point_to_segment_dist_sq(query, seg[0], seg[1]);
+            if d_sq < best.1 {
+                // Recover the foot point for the winning segment.
+                let ab = seg[1] - seg[0];
+                let len_sq = ab.x * ab.x + ab.y * ab.y;
+                let t = if len_sq <= f64::EPSILON {
+                    0.0
+                } else {
+                    (((query.x - seg[0].x) * ab.x + (query.y - seg[0].y) * ab.y) / len_sq)
+                        .clamp(0.0, 1.0)
+                };
+                best = (seg[0].lerp(seg[1], t), d_sq);
+            }
+        }
+    }
+    (best.0, best.1.sqrt())
+}
+
+/// Offset a closed ring sideways by `distance` along per-vertex averaged
+/// normals (positive offsets toward the `perp` side of travel, so the sign
+/// of "outward" follows the ring's winding). The ring must repeat its first
+/// point at the end, as produced by `flatten`, and the result does too.
+pub fn offset_ring(ring: &[Point], distance: f64) -> Vec<Point> {
+    let n = ring.len();
+    if n < 4 || ring.first() != ring.last() {
+        return ring.to_vec();
+    }
+    // Work on the unique points; the seam wraps around.
+    let unique = &ring[..n - 1];
+    let m = unique.len();
+    let mut out = Vec::with_capacity(n);
+    for i in 0..m {
+        let prev = unique[(i + m - 1) % m];
+        let next = unique[(i + 1) % m];
+        let tangent = (next - prev).normalized();
+        out.push(unique[i] + tangent.perp() * distance);
+    }
+    out.push(out[0]);
+    out
+}
+
+/// Corner treatment for [`offset_path`]. `offset_ring`'s averaged normals
+/// pull sharp corners short; segment-wise offsetting is faithful along the
+/// edges but needs an explicit join where they meet.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "join", content = "value", rename_all = "snake_case")]
+pub enum OffsetJoin {
+    /// Extend the offset edges to their intersection, falling back to a
+    /// bevel when the miter would reach past `limit` × distance (the SVG
+    /// `stroke-miterlimit` convention), so spikes stay bounded.
+    Miter(f64),
+    /// Arc around the corner at the offset distance.
+    Round,
+    /// Connect the offset edges directly.
+    Bevel,
+}
+
+/// Intersection of the infinite lines through `a`→`b` and `c`→`d`, or
+/// `None` when they are (near) parallel.
+fn line_intersection(a: Point, b: Point, c: Point, d: Point) -> Option<Point> {
+    let r = b - a;
+    let s = d - c;
+    let denom = r.x * s.y - r.y * s.x;
+    if denom.abs() <= 1e-12 {
+        return None;
+    }
+    let t = ((c.x - a.x) * s.y - (c.y - a.y) * s.x) / denom;
+    Some(Point::new(a.x + r.x * t, a.y + r.y * t))
+}
+
+/// Append a round join: an arc around `vertex` at the offset distance,
+/// sweeping the short way from `from` to `to`.
+fn push_round_join(out: &mut Vec<Point>, vertex: Point, from: Point, to: Point, d: f64) {
+    let a0 = (from.y - vertex.y).atan2(from.x - vertex.x);
+    let mut a1 = (to.y - vertex.y).atan2(to.x - vertex.x);
+    while a1 - a0 > std::f64::consts::PI {
+        a1 -= std::f64::consts::TAU;
+    }
+    while a1 - a0 < -std::f64::consts::PI {
+        a1 += std::f64::consts::TAU;
+    }
+    let steps = ((a1 - a0).abs() / 0.26).ceil().max(1.0) as usize;
+    for i in 0..=steps {
+        let a = a0 + (a1 - a0) * (i as f64 / steps as f64);
+        out.push(Point::new(
+            vertex.x + d.abs() * a.cos(),
+            vertex.y + d.abs() * a.sin(),
+        ));
+    }
+}
+
+/// Offset a polyline segment-wise by `distance` toward the `perp` side of
+/// travel (matching [`offset_ring`]'s sign convention), with explicit
+/// corner joins. `closed` rings must repeat their first point, as produced
+/// by `flatten`, and the result does too.
+pub fn offset_polyline_with_join(
+    points: &[Point],
+    distance: f64,
+    join: OffsetJoin,
+    closed: bool,
+) -> Vec<Point> {
+    let unique: &[Point] = if closed {
+        &points[..points.len() - 1]
+    } else {
+        points
+    };
+    let m = unique.len();
+    if m < 2 {
+        return points.to_vec();
+    }
+    // Offset endpoints of every edge: edge i runs unique[i] → unique[i+1].
+    let edge_count = if closed { m } else { m - 1 };
+    let edges: Vec<(Point, Point)> = (0..edge_count)
+        .map(|i| {
+            let (a, b) = (unique[i], unique[(i + 1) % m]);
+            let n = (b - a).normalized().perp();
+            (a + n * distance, b + n * distance)
+        })
+        .collect();
+
+    let mut out = Vec::with_capacity(points.len());
+    if !closed {
+        out.push(edges[0].0);
+    }
+    let start = if closed { 0 } else { 1 };
+    for v in start..edge_count {
+        let prev = edges[(v + edge_count - 1) % edge_count];
+        let next = edges[v];
+        let vertex = unique[v % m];
+        let (from, to) = (prev.1, next.0);
+        if from.distance_to(to) <= 1e-9 {
+            out.push(from);
+            continue;
+        }
+        match join {
+            // Intersect the offset *edge lines* (not vertex rays — those
+            // are ill-posed when the endpoints sit on rays from the
+            // vertex); past the limit the corner bevels.
+            OffsetJoin::Miter(limit) => match line_intersection(prev.0, prev.1, next.0, next.1)
+                .filter(|tip| vertex.distance_to(*tip) <= limit.max(1.0) * distance.abs())
+            {
+                Some(tip) => out.push(tip),
+                None => {
+                    out.push(from);
+                    out.push(to);
+                }
+            },
+            OffsetJoin::Round => push_round_join(&mut out, vertex, from, to, distance),
+            OffsetJoin::Bevel => {
+                out.push(from);
+                out.push(to);
+            }
+        }
+    }
+    if closed {
+        out.push(out[0]);
+    } else {
+        out.push(edges[edge_count - 1].1);
+    }
+    out
+}
+
+/// Offset every subpath of `path` sideways by `distance` with the given
+/// corner [`OffsetJoin`]. Curves are flattened first; the result is a
+/// polyline path. Positive distances offset toward the `perp` side of
+/// travel, so for positively wound rings they move inward, matching
+/// [`offset_ring`].
+pub fn offset_path(path: &VectorPath, distance: f64, join: OffsetJoin) -> VectorPath {
+    let mut commands = Vec::new();
+    for subpath in path.flatten(DEFAULT_FLATTEN_TOLERANCE) {
+        if subpath.len() < 2 {
+            continue;
+        }
+        let closed = subpath.first() == subpath.last() && subpath.len() >= 4;
+        let offset = offset_polyline_with_join(&subpath, distance, join, closed);
+        commands.push(PathCommand::MoveTo { to: offset[0] });
+        let last = if closed { offset.len() - 1 } else { offset.len() };
+        for p in &offset[1..last] {
+            commands.push(PathCommand::LineTo { to: *p });
+        }
+        if closed {
+            commands.push(PathCommand::Close);
+        }
+    }
+    VectorPath { commands }
+}
+
+/// Set operation for [`boolean`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BooleanOp {
+    Union,
+    Intersection,
+    /// Area of the first operand not covered by the second.
+    Difference,
+}
+
+/// Intersection parameter of segment `a0`→`a1` with segment `b0`→`b1`, as
+/// `t` along the first segment, when the crossing is interior to both.
+fn segment_intersection_t(a0: Point, a1: Point, b0: Point, b1: Point) -> Option<f64> {
+    let r = a1 - a0;
+    let s = b1 - b0;
+    let denom = r.x * s.y - r.y * s.x;
+    if denom.abs() <= 1e-12 {
+        return None;
+    }
+    let t = ((b0.x - a0.x) * s.y - (b0.y - a0.y) * s.x) / denom;
+    let u = ((b0.x - a0.x) * r.y - (b0.y - a0.y) * r.x) / denom;
+    const EPS: f64 = 1e-9;
+    (t > EPS && t < 1.0 - EPS && u > -EPS && u < 1.0 + EPS).then_some(t)
+}
+
+/// Split every edge of `rings` at its crossings with `other`, returning
+/// directed fragments whose midpoints are strictly on one side of `other`.
+fn fragment_edges(rings: &[Vec<Point>], other: &[Vec<Point>]) -> Vec<(Point, Point)> {
+    let mut out = Vec::new();
+    for ring in rings {
+        for seg in ring.windows(2) {
+            let (p, q) = (seg[0], seg[1]);
+            let mut ts = vec![0.0, 1.0];
+            for oring in other {
+                for oseg in oring.windows(2) {
+                    if let Some(t) = segment_intersection_t(p, q, oseg[0], oseg[1]) {
+                        ts.push(t);
+                    }
+                }
+            }
+            ts.sort_by(f64::total_cmp);
+            for w in ts.windows(2) {
+                if w[1] - w[0] > 1e-9 {
+                    out.push((p.lerp(q, w[0]), p.lerp(q, w[1])));
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Chain directed fragments into closed loops by matching endpoints.
+fn stitch_fragments(mut fragments: Vec<(Point, Point)>) -> Vec<Vec<Point>> {
+    const EPS: f64 = 1e-6;
+    let mut loops = Vec::new();
+    while let Some((start, mut cursor)) = fragments.pop() {
+        let mut ring = vec![start, cursor];
+        while cursor.distance_to(start) > EPS {
+            let Some(next) = fragments
+                .iter()
+                .position(|(from, _)| from.distance_to(cursor) <= EPS)
+            else {
+                break; // Open chain (numeric noise): drop it.
+            };
+            let (_, to) = fragments.swap_remove(next);
+            cursor = to;
+            ring.push(to);
+        }
+        if cursor.distance_to(start) <= EPS && ring.len() >= 4 {
+            *ring.last_mut().expect("non-empty ring") = start;
+            loops.push(ring);
+        }
+    }
+    loops
+}
+
+/// Polygon boolean of two paths. Curves are flattened first; the result is
+/// a polyline path whose subpaths are the boundary loops of the combined
+/// region (hole loops included, in even-odd terms). Both operands must be
+/// closed; open subpaths are ignored.
+pub fn boolean(a: &VectorPath, b: &VectorPath, op: BooleanOp) -> VectorPath {
+    let closed = |rings: Vec<Vec<Point>>| -> Vec<Vec<Point>> {
+        rings
+            .into_iter()
+            .filter(|r| r.len() >= 4 && r.first() == r.last())
+            .collect()
+    };
+    let rings_a = closed(a.flatten(DEFAULT_FLATTEN_TOLERANCE));
+    let rings_b = closed(b.flatten(DEFAULT_FLATTEN_TOLERANCE));
+
+    let inside = |rings: &[Vec<Point>], p: Point, q: Point| point_in_rings(rings, p.lerp(q, 0.5));
+    let mut kept: Vec<(Point, Point)> = Vec::new();
+    for (p, q) in fragment_edges(&rings_a, &rings_b) {
+        let keep = match op {
+            BooleanOp::Union | BooleanOp::Difference => !inside(&rings_b, p, q),
+            BooleanOp::Intersection => inside(&rings_b, p, q),
+        };
+        if keep {
+            kept.push((p, q));
+        }
+    }
+    for (p, q) in fragment_edges(&rings_b, &rings_a) {
+        match op {
+            BooleanOp::Union if !inside(&rings_a, p, q) => kept.push((p, q)),
+            BooleanOp::Intersection if inside(&rings_a, p, q) => kept.push((p, q)),
+            // Subtracted boundary runs reversed so loops keep a consistent
+            // direction of travel.
+            BooleanOp::Difference if inside(&rings_a, p, q) => kept.push((q, p)),
+            _ => {}
+        }
+    }
+
+    let mut commands = Vec::new();
+    for ring in stitch_fragments(kept) {
+        commands.push(PathCommand::MoveTo { to: ring[0] });
+        for p in &ring[1..ring.len() - 1] {
+            commands.push(PathCommand::LineTo { to: *p });
+        }
+        commands.push(PathCommand::Close);
+    }
+    VectorPath { commands }
+}
+
+/// [`boolean`] union, with each boundary loop returned as its own path.
+pub fn union(a: &VectorPath, b: &VectorPath) -> Vec<VectorPath> {
+    split_loops(boolean(a, b, BooleanOp::Union))
+}
+
+/// [`boolean`] intersection, one path per boundary loop. Disjoint operands
+/// produce an empty vec.
+pub fn intersection(a: &VectorPath, b: &VectorPath) -> Vec<VectorPath> {
+    split_loops(boolean(a, b, BooleanOp::Intersection))
+}
+
+/// [`boolean`] difference (`a` minus `b`), one path per boundary loop.
+/// Disjoint operands return `a`'s loops unchanged.
+pub fn difference(a: &VectorPath, b: &VectorPath) -> Vec<VectorPath> {
+    split_loops(boolean(a, b, BooleanOp::Difference))
+}
+
+/// Split a multi-loop path into one path per `MoveTo..Close` run.
+fn split_loops(path: VectorPath) -> Vec<VectorPath> {
+    let mut out = Vec::new();
+    let mut commands: Vec<PathCommand> = Vec::new();
+    for cmd in path.commands {
+        if matches!(cmd, PathCommand::MoveTo { .. }) && !commands.is_empty() {
+            out.push(VectorPath {
+                commands: std::mem::take(&mut commands),
+            });
+        }
+        commands.push(cmd);
+    }
+    if !commands.is_empty() {
+        out.push(VectorPath { commands });
+    }
+    out
+}
+
+/// Strip import noise from a path: zero-length segments (consecutive
+/// coincident points) are dropped, degenerate curves collapse, and runs of
+/// collinear line segments merge into one, all judged against
+/// `epsilon_mm`. Subpath structure and real curves are left alone, so the
+/// flattened geometry is unchanged within the epsilon.
+pub fn clean(path: &VectorPath, epsilon_mm: f64) -> VectorPath {
+    let eps = epsilon_mm.max(0.0);
+    let mut out: Vec<PathCommand> = Vec::with_capacity(path.commands.len());
+    // Start of the most recent kept line segment, for collinearity checks.
+    let mut line_anchor: Option<Point> = None;
+    let mut current = Point::default();
+    for cmd in &path.commands {
+        match *cmd {
+            PathCommand::MoveTo { to } => {
+                out.push(PathCommand::MoveTo { to });
+                current = to;
+                line_anchor = None;
+            }
+            PathCommand::LineTo { to } => {
+                if to.distance_to(current) <= eps {
+                    continue;
+                }
+                // Collapse A→B, B→C into A→C when B sits on the A→C chord.
+                if let (Some(anchor), Some(PathCommand::LineTo { to: last })) =
+                    (line_anchor, out.last_mut())
+                {
+                    if crate::scene::point_to_segment_dist_sq(current, anchor, to) <= eps * eps {
+                        *last = to;
+                        current = to;
+                        continue;
+                    }
+                }
+                line_anchor = Some(current);
+                out.push(PathCommand::LineTo { to });
+                current = to;
+            }
+            PathCommand::CurveTo { c1, c2, to } => {
+                if c1.distance_to(current) <= eps
+                    && c2.distance_to(current) <= eps
+                    && to.distance_to(current) <= eps
+                {
+                    continue;
+                }
+                out.push(*cmd);
+                current = to;
+                line_anchor = None;
+            }
+            PathCommand::Close => {
+                out.push(PathCommand::Close);
+                line_anchor = None;
+            }
+        }
+    }
+    VectorPath { commands: out }
+}
+
+/// The point at arc length `length` along a flattened polyline (clamped to
+/// the ends). This is the parameterization the stitch generators place by:
+/// equal length steps give equal spacing no matter how unevenly the
+/// polyline's vertices are distributed.
+pub fn param_at_length(points: &[Point], length: f64) -> Point {
+    if points.is_empty() {
+        return Point::new(0.0, 0.0);
+    }
+    if points.len() == 1 {
+        return points[0];
+    }
+    let cumulative = crate::stitch::running::cumulative_lengths(points);
+    crate::stitch::running::point_at(points, &cumulative, length)
+}
+
+/// Arc length at the uniform vertex parameter `t` (0 at the first point,
+/// 1 at the last, linear in vertex index). Chord stepping — advancing `t`
+/// in equal increments — lands here, which drifts from equal arc-length
+/// spacing wherever segment lengths vary.
+pub fn length_at_param(points: &[Point], t: f64) -> f64 {
+    if points.len() < 2 {
+        return 0.0;
+    }
+    let cumulative = crate::stitch::running::cumulative_lengths(points);
+    let pos = t.clamp(0.0, 1.0) * (points.len() - 1) as f64;
+    let seg = (pos.floor() as usize).min(points.len() - 2);
+    let frac = pos - seg as f64;
+    cumulative[seg] + frac * (cumulative[seg + 1] - cumulative[seg])
+}
+
+/// Even-odd point-in-polygon test over a set of rings.
+pub fn point_in_rings(rings: &[Vec<Point>], p: Point) -> bool {
+    let mut inside = false;
+    for ring in rings {
+        let n = ring.len();
+        if n < 3 {
+            continue;
+        }
+        let mut j = n - 1;
+        for i in 0..n {
+            let a = ring[i];
+            let b = ring[j];
+            if (a.y > p.y) != (b.y > p.y) {
+                let x_cross = a.x + (p.y - a.y) / (b.y - a.y) * (b.x - a.x);
+                if p.x < x_cross {
+                    inside = !inside;
+                }
+            }
+            j = i;
+        }
+    }
+    inside
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flatten_polyline_is_identity() {
+        let pts = [
+            Point::new(0.0, 0.0),
+            Point::new(5.0, 0.0),
+            Point::new(5.0, 5.0),
+        ];
+        let path = VectorPath::from_polyline(&pts);
+        let flat = path.flatten(DEFAULT_FLATTEN_TOLERANCE);
+        assert_eq!(flat.len(), 1);
+        assert_eq!(flat[0], pts.to_vec());
+        assert!(!path.is_closed());
+    }
+
+    #[test]
+    fn flatten_closed_polygon_repeats_start() {
+        let pts = [
+            Point::new(0.0, 0.0),
+            Point::new(10.0, 0.0),
+            Point::new(10.0, 10.0),
+        ];
+        let path = VectorPath::from_polygon(&pts);
+        assert!(path.is_closed());
+        let flat = path.flatten(DEFAULT_FLATTEN_TOLERANCE);
+        assert_eq!(flat[0].first(), flat[0].last());
+    }
+
+    #[test]
+    fn cubic_flattening_respects_tolerance() {
+        // Quarter-circle-ish arc of radius 10.
+        let path = VectorPath {
+            commands: vec![
+                PathCommand::MoveTo {
+                    to: Point::new(10.0, 0.0),
+                },
+                PathCommand::CurveTo {
+                    c1: Point::new(10.0, 5.52),
+                    c2: Point::new(5.52, 10.0),
+                    to: Point::new(0.0, 10.0),
+                },
+            ],
+        };
+        let flat = path.flatten(0.01);
+        assert!(flat[0].len() > 4);
+        for p in &flat[0] {
+            let r = p.length();
+            assert!((r - 10.0).abs() < 0.1, "radius {r} too far off");
+        }
+    }
+
+    #[test]
+    fn closest_point_is_perpendicular_foot() {
+        let path = VectorPath::from_polyline(&[Point::new(0.0, 0.0), Point::new(10.0, 0.0)]);
+        let (p, d) = closest_point_on_path(&path, Point::new(4.0, 3.0));
+        assert!((p.x - 4.0).abs() < 1e-9);
+        assert!(p.y.abs() < 1e-9);
+        assert!((d - 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn closest_point_clamps_to_endpoints() {
+        let path = VectorPath::from_polyline(&[Point::new(0.0, 0.0), Point::new(10.0, 0.0)]);
+        let (p, d) = closest_point_on_path(&path, Point::new(13.0, 4.0));
+        assert!((p.x - 10.0).abs() < 1e-9);
+        assert!((d - 5.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn clean_collapses_noise_without_moving_geometry() {
+        // L-shape with a duplicated vertex, a zero-length segment, and a
+        // collinear midpoint along the first leg.
+        let noisy = VectorPath {
+            commands: vec![
+                PathCommand::MoveTo {
+                    to: Point::new(0.0, 0.0),
+                },
+                PathCommand::LineTo {
+                    to: Point::new(5.0, 0.0),
+                },
+                PathCommand::LineTo {
+                    to: Point::new(5.0, 0.0),
+                },
+                PathCommand::LineTo {
+                    to: Point::new(10.0, 0.0),
+                },
+                PathCommand::LineTo {
+                    to: Point::new(10.0, 10.0),
+                },
+            ],
+        };
+        let cleaned = clean(&noisy, 1e-6);
+        assert_eq!(
+            cleaned.commands,
+            vec![
+                PathCommand::MoveTo {
+                    to: Point::new(0.0, 0.0)
+                },
+                PathCommand::LineTo {
+                    to: Point::new(10.0, 0.0)
+                },
+                PathCommand::LineTo {
+                    to: Point::new(10.0, 10.0)
+                },
+            ]
+        );
+        // The flattened outline still traces the same corners.
+        let flat = cleaned.flatten(DEFAULT_FLATTEN_TOLERANCE);
+        assert_eq!(
+            flat[0],
+            vec![
+                Point::new(0.0, 0.0),
+                Point::new(10.0, 0.0),
+                Point::new(10.0, 10.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn arc_length_steps_stay_even_where_chord_steps_drift() {
+        // Quarter circle flattened with vertices clustered near the start,
+        // the way adaptive flattening clusters them in high curvature.
+        let curve: Vec<Point> = [0.0, 0.05, 0.12, 0.22, 0.4, 0.65, 1.0]
+            .iter()
+            .map(|t| {
+                let a = t * std::f64::consts::FRAC_PI_2;
+                Point::new(10.0 * a.cos(), 10.0 * a.sin())
+            })
+            .collect();
+        let total: f64 = curve.windows(2).map(|w| w[0].distance_to(w[1])).sum();
+
+        // Equal arc-length steps land equally far apart along the polyline.
+        let step = total / 10.0;
+        let walked: Vec<Point> = (0..=10)
+            .map(|i| param_at_length(&curve, step * i as f64))
+            .collect();
+        for w in walked.windows(2) {
+            let d = w[0].distance_to(w[1]);
+            // Straight-line spacing dips slightly below `step` at corners.
+            assert!(d <= step + 1e-9 && d > step * 0.95, "spacing {d} vs {step}");
+        }
+
+        // Uniform vertex-parameter steps cover wildly different lengths.
+        let chord: Vec<f64> = (0..=6)
+            .map(|i| length_at_param(&curve, i as f64 / 6.0))
+            .collect();
+        let spans: Vec<f64> = chord.windows(2).map(|w| w[1] - w[0]).collect();
+        let (min, max) = spans
+            .iter()
+            .fold((f64::INFINITY, 0.0_f64), |(lo, hi), &s| (lo.min(s), hi.max(s)));
+        assert!(max / min > 3.0, "chord stepping spans {min}..{max}");
+    }
+
+    #[test]
+    fn point_in_rings_even_odd() {
+        let outer = vec![
+            Point::new(0.0, 0.0),
+            Point::new(10.0, 0.0),
+            Point::new(10.0, 10.0),
+            Point::new(0.0, 10.0),
+        ];
+        let hole = vec![
+            Point::new(4.0, 4.0),
+            Point::new(6.0, 4.0),
+            Point::new(6.0, 6.0),
+            Point::new(4.0, 6.0),
+        ];
+        let rings = vec![outer, hole];
+        assert!(point_in_rings(&rings, Point::new(2.0, 2.0)));
+        assert!(!point_in_rings(&rings, Point::new(5.0, 5.0)));
+        assert!(!point_in_rings(&rings, Point::new(20.0, 5.0)));
+    }
+
+    #[test]
+    fn convex_outward_offset_grows_cleanly_mitered_corners() {
+        // CCW square, so outward is the negative-`perp` side.
+        let ring = vec![
+            Point::new(0.0, 0.0),
+            Point::new(10.0, 0.0),
+            Point::new(10.0, 10.0),
+            Point::new(0.0, 10.0),
+            Point::new(0.0, 0.0),
+        ];
+        let offset = offset_polyline_with_join(&ring, -2.0, OffsetJoin::Miter(4.0), true);
+        // Each 90° corner resolves to a single miter tip: no extra points,
+        // no crossings — exactly the input's shape, two units out.
+        assert_eq!(offset.len(), ring.len());
+        let area = offset
+            .windows(2)
+            .map(|w| w[0].x * w[1].y - w[1].x * w[0].y)
+            .sum::<f64>()
+            .abs()
+            * 0.5;
+        assert!((area - 196.0).abs() < 1e-6, "area {area}");
+        for p in &offset {
+            assert!((-2.0 - 1e-9..=12.0 + 1e-9).contains(&p.x));
+            assert!((-2.0 - 1e-9..=12.0 + 1e-9).contains(&p.y));
+        }
+    }
+
+    #[test]
+    fn boolean_ops_cover_the_expected_area() {
+        let rect = |x: f64, y: f64, w: f64, h: f64| {
+            VectorPath::from_polygon(&[
+                Point::new(x, y),
+                Point::new(x + w, y),
+                Point::new(x + w, y + h),
+                Point::new(x, y + h),
+            ])
+        };
+        let area = |path: &VectorPath| -> f64 {
+            path.flatten(0.1)
+                .iter()
+                .map(|ring| {
+                    ring.windows(2)
+                        .map(|w| w[0].x * w[1].y - w[1].x * w[0].y)
+                        .sum::<f64>()
+                        .abs()
+                        * 0.5
+                })
+                .sum()
+        };
+        // 10×10 squares with a 5×7 overlap.
+        let a = rect(0.0, 0.0, 10.0, 10.0);
+        let b = rect(5.0, 3.0, 10.0, 10.0);
+        assert!((area(&boolean(&a, &b, BooleanOp::Union)) - 165.0).abs() < 0.5);
+        assert!((area(&boolean(&a, &b, BooleanOp::Intersection)) - 35.0).abs() < 0.5);
+        assert!((area(&boolean(&a, &b, BooleanOp::Difference)) - 65.0).abs() < 0.5);
+    }
+
+    #[test]
+    fn boolean_wrappers_handle_disjoint_and_contained_operands() {
+        let rect = |x: f64, y: f64, w: f64, h: f64| {
+            VectorPath::from_polygon(&[
+                Point::new(x, y),
+                Point::new(x + w, y),
+                Point::new(x + w, y + h),
+                Point::new(x, y + h),
+            ])
+        };
+        let area = |paths: &[VectorPath]| -> f64 {
+            paths
+                .iter()
+                .flat_map(|p| p.flatten(0.1))
+                .map(|ring| {
+                    ring.windows(2)
+                        .map(|w| w[0].x * w[1].y - w[1].x * w[0].y)
+                        .sum::<f64>()
+                        .abs()
+                        * 0.5
+                })
+                .sum()
+        };
+        let a = rect(0.0, 0.0, 10.0, 10.0);
+
+        // Disjoint: difference leaves `a`, intersection is empty, union is
+        // both loops.
+        let far = rect(20.0, 3.0, 10.0, 10.0);
+        assert!(intersection(&a, &far).is_empty());
+        let diff = difference(&a, &far);
+        assert_eq!(diff.len(), 1);
+        assert!((area(&diff) - 100.0).abs() < 0.5);
+        assert!((area(&union(&a, &far)) - 200.0).abs() < 0.5);
+
+        // Contained: union collapses to the outer, intersection to the
+        // inner.
+        let inner = rect(3.0, 3.0, 4.0, 3.0);
+        let merged = union(&a, &inner);
+        assert_eq!(merged.len(), 1);
+        assert!((area(&merged) - 100.0).abs() < 0.5);
+        assert!((area(&intersection(&a, &inner)) - 12.0).abs() < 0.5);
+
+        // Overlap: each loop comes back as its own path.
+        let b = rect(5.0, 3.0, 10.0, 10.0);
+        assert_eq!(union(&a, &b).len(), 1);
+        assert_eq!(intersection(&a, &b).len(), 1);
+        assert_eq!(difference(&a, &b).len(), 1);
+    }
+
+    /// A five-pointed star centered on the origin, outer radius 10.
+    fn star_path() -> VectorPath {
+        let pts: Vec<Point> = (0..10)
+            .map(|i| {
+                let r = if i % 2 == 0 { 10.0 } else { 4.0 };
+                let a = std::f64::consts::TAU * i as f64 / 10.0;
+                Point::new(r * a.cos(), r * a.sin())
+            })
+            .collect();
+        VectorPath::from_polygon(&pts)
+    }
+
+    #[test]
+    fn miter_limit_caps_offset_spikes() {
+        let star = star_path();
+        // Offset outward (negative for CCW winding). The star's points are
+        // ~36° — an unbounded miter there reaches ~3× the offset distance.
+        let capped = offset_path(&star, -2.0, OffsetJoin::Miter(1.5));
+        for subpath in capped.flatten(0.1) {
+            for p in subpath {
+                let from_origin = p.length();
+                assert!(from_origin < 10.0 + 2.0 * 1.5 + 0.01, "spike at {p:?}");
+            }
+        }
+        // Without the cap the same corner overshoots the limit.
+        let spiky = offset_path(&star, -2.0, OffsetJoin::Miter(100.0));
+        let max = spiky
+            .flatten(0.1)
+            .iter()
+            .flatten()
+            .map(|p| p.length())
+            .fold(0.0_f64, f64::max);
+        assert!(max > 10.0 + 2.0 * 1.5 + 0.01, "max {max}");
+    }
+
+    #[test]
+    fn round_join_arcs_never_leave_the_offset_band() {
+        let star = star_path();
+        let rounded = offset_path(&star, -1.0, OffsetJoin::Round);
+        // Round corners cap at exactly the offset distance, so nothing in
+        // the output strays farther than that from the source outline.
+        let mut max_dist = 0.0_f64;
+        for subpath in rounded.flatten(0.1) {
+            for p in subpath {
+                let (_, dist) = closest_point_on_path(&star, p);
+                max_dist = max_dist.max(dist);
+            }
+        }
+        assert!(max_dist <= 1.0 + 0.05, "round join overshoots: {max_dist}");
+        // And the arcs add points a bevel would not.
+        let bevel = offset_path(&star, -1.0, OffsetJoin::Bevel);
+        assert!(rounded.commands.len() > bevel.commands.len());
+    }
+}
@@ -0,0 +1,1862 @@
+//! Scene graph: the authoritative document model for the editor.
+//!
+//! Nodes form a tree (groups and shapes) addressed by stable numeric IDs.
+//! Rendering and export both traverse `root_children` depth-first, so
+//! traversal order is the layer order.
+
+use crate::error::EngineError;
+use crate::geometry::{BoundingBox, Point, Transform, Units};
+use crate::path::{PathCommand, VectorPath};
+use crate::shapes::{ShapeData, ShapeStyle};
+use crate::stitch::StitchParams;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+pub type NodeId = u64;
+
+/// Per-shape stitch sequencing metadata. Stitch order defaults to render
+/// order; an explicit index pins a shape to a position in the sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShapeSequencerMeta {
+    /// Explicit stitch-order position; `None` follows render order.
+    pub sequencer_index: Option<usize>,
+}
+
+/// A shape node's payload: geometry, styling, and stitch intent.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShapeNode {
+    pub data: ShapeData,
+    #[serde(default)]
+    pub style: ShapeStyle,
+    #[serde(default)]
+    pub stitch: StitchParams,
+    #[serde(default)]
+    pub sequencer: ShapeSequencerMeta,
+}
+
+/// What a node is. Groups only aggregate children; shapes carry geometry.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NodeKind {
+    Group,
+    Shape(Box<ShapeNode>),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Node {
+    pub id: NodeId,
+    pub parent: Option<NodeId>,
+    #[serde(default)]
+    pub children: Vec<NodeId>,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default = "default_true")]
+    pub visible: bool,
+    #[serde(default)]
+    pub locked: bool,
+    /// Render but never stitch: construction guides, registration marks.
+    /// Distinct from `visible`, which hides the node everywhere.
+    #[serde(default)]
+    pub export_excluded: bool,
+    #[serde(default)]
+    pub transform: Transform,
+    pub kind: NodeKind,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// A render item plus its shape's world-space outline flattened at a
+/// caller-chosen tolerance, for zoom-aware previews.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RenderItemLod {
+    pub node_id: NodeId,
+    pub world_transform: Transform,
+    /// World-space flattened subpaths; empty for group nodes.
+    pub polylines: Vec<Vec<Point>>,
+}
+
+/// One entry of the flattened render traversal, in draw order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RenderItem {
+    pub node_id: NodeId,
+    pub world_transform: Transform,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "SceneDoc")]
+pub struct Scene {
+    pub nodes: HashMap<NodeId, Node>,
+    pub root_children: Vec<NodeId>,
+    /// Design name shown by machines and embedded in exports. Persisted
+    /// with the document.
+    pub design_name: String,
+    /// One past the highest ID ever allocated. Derived, so saves omit it
+    /// and loading rebuilds it — stored copies can only drift from the
+    /// node map.
+    #[serde(skip)]
+    next_id: NodeId,
+    /// Lazily filled local-space shape bboxes, so per-frame bbox queries
+    /// don't re-flatten unchanged geometry. Invalidated on any mutable
+    /// node access.
+    #[serde(skip)]
+    bbox_cache: BboxCache,
+}
+
+/// The shape bbox cache: pure memoization, so it is ignored by equality
+/// and always cloned as filled so far.
+#[derive(Debug, Clone, Default)]
+struct BboxCache(RefCell<HashMap<NodeId, BoundingBox>>);
+
+impl PartialEq for BboxCache {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+/// The on-disk shape of [`Scene`]: only the authoritative node map and root
+/// ordering. Derived state is rebuilt in the [`From`] conversion.
+#[derive(Deserialize)]
+struct SceneDoc {
+    #[serde(default)]
+    nodes: HashMap<NodeId, Node>,
+    #[serde(default)]
+    root_children: Vec<NodeId>,
+    #[serde(default = "default_design_name")]
+    design_name: String,
+}
+
+fn default_design_name() -> String {
+    "design".to_string()
+}
+
+impl From<SceneDoc> for Scene {
+    fn from(doc: SceneDoc) -> Self {
+        let next_id = doc.nodes.keys().max().map_or(1, |max| max + 1);
+        Self {
+            nodes: doc.nodes,
+            root_children: doc.root_children,
+            design_name: doc.design_name,
+            next_id,
+            bbox_cache: BboxCache::default(),
+        }
+    }
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Scene {
+    pub fn new() -> Self {
+        Self {
+            nodes: HashMap::new(),
+            root_children: Vec::new(),
+            design_name: "design".to_string(),
+            next_id: 1,
+            bbox_cache: BboxCache::default(),
+        }
+    }
+
+    /// Mark a node as render-only: it keeps drawing but the export skips
+    /// it. Returns the previous value.
+    pub fn set_export_excluded(&mut self, id: NodeId, excluded: bool) -> Result<bool, EngineError> {
+        let node = self.node_mut(id)?;
+        Ok(std::mem::replace(&mut node.export_excluded, excluded))
+    }
+
+    /// Set the design name used by exports. Empty names fall back to the
+    /// default so headers never carry a blank field.
+    pub fn set_design_name(&mut self, name: &str) {
+        let trimmed = name.trim();
+        self.design_name = if trimmed.is_empty() {
+            "design".to_string()
+        } else {
+            trimmed.to_string()
+        };
+    }
+
+    fn alloc_id(&mut self) -> NodeId {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    /// Insert a node under `parent` (or at the root), appended last in layer
+    /// order. Returns the new node's ID.
+    pub fn add_node(&mut self, kind: NodeKind, parent: Option<NodeId>) -> Result<NodeId, EngineError> {
+        if let Some(pid) = parent {
+            match self.nodes.get(&pid) {
+                Some(p) if matches!(p.kind, NodeKind::Group) => {}
+                Some(_) => return Err(EngineError::InvalidInput(format!("node {pid} is not a group"))),
+                None => return Err(EngineError::NodeNotFound(pid)),
+            }
+        }
+        let id = self.alloc_id();
+        let node = Node {
+            id,
+            parent,
+            children: Vec::new(),
+            name: String::new(),
+            visible: true,
+            locked: false,
+            export_excluded: false,
+            transform: Transform::identity(),
+            kind,
+        };
+        self.nodes.insert(id, node);
+        match parent {
+            Some(pid) => self
+                .nodes
+                .get_mut(&pid)
+                .expect("parent checked above")
+                .children
+                .push(id),
+            None => self.root_children.push(id),
+        }
+        Ok(id)
+    }
+
+    pub fn node(&self, id: NodeId) -> Result<&Node, EngineError> {
+        self.nodes.get(&id).ok_or(EngineError::NodeNotFound(id))
+    }
+
+    pub fn node_mut(&mut self, id: NodeId) -> Result<&mut Node, EngineError> {
+        // Any mutable access may rewrite geometry; drop the cached bbox.
+        self.bbox_cache.0.borrow_mut().remove(&id);
+        self.nodes
+            .get_mut(&id)
+            .ok_or(EngineError::NodeNotFound(id))
+    }
+
+    /// Remove a node and its whole subtree.
+    pub fn remove_node(&mut self, id: NodeId) -> Result<(), EngineError> {
+        let node = self.node(id)?;
+        if node.locked {
+            return Err(EngineError::Locked(id));
+        }
+        let parent = node.parent;
+        let mut stack = vec![id];
+        while let Some(nid) = stack.pop() {
+            if let Some(n) = self.nodes.remove(&nid) {
+                stack.extend(n.children);
+            }
+        }
+        match parent {
+            Some(pid) => {
+                if let Some(p) = self.nodes.get_mut(&pid) {
+                    p.children.retain(|c| *c != id);
+                }
+            }
+            None => self.root_children.retain(|c| *c != id),
+        }
+        Ok(())
+    }
+
+    pub fn set_transform(&mut self, id: NodeId, transform: Transform) -> Result<(), EngineError> {
+        let node = self.node_mut(id)?;
+        if node.locked {
+            return Err(EngineError::Locked(id));
+        }
+        node.transform = transform;
+        Ok(())
+    }
+
+    /// Set (or clear) the thread color stitched for a shape's block,
+    /// independent of its fill/stroke. Returns the previous override.
+    pub fn set_block_color(
+        &mut self,
+        id: NodeId,
+        color: Option<crate::shapes::Color>,
+    ) -> Result<Option<crate::shapes::Color>, EngineError> {
+        let node = self.node_mut(id)?;
+        if node.locked {
+            return Err(EngineError::Locked(id));
+        }
+        let NodeKind::Shape(shape) = &mut node.kind else {
+            return Err(EngineError::NotAShape(id));
+        };
+        Ok(std::mem::replace(&mut shape.stitch.color_override, color))
+    }
+
+    /// Insert a manual machine command before the stitch at `at_index` of a
+    /// shape's block. The command list stays sorted by index.
+    pub fn insert_stitch_command(
+        &mut self,
+        id: NodeId,
+        at_index: usize,
+        kind: crate::stitch::ManualCommandKind,
+    ) -> Result<(), EngineError> {
+        let node = self.node_mut(id)?;
+        if node.locked {
+            return Err(EngineError::Locked(id));
+        }
+        let NodeKind::Shape(shape) = &mut node.kind else {
+            return Err(EngineError::NotAShape(id));
+        };
+        let commands = &mut shape.stitch.manual_commands;
+        let pos = commands.partition_point(|c| c.at_index <= at_index);
+        commands.insert(pos, crate::stitch::ManualStitchCommand { at_index, kind });
+        Ok(())
+    }
+
+    /// Remove the manual command anchored at `at_index` of a shape's block;
+    /// returns the removed command.
+    pub fn remove_stitch_command(
+        &mut self,
+        id: NodeId,
+        at_index: usize,
+    ) -> Result<crate::stitch::ManualStitchCommand, EngineError> {
+        let node = self.node_mut(id)?;
+        if node.locked {
+            return Err(EngineError::Locked(id));
+        }
+        let NodeKind::Shape(shape) = &mut node.kind else {
+            return Err(EngineError::NotAShape(id));
+        };
+        let commands = &mut shape.stitch.manual_commands;
+        let pos = commands
+            .iter()
+            .position(|c| c.at_index == at_index)
+            .ok_or_else(|| {
+                EngineError::InvalidInput(format!(
+                    "node {id} has no manual command at index {at_index}"
+                ))
+            })?;
+        Ok(commands.remove(pos))
+    }
+
+    /// A shape's stitch parameters and style together, for technique
+    /// conversions that rewrite both (history snapshots).
+    pub fn stitch_profile(
+        &self,
+        id: NodeId,
+    ) -> Result<(crate::stitch::StitchParams, crate::shapes::ShapeStyle), EngineError> {
+        let node = self.node(id)?;
+        let NodeKind::Shape(shape) = &node.kind else {
+            return Err(EngineError::NotAShape(id));
+        };
+        Ok((shape.stitch.clone(), shape.style.clone()))
+    }
+
+    /// Replace a shape's stitch parameters and style wholesale (history
+    /// walks).
+    pub(crate) fn set_stitch_profile(
+        &mut self,
+        id: NodeId,
+        profile: &(crate::stitch::StitchParams, crate::shapes::ShapeStyle),
+    ) {
+        if let Ok(node) = self.node_mut(id) {
+            if let NodeKind::Shape(shape) = &mut node.kind {
+                shape.stitch = profile.0.clone();
+                shape.style = profile.1.clone();
+            }
+        }
+    }
+
+    /// Replace nodes `a` and `b` with the boolean of their world-space
+    /// geometry. Each outer loop of the result becomes its own sibling
+    /// node (holes stay with the outer that contains them), inheriting
+    /// `a`'s style, stitch params, and parent. Returns the created IDs;
+    /// the caller records history.
+    pub(crate) fn boolean_replace(
+        &mut self,
+        a: NodeId,
+        b: NodeId,
+        op: crate::path::BooleanOp,
+    ) -> Result<Vec<NodeId>, EngineError> {
+        let world_path = |scene: &Self, id: NodeId| -> Result<VectorPath, EngineError> {
+            let node = scene.node(id)?;
+            let NodeKind::Shape(shape) = &node.kind else {
+                return Err(EngineError::NotAShape(id));
+            };
+            Ok(shape.data.to_path().transformed(&scene.world_transform(id)?))
+        };
+        let result = crate::path::boolean(&world_path(self, a)?, &world_path(self, b)?, op);
+        if result.commands.is_empty() {
+            return Err(EngineError::InvalidInput(
+                "boolean result has no geometry".to_string(),
+            ));
+        }
+
+        // Group the result loops into pieces: outer loops become nodes,
+        // hole loops attach to the first outer containing them (even-odd).
+        let loops = result.flatten(crate::path::DEFAULT_FLATTEN_TOLERANCE);
+        let is_hole: Vec<bool> = loops
+            .iter()
+            .enumerate()
+            .map(|(i, ring)| {
+                let probe = ring[0].lerp(ring[1], 0.5);
+                loops
+                    .iter()
+                    .enumerate()
+                    .filter(|(j, other)| *j != i && crate::path::point_in_rings(&[(*other).clone()], probe))
+                    .count()
+                    % 2
+                    == 1
+            })
+            .collect();
+        let mut pieces: Vec<Vec<&Vec<Point>>> = Vec::new();
+        for (ring, hole) in loops.iter().zip(&is_hole) {
+            if !hole {
+                pieces.push(vec![ring]);
+            }
+        }
+        for (ring, hole) in loops.iter().zip(&is_hole) {
+            if *hole {
+                let probe = ring[0];
+                if let Some(piece) = pieces
+                    .iter_mut()
+                    .find(|p| crate::path::point_in_rings(&[p[0].clone()], probe))
+                {
+                    piece.push(ring);
+                }
+            }
+        }
+
+        let src = self.node(a)?;
+        let parent = src.parent;
+        let NodeKind::Shape(shape) = &src.kind else {
+            return Err(EngineError::NotAShape(a));
+        };
+        let (style, stitch) = (shape.style.clone(), shape.stitch.clone());
+        // Result geometry is world-space; bake it back into the parent's
+        // frame so the new nodes carry identity transforms.
+        let to_parent = match parent {
+            Some(pid) => self
+                .world_transform(pid)?
+                .inverse()
+                .ok_or_else(|| EngineError::InvalidInput("parent transform is singular".to_string()))?,
+            None => Transform::identity(),
+        };
+
+        self.remove_subtree_raw(a);
+        self.remove_subtree_raw(b);
+        let mut created = Vec::new();
+        for piece in pieces {
+            let mut commands = Vec::new();
+            for ring in piece {
+                commands.push(PathCommand::MoveTo { to: ring[0] });
+                for p in &ring[1..ring.len() - 1] {
+                    commands.push(PathCommand::LineTo { to: *p });
+                }
+                commands.push(PathCommand::Close);
+            }
+            let path = VectorPath { commands }.transformed(&to_parent);
+            created.push(self.add_node(
+                NodeKind::Shape(Box::new(ShapeNode {
+                    data: ShapeData::Path(path),
+                    style: style.clone(),
+                    stitch: stitch.clone(),
+                    sequencer: Default::default(),
+                })),
+                parent,
+            )?);
+        }
+        Ok(created)
+    }
+
+    /// Upgrade a running-stitch line to a satin column of `width_mm` at
+    /// `density` mm zigzag spacing. The stroke drives satin width during
+    /// export, so the style's stroke width is rewritten and a missing
+    /// stroke color is filled from the block's thread color.
+    pub fn running_to_satin(
+        &mut self,
+        id: NodeId,
+        width_mm: f64,
+        density: f64,
+    ) -> Result<(), EngineError> {
+        if width_mm <= 0.0 || density <= 0.0 {
+            return Err(EngineError::InvalidInput(
+                "satin width and density must be positive".to_string(),
+            ));
+        }
+        let node = self.node_mut(id)?;
+        if node.locked {
+            return Err(EngineError::Locked(id));
+        }
+        let NodeKind::Shape(shape) = &mut node.kind else {
+            return Err(EngineError::NotAShape(id));
+        };
+        let thread = shape.style.thread_color();
+        shape.stitch.stitch_type = crate::stitch::StitchType::Satin;
+        shape.stitch.density = density;
+        shape.style.stroke.get_or_insert(thread);
+        shape.style.stroke_width = width_mm;
+        Ok(())
+    }
+
+    /// Downgrade a satin column back to a monoline running stitch along
+    /// its centerline.
+    pub fn satin_to_running(&mut self, id: NodeId) -> Result<(), EngineError> {
+        let node = self.node_mut(id)?;
+        if node.locked {
+            return Err(EngineError::Locked(id));
+        }
+        let NodeKind::Shape(shape) = &mut node.kind else {
+            return Err(EngineError::NotAShape(id));
+        };
+        shape.stitch.stitch_type = crate::stitch::StitchType::Running;
+        shape.style.stroke_width = 1.0;
+        Ok(())
+    }
+
+    /// A shape's manual command list.
+    pub fn manual_commands(
+        &self,
+        id: NodeId,
+    ) -> Result<Vec<crate::stitch::ManualStitchCommand>, EngineError> {
+        let node = self.node(id)?;
+        let NodeKind::Shape(shape) = &node.kind else {
+            return Err(EngineError::NotAShape(id));
+        };
+        Ok(shape.stitch.manual_commands.clone())
+    }
+
+    /// Replace a shape's manual command list wholesale (history walks).
+    pub(crate) fn set_manual_commands(
+        &mut self,
+        id: NodeId,
+        commands: Vec<crate::stitch::ManualStitchCommand>,
+    ) {
+        if let Ok(node) = self.node_mut(id) {
+            if let NodeKind::Shape(shape) = &mut node.kind {
+                shape.stitch.manual_commands = commands;
+            }
+        }
+    }
+
+    /// Run the geometry clean pass (see [`crate::path::clean`]) on a Path
+    /// shape in place; returns the path as it was before. Primitive shapes
+    /// have no command list to clean and error.
+    pub fn clean_path(
+        &mut self,
+        id: NodeId,
+        epsilon_mm: f64,
+    ) -> Result<crate::path::VectorPath, EngineError> {
+        let node = self.node_mut(id)?;
+        if node.locked {
+            return Err(EngineError::Locked(id));
+        }
+        let NodeKind::Shape(shape) = &mut node.kind else {
+            return Err(EngineError::NotAShape(id));
+        };
+        let crate::shapes::ShapeData::Path(path) = &mut shape.data else {
+            return Err(EngineError::InvalidInput(format!("node {id} is not a path shape")));
+        };
+        let before = path.clone();
+        *path = crate::path::clean(&before, epsilon_mm);
+        Ok(before)
+    }
+
+    /// Replace a Path shape's geometry wholesale (history walks).
+    pub(crate) fn set_shape_path(&mut self, id: NodeId, path: crate::path::VectorPath) {
+        if let Ok(node) = self.node_mut(id) {
+            if let NodeKind::Shape(shape) = &mut node.kind {
+                if let crate::shapes::ShapeData::Path(p) = &mut shape.data {
+                    *p = path;
+                }
+            }
+        }
+    }
+
+    /// Visible shape IDs in render (layer) order.
+    fn render_shape_ids(&self) -> Vec<NodeId> {
+        self.render_list()
+            .iter()
+            .filter(|item| {
+                matches!(
+                    self.nodes.get(&item.node_id).map(|n| &n.kind),
+                    Some(NodeKind::Shape(_))
+                )
+            })
+            .map(|item| item.node_id)
+            .collect()
+    }
+
+    /// Visible shape IDs in stitch sequence order: explicit sequencer
+    /// indexes win, unpinned shapes keep their render position (the sort is
+    /// stable, so ties stay in layer order).
+    pub fn sequencer_shape_ids(&self) -> Vec<NodeId> {
+        let mut shapes: Vec<(usize, NodeId)> = self
+            .render_shape_ids()
+            .into_iter()
+            .enumerate()
+            .collect();
+        shapes.sort_by_key(|(render_pos, id)| {
+            let pinned = match self.nodes.get(id).map(|n| &n.kind) {
+                Some(NodeKind::Shape(s)) => s.sequencer.sequencer_index,
+                _ => None,
+            };
+            pinned.unwrap_or(*render_pos)
+        });
+        shapes.into_iter().map(|(_, id)| id).collect()
+    }
+
+    /// Shapes whose stitch-sequence position disagrees with their render
+    /// (layer) position, so the UI can warn about what stitches first.
+    pub fn sequence_render_mismatches(&self) -> Vec<NodeId> {
+        self.render_shape_ids()
+            .iter()
+            .zip(self.sequencer_shape_ids())
+            .filter(|(render_id, seq_id)| **render_id != *seq_id)
+            .map(|(_, seq_id)| seq_id)
+            .collect()
+    }
+
+    /// Pin (or unpin) a shape's stitch-sequence position. Returns the
+    /// previous index.
+    pub fn set_sequencer_index(
+        &mut self,
+        id: NodeId,
+        index: Option<usize>,
+    ) -> Result<Option<usize>, EngineError> {
+        let node = self.node_mut(id)?;
+        let NodeKind::Shape(shape) = &mut node.kind else {
+            return Err(EngineError::NotAShape(id));
+        };
+        Ok(std::mem::replace(
+            &mut shape.sequencer.sequencer_index,
+            index,
+        ))
+    }
+
+    /// Rebuild everything derived from the node map after edits that
+    /// bypassed the command layer (batch imports, host-side document
+    /// surgery): the ID allocator, the bbox cache, and the stitch-sequence
+    /// pins. The resolved stitch order is kept exactly as it stands and
+    /// every shape is re-pinned to its position in it, so stale,
+    /// duplicate, or out-of-range pins normalize without changing the
+    /// user's ordering (the same freeze `reset_sequence_to_render_order`
+    /// performs, but against the sequence instead of the layers).
+    pub fn rebuild_derived_state(&mut self) {
+        self.next_id = self.nodes.keys().max().map_or(1, |max| max + 1);
+        self.bbox_cache.0.borrow_mut().clear();
+        for (pos, id) in self.sequencer_shape_ids().into_iter().enumerate() {
+            let _ = self.set_sequencer_index(id, Some(pos));
+        }
+    }
+
+    /// Replace the whole stitch sequence at once (sequencer drag-and-drop
+    /// of several blocks). `ids` must be a permutation of the current
+    /// sequence — anything missing or extra is rejected before any pin
+    /// changes. Returns `(id, previous_index)` pairs for undo.
+    pub fn set_sequence_order(
+        &mut self,
+        ids: &[NodeId],
+    ) -> Result<Vec<(NodeId, Option<usize>)>, EngineError> {
+        let current = self.sequencer_shape_ids();
+        let mut want = ids.to_vec();
+        let mut have = current.clone();
+        want.sort_unstable();
+        have.sort_unstable();
+        if want != have {
+            return Err(EngineError::InvalidInput(format!(
+                "sequence order must be a permutation of the {} current blocks",
+                current.len()
+            )));
+        }
+        let mut before = Vec::new();
+        for (pos, id) in ids.iter().enumerate() {
+            before.push((*id, self.set_sequencer_index(*id, Some(pos))?));
+        }
+        Ok(before)
+    }
+
+    /// Rewrite every visible shape's sequencer index to match the current
+    /// render traversal. Returns `(id, previous_index)` pairs for undo.
+    pub fn reset_sequence_to_render_order(&mut self) -> Vec<(NodeId, Option<usize>)> {
+        let mut before = Vec::new();
+        for (pos, id) in self.render_shape_ids().into_iter().enumerate() {
+            if let Ok(prev) = self.set_sequencer_index(id, Some(pos)) {
+                before.push((id, prev));
+            }
+        }
+        before
+    }
+
+    /// Replay explicit `(id, index)` assignments (history walks).
+    pub(crate) fn apply_sequencer_indexes(&mut self, entries: &[(NodeId, Option<usize>)]) {
+        for (id, index) in entries {
+            let _ = self.set_sequencer_index(*id, *index);
+        }
+    }
+
+    /// Clone the subtree at `id` as a sibling, shifting the clone's root
+    /// transform by `(dx, dy)` in its parent's space. Every node in the
+    /// clone gets a fresh ID; the clone is appended after its siblings.
+    pub(crate) fn duplicate_subtree(
+        &mut self,
+        id: NodeId,
+        dx: f64,
+        dy: f64,
+    ) -> Result<NodeId, EngineError> {
+        let snapshot = self.take_subtree_snapshot(id)?;
+        let mut remap: HashMap<NodeId, NodeId> = HashMap::new();
+        for node in &snapshot.nodes {
+            remap.insert(node.id, self.alloc_id());
+        }
+        for node in &snapshot.nodes {
+            let mut clone = node.clone();
+            clone.id = remap[&node.id];
+            // The root's parent sits outside the subtree and keeps its ID.
+            clone.parent = node.parent.map(|p| remap.get(&p).copied().unwrap_or(p));
+            clone.children = node.children.iter().map(|c| remap[c]).collect();
+            self.nodes.insert(clone.id, clone);
+        }
+        let new_root = remap[&id];
+        {
+            let root = self.node_mut(new_root)?;
+            root.transform = Transform::translation(dx, dy).compose(&root.transform);
+        }
+        match snapshot.parent {
+            Some(pid) => self.node_mut(pid)?.children.push(new_root),
+            None => self.root_children.push(new_root),
+        }
+        Ok(new_root)
+    }
+
+    /// Replicate the subtree at `id` across a `rows` x `cols` grid with
+    /// `(dx, dy)` cell spacing, for polka-dot and repeat patterns. Returns
+    /// the clone roots in row-major order; the cell-`(0, 0)` clone sits on
+    /// the source. Prefer [`CommandHistory::apply_array_replicate`] so the
+    /// whole grid is one undo step.
+    pub fn array_replicate(
+        &mut self,
+        id: NodeId,
+        rows: usize,
+        cols: usize,
+        dx: f64,
+        dy: f64,
+    ) -> Result<Vec<NodeId>, EngineError> {
+        if rows == 0 || cols == 0 {
+            return Err(EngineError::InvalidInput(
+                "array replication needs at least one row and column".to_string(),
+            ));
+        }
+        let mut created = Vec::with_capacity(rows * cols);
+        for r in 0..rows {
+            for c in 0..cols {
+                created.push(self.duplicate_subtree(id, c as f64 * dx, r as f64 * dy)?);
+            }
+        }
+        Ok(created)
+    }
+
+    /// Snapshot a subtree (depth-first, root first) with its attachment
+    /// point, for history entries.
+    pub(crate) fn take_subtree_snapshot(
+        &self,
+        id: NodeId,
+    ) -> Result<crate::command::SubtreeSnapshot, EngineError> {
+        let root = self.node(id)?;
+        let index = match root.parent {
+            Some(pid) => self
+                .node(pid)?
+                .children
+                .iter()
+                .position(|c| *c == id)
+                .ok_or_else(|| {
+                    EngineError::InvalidInput(format!("node {id} missing from parent child list"))
+                })?,
+            None => self
+                .root_children
+                .iter()
+                .position(|c| *c == id)
+                .ok_or_else(|| {
+                    EngineError::InvalidInput(format!("node {id} missing from root children"))
+                })?,
+        };
+        let mut nodes = Vec::new();
+        let mut stack = vec![id];
+        while let Some(nid) = stack.pop() {
+            let node = self.node(nid)?;
+            nodes.push(node.clone());
+            // Reverse so children pop in order, keeping DFS order stable.
+            stack.extend(node.children.iter().rev());
+        }
+        Ok(crate::command::SubtreeSnapshot {
+            nodes,
+            parent: root.parent,
+            index,
+        })
+    }
+
+    /// Remove a subtree without lock checks — history replay only.
+    pub(crate) fn remove_subtree_raw(&mut self, id: NodeId) {
+        let parent = self.nodes.get(&id).and_then(|n| n.parent);
+        let mut stack = vec![id];
+        while let Some(nid) = stack.pop() {
+            if let Some(n) = self.nodes.remove(&nid) {
+                stack.extend(n.children);
+            }
+        }
+        match parent {
+            Some(pid) => {
+                if let Some(p) = self.nodes.get_mut(&pid) {
+                    p.children.retain(|c| *c != id);
+                }
+            }
+            None => self.root_children.retain(|c| *c != id),
+        }
+    }
+
+    /// Re-insert a snapshotted subtree at its original position.
+    pub(crate) fn restore_subtree(
+        &mut self,
+        snapshot: &crate::command::SubtreeSnapshot,
+    ) -> Result<(), EngineError> {
+        let root_id = snapshot
+            .nodes
+            .first()
+            .ok_or_else(|| "empty subtree snapshot".to_string())?
+            .id;
+        for node in &snapshot.nodes {
+            self.next_id = self.next_id.max(node.id + 1);
+            self.nodes.insert(node.id, node.clone());
+        }
+        let index = snapshot.index;
+        match snapshot.parent {
+            Some(pid) => {
+                let parent = self.node_mut(pid)?;
+                let at = index.min(parent.children.len());
+                parent.children.insert(at, root_id);
+            }
+            None => {
+                let at = index.min(self.root_children.len());
+                self.root_children.insert(at, root_id);
+            }
+        }
+        Ok(())
+    }
+
+    /// The node's transform composed with all ancestors.
+    pub fn world_transform(&self, id: NodeId) -> Result<Transform, EngineError> {
+        let mut node = self.node(id)?;
+        let mut t = node.transform;
+        while let Some(pid) = node.parent {
+            node = self.node(pid)?;
+            t = node.transform.compose(&t);
+        }
+        Ok(t)
+    }
+
+    /// Depth-first traversal of visible nodes in draw order. Invisible nodes
+    /// hide their whole subtree.
+    pub fn render_list(&self) -> Vec<RenderItem> {
+        let mut out = Vec::new();
+        for &id in &self.root_children {
+            self.collect_render(id, Transform::identity(), &mut out);
+        }
+        out
+    }
+
+    fn collect_render(&self, id: NodeId, parent_t: Transform, out: &mut Vec<RenderItem>) {
+        let Some(node) = self.nodes.get(&id) else {
+            return;
+        };
+        if !node.visible {
+            return;
+        }
+        let world = parent_t.compose(&node.transform);
+        out.push(RenderItem {
+            node_id: id,
+            world_transform: world,
+        });
+        for &child in &node.children {
+            self.collect_render(child, world, out);
+        }
+    }
+
+    /// The render traversal with translations converted to `units` and
+    /// every transform component rounded to `decimals` places. The linear
+    /// part (scale/rotation) is unitless, so only `tx`/`ty` are scaled;
+    /// rounding alone shrinks JSON payloads considerably.
+    pub fn render_list_in_units(&self, units: Units, decimals: u32) -> Vec<RenderItem> {
+        let factor = units.per_mm();
+        let r = |v: f64| crate::geometry::round_to(v, decimals);
+        self.render_list()
+            .into_iter()
+            .map(|mut item| {
+                let t = &mut item.world_transform;
+                *t = Transform {
+                    a: r(t.a),
+                    b: r(t.b),
+                    c: r(t.c),
+                    d: r(t.d),
+                    tx: r(t.tx * factor),
+                    ty: r(t.ty * factor),
+                };
+                item
+            })
+            .collect()
+    }
+
+    /// The render traversal with each shape flattened at `tolerance` (mm in
+    /// world space). Zoomed-in callers pass a tighter tolerance for smooth
+    /// curves; zoomed-out callers a looser one to cut point counts.
+    pub fn render_list_lod(&self, tolerance: f64) -> Result<Vec<RenderItemLod>, EngineError> {
+        if tolerance <= 0.0 {
+            return Err(EngineError::InvalidInput(
+                "flatten tolerance must be positive".to_string(),
+            ));
+        }
+        Ok(self
+            .render_list()
+            .into_iter()
+            .map(|item| {
+                let polylines = match self.nodes.get(&item.node_id).map(|n| &n.kind) {
+                    Some(NodeKind::Shape(shape)) => shape
+                        .data
+                        .to_path()
+                        .transformed(&item.world_transform)
+                        .flatten(tolerance),
+                    _ => Vec::new(),
+                };
+                RenderItemLod {
+                    node_id: item.node_id,
+                    world_transform: item.world_transform,
+                    polylines,
+                }
+            })
+            .collect())
+    }
+
+    /// World-space bounding box of a node's subtree geometry, or `None` for
+    /// a subtree without shapes.
+    pub fn node_bounding_box(&self, id: NodeId) -> Result<Option<BoundingBox>, EngineError> {
+        let node = self.node(id)?;
+        let world = self.world_transform(id)?;
+        let mut bbox = BoundingBox::empty();
+        self.accumulate_bbox(node, world, &mut bbox);
+        Ok(if bbox.is_empty() { None } else { Some(bbox) })
+    }
+
+    fn accumulate_bbox(&self, node: &Node, world: Transform, bbox: &mut BoundingBox) {
+        if let NodeKind::Shape(shape) = &node.kind {
+            let local = self.local_shape_bbox(node.id, shape);
+            if !local.is_empty() {
+                for corner in [
+                    Point::new(local.min_x, local.min_y),
+                    Point::new(local.max_x, local.min_y),
+                    Point::new(local.min_x, local.max_y),
+                    Point::new(local.max_x, local.max_y),
+                ] {
+                    bbox.include(world.apply(corner));
+                }
+            }
+        }
+        for &child in &node.children {
+            if let Some(c) = self.nodes.get(&child) {
+                let child_world = world.compose(&c.transform);
+                self.accumulate_bbox(c, child_world, bbox);
+            }
+        }
+    }
+
+    /// The local-space bbox of one shape, memoized. Composing world boxes
+    /// from the cached corners is exact for axis-aligned transforms and
+    /// conservative (never too small) under rotation.
+    fn local_shape_bbox(&self, id: NodeId, shape: &ShapeNode) -> BoundingBox {
+        if let Some(b) = self.bbox_cache.0.borrow().get(&id) {
+            return *b;
+        }
+        let b = shape
+            .data
+            .to_path()
+            .bounding_box(crate::path::DEFAULT_FLATTEN_TOLERANCE);
+        self.bbox_cache.0.borrow_mut().insert(id, b);
+        b
+    }
+
+    /// Union world bbox of a selection, from the cached per-shape boxes.
+    /// IDs that are missing or empty subtrees are skipped.
+    pub fn selection_bbox(&self, ids: &[NodeId]) -> Option<BoundingBox> {
+        let mut bbox = BoundingBox::empty();
+        for &id in ids {
+            if let Ok(Some(b)) = self.node_bounding_box(id) {
+                bbox = bbox.union(&b);
+            }
+        }
+        if bbox.is_empty() {
+            None
+        } else {
+            Some(bbox)
+        }
+    }
+
+    /// Shape area in world units² (local area scaled by the world
+    /// transform's determinant).
+    pub fn node_area(&self, id: NodeId) -> Result<f64, EngineError> {
+        let node = self.node(id)?;
+        let NodeKind::Shape(shape) = &node.kind else {
+            return Err(EngineError::NotAShape(id));
+        };
+        let world = self.world_transform(id)?;
+        let det = (world.a * world.d - world.b * world.c).abs();
+        Ok(shape.data.area() * det)
+    }
+
+    /// Shape perimeter in world units. Uniformly scaled shapes use the exact
+    /// local formula; anisotropic transforms measure the transformed path.
+    pub fn node_perimeter(&self, id: NodeId) -> Result<f64, EngineError> {
+        let node = self.node(id)?;
+        let NodeKind::Shape(shape) = &node.kind else {
+            return Err(EngineError::NotAShape(id));
+        };
+        let world = self.world_transform(id)?;
+        let sx = (world.a * world.a + world.b * world.b).sqrt();
+        let sy = (world.c * world.c + world.d * world.d).sqrt();
+        if (sx - sy).abs() < 1e-9 {
+            Ok(shape.data.perimeter() * sx)
+        } else {
+            Ok(shape.data.to_path().transformed(&world).perimeter())
+        }
+    }
+
+    /// World-space point at arc length `length` along a shape's outline
+    /// (the first flattened subpath; clamped to its ends).
+    pub fn node_point_at_length(&self, id: NodeId, length: f64) -> Result<Point, EngineError> {
+        let node = self.node(id)?;
+        let NodeKind::Shape(shape) = &node.kind else {
+            return Err(EngineError::NotAShape(id));
+        };
+        let world = self.world_transform(id)?;
+        let path = shape.data.to_path().transformed(&world);
+        let subpaths = path.flatten(crate::path::DEFAULT_FLATTEN_TOLERANCE);
+        let outline = subpaths
+            .first()
+            .filter(|s| !s.is_empty())
+            .ok_or_else(|| EngineError::InvalidInput(format!("node {id} has no outline")))?;
+        Ok(crate::path::param_at_length(outline, length))
+    }
+
+    /// Union bounding box of all visible content.
+    pub fn content_bounds(&self) -> Option<BoundingBox> {
+        let mut bbox = BoundingBox::empty();
+        for item in self.render_list() {
+            if let Ok(node) = self.node(item.node_id) {
+                if let NodeKind::Shape(shape) = &node.kind {
+                    let path = shape.data.to_path().transformed(&item.world_transform);
+                    let b = path.bounding_box(crate::path::DEFAULT_FLATTEN_TOLERANCE);
+                    if !b.is_empty() {
+                        bbox = bbox.union(&b);
+                    }
+                }
+            }
+        }
+        if bbox.is_empty() {
+            None
+        } else {
+            Some(bbox)
+        }
+    }
+}
+
+/// Result of a scene-wide nearest-point query, in world coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ClosestPointHit {
+    pub node_id: NodeId,
+    pub x: f64,
+    pub y: f64,
+    pub distance: f64,
+}
+
+impl Scene {
+    /// Closest point on any visible shape's outline within `max_dist` of the
+    /// query, for snapping tools. `None` when nothing is near enough.
+    pub fn closest_point(&self, query: Point, max_dist: f64) -> Option<ClosestPointHit> {
+        let mut best: Option<ClosestPointHit> = None;
+        for item in self.render_list() {
+            let Ok(node) = self.node(item.node_id) else {
+                continue;
+            };
+            let NodeKind::Shape(shape) = &node.kind else {
+                continue;
+            };
+            let world_path = shape.data.to_path().transformed(&item.world_transform);
+            let (point, distance) = crate::path::closest_point_on_path(&world_path, query);
+            if distance <= max_dist && best.as_ref().is_none_or(|b| distance < b.distance) {
+                best = Some(ClosestPointHit {
+                    node_id: item.node_id,
+                    x: point.x,
+                    y: point.y,
+                    distance,
+                });
+            }
+        }
+        best
+    }
+}
+
+/// Which attributes [`Scene::find_similar`] matches on. Enabled criteria
+/// combine with AND; all fields default to off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SimilarityCriteria {
+    pub fill: bool,
+    pub stroke: bool,
+    pub stitch_type: bool,
+    pub shape_kind: bool,
+}
+
+impl Scene {
+    /// All visible shapes matching `id` on every enabled criterion, in
+    /// render order (the reference shape itself included). Backs the
+    /// "select similar" UI action.
+    pub fn find_similar(
+        &self,
+        id: NodeId,
+        criteria: SimilarityCriteria,
+    ) -> Result<Vec<NodeId>, EngineError> {
+        let NodeKind::Shape(reference) = &self.node(id)?.kind else {
+            return Err(EngineError::NotAShape(id));
+        };
+        let mut matches = Vec::new();
+        for item in self.render_list() {
+            let Ok(node) = self.node(item.node_id) else {
+                continue;
+            };
+            let NodeKind::Shape(shape) = &node.kind else {
+                continue;
+            };
+            if criteria.fill && shape.style.fill != reference.style.fill {
+                continue;
+            }
+            if criteria.stroke && shape.style.stroke != reference.style.stroke {
+                continue;
+            }
+            if criteria.stitch_type && shape.stitch.stitch_type != reference.stitch.stitch_type {
+                continue;
+            }
+            if criteria.shape_kind
+                && std::mem::discriminant(&shape.data) != std::mem::discriminant(&reference.data)
+            {
+                continue;
+            }
+            matches.push(item.node_id);
+        }
+        Ok(matches)
+    }
+}
+
+/// One inconsistency found by [`Scene::validate`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "issue", rename_all = "snake_case")]
+pub enum SceneIntegrityIssue {
+    /// A node's `parent` points at an ID not in the map.
+    DanglingParent { id: NodeId, parent: NodeId },
+    /// A child list entry points at an ID not in the map.
+    MissingChild { id: NodeId, child: NodeId },
+    /// A child's `parent` doesn't point back at the node listing it.
+    ParentMismatch { id: NodeId, child: NodeId },
+    /// A `root_children` entry is missing or has a parent.
+    BadRootEntry { id: NodeId },
+    /// A parentless node absent from `root_children`.
+    UnrootedNode { id: NodeId },
+    /// A node reachable from itself through parent links.
+    Cycle { id: NodeId },
+}
+
+impl Scene {
+    /// Check the graph's structural invariants — bidirectional
+    /// parent/child links, existing IDs, acyclic parent chains, and
+    /// `root_children` covering exactly the parentless nodes. A healthy
+    /// scene returns an empty list; anything else is a bug upstream, and
+    /// this is the safety net crash recovery runs before trusting a
+    /// restored document.
+    pub fn validate(&self) -> Vec<SceneIntegrityIssue> {
+        let mut issues = Vec::new();
+        for (&id, node) in &self.nodes {
+            if let Some(pid) = node.parent {
+                match self.nodes.get(&pid) {
+                    Some(p) => {
+                        if !p.children.contains(&id) {
+                            issues.push(SceneIntegrityIssue::ParentMismatch {
+                                id: pid,
+                                child: id,
+                            });
+                        }
+                    }
+                    None => issues.push(SceneIntegrityIssue::DanglingParent { id, parent: pid }),
+                }
+            } else if !self.root_children.contains(&id) {
+                issues.push(SceneIntegrityIssue::UnrootedNode { id });
+            }
+            for &child in &node.children {
+                match self.nodes.get(&child) {
+                    Some(c) => {
+                        if c.parent != Some(id) {
+                            issues.push(SceneIntegrityIssue::ParentMismatch { id, child });
+                        }
+                    }
+                    None => issues.push(SceneIntegrityIssue::MissingChild { id, child }),
+                }
+            }
+            // Walk the parent chain; revisiting `id` means a cycle, and a
+            // chain longer than the node count can only loop.
+            let mut cursor = node.parent;
+            let mut steps = 0;
+            while let Some(pid) = cursor {
+                if pid == id || steps > self.nodes.len() {
+                    issues.push(SceneIntegrityIssue::Cycle { id });
+                    break;
+                }
+                cursor = self.nodes.get(&pid).and_then(|n| n.parent);
+                steps += 1;
+            }
+        }
+        for &id in &self.root_children {
+            match self.nodes.get(&id) {
+                Some(n) if n.parent.is_none() => {}
+                _ => issues.push(SceneIntegrityIssue::BadRootEntry { id }),
+            }
+        }
+        issues
+    }
+}
+
+/// One fix applied by [`Scene::repair`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum RepairAction {
+    /// Removed a child list entry pointing at a missing node.
+    DroppedChildRef { id: NodeId, child: NodeId },
+    /// Re-pointed a child's `parent` at the node actually listing it.
+    RelinkedParent { id: NodeId, parent: NodeId },
+    /// Cleared a `parent` link that pointed at a missing node, or broke a
+    /// cycle, and appended the node to `root_children`.
+    Rerooted { id: NodeId },
+    /// Removed a `root_children` entry that was missing or parented.
+    DroppedRootRef { id: NodeId },
+    /// Appended a parentless node missing from `root_children`.
+    AddedRootRef { id: NodeId },
+}
+
+impl Scene {
+    /// Fix everything [`Scene::validate`] reports, preferring the repair
+    /// that keeps the most content reachable: dangling references are
+    /// dropped, stranded nodes are re-rooted, and cycles are broken by
+    /// detaching one link. Returns the actions taken (empty for a healthy
+    /// scene); `validate` is empty afterwards.
+    pub fn repair(&mut self) -> Vec<RepairAction> {
+        let mut actions = Vec::new();
+        let ids: Vec<NodeId> = self.nodes.keys().copied().collect();
+
+        // Child lists first: drop entries for missing nodes, and make the
+        // listed children point back (membership wins over the stale link).
+        for &id in &ids {
+            let children = self.nodes[&id].children.clone();
+            for child in children {
+                if !self.nodes.contains_key(&child) {
+                    self.nodes
+                        .get_mut(&id)
+                        .expect("iterating existing ids")
+                        .children
+                        .retain(|c| *c != child);
+                    actions.push(RepairAction::DroppedChildRef { id, child });
+                } else if self.nodes[&child].parent != Some(id) {
+                    self.nodes
+                        .get_mut(&child)
+                        .expect("checked above")
+                        .parent = Some(id);
+                    actions.push(RepairAction::RelinkedParent {
+                        id: child,
+                        parent: id,
+                    });
+                }
+            }
+        }
+
+        // Dangling parents and cycles both detach to the root.
+        for &id in &ids {
+            let Some(pid) = self.nodes[&id].parent else {
+                continue;
+            };
+            let dangling = !self.nodes.contains_key(&pid);
+            let mut cyclic = false;
+            if !dangling {
+                let mut cursor = Some(pid);
+                let mut steps = 0;
+                while let Some(p) = cursor {
+                    if p == id || steps > self.nodes.len() {
+                        cyclic = true;
+                        break;
+                    }
+                    cursor = self.nodes.get(&p).and_then(|n| n.parent);
+                    steps += 1;
+                }
+            }
+            if dangling || cyclic {
+                if !dangling {
+                    if let Some(p) = self.nodes.get_mut(&pid) {
+                        p.children.retain(|c| *c != id);
+                    }
+                }
+                let node = self.nodes.get_mut(&id).expect("iterating existing ids");
+                node.parent = None;
+                self.root_children.push(id);
+                actions.push(RepairAction::Rerooted { id });
+            }
+        }
+
+        // Reconcile root_children with the parentless set.
+        let mut seen = std::collections::HashSet::new();
+        let roots = std::mem::take(&mut self.root_children);
+        for id in roots {
+            let keep = seen.insert(id)
+                && matches!(self.nodes.get(&id), Some(n) if n.parent.is_none());
+            if keep {
+                self.root_children.push(id);
+            } else {
+                actions.push(RepairAction::DroppedRootRef { id });
+            }
+        }
+        for &id in &ids {
+            if self.nodes[&id].parent.is_none() && !self.root_children.contains(&id) {
+                self.root_children.push(id);
+                actions.push(RepairAction::AddedRootRef { id });
+            }
+        }
+
+        if !actions.is_empty() {
+            self.bbox_cache.0.borrow_mut().clear();
+        }
+        actions
+    }
+}
+
+/// A minimal, serializable description of one scene change, for incremental
+/// sync. Produced by [`Scene::diff`] and replayed with [`Scene::apply_delta`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "delta", rename_all = "snake_case")]
+pub enum SceneDelta {
+    NodeAdded {
+        node: Node,
+    },
+    NodeRemoved {
+        id: NodeId,
+    },
+    TransformChanged {
+        id: NodeId,
+        transform: Transform,
+    },
+    KindChanged {
+        id: NodeId,
+        kind: NodeKind,
+    },
+    MetaChanged {
+        id: NodeId,
+        name: String,
+        visible: bool,
+        locked: bool,
+    },
+    /// Child ordering (or reparenting) of a group, or of the root when `id`
+    /// is `None`.
+    ChildrenChanged {
+        id: Option<NodeId>,
+        children: Vec<NodeId>,
+    },
+}
+
+impl Scene {
+    /// Produce the minimal delta list that turns `self` into `other`.
+    ///
+    /// Per-field variants keep common edits (a drag, a recolor) to a single
+    /// small delta instead of shipping whole nodes.
+    pub fn diff(&self, other: &Scene) -> Vec<SceneDelta> {
+        let mut deltas = Vec::new();
+
+        let mut removed: Vec<NodeId> = self
+            .nodes
+            .keys()
+            .filter(|id| !other.nodes.contains_key(id))
+            .copied()
+            .collect();
+        removed.sort_unstable();
+        for id in removed {
+            deltas.push(SceneDelta::NodeRemoved { id });
+        }
+
+        let mut added: Vec<NodeId> = other
+            .nodes
+            .keys()
+            .filter(|id| !self.nodes.contains_key(id))
+            .copied()
+            .collect();
+        added.sort_unstable();
+        for id in added {
+            deltas.push(SceneDelta::NodeAdded {
+                node: other.nodes[&id].clone(),
+            });
+        }
+
+        let mut shared: Vec<NodeId> = self
+            .nodes
+            .keys()
+            .filter(|id| other.nodes.contains_key(id))
+            .copied()
+            .collect();
+        shared.sort_unstable();
+        for id in shared {
+            let a = &self.nodes[&id];
+            let b = &other.nodes[&id];
+            if a.transform != b.transform {
+                deltas.push(SceneDelta::TransformChanged {
+                    id,
+                    transform: b.transform,
+                });
+            }
+            if a.kind != b.kind {
+                deltas.push(SceneDelta::KindChanged {
+                    id,
+                    kind: b.kind.clone(),
+                });
+            }
+            if a.name != b.name || a.visible != b.visible || a.locked != b.locked {
+                deltas.push(SceneDelta::MetaChanged {
+                    id,
+                    name: b.name.clone(),
+                    visible: b.visible,
+                    locked: b.locked,
+                });
+            }
+            if a.children != b.children {
+                deltas.push(SceneDelta::ChildrenChanged {
+                    id: Some(id),
+                    children: b.children.clone(),
+                });
+            }
+        }
+
+        if self.root_children != other.root_children {
+            deltas.push(SceneDelta::ChildrenChanged {
+                id: None,
+                children: other.root_children.clone(),
+            });
+        }
+
+        deltas
+    }
+
+    /// Replay one delta produced by [`Scene::diff`].
+    pub fn apply_delta(&mut self, delta: SceneDelta) -> Result<(), EngineError> {
+        match delta {
+            SceneDelta::NodeAdded { node } => {
+                self.next_id = self.next_id.max(node.id + 1);
+                match node.parent {
+                    Some(pid) => {
+                        if let Some(p) = self.nodes.get_mut(&pid) {
+                            if !p.children.contains(&node.id) {
+                                p.children.push(node.id);
+                            }
+                        }
+                    }
+                    None => {
+                        if !self.root_children.contains(&node.id) {
+                            self.root_children.push(node.id);
+                        }
+                    }
+                }
+                self.bbox_cache.0.borrow_mut().remove(&node.id);
+                self.nodes.insert(node.id, node);
+                Ok(())
+            }
+            SceneDelta::NodeRemoved { id } => {
+                if let Some(node) = self.nodes.remove(&id) {
+                    match node.parent {
+                        Some(pid) => {
+                            if let Some(p) = self.nodes.get_mut(&pid) {
+                                p.children.retain(|c| *c != id);
+                            }
+                        }
+                        None => self.root_children.retain(|c| *c != id),
+                    }
+                }
+                Ok(())
+            }
+            SceneDelta::TransformChanged { id, transform } => {
+                self.node_mut(id)?.transform = transform;
+                Ok(())
+            }
+            SceneDelta::KindChanged { id, kind } => {
+                self.node_mut(id)?.kind = kind;
+                Ok(())
+            }
+            SceneDelta::MetaChanged {
+                id,
+                name,
+                visible,
+                locked,
+            } => {
+                let node = self.node_mut(id)?;
+                node.name = name;
+                node.visible = visible;
+                node.locked = locked;
+                Ok(())
+            }
+            SceneDelta::ChildrenChanged { id, children } => {
+                for &child in &children {
+                    if let Some(c) = self.nodes.get_mut(&child) {
+                        c.parent = id;
+                    }
+                }
+                match id {
+                    Some(pid) => self.node_mut(pid)?.children = children,
+                    None => self.root_children = children,
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Squared distance from `p` to segment `ab`; shared by hit-testing helpers.
+pub fn point_to_segment_dist_sq(p: Point, a: Point, b: Point) -> f64 {
+    let ab = b - a;
+    let len_sq = ab.x * ab.x + ab.y * ab.y;
+    if len_sq <= f64::EPSILON {
+        let d = p - a;
+        return d.x * d.x + d.y * d.y;
+    }
+    let t = (((p.x - a.x) * ab.x + (p.y - a.y) * ab.y) / len_sq).clamp(0.0, 1.0);
+    let proj = a.lerp(b, t);
+    let d = p - proj;
+    d.x * d.x + d.y * d.y
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shapes::{RectShape, ShapeData};
+
+    fn rect_node(w: f64, h: f64) -> NodeKind {
+        NodeKind::Shape(Box::new(ShapeNode {
+            data: ShapeData::Rect(RectShape {
+                width: w,
+                height: h,
+            }),
+            style: ShapeStyle::default(),
+            stitch: StitchParams::default(),
+            sequencer: Default::default(),
+        }))
+    }
+
+    #[test]
+    fn add_and_traverse_in_layer_order() {
+        let mut scene = Scene::new();
+        let a = scene.add_node(rect_node(10.0, 10.0), None).unwrap();
+        let g = scene.add_node(NodeKind::Group, None).unwrap();
+        let b = scene.add_node(rect_node(5.0, 5.0), Some(g)).unwrap();
+        let order: Vec<NodeId> = scene.render_list().iter().map(|i| i.node_id).collect();
+        assert_eq!(order, vec![a, g, b]);
+    }
+
+    #[test]
+    fn rebuild_refreshes_state_after_direct_mutation() {
+        let mut scene = Scene::new();
+        let a = scene.add_node(rect_node(10.0, 10.0), None).unwrap();
+        let b = scene.add_node(rect_node(10.0, 10.0), None).unwrap();
+        let before = scene.node_bounding_box(a).unwrap().unwrap();
+        assert_eq!(before.max_x - before.min_x, 10.0);
+
+        // Mutate the node map directly, bypassing commands and the cache
+        // invalidation in `node_mut`, and leave a wildly out-of-range pin.
+        if let NodeKind::Shape(shape) = &mut scene.nodes.get_mut(&a).unwrap().kind {
+            shape.data = ShapeData::Rect(RectShape {
+                width: 20.0,
+                height: 10.0,
+            });
+            shape.sequencer.sequencer_index = Some(99);
+        }
+
+        scene.rebuild_derived_state();
+        let after = scene.node_bounding_box(a).unwrap().unwrap();
+        assert_eq!(after.max_x - after.min_x, 20.0);
+        // The pin survives as "stitch last" but is normalized in range.
+        assert_eq!(scene.sequencer_shape_ids(), vec![b, a]);
+        let NodeKind::Shape(shape) = &scene.node(a).unwrap().kind else {
+            unreachable!()
+        };
+        assert_eq!(shape.sequencer.sequencer_index, Some(1));
+        assert!(scene.node(b).is_ok());
+    }
+
+    #[test]
+    fn unit_render_list_converts_and_rounds() {
+        let mut scene = Scene::new();
+        let id = scene.add_node(rect_node(4.0, 4.0), None).unwrap();
+        scene
+            .set_transform(id, Transform::translation(25.4, 3.456789))
+            .unwrap();
+
+        let inches = scene.render_list_in_units(Units::Inch, 3);
+        assert_eq!(inches[0].world_transform.tx, 1.0);
+
+        let mm = scene.render_list_in_units(Units::Mm, 2);
+        assert_eq!(mm[0].world_transform.ty, 3.46);
+    }
+
+    #[test]
+    fn tighter_lod_tolerance_yields_more_points_on_curves() {
+        let mut scene = Scene::new();
+        scene
+            .add_node(
+                NodeKind::Shape(Box::new(ShapeNode {
+                    data: ShapeData::Ellipse(crate::shapes::EllipseShape {
+                        rx: 20.0,
+                        ry: 12.0,
+                    }),
+                    style: ShapeStyle::default(),
+                    stitch: StitchParams::default(),
+                    sequencer: Default::default(),
+                })),
+                None,
+            )
+            .unwrap();
+
+        let points = |tolerance: f64| -> usize {
+            scene.render_list_lod(tolerance).unwrap()[0]
+                .polylines
+                .iter()
+                .map(|p| p.len())
+                .sum()
+        };
+        assert!(points(0.01) > points(1.0));
+        assert!(scene.render_list_lod(0.0).is_err());
+    }
+
+    #[test]
+    fn world_transform_composes_through_groups() {
+        let mut scene = Scene::new();
+        let g = scene.add_node(NodeKind::Group, None).unwrap();
+        scene
+            .set_transform(g, Transform::translation(10.0, 0.0))
+            .unwrap();
+        let s = scene.add_node(rect_node(2.0, 2.0), Some(g)).unwrap();
+        scene
+            .set_transform(s, Transform::translation(0.0, 5.0))
+            .unwrap();
+        let w = scene.world_transform(s).unwrap();
+        let p = w.apply(Point::new(0.0, 0.0));
+        assert_eq!((p.x, p.y), (10.0, 5.0));
+    }
+
+    #[test]
+    fn remove_node_drops_subtree() {
+        let mut scene = Scene::new();
+        let g = scene.add_node(NodeKind::Group, None).unwrap();
+        let s = scene.add_node(rect_node(2.0, 2.0), Some(g)).unwrap();
+        scene.remove_node(g).unwrap();
+        assert!(scene.node(s).is_err());
+        assert!(scene.render_list().is_empty());
+    }
+
+    #[test]
+    fn find_similar_by_fill_returns_only_matching_shapes() {
+        let mut scene = Scene::new();
+        let red = Some(crate::shapes::Color::rgb(255, 0, 0));
+        let blue = Some(crate::shapes::Color::rgb(0, 0, 255));
+        let mut ids = Vec::new();
+        for fill in [red, blue, red, blue] {
+            let mut kind = rect_node(5.0, 5.0);
+            if let NodeKind::Shape(shape) = &mut kind {
+                shape.style.fill = fill;
+            }
+            ids.push(scene.add_node(kind, None).unwrap());
+        }
+        let criteria = SimilarityCriteria {
+            fill: true,
+            ..Default::default()
+        };
+        let similar = scene.find_similar(ids[0], criteria).unwrap();
+        assert_eq!(similar, vec![ids[0], ids[2]]);
+    }
+
+    #[test]
+    fn saves_omit_derived_state_and_loading_rebuilds_it() {
+        let mut scene = Scene::new();
+        scene.add_node(rect_node(4.0, 4.0), None).unwrap();
+        let dropped = scene.add_node(rect_node(2.0, 2.0), None).unwrap();
+        scene.remove_node(dropped).unwrap();
+
+        let json = serde_json::to_string(&scene).unwrap();
+        assert!(!json.contains("next_id"));
+
+        let mut loaded: Scene = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.render_list(), scene.render_list());
+        // Fresh allocations after a load never collide with stored nodes.
+        let new_id = loaded.add_node(rect_node(1.0, 1.0), None).unwrap();
+        assert!(!scene.nodes.contains_key(&new_id));
+        assert_eq!(loaded.render_list().len(), 2);
+    }
+
+    #[test]
+    fn cached_bbox_matches_direct_computation_and_invalidates_on_edit() {
+        let mut scene = Scene::new();
+        let id = scene.add_node(rect_node(10.0, 4.0), None).unwrap();
+        scene
+            .set_transform(id, Transform::translation(5.0, -2.0))
+            .unwrap();
+
+        let direct = {
+            let NodeKind::Shape(shape) = &scene.node(id).unwrap().kind else {
+                unreachable!()
+            };
+            shape
+                .data
+                .to_path()
+                .transformed(&scene.world_transform(id).unwrap())
+                .bounding_box(crate::path::DEFAULT_FLATTEN_TOLERANCE)
+        };
+        // Query twice so the second answer comes from the cache.
+        assert_eq!(scene.node_bounding_box(id).unwrap(), Some(direct));
+        assert_eq!(scene.node_bounding_box(id).unwrap(), Some(direct));
+        assert_eq!(scene.selection_bbox(&[id]), Some(direct));
+
+        // A geometry edit must not serve the stale box.
+        if let NodeKind::Shape(shape) = &mut scene.node_mut(id).unwrap().kind {
+            shape.data = ShapeData::Rect(RectShape {
+                width: 20.0,
+                height: 4.0,
+            });
+        }
+        let grown = scene.node_bounding_box(id).unwrap().unwrap();
+        assert!((grown.width() - 20.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn validate_flags_an_orphan_child_and_passes_a_healthy_scene() {
+        let mut scene = Scene::new();
+        let g = scene.add_node(NodeKind::Group, None).unwrap();
+        let s = scene.add_node(rect_node(3.0, 3.0), Some(g)).unwrap();
+        assert!(scene.validate().is_empty());
+
+        // Corrupt the graph: drop the child node but keep the listing.
+        scene.nodes.remove(&s);
+        let issues = scene.validate();
+        assert!(issues.contains(&SceneIntegrityIssue::MissingChild { id: g, child: s }));
+    }
+
+    #[test]
+    fn repair_restores_a_corrupted_scene_and_skips_a_healthy_one() {
+        let mut scene = Scene::new();
+        let g = scene.add_node(NodeKind::Group, None).unwrap();
+        let s = scene.add_node(rect_node(3.0, 3.0), Some(g)).unwrap();
+        assert!(scene.repair().is_empty());
+
+        // Orphan reference: the child node vanishes, the listing stays.
+        scene.nodes.remove(&s);
+        // And a stranded node: parentless but not rooted.
+        let stray = scene.add_node(rect_node(1.0, 1.0), None).unwrap();
+        scene.root_children.retain(|c| *c != stray);
+        assert!(!scene.validate().is_empty());
+
+        let actions = scene.repair();
+        assert!(actions.contains(&RepairAction::DroppedChildRef { id: g, child: s }));
+        assert!(actions.contains(&RepairAction::AddedRootRef { id: stray }));
+        assert!(scene.validate().is_empty());
+    }
+
+    #[test]
+    fn remove_node_reports_the_missing_id() {
+        let mut scene = Scene::new();
+        match scene.remove_node(404) {
+            Err(EngineError::NodeNotFound(id)) => assert_eq!(id, 404),
+            other => panic!("expected NodeNotFound, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn diff_of_moved_node_is_one_transform_delta() {
+        let mut scene = Scene::new();
+        let a = scene.add_node(rect_node(10.0, 10.0), None).unwrap();
+        scene.add_node(rect_node(5.0, 5.0), None).unwrap();
+        let mut target = scene.clone();
+        target
+            .set_transform(a, Transform::translation(7.0, -3.0))
+            .unwrap();
+
+        let deltas = scene.diff(&target);
+        assert_eq!(deltas.len(), 1);
+        assert!(matches!(
+            deltas[0],
+            SceneDelta::TransformChanged { id, .. } if id == a
+        ));
+
+        // Deltas are serializable for the wire.
+        let json = serde_json::to_string(&deltas).unwrap();
+        let back: Vec<SceneDelta> = serde_json::from_str(&json).unwrap();
+        assert_eq!(deltas, back);
+
+        for delta in back {
+            scene.apply_delta(delta).unwrap();
+        }
+        assert_eq!(scene, target);
+    }
+
+    #[test]
+    fn diff_covers_added_and_removed_nodes() {
+        let mut scene = Scene::new();
+        let a = scene.add_node(rect_node(10.0, 10.0), None).unwrap();
+        let mut target = scene.clone();
+        target.remove_node(a).unwrap();
+        let b = target.add_node(rect_node(2.0, 2.0), None).unwrap();
+
+        let mut replay = scene.clone();
+        for delta in scene.diff(&target) {
+            replay.apply_delta(delta).unwrap();
+        }
+        assert!(replay.node(a).is_err());
+        assert!(replay.node(b).is_ok());
+        assert_eq!(replay.root_children, target.root_children);
+    }
+
+    #[test]
+    fn sequencer_reorder_is_flagged_and_reset_clears_it() {
+        let mut scene = Scene::new();
+        let a = scene.add_node(rect_node(10.0, 10.0), None).unwrap();
+        let b = scene.add_node(rect_node(5.0, 5.0), None).unwrap();
+        assert!(scene.sequence_render_mismatches().is_empty());
+
+        // Pin the first shape past the end so it stitches last.
+        scene.set_sequencer_index(a, Some(5)).unwrap();
+        assert_eq!(scene.sequencer_shape_ids(), vec![b, a]);
+        assert!(!scene.sequence_render_mismatches().is_empty());
+
+        scene.reset_sequence_to_render_order();
+        assert_eq!(scene.sequencer_shape_ids(), vec![a, b]);
+        assert!(scene.sequence_render_mismatches().is_empty());
+    }
+
+    #[test]
+    fn closest_point_snaps_to_nearest_visible_shape() {
+        let mut scene = Scene::new();
+        // 10×10 rect centered at (20, 0): right edge at x=25.
+        let a = scene.add_node(rect_node(10.0, 10.0), None).unwrap();
+        scene
+            .set_transform(a, Transform::translation(20.0, 0.0))
+            .unwrap();
+        let hit = scene.closest_point(Point::new(28.0, 0.0), 5.0).unwrap();
+        assert_eq!(hit.node_id, a);
+        assert!((hit.x - 25.0).abs() < 1e-9);
+        assert!(hit.y.abs() < 1e-9);
+        assert!((hit.distance - 3.0).abs() < 1e-9);
+        // Out of range: no snap.
+        assert!(scene.closest_point(Point::new(28.0, 0.0), 2.0).is_none());
+    }
+
+    #[test]
+    fn hidden_subtree_is_excluded_from_render_list() {
+        let mut scene = Scene::new();
+        let g = scene.add_node(NodeKind::Group, None).unwrap();
+        scene.add_node(rect_node(2.0, 2.0), Some(g)).unwrap();
+        scene.node_mut(g).unwrap().visible = false;
+        assert!(scene.render_list().is_empty());
+    }
+}
@@ -0,0 +1,531 @@
+//! Shape primitives and styling. Shapes are defined in local coordinates,
+//! centered on the origin; the owning scene node carries the transform.
+
+use crate::geometry::{BoundingBox, Point};
+use crate::path::{PathCommand, VectorPath};
+use serde::{Deserialize, Serialize};
+
+/// An sRGB color with 8-bit channels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    #[serde(default = "default_alpha")]
+    pub a: u8,
+}
+
+fn default_alpha() -> u8 {
+    255
+}
+
+impl Color {
+    pub const BLACK: Color = Color::rgb(0, 0, 0);
+
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b, a: 255 }
+    }
+}
+
+impl Default for Color {
+    fn default() -> Self {
+        Color::BLACK
+    }
+}
+
+/// Fill/stroke styling for a shape.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShapeStyle {
+    pub fill: Option<Color>,
+    pub stroke: Option<Color>,
+    pub stroke_width: f64,
+}
+
+impl Default for ShapeStyle {
+    fn default() -> Self {
+        Self {
+            fill: None,
+            stroke: Some(Color::BLACK),
+            stroke_width: 1.0,
+        }
+    }
+}
+
+impl ShapeStyle {
+    /// The color a stitch block derives from this style: stroke wins for
+    /// outline work, then fill, then black.
+    pub fn thread_color(&self) -> Color {
+        self.stroke.or(self.fill).unwrap_or_default()
+    }
+}
+
+/// A rectangle centered on the origin.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RectShape {
+    pub width: f64,
+    pub height: f64,
+}
+
+/// An ellipse centered on the origin.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct EllipseShape {
+    pub rx: f64,
+    pub ry: f64,
+}
+
+/// A regular polygon centered on the origin, one vertex at the top.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RegularPolygonShape {
+    pub sides: u32,
+    pub radius: f64,
+}
+
+/// A star centered on the origin, one point at the top: `points` outer
+/// vertices on `outer_radius` alternating with inner vertices on
+/// `inner_radius`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct StarShape {
+    pub points: u32,
+    pub outer_radius: f64,
+    pub inner_radius: f64,
+}
+
+/// A single line of text rendered with the built-in stroke font
+/// ([`crate::stroke_font`]), centered on the origin. Open polyline glyphs,
+/// so text is stroke-only: it has no fill region.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TextShape {
+    pub text: String,
+    pub height_mm: f64,
+    pub letter_spacing_mm: f64,
+}
+
+/// Lay the stroke-font glyphs of `t.text` out left to right, scaled so the
+/// cap box spans `height_mm`, then center the result on the origin like
+/// the other primitives. Characters outside the font table (and spaces)
+/// contribute a blank advance.
+fn text_path(t: &TextShape) -> VectorPath {
+    let scale = t.height_mm / crate::stroke_font::GRID_HEIGHT;
+    let advance = crate::stroke_font::GRID_ADVANCE * scale + t.letter_spacing_mm;
+    let mut commands = Vec::new();
+    let mut pen_x = 0.0;
+    for c in t.text.chars() {
+        if let Some(strokes) = crate::stroke_font::glyph(c) {
+            for stroke in strokes {
+                for (i, &(gx, gy)) in stroke.iter().enumerate() {
+                    let to = Point::new(pen_x + gx as f64 * scale, gy as f64 * scale);
+                    commands.push(if i == 0 {
+                        PathCommand::MoveTo { to }
+                    } else {
+                        PathCommand::LineTo { to }
+                    });
+                }
+            }
+        }
+        pen_x += advance;
+    }
+    let mut path = VectorPath { commands };
+    let bbox = path.bounding_box(crate::path::DEFAULT_FLATTEN_TOLERANCE);
+    if !bbox.is_empty() {
+        let Point { x: cx, y: cy } = bbox.center();
+        for cmd in &mut path.commands {
+            match cmd {
+                PathCommand::MoveTo { to } | PathCommand::LineTo { to } => {
+                    to.x -= cx;
+                    to.y -= cy;
+                }
+                PathCommand::CurveTo { .. } | PathCommand::Close => {}
+            }
+        }
+    }
+    path
+}
+
+/// Vertices of a regular `n`-gon of radius `r`, counter-clockwise on
+/// screen (Y-down) from the top. `inner` interleaves a second radius
+/// halfway between outer vertices, for stars.
+fn radial_vertices(n: u32, r: f64, inner: Option<f64>) -> Vec<Point> {
+    let n = n.max(3) as usize;
+    let count = if inner.is_some() { n * 2 } else { n };
+    (0..count)
+        .map(|i| {
+            let radius = match inner {
+                Some(inner) if i % 2 == 1 => inner,
+                _ => r,
+            };
+            // Y-down: the top is -90°, and decreasing angle turns
+            // counter-clockwise on screen.
+            let a = -std::f64::consts::FRAC_PI_2 - std::f64::consts::TAU * i as f64 / count as f64;
+            Point::new(radius * a.cos(), radius * a.sin())
+        })
+        .collect()
+}
+
+/// Magic number for a cubic approximation of a quarter circle.
+pub(crate) const KAPPA: f64 = 0.552_284_749_830_793_4;
+
+/// The geometry payload of a shape node.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ShapeData {
+    Rect(RectShape),
+    Ellipse(EllipseShape),
+    RegularPolygon(RegularPolygonShape),
+    Star(StarShape),
+    Text(TextShape),
+    Path(VectorPath),
+}
+
+impl ShapeData {
+    /// Convert to a vector path in local coordinates.
+    pub fn to_path(&self) -> VectorPath {
+        match self {
+            ShapeData::Rect(r) => {
+                let hw = r.width * 0.5;
+                let hh = r.height * 0.5;
+                VectorPath::from_polygon(&[
+                    Point::new(-hw, -hh),
+                    Point::new(hw, -hh),
+                    Point::new(hw, hh),
+                    Point::new(-hw, hh),
+                ])
+            }
+            ShapeData::Ellipse(e) => {
+                let (rx, ry) = (e.rx, e.ry);
+                let (cx, cy) = (rx * KAPPA, ry * KAPPA);
+                VectorPath {
+                    commands: vec![
+                        PathCommand::MoveTo {
+                            to: Point::new(rx, 0.0),
+                        },
+                        PathCommand::CurveTo {
+                            c1: Point::new(rx, cy),
+                            c2: Point::new(cx, ry),
+                            to: Point::new(0.0, ry),
+                        },
+                        PathCommand::CurveTo {
+                            c1: Point::new(-cx, ry),
+                            c2: Point::new(-rx, cy),
+                            to: Point::new(-rx, 0.0),
+                        },
+                        PathCommand::CurveTo {
+                            c1: Point::new(-rx, -cy),
+                            c2: Point::new(-cx, -ry),
+                            to: Point::new(0.0, -ry),
+                        },
+                        PathCommand::CurveTo {
+                            c1: Point::new(cx, -ry),
+                            c2: Point::new(rx, -cy),
+                            to: Point::new(rx, 0.0),
+                        },
+                        PathCommand::Close,
+                    ],
+                }
+            }
+            ShapeData::RegularPolygon(p) => {
+                VectorPath::from_polygon(&radial_vertices(p.sides, p.radius, None))
+            }
+            ShapeData::Star(s) => VectorPath::from_polygon(&radial_vertices(
+                s.points,
+                s.outer_radius,
+                Some(s.inner_radius),
+            )),
+            ShapeData::Text(t) => text_path(t),
+            ShapeData::Path(p) => p.clone(),
+        }
+    }
+
+    /// Vertex ring for the polygonal primitives, `None` for the rest.
+    fn vertices(&self) -> Option<Vec<Point>> {
+        match self {
+            ShapeData::RegularPolygon(p) => Some(radial_vertices(p.sides, p.radius, None)),
+            ShapeData::Star(s) => Some(radial_vertices(
+                s.points,
+                s.outer_radius,
+                Some(s.inner_radius),
+            )),
+            _ => None,
+        }
+    }
+
+    pub fn bounding_box(&self) -> BoundingBox {
+        match self {
+            ShapeData::Rect(r) => BoundingBox {
+                min_x: -r.width * 0.5,
+                min_y: -r.height * 0.5,
+                max_x: r.width * 0.5,
+                max_y: r.height * 0.5,
+            },
+            ShapeData::Ellipse(e) => BoundingBox {
+                min_x: -e.rx,
+                min_y: -e.ry,
+                max_x: e.rx,
+                max_y: e.ry,
+            },
+            ShapeData::RegularPolygon(_) | ShapeData::Star(_) => {
+                let mut bbox = BoundingBox::empty();
+                for p in self.vertices().expect("polygonal primitive") {
+                    bbox.include(p);
+                }
+                bbox
+            }
+            ShapeData::Text(_) | ShapeData::Path(_) => self
+                .to_path()
+                .bounding_box(crate::path::DEFAULT_FLATTEN_TOLERANCE),
+        }
+    }
+
+    /// Area in local units — exact for primitives, shoelace for paths (whose
+    /// sign follows winding).
+    pub fn area(&self) -> f64 {
+        match self {
+            ShapeData::Rect(r) => r.width * r.height,
+            ShapeData::Ellipse(e) => std::f64::consts::PI * e.rx * e.ry,
+            // Shoelace over the exact vertices — no flattening involved.
+            ShapeData::RegularPolygon(_) | ShapeData::Star(_) => {
+                let v = self.vertices().expect("polygonal primitive");
+                let n = v.len();
+                (0..n)
+                    .map(|i| {
+                        let (p, q) = (v[i], v[(i + 1) % n]);
+                        p.x * q.y - q.x * p.y
+                    })
+                    .sum::<f64>()
+                    .abs()
+                    * 0.5
+            }
+            // Open stroke glyphs enclose nothing.
+            ShapeData::Text(_) => 0.0,
+            ShapeData::Path(p) => p.area(),
+        }
+    }
+
+    /// Perimeter in local units — exact for rects, Ramanujan's approximation
+    /// for ellipses, flattened length for paths.
+    pub fn perimeter(&self) -> f64 {
+        match self {
+            ShapeData::Rect(r) => 2.0 * (r.width + r.height),
+            ShapeData::Ellipse(e) => {
+                let (a, b) = (e.rx, e.ry);
+                let h = ((a - b) / (a + b)).powi(2);
+                std::f64::consts::PI * (a + b) * (1.0 + 3.0 * h / (10.0 + (4.0 - 3.0 * h).sqrt()))
+            }
+            ShapeData::RegularPolygon(_) | ShapeData::Star(_) => {
+                let v = self.vertices().expect("polygonal primitive");
+                let n = v.len();
+                (0..n).map(|i| v[i].distance_to(v[(i + 1) % n])).sum()
+            }
+            // Total stroke length — what the stitch pipeline runs along.
+            ShapeData::Text(t) => text_path(t).perimeter(),
+            ShapeData::Path(p) => p.perimeter(),
+        }
+    }
+
+    pub fn contains_point(&self, p: Point) -> bool {
+        match self {
+            ShapeData::Rect(r) => p.x.abs() <= r.width * 0.5 && p.y.abs() <= r.height * 0.5,
+            ShapeData::Ellipse(e) => {
+                if e.rx <= 0.0 || e.ry <= 0.0 {
+                    return false;
+                }
+                let nx = p.x / e.rx;
+                let ny = p.y / e.ry;
+                nx * nx + ny * ny <= 1.0
+            }
+            ShapeData::RegularPolygon(_) | ShapeData::Star(_) => {
+                let ring = self.vertices().expect("polygonal primitive");
+                crate::path::point_in_rings(std::slice::from_ref(&ring), p)
+            }
+            // Stroke-only: no interior to hit-test.
+            ShapeData::Text(_) => false,
+            ShapeData::Path(path) => {
+                let rings = path.flatten(crate::path::DEFAULT_FLATTEN_TOLERANCE);
+                crate::path::point_in_rings(&rings, p)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rect_path_is_closed_with_expected_corners() {
+        let rect = ShapeData::Rect(RectShape {
+            width: 10.0,
+            height: 4.0,
+        });
+        let path = rect.to_path();
+        assert!(path.is_closed());
+        let bbox = path.bounding_box(0.1);
+        assert_eq!(bbox.width(), 10.0);
+        assert_eq!(bbox.height(), 4.0);
+    }
+
+    #[test]
+    fn ellipse_contains_point() {
+        let e = ShapeData::Ellipse(EllipseShape { rx: 5.0, ry: 2.0 });
+        assert!(e.contains_point(Point::new(4.9, 0.0)));
+        assert!(!e.contains_point(Point::new(0.0, 2.1)));
+    }
+
+    #[test]
+    fn area_and_perimeter_formulas() {
+        let rect = ShapeData::Rect(RectShape {
+            width: 10.0,
+            height: 10.0,
+        });
+        assert_eq!(rect.area(), 100.0);
+        assert_eq!(rect.perimeter(), 40.0);
+
+        let circle = ShapeData::Ellipse(EllipseShape { rx: 1.0, ry: 1.0 });
+        assert!((circle.area() - std::f64::consts::PI).abs() < 1e-12);
+        assert!((circle.perimeter() - std::f64::consts::TAU).abs() < 1e-6);
+
+        // Path area of a unit circle approximation also lands near π
+        // (within the default flatten tolerance, which is coarse at r=1).
+        let path = ShapeData::Path(circle.to_path());
+        assert!((path.area().abs() - std::f64::consts::PI).abs() < 0.1);
+    }
+
+    #[test]
+    fn donut_area_subtracts_hole() {
+        use crate::path::VectorPath;
+        let outer = [
+            Point::new(0.0, 0.0),
+            Point::new(10.0, 0.0),
+            Point::new(10.0, 10.0),
+            Point::new(0.0, 10.0),
+        ];
+        // Opposite winding so the hole's signed area cancels out.
+        let hole = [
+            Point::new(4.0, 4.0),
+            Point::new(4.0, 6.0),
+            Point::new(6.0, 6.0),
+            Point::new(6.0, 4.0),
+        ];
+        let mut path = VectorPath::from_polygon(&outer);
+        path.commands
+            .extend(VectorPath::from_polygon(&hole).commands);
+        assert!((path.area().abs() - (100.0 - 4.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn hexagon_has_six_distinct_vertices_counter_clockwise_from_the_top() {
+        let hex = ShapeData::RegularPolygon(RegularPolygonShape {
+            sides: 6,
+            radius: 5.0,
+        });
+        let rings = hex.to_path().flatten(0.1);
+        let ring = &rings[0];
+        // Closed ring: six unique vertices plus the repeated first point.
+        assert_eq!(ring.len(), 7);
+        for (i, p) in ring[..6].iter().enumerate() {
+            assert!((p.distance_to(Point::default()) - 5.0).abs() < 1e-9);
+            for q in &ring[..i] {
+                assert!(p.distance_to(*q) > 1.0, "vertices {p:?} and {q:?} collide");
+            }
+        }
+        // Starts at the top (Y-down) and turns counter-clockwise on screen:
+        // the second vertex sits to the left.
+        assert!((ring[0].x).abs() < 1e-9 && ring[0].y < 0.0);
+        assert!(ring[1].x < 0.0);
+        // Exact regular-polygon area: 1.5·√3·r².
+        assert!((hex.area() - 1.5 * 3.0_f64.sqrt() * 25.0).abs() < 1e-9);
+        assert!(hex.contains_point(Point::new(0.0, 0.0)));
+        assert!(!hex.contains_point(Point::new(5.0, 5.0)));
+    }
+
+    #[test]
+    fn star_bounding_box_spans_the_outer_radius() {
+        let star = ShapeData::Star(StarShape {
+            points: 4,
+            outer_radius: 6.0,
+            inner_radius: 2.0,
+        });
+        // Four points land on the axes, so the box is the outer extents.
+        let bbox = star.bounding_box();
+        assert!((bbox.min_x + 6.0).abs() < 1e-9 && (bbox.max_x - 6.0).abs() < 1e-9);
+        assert!((bbox.min_y + 6.0).abs() < 1e-9 && (bbox.max_y - 6.0).abs() < 1e-9);
+        // The waist between points only reaches the inner radius.
+        assert!(star.contains_point(Point::new(0.0, -5.9)));
+        assert!(!star.contains_point(Point::new(2.0, -2.0)));
+    }
+
+    #[test]
+    fn text_renders_strokes_and_scales_with_height() {
+        let text = ShapeData::Text(TextShape {
+            text: "AB".to_string(),
+            height_mm: 10.0,
+            letter_spacing_mm: 1.0,
+        });
+        let path = text.to_path();
+        assert!(!path.commands.is_empty());
+        // Centered on the origin like the other primitives.
+        let bbox = text.bounding_box();
+        assert!((bbox.min_y + bbox.max_y).abs() < 1e-9);
+        assert!((bbox.min_x + bbox.max_x).abs() < 1e-9);
+        assert!((bbox.height() - 10.0).abs() < 1e-9);
+
+        let doubled = ShapeData::Text(TextShape {
+            text: "AB".to_string(),
+            height_mm: 20.0,
+            letter_spacing_mm: 1.0,
+        });
+        assert!((doubled.bounding_box().height() - 2.0 * bbox.height()).abs() < 1e-9);
+
+        // Open strokes: no fill region, but a real run length.
+        assert_eq!(text.area(), 0.0);
+        assert!(text.perimeter() > 10.0);
+        assert!(!text.contains_point(Point::new(0.0, 0.0)));
+    }
+
+    #[test]
+    fn unknown_characters_advance_without_strokes() {
+        let spaced = ShapeData::Text(TextShape {
+            text: "A B".to_string(),
+            height_mm: 6.0,
+            letter_spacing_mm: 0.0,
+        });
+        let tight = ShapeData::Text(TextShape {
+            text: "AB".to_string(),
+            height_mm: 6.0,
+            letter_spacing_mm: 0.0,
+        });
+        // The space contributes one blank advance of width.
+        assert!(spaced.bounding_box().width() > tight.bounding_box().width() + 4.0);
+    }
+
+    #[test]
+    fn shape_data_round_trips_through_json() {
+        let rect = ShapeData::Rect(RectShape {
+            width: 3.0,
+            height: 7.0,
+        });
+        let json = serde_json::to_string(&rect).unwrap();
+        let back: ShapeData = serde_json::from_str(&json).unwrap();
+        assert_eq!(rect, back);
+
+        let star = ShapeData::Star(StarShape {
+            points: 5,
+            outer_radius: 10.0,
+            inner_radius: 4.0,
+        });
+        let json = serde_json::to_string(&star).unwrap();
+        assert!(json.contains(r#""kind":"star""#));
+        let back: ShapeData = serde_json::from_str(&json).unwrap();
+        assert_eq!(star, back);
+
+        let text = ShapeData::Text(TextShape {
+            text: "HELLO-1".to_string(),
+            height_mm: 8.0,
+            letter_spacing_mm: 0.5,
+        });
+        let json = serde_json::to_string(&text).unwrap();
+        assert!(json.contains(r#""kind":"text""#));
+        let back: ShapeData = serde_json::from_str(&json).unwrap();
+        assert_eq!(text, back);
+    }
+}
@@ -0,0 +1,80 @@
+//! Chain (moss) stitch: linked loops along a path for a raised, rope-like
+//! border.
+
+use crate::geometry::Point;
+use crate::stitch::running::{cumulative_lengths, point_at};
+use crate::stitch::Stitch;
+
+/// Generate chain stitches along `points`. Each link spans `stitch_length`
+/// of the path and swings a loop point `loop_size * 0.5` out along the
+/// normal, alternating sides so the chain reads symmetrically. The needle
+/// re-penetrates the link's anchor after the loop (the back-and-forth that
+/// locks each loop under the next).
+pub fn generate_chain_stitch(points: &[Point], loop_size: f64, stitch_length: f64) -> Vec<Stitch> {
+    let mut out = Vec::new();
+    if points.len() < 2 || loop_size <= 0.0 || stitch_length <= 0.0 {
+        return out;
+    }
+    let cumulative = cumulative_lengths(points);
+    let total = *cumulative.last().expect("non-empty lengths");
+    if total <= f64::EPSILON {
+        return out;
+    }
+    let links = ((total / stitch_length).ceil() as usize).max(1);
+    let step = total / links as f64;
+
+    let start = points[0];
+    out.push(Stitch::normal(start.x, start.y));
+    let mut side = 1.0;
+    for i in 0..links {
+        let a = point_at(points, &cumulative, i as f64 * step);
+        let b = point_at(points, &cumulative, (i + 1) as f64 * step);
+        let dir = (b - a).normalized();
+        let normal = dir.perp();
+        let mid = a.lerp(b, 0.5);
+        let loop_pt = mid + normal * (loop_size * 0.5 * side);
+        out.push(Stitch::normal(loop_pt.x, loop_pt.y));
+        out.push(Stitch::normal(a.x, a.y));
+        out.push(Stitch::normal(b.x, b.y));
+        side = -side;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loops_alternate_sides_of_the_centerline() {
+        let pts = [Point::new(0.0, 0.0), Point::new(10.0, 0.0)];
+        let stitches = generate_chain_stitch(&pts, 1.0, 2.0);
+        // Loop points are the only penetrations off the centerline.
+        let loop_ys: Vec<f64> = stitches
+            .iter()
+            .map(|s| s.y)
+            .filter(|y| y.abs() > 1e-9)
+            .collect();
+        assert_eq!(loop_ys.len(), 5, "one loop per 2 mm link");
+        for pair in loop_ys.windows(2) {
+            assert!(pair[0] * pair[1] < 0.0, "loops on the same side: {pair:?}");
+        }
+        assert!((loop_ys[0].abs() - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn links_anchor_back_onto_the_path() {
+        let pts = [Point::new(0.0, 0.0), Point::new(4.0, 0.0)];
+        let stitches = generate_chain_stitch(&pts, 1.0, 2.0);
+        // Start, then per link: loop, re-penetrated anchor, link end.
+        assert_eq!(stitches.len(), 1 + 3 * 2);
+        assert_eq!((stitches[2].x, stitches[2].y), (0.0, 0.0));
+        assert_eq!((stitches[3].x, stitches[3].y), (2.0, 0.0));
+    }
+
+    #[test]
+    fn degenerate_input_is_empty() {
+        assert!(generate_chain_stitch(&[], 1.0, 2.0).is_empty());
+        assert!(generate_chain_stitch(&[Point::new(1.0, 1.0)], 1.0, 2.0).is_empty());
+    }
+}
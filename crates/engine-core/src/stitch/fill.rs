@@ -0,0 +1,865 @@
+//! Fill stitch generation over closed rings (tatami scanline fills).
+
+use crate::cancel::CancelToken;
+use crate::error::EngineError;
+use crate::geometry::{Point, Transform};
+use crate::stitch::Stitch;
+use serde::{Deserialize, Serialize};
+
+/// How fill rows meet the shape boundary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FillEdgeStyle {
+    /// Row endpoints land on the stitch-length grid, leaving a stair-step
+    /// edge (cheapest, fine under a border).
+    #[default]
+    Raw,
+    /// Snap each row's endpoints exactly onto the boundary intersection so
+    /// the fill edge is crisp.
+    CleanEdge,
+}
+
+/// Stabilizing passes stitched under a fill before the top rows go down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FillUnderlay {
+    /// Top fill only.
+    #[default]
+    None,
+    /// A sparse pass along the top fill's own angle.
+    Parallel,
+    /// A sparse pass at 90° to the top fill — the standard tatami
+    /// stabilizer, holding the fabric against row-direction pull.
+    Perpendicular,
+    /// Perpendicular, then parallel — a sparse lattice under the fill.
+    Both,
+    /// A running pass traced along the shape boundary, anchoring the edge
+    /// before any rows go down.
+    EdgeRun,
+}
+
+impl FillUnderlay {
+    /// The pass angles to stitch before the top fill at `top_angle`.
+    /// [`FillUnderlay::EdgeRun`] has no row passes — it traces the
+    /// boundary instead.
+    pub fn pass_angles(self, top_angle: f64) -> Vec<f64> {
+        match self {
+            FillUnderlay::None | FillUnderlay::EdgeRun => Vec::new(),
+            FillUnderlay::Parallel => vec![top_angle],
+            FillUnderlay::Perpendicular => vec![top_angle + 90.0],
+            FillUnderlay::Both => vec![top_angle + 90.0, top_angle],
+        }
+    }
+}
+
+/// X-intersections of the scanline `y` with the ring set, sorted ascending.
+/// Even-odd pairing of the result yields the interior segments.
+fn scanline_intersections(rings: &[Vec<Point>], y: f64) -> Vec<f64> {
+    let mut xs = Vec::new();
+    for ring in rings {
+        for edge in ring.windows(2) {
+            let (a, b) = (edge[0], edge[1]);
+            if (a.y > y) != (b.y > y) {
+                xs.push(a.x + (y - a.y) / (b.y - a.y) * (b.x - a.x));
+            }
+        }
+    }
+    xs.sort_by(|p, q| p.partial_cmp(q).unwrap());
+    xs
+}
+
+/// Generate a tatami (boustrophedon scanline) fill over closed `rings`.
+///
+/// Rows run along `angle_degrees`, spaced `density` mm apart, alternating
+/// direction. Odd rows are staggered by half a density step so penetrations
+/// don't line up into visible channels.
+pub fn generate_tatami_fill(
+    rings: &[Vec<Point>],
+    angle_degrees: f64,
+    density: f64,
+    stitch_length: f64,
+    edge_style: FillEdgeStyle,
+) -> Vec<Stitch> {
+    generate_tatami_fill_with_progress(
+        rings,
+        angle_degrees,
+        density,
+        stitch_length,
+        edge_style,
+        &mut |_| {},
+    )
+}
+
+/// [`generate_tatami_fill`] that reports completion in `0..=1` after each
+/// row, so large fills can drive a determinate progress bar.
+pub fn generate_tatami_fill_with_progress(
+    rings: &[Vec<Point>],
+    angle_degrees: f64,
+    density: f64,
+    stitch_length: f64,
+    edge_style: FillEdgeStyle,
+    progress: &mut dyn FnMut(f32),
+) -> Vec<Stitch> {
+    generate_tatami_fill_cancellable(
+        rings,
+        angle_degrees,
+        density,
+        stitch_length,
+        edge_style,
+        &CancelToken::default(),
+        progress,
+    )
+    .expect("a fresh token never cancels")
+}
+
+/// Fully parameterized tatami generation: progress reporting plus a cancel
+/// token polled after every row.
+pub fn generate_tatami_fill_cancellable(
+    rings: &[Vec<Point>],
+    angle_degrees: f64,
+    density: f64,
+    stitch_length: f64,
+    edge_style: FillEdgeStyle,
+    cancel: &CancelToken,
+    progress: &mut dyn FnMut(f32),
+) -> Result<Vec<Stitch>, EngineError> {
+    if rings.is_empty() {
+        return Ok(Vec::new());
+    }
+    // Rotate the rings so rows are horizontal, stitch, then rotate back.
+    let angle = angle_degrees.to_radians();
+    let to_rows = Transform::rotation(-angle);
+    let rotated: Vec<Vec<Point>> = rings
+        .iter()
+        .map(|ring| ring.iter().map(|p| to_rows.apply(*p)).collect())
+        .collect();
+
+    let mut min_y = f64::INFINITY;
+    let mut max_y = f64::NEG_INFINITY;
+    for ring in &rotated {
+        for p in ring {
+            min_y = min_y.min(p.y);
+            max_y = max_y.max(p.y);
+        }
+    }
+    scan_fill(
+        &|y| scanline_intersections(&rotated, y),
+        &|p| crate::path::point_in_rings(rings, p),
+        (min_y, max_y),
+        angle,
+        &|_| density,
+        stitch_length,
+        edge_style,
+        cancel,
+        progress,
+    )
+}
+
+/// Gradient tatami fill: coverage fades across the shape by ramping the
+/// row spacing linearly from `density_start` at the gradient's near edge to
+/// `density_end` at its far edge. `gradient_angle_degrees` is the direction
+/// the ramp advances along; rows run perpendicular to it.
+pub fn generate_gradient_tatami_fill(
+    rings: &[Vec<Point>],
+    gradient_angle_degrees: f64,
+    density_start: f64,
+    density_end: f64,
+    stitch_length: f64,
+    edge_style: FillEdgeStyle,
+) -> Vec<Stitch> {
+    if rings.is_empty() {
+        return Vec::new();
+    }
+    // Rows advance along the frame's +v axis, so aim that axis down the
+    // gradient: the row angle sits 90° behind the gradient direction.
+    let angle = (gradient_angle_degrees - 90.0).to_radians();
+    let to_rows = Transform::rotation(-angle);
+    let rotated: Vec<Vec<Point>> = rings
+        .iter()
+        .map(|ring| ring.iter().map(|p| to_rows.apply(*p)).collect())
+        .collect();
+
+    let mut min_y = f64::INFINITY;
+    let mut max_y = f64::NEG_INFINITY;
+    for ring in &rotated {
+        for p in ring {
+            min_y = min_y.min(p.y);
+            max_y = max_y.max(p.y);
+        }
+    }
+    scan_fill(
+        &|y| scanline_intersections(&rotated, y),
+        &|p| crate::path::point_in_rings(rings, p),
+        (min_y, max_y),
+        angle,
+        &|t| density_start + (density_end - density_start) * t,
+        stitch_length,
+        edge_style,
+        &CancelToken::default(),
+        &mut |_| {},
+    )
+    .expect("a fresh token never cancels")
+}
+
+/// Radial (sunburst) fill: spokes run from `focus` outward, clipped to the
+/// region, so the stitch direction turns around the focal point instead of
+/// holding one angle. Spokes are spaced so the arc gap at the region's far
+/// edge is `density` mm; nearer the focus coverage is denser by nature.
+pub fn generate_radial_fill(
+    rings: &[Vec<Point>],
+    focus: Point,
+    density: f64,
+    stitch_length: f64,
+) -> Vec<Stitch> {
+    let mut out = Vec::new();
+    if rings.is_empty() || density <= 0.0 || stitch_length <= 0.0 {
+        return out;
+    }
+    let max_r = rings
+        .iter()
+        .flatten()
+        .map(|p| p.distance_to(focus))
+        .fold(0.0_f64, f64::max);
+    if max_r <= 0.0 {
+        return out;
+    }
+    let spokes = ((std::f64::consts::TAU * max_r / density).ceil() as usize).max(8);
+    let focus_inside = crate::path::point_in_rings(rings, focus);
+
+    for k in 0..spokes {
+        let angle = std::f64::consts::TAU * k as f64 / spokes as f64;
+        let dir = Point::new(angle.cos(), angle.sin());
+        // Ray crossings with every edge, as distances from the focus.
+        let mut ts: Vec<f64> = Vec::new();
+        for ring in rings {
+            for seg in ring.windows(2) {
+                let e = seg[1] - seg[0];
+                let denom = dir.x * e.y - dir.y * e.x;
+                if denom.abs() <= 1e-12 {
+                    continue;
+                }
+                let t = ((seg[0].x - focus.x) * e.y - (seg[0].y - focus.y) * e.x) / denom;
+                let u = ((seg[0].x - focus.x) * dir.y - (seg[0].y - focus.y) * dir.x) / denom;
+                if t > 1e-9 && (-1e-9..1.0 - 1e-9).contains(&u) {
+                    ts.push(t);
+                }
+            }
+        }
+        if focus_inside {
+            ts.push(0.0);
+        }
+        ts.sort_by(f64::total_cmp);
+        // Even-odd pairs are the interior spans of this spoke. Alternate
+        // travel direction per spoke so consecutive spokes meet.
+        for pair in ts.chunks_exact(2) {
+            let (t0, t1) = (pair[0], pair[1]);
+            if t1 - t0 <= 1e-6 {
+                continue;
+            }
+            let a = Point::new(focus.x + dir.x * t0, focus.y + dir.y * t0);
+            let b = Point::new(focus.x + dir.x * t1, focus.y + dir.y * t1);
+            let (from, to) = if k % 2 == 0 { (a, b) } else { (b, a) };
+            let mut run =
+                crate::stitch::running::generate_running_stitches(&[from, to], stitch_length);
+            if let (Some(first), false) = (run.first().copied(), out.is_empty()) {
+                out.push(Stitch::jump(first.x, first.y));
+            }
+            out.append(&mut run);
+        }
+    }
+    out
+}
+
+/// Analytic scanline fill for a true (axis-aligned) ellipse: each row's
+/// x-intersections come from the ellipse equation, so the fill edge is exact
+/// instead of faceted by flatten tolerance.
+pub fn generate_ellipse_tatami_fill(
+    center: Point,
+    rx: f64,
+    ry: f64,
+    angle_degrees: f64,
+    density: f64,
+    stitch_length: f64,
+    edge_style: FillEdgeStyle,
+) -> Vec<Stitch> {
+    if rx <= 0.0 || ry <= 0.0 {
+        return Vec::new();
+    }
+    let angle = angle_degrees.to_radians();
+    let (s, c) = angle.sin_cos();
+    // Row-frame v coordinate of the center, and the ellipse's half-extent
+    // along the row normal.
+    let v0 = -center.x * s + center.y * c;
+    let v_half = ((rx * s).powi(2) + (ry * c).powi(2)).sqrt();
+
+    // A design point (x, y) = R(angle)·(u, v). Substituting into the
+    // ellipse equation gives a quadratic in u for each row v.
+    let intersections = move |v: f64| -> Vec<f64> {
+        let a1 = c / rx;
+        let b1 = (-v * s - center.x) / rx;
+        let a2 = s / ry;
+        let b2 = (v * c - center.y) / ry;
+        let qa = a1 * a1 + a2 * a2;
+        let qb = 2.0 * (a1 * b1 + a2 * b2);
+        let qc = b1 * b1 + b2 * b2 - 1.0;
+        let disc = qb * qb - 4.0 * qa * qc;
+        if disc <= 0.0 {
+            return Vec::new();
+        }
+        let root = disc.sqrt();
+        vec![(-qb - root) / (2.0 * qa), (-qb + root) / (2.0 * qa)]
+    };
+
+    scan_fill(
+        &intersections,
+        &|p| {
+            ((p.x - center.x) / rx).powi(2) + ((p.y - center.y) / ry).powi(2) <= 1.0 + 1e-9
+        },
+        (v0 - v_half, v0 + v_half),
+        angle,
+        &|_| density,
+        stitch_length,
+        edge_style,
+        &CancelToken::default(),
+        &mut |_| {},
+    )
+    .expect("a fresh token never cancels")
+}
+
+/// Generate a concentric-ring (Archimedean spiral) fill out to `radius`.
+///
+/// The radius grows by exactly `spacing` per full turn, and theta is stepped
+/// by the local arc-length derivative so penetrations stay near
+/// `stitch_length` apart all the way from the center out — stepping by
+/// `stitch_length / r` alone collapses the spacing near the center.
+pub fn generate_spiral_fill(
+    center: Point,
+    radius: f64,
+    spacing: f64,
+    stitch_length: f64,
+) -> Vec<Stitch> {
+    let mut out = Vec::new();
+    if radius <= 0.0 || spacing <= 0.0 || stitch_length <= 0.0 {
+        return out;
+    }
+    // r = b * theta with b chosen so one turn advances the radius by
+    // `spacing`; ds/dtheta = sqrt(r^2 + b^2) never reaches zero, so the
+    // step stays finite at the center.
+    let b = spacing / std::f64::consts::TAU;
+    let mut theta = 0.0;
+    loop {
+        let r = b * theta;
+        if r > radius {
+            break;
+        }
+        out.push(Stitch::normal(
+            center.x + r * theta.cos(),
+            center.y + r * theta.sin(),
+        ));
+        // Cap the angular step so the innermost turns stay round instead
+        // of degenerating into long chords across the center.
+        theta += (stitch_length / (r * r + b * b).sqrt()).min(0.5);
+    }
+    out
+}
+
+/// Counted cross-stitch fill: a square grid of `cell_size_mm` cells laid
+/// over the region (rotated by `angle_degrees`), each interior cell stitched
+/// as two diagonal legs forming an X with a short connector along the top
+/// edge between them. Cells that straddle the boundary are skipped rather
+/// than clipped — partial crosses read as defects in the counted style.
+pub fn generate_cross_stitch_fill(
+    rings: &[Vec<Point>],
+    cell_size_mm: f64,
+    angle_degrees: f64,
+) -> Vec<Stitch> {
+    let mut out = Vec::new();
+    if rings.is_empty() || cell_size_mm <= 0.0 {
+        return out;
+    }
+    // Grid-align the region, pick cells, then rotate penetrations back.
+    let angle = angle_degrees.to_radians();
+    let to_grid = Transform::rotation(-angle);
+    let to_design = Transform::rotation(angle);
+    let rotated: Vec<Vec<Point>> = rings
+        .iter()
+        .map(|ring| ring.iter().map(|p| to_grid.apply(*p)).collect())
+        .collect();
+
+    let (mut min_x, mut min_y, mut max_x, mut max_y) =
+        (f64::INFINITY, f64::INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY);
+    for p in rotated.iter().flatten() {
+        min_x = min_x.min(p.x);
+        min_y = min_y.min(p.y);
+        max_x = max_x.max(p.x);
+        max_y = max_y.max(p.y);
+    }
+    let cols = ((max_x - min_x) / cell_size_mm).floor() as usize;
+    let rows = ((max_y - min_y) / cell_size_mm).floor() as usize;
+
+    for row in 0..rows {
+        let y0 = min_y + row as f64 * cell_size_mm;
+        let y1 = y0 + cell_size_mm;
+        // Boustrophedon over the columns so adjacent crosses chain.
+        let cells: Box<dyn Iterator<Item = usize>> = if row % 2 == 0 {
+            Box::new(0..cols)
+        } else {
+            Box::new((0..cols).rev())
+        };
+        for col in cells {
+            let x0 = min_x + col as f64 * cell_size_mm;
+            let x1 = x0 + cell_size_mm;
+            let corners = [
+                Point::new(x0, y0),
+                Point::new(x1, y0),
+                Point::new(x1, y1),
+                Point::new(x0, y1),
+            ];
+            let center = Point::new((x0 + x1) * 0.5, (y0 + y1) * 0.5);
+            let inside = corners
+                .iter()
+                .chain(std::iter::once(&center))
+                .all(|p| crate::path::point_in_rings(&rotated, *p));
+            if !inside {
+                continue;
+            }
+            // bottom-left → top-right, hop to top-left, → bottom-right.
+            let cross = [corners[0], corners[2], corners[3], corners[1]];
+            let first = to_design.apply(cross[0]);
+            // Adjacent cells chain directly (the previous bottom-right is
+            // this bottom-left); only hop when there's a real gap.
+            if let Some(last) = out.last() {
+                if (last.x - first.x).abs() > 1e-9 || (last.y - first.y).abs() > 1e-9 {
+                    out.push(Stitch::jump(first.x, first.y));
+                }
+            }
+            for p in cross {
+                let p = to_design.apply(p);
+                out.push(Stitch::normal(p.x, p.y));
+            }
+        }
+    }
+    out
+}
+
+/// Shared boustrophedon row loop over an abstract scanline source.
+/// `intersections(v)` returns the sorted u-intersections of row `v` in the
+/// rotated row frame; `inside` tests design-space containment so connectors
+/// that would cross empty space (concave notches) become jumps; `angle` maps
+/// the row frame back into design space. `spacing` maps the normalized
+/// 0..1 advance across `v_range` to the local row spacing, so constant
+/// callers pass `&|_| density` and gradients ramp it.
+#[allow(clippy::too_many_arguments)]
+fn scan_fill(
+    intersections: &dyn Fn(f64) -> Vec<f64>,
+    inside: &dyn Fn(Point) -> bool,
+    v_range: (f64, f64),
+    angle: f64,
+    spacing: &dyn Fn(f64) -> f64,
+    stitch_length: f64,
+    edge_style: FillEdgeStyle,
+    cancel: &CancelToken,
+    progress: &mut dyn FnMut(f32),
+) -> Result<Vec<Stitch>, EngineError> {
+    let mut out = Vec::new();
+    if spacing(0.0) <= 0.0 || spacing(1.0) <= 0.0 || stitch_length <= 0.0 {
+        return Ok(out);
+    }
+    let (min_y, max_y) = v_range;
+    if !min_y.is_finite() || max_y - min_y <= spacing(0.0) {
+        return Ok(out);
+    }
+
+    let from_rows = Transform::rotation(angle);
+
+    let mut y = min_y + spacing(0.0) * 0.5;
+    let mut row = 0usize;
+    while y <= max_y - spacing((y - min_y) / (max_y - min_y)) * 0.5 {
+        cancel.check()?;
+        let density = spacing((y - min_y) / (max_y - min_y));
+        let xs = intersections(y);
+        let left_to_right = row.is_multiple_of(2);
+
+        let mut segments: Vec<(f64, f64)> = xs.chunks_exact(2).map(|c| (c[0], c[1])).collect();
+        if !left_to_right {
+            segments.reverse();
+        }
+        for &(seg_left, seg_right) in &segments {
+            let span = seg_right - seg_left;
+            if span <= f64::EPSILON {
+                continue;
+            }
+            let (anchor, dir) = if left_to_right {
+                (seg_left, 1.0)
+            } else {
+                (seg_right, -1.0)
+            };
+            // Stagger the grid half a density step into the travel
+            // direction, clamped inside the span: a segment narrower than
+            // the stagger shrinks its offset instead of inverting and
+            // being dropped (which left periodic gaps on narrow shapes).
+            let start_x = anchor + dir * (density * 0.5).min(span * 0.5);
+
+            // If the straight connector from the previous penetration to
+            // this segment's entry would leave the shape (a concave notch
+            // between scanline segments), travel there as a jump.
+            let entry_x = match edge_style {
+                FillEdgeStyle::CleanEdge => anchor,
+                FillEdgeStyle::Raw => start_x,
+            };
+            let entry = from_rows.apply(Point::new(entry_x, y));
+            let mut pending_jump = out.last().is_some_and(|prev: &Stitch| {
+                !inside(Point::new(
+                    (prev.x + entry.x) * 0.5,
+                    (prev.y + entry.y) * 0.5,
+                ))
+            });
+            let mut emit = |x: f64, y: f64, out: &mut Vec<Stitch>| {
+                let p = from_rows.apply(Point::new(x, y));
+                out.push(Stitch {
+                    x: p.x,
+                    y: p.y,
+                    is_jump: std::mem::take(&mut pending_jump),
+                });
+            };
+
+            if edge_style == FillEdgeStyle::CleanEdge {
+                emit(if left_to_right { seg_left } else { seg_right }, y, &mut out);
+            }
+            let reach = span - (start_x - anchor).abs();
+            let steps = (reach / stitch_length).floor() as usize;
+            for i in 0..=steps {
+                emit(start_x + dir * i as f64 * stitch_length, y, &mut out);
+            }
+            if edge_style == FillEdgeStyle::CleanEdge {
+                emit(if left_to_right { seg_right } else { seg_left }, y, &mut out);
+            }
+        }
+        progress((((y - min_y) / (max_y - min_y)) as f32).min(1.0));
+        y += density;
+        row += 1;
+    }
+    progress(1.0);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn radial_fill_orientation_turns_around_the_focus() {
+        let ring = vec![
+            Point::new(-10.0, -10.0),
+            Point::new(10.0, -10.0),
+            Point::new(10.0, 10.0),
+            Point::new(-10.0, 10.0),
+            Point::new(-10.0, -10.0),
+        ];
+        let rings = vec![ring];
+        let stitches = generate_radial_fill(&rings, Point::new(0.0, 0.0), 1.0, 2.0);
+        assert!(!stitches.is_empty());
+
+        // Collect per-segment directions of the stitched spans.
+        let mut horizontal = 0usize;
+        let mut vertical = 0usize;
+        for w in stitches.windows(2) {
+            if w[1].is_jump {
+                continue;
+            }
+            let (dx, dy) = ((w[1].x - w[0].x).abs(), (w[1].y - w[0].y).abs());
+            if dx > dy * 2.0 {
+                horizontal += 1;
+            } else if dy > dx * 2.0 {
+                vertical += 1;
+            }
+        }
+        // A fixed-angle fill is all one orientation; spokes cover both.
+        assert!(horizontal > 0 && vertical > 0, "h {horizontal} v {vertical}");
+
+        // Everything stays inside the region.
+        for s in &stitches {
+            assert!(s.x.abs() <= 10.0 + 1e-6 && s.y.abs() <= 10.0 + 1e-6);
+        }
+    }
+
+    fn rect_ring(w: f64, h: f64) -> Vec<Vec<Point>> {
+        let hw = w * 0.5;
+        let hh = h * 0.5;
+        vec![vec![
+            Point::new(-hw, -hh),
+            Point::new(hw, -hh),
+            Point::new(hw, hh),
+            Point::new(-hw, hh),
+            Point::new(-hw, -hh),
+        ]]
+    }
+
+    /// Max |x|-deviation of each row's outermost penetration from the rect
+    /// boundary at ±w/2.
+    fn max_edge_deviation(stitches: &[Stitch], half_width: f64) -> f64 {
+        use std::collections::BTreeMap;
+        let mut rows: BTreeMap<i64, (f64, f64)> = BTreeMap::new();
+        for s in stitches {
+            let key = (s.y * 1000.0).round() as i64;
+            let entry = rows.entry(key).or_insert((f64::INFINITY, f64::NEG_INFINITY));
+            entry.0 = entry.0.min(s.x);
+            entry.1 = entry.1.max(s.x);
+        }
+        let mut worst: f64 = 0.0;
+        for (min_x, max_x) in rows.values() {
+            worst = worst.max((min_x + half_width).abs());
+            worst = worst.max((max_x - half_width).abs());
+        }
+        worst
+    }
+
+    #[test]
+    fn fill_covers_rows_and_stays_inside() {
+        let rings = rect_ring(10.0, 6.0);
+        let stitches = generate_tatami_fill(&rings, 0.0, 0.5, 3.0, FillEdgeStyle::Raw);
+        assert!(!stitches.is_empty());
+        for s in &stitches {
+            assert!(s.x >= -5.0 - 1e-9 && s.x <= 5.0 + 1e-9);
+            assert!(s.y >= -3.0 - 1e-9 && s.y <= 3.0 + 1e-9);
+        }
+    }
+
+    #[test]
+    fn clean_edge_snaps_rows_onto_boundary() {
+        let rings = rect_ring(10.0, 6.0);
+        let raw = generate_tatami_fill(&rings, 0.0, 0.5, 3.0, FillEdgeStyle::Raw);
+        let clean = generate_tatami_fill(&rings, 0.0, 0.5, 3.0, FillEdgeStyle::CleanEdge);
+        assert!(max_edge_deviation(&clean, 5.0) < 0.01);
+        assert!(max_edge_deviation(&raw, 5.0) > 0.1);
+    }
+
+    #[test]
+    fn notch_crossing_connectors_become_jumps() {
+        // U-shape: two vertical arms joined at the bottom, notch open at
+        // the top between x=10 and x=20 above y=10.
+        let rings = vec![vec![
+            Point::new(0.0, 0.0),
+            Point::new(30.0, 0.0),
+            Point::new(30.0, 30.0),
+            Point::new(20.0, 30.0),
+            Point::new(20.0, 10.0),
+            Point::new(10.0, 10.0),
+            Point::new(10.0, 30.0),
+            Point::new(0.0, 30.0),
+            Point::new(0.0, 0.0),
+        ]];
+        let stitches = generate_tatami_fill(&rings, 0.0, 0.63, 2.7, FillEdgeStyle::Raw);
+        assert!(stitches.iter().any(|s| s.is_jump));
+        for w in stitches.windows(2) {
+            let mid = Point::new((w[0].x + w[1].x) * 0.5, (w[0].y + w[1].y) * 0.5);
+            assert!(
+                w[1].is_jump || crate::path::point_in_rings(&rings, mid),
+                "normal connector crosses the notch near ({}, {})",
+                mid.x,
+                mid.y
+            );
+        }
+    }
+
+    #[test]
+    fn spiral_turns_stay_spacing_apart_at_every_angle() {
+        let spacing = 1.0;
+        let stitches = generate_spiral_fill(Point::new(0.0, 0.0), 12.0, spacing, 0.8);
+        // Unwrap each stitch to (theta, r); the spiral winds monotonically
+        // and the angular step is capped well below pi.
+        let mut unwrapped: Vec<(f64, f64)> = Vec::new();
+        let mut prev = 0.0_f64;
+        for s in &stitches {
+            let r = (s.x * s.x + s.y * s.y).sqrt();
+            let mut a = s.y.atan2(s.x);
+            while a < prev - std::f64::consts::PI {
+                a += std::f64::consts::TAU;
+            }
+            prev = a;
+            unwrapped.push((a, r));
+        }
+        for probe in [0.0, 1.0, 2.5, 4.0] {
+            // Radii where successive turns cross the ray at angle `probe`.
+            let mut crossings = Vec::new();
+            for w in unwrapped.windows(2) {
+                let ((a0, r0), (a1, r1)) = (w[0], w[1]);
+                let k = ((a0 - probe) / std::f64::consts::TAU).ceil();
+                let target = probe + k * std::f64::consts::TAU;
+                if target >= a0 && target < a1 {
+                    let t = (target - a0) / (a1 - a0);
+                    crossings.push(r0 + t * (r1 - r0));
+                }
+            }
+            assert!(crossings.len() >= 8, "too few turns at angle {probe}");
+            for pair in crossings.windows(2) {
+                let step = pair[1] - pair[0];
+                assert!(
+                    (step - spacing).abs() <= spacing * 0.1,
+                    "turn spacing {step} at angle {probe}"
+                );
+            }
+        }
+    }
+
+    /// Worst per-row edge residual against the ideal ellipse equation, in
+    /// approximate mm of radial error.
+    fn ellipse_edge_residual(stitches: &[Stitch], rx: f64, ry: f64) -> f64 {
+        use std::collections::BTreeMap;
+        let mut rows: BTreeMap<i64, (f64, f64)> = BTreeMap::new();
+        for s in stitches {
+            let key = (s.y * 1000.0).round() as i64;
+            let entry = rows.entry(key).or_insert((f64::INFINITY, f64::NEG_INFINITY));
+            entry.0 = entry.0.min(s.x);
+            entry.1 = entry.1.max(s.x);
+        }
+        let mut worst: f64 = 0.0;
+        for (key, (min_x, max_x)) in rows {
+            let y = key as f64 / 1000.0;
+            for x in [min_x, max_x] {
+                let r = ((x / rx).powi(2) + (y / ry).powi(2)).sqrt();
+                worst = worst.max((r - 1.0).abs() * rx.min(ry));
+            }
+        }
+        worst
+    }
+
+    #[test]
+    fn analytic_ellipse_edge_beats_flattened_polygon() {
+        let (rx, ry) = (10.0, 6.0);
+        let analytic = generate_ellipse_tatami_fill(
+            Point::new(0.0, 0.0),
+            rx,
+            ry,
+            0.0,
+            0.4,
+            3.0,
+            FillEdgeStyle::CleanEdge,
+        );
+        // Coarsely flattened ellipse ring, as the polygon path would see it.
+        let n = 24;
+        let ring: Vec<Point> = (0..=n)
+            .map(|i| {
+                let t = i as f64 / n as f64 * std::f64::consts::TAU;
+                Point::new(rx * t.cos(), ry * t.sin())
+            })
+            .collect();
+        let flattened =
+            generate_tatami_fill(&[ring], 0.0, 0.4, 3.0, FillEdgeStyle::CleanEdge);
+
+        let analytic_err = ellipse_edge_residual(&analytic, rx, ry);
+        let flattened_err = ellipse_edge_residual(&flattened, rx, ry);
+        assert!(analytic_err < 1e-6, "analytic residual {analytic_err}");
+        assert!(flattened_err > 1e-2, "flattened residual {flattened_err}");
+    }
+
+    #[test]
+    fn narrow_shape_rows_are_never_dropped() {
+        // Segments narrower than the half-density stagger used to invert
+        // and be skipped entirely. A spacing wider than the 4mm shape is
+        // the extreme of that case: every row is a narrow segment.
+        let rings = rect_ring(4.0, 40.0);
+        let stitches = generate_tatami_fill(&rings, 0.0, 9.0, 3.0, FillEdgeStyle::Raw);
+        let mut rows: Vec<i64> = stitches.iter().map(|s| (s.y * 1000.0).round() as i64).collect();
+        rows.sort_unstable();
+        rows.dedup();
+        // floor(40 / 9) interior rows, each with at least one penetration.
+        assert_eq!(rows.len(), 4, "rows dropped: {stitches:?}");
+    }
+
+    #[test]
+    fn progress_is_monotonic_and_completes() {
+        let rings = rect_ring(10.0, 10.0);
+        let mut reports: Vec<f32> = Vec::new();
+        generate_tatami_fill_with_progress(&rings, 0.0, 0.4, 3.0, FillEdgeStyle::Raw, &mut |p| {
+            reports.push(p)
+        });
+        assert!(!reports.is_empty());
+        assert!(reports.windows(2).all(|w| w[0] <= w[1]));
+        assert!((reports.last().unwrap() - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn cancelling_mid_generation_stops_promptly() {
+        let rings = rect_ring(20.0, 20.0);
+        let token = CancelToken::new();
+        let hook_token = token.clone();
+        let mut rows_seen = 0u32;
+        let result = generate_tatami_fill_cancellable(
+            &rings,
+            0.0,
+            0.4,
+            3.0,
+            FillEdgeStyle::Raw,
+            &token,
+            &mut |_| {
+                rows_seen += 1;
+                if rows_seen == 3 {
+                    hook_token.cancel();
+                }
+            },
+        );
+        assert_eq!(result.unwrap_err(), crate::error::EngineError::Cancelled);
+        assert_eq!(rows_seen, 3);
+    }
+
+    #[test]
+    fn angled_fill_rotates_rows() {
+        let rings = rect_ring(10.0, 10.0);
+        let stitches = generate_tatami_fill(&rings, 90.0, 0.5, 3.0, FillEdgeStyle::Raw);
+        assert!(!stitches.is_empty());
+        // With rows at 90°, consecutive penetrations in a row share an x.
+        let same_x = stitches
+            .windows(2)
+            .filter(|w| (w[0].x - w[1].x).abs() < 1e-6)
+            .count();
+        assert!(same_x > stitches.len() / 2);
+    }
+
+    #[test]
+    fn gradient_rows_spread_apart_along_the_ramp() {
+        let rings = rect_ring(10.0, 20.0);
+        // Ramp along +y: dense rows at the bottom edge, sparse at the top.
+        let stitches =
+            generate_gradient_tatami_fill(&rings, 90.0, 0.4, 1.6, 3.0, FillEdgeStyle::Raw);
+        assert!(!stitches.is_empty());
+        let mut row_ys: Vec<f64> = stitches.iter().map(|s| s.y).collect();
+        row_ys.sort_by(f64::total_cmp);
+        row_ys.dedup_by(|a, b| (*a - *b).abs() < 1e-6);
+        assert!(row_ys.len() > 10);
+        let first_gap = row_ys[1] - row_ys[0];
+        let last_gap = row_ys[row_ys.len() - 1] - row_ys[row_ys.len() - 2];
+        assert!(
+            last_gap > first_gap * 2.0,
+            "first {first_gap} last {last_gap}"
+        );
+        // Equal start and end spacing degenerates to the constant fill's
+        // row positions.
+        let flat = generate_gradient_tatami_fill(&rings, 90.0, 0.5, 0.5, 3.0, FillEdgeStyle::Raw);
+        let tatami = generate_tatami_fill(&rings, 0.0, 0.5, 3.0, FillEdgeStyle::Raw);
+        assert_eq!(flat.len(), tatami.len());
+    }
+
+    #[test]
+    fn cross_stitch_cells_stay_inside_and_scale_with_cell_size() {
+        let rings = rect_ring(10.0, 10.0);
+        let coarse = generate_cross_stitch_fill(&rings, 2.0, 0.0);
+        let fine = generate_cross_stitch_fill(&rings, 1.0, 0.0);
+        assert!(!coarse.is_empty());
+        // Partial edge cells are skipped, never clipped: every penetration
+        // lands inside the ring bounding box.
+        for s in coarse.iter().chain(&fine) {
+            assert!(s.x >= -5.0 - 1e-9 && s.x <= 5.0 + 1e-9);
+            assert!(s.y >= -5.0 - 1e-9 && s.y <= 5.0 + 1e-9);
+        }
+        // Halving the cell size roughly quadruples the cell count (4 normal
+        // stitches per cross).
+        let cells = |stitches: &[Stitch]| stitches.iter().filter(|s| !s.is_jump).count() / 4;
+        assert!(cells(&fine) >= 3 * cells(&coarse));
+        // Each cross is two diagonal legs: the first pair of penetrations
+        // spans a full cell diagonal.
+        let (a, b) = (coarse[0], coarse[1]);
+        assert!(((a.x - b.x).abs() - 2.0).abs() < 1e-9);
+        assert!(((a.y - b.y).abs() - 2.0).abs() < 1e-9);
+    }
+}
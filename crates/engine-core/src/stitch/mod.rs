@@ -0,0 +1,365 @@
+//! Stitch generation: shared types plus per-technique generators.
+
+pub mod chain;
+pub mod fill;
+pub mod motif;
+pub mod running;
+pub mod satin;
+
+use serde::{Deserialize, Serialize};
+
+/// A single needle penetration in design space (mm). `is_jump` marks a
+/// movement without a penetration (machine lifts the needle).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Stitch {
+    pub x: f64,
+    pub y: f64,
+    #[serde(default)]
+    pub is_jump: bool,
+}
+
+impl Stitch {
+    pub fn normal(x: f64, y: f64) -> Self {
+        Self {
+            x,
+            y,
+            is_jump: false,
+        }
+    }
+
+    pub fn jump(x: f64, y: f64) -> Self {
+        Self {
+            x,
+            y,
+            is_jump: true,
+        }
+    }
+}
+
+/// Where a shape's pull compensation comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompensationMode {
+    /// Use `pull_compensation` as entered.
+    #[default]
+    Manual,
+    /// Derive compensation from the `fabric` hint and satin width.
+    Auto,
+}
+
+/// Which side of the outline a satin band sits on, mirroring SVG stroke
+/// alignment: centered on the path, fully inside the filled region, or
+/// fully outside it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StrokeAlign {
+    #[default]
+    Center,
+    Inside,
+    Outside,
+}
+
+/// A machine command a user can insert by hand into a shape's stitch run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ManualCommandKind {
+    Jump,
+    Trim,
+    Stop,
+    ColorChange,
+}
+
+/// A manual command anchored before the stitch at `at_index` in the shape's
+/// generated block. Commands survive regeneration: the shape keeps producing
+/// its stitches and the commands are re-applied at the stored indexes during
+/// export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManualStitchCommand {
+    pub at_index: usize,
+    pub kind: ManualCommandKind,
+}
+
+/// Which stitch technique a shape is digitized with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StitchType {
+    #[default]
+    Running,
+    Satin,
+    Tatami,
+    Chain,
+    /// Triple (or n-fold) running stitch: each segment sewn
+    /// forward-back-forward for durable outlines.
+    Bean,
+    /// Counted-style fill: a grid of X crosses over the region.
+    CrossStitch,
+    /// Tatami whose row spacing ramps from `density` to `density_end_mm`
+    /// along `gradient_angle`, for shaded coverage.
+    GradientFill,
+    /// Blanket/comb finish for appliqué edges: a run along the outline
+    /// with perpendicular legs reaching across the band.
+    EStitch,
+}
+
+/// Per-shape stitch generation parameters. All fields have serde defaults so
+/// documents only store what the user changed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StitchParams {
+    pub stitch_type: StitchType,
+    /// Row/zigzag spacing in mm (satin and fill techniques).
+    pub density: f64,
+    /// Fill angle in degrees.
+    pub angle_degrees: f64,
+    /// Extra rail width (mm) added on each side of a satin column to counter
+    /// fabric pull.
+    pub pull_compensation: f64,
+    /// How fill rows terminate at the shape boundary.
+    pub fill_edge_style: fill::FillEdgeStyle,
+    /// `(dash_mm, gap_mm)` for broken running stitches; `None` is a solid
+    /// run.
+    pub dash: Option<(f64, f64)>,
+    /// Thread color stitched for this shape's block, independent of the
+    /// shape's fill/stroke. `None` derives the thread from the style.
+    pub color_override: Option<crate::shapes::Color>,
+    /// Where pull compensation comes from.
+    pub compensation_mode: CompensationMode,
+    /// Substrate hint used by `CompensationMode::Auto`.
+    pub fabric: Option<crate::fabric::Fabric>,
+    /// Fill shapes with a bounding-box area (mm²) below this export as satin
+    /// or a running pass instead of tatami; `0.0` disables the fallback.
+    pub min_fill_area_mm2: f64,
+    /// Hand-inserted machine commands, kept sorted by `at_index`.
+    pub manual_commands: Vec<ManualStitchCommand>,
+    /// Loop width (mm) for chain stitch.
+    pub chain_loop_mm: f64,
+    /// Passes per segment for bean stitch; 3 is the classic triple run.
+    pub bean_repeats: usize,
+    /// Grid cell size (mm) for cross-stitch fill.
+    pub cell_size_mm: f64,
+    /// Row spacing (mm) at the far edge of a gradient fill; `density` is
+    /// the spacing at the near edge.
+    pub density_end_mm: f64,
+    /// Direction (degrees) the gradient fill's spacing ramp advances along.
+    pub gradient_angle: f64,
+    /// Keep thread coverage constant under transform scale: the spacing
+    /// stitched in world space is always `density` mm. Off, `density` is
+    /// measured in the shape's local units, so scaling a node up spreads
+    /// its rows apart with it.
+    pub density_follows_scale: bool,
+    /// Maximum random offset (mm) applied to each penetration for a
+    /// hand-stitched look; `0.0` keeps penetrations exact.
+    pub jitter_mm: f64,
+    /// How motif-fill textures lay their tiles out across the region.
+    pub motif_arrangement: motif::MotifArrangement,
+    /// Which side of the outline the satin band sits on.
+    pub stroke_align: StrokeAlign,
+    /// Stabilizing pass(es) stitched under a tatami fill.
+    pub fill_underlay: fill::FillUnderlay,
+    /// Shape-local focal point for radial (sunburst) fills. `Some` swaps
+    /// the constant-angle scanline for spokes aimed at this point.
+    pub fill_focus: Option<crate::geometry::Point>,
+    /// Insert short stitches on the outer rail of tight satin curves so
+    /// outer coverage keeps up with the longer arc.
+    pub short_stitch_enabled: bool,
+    /// Rail turn (degrees between successive columns) past which short
+    /// stitches are inserted.
+    pub short_stitch_angle_deg: f64,
+}
+
+impl Default for StitchParams {
+    fn default() -> Self {
+        Self {
+            stitch_type: StitchType::default(),
+            density: 0.4,
+            angle_degrees: 0.0,
+            pull_compensation: 0.0,
+            fill_edge_style: fill::FillEdgeStyle::default(),
+            dash: None,
+            color_override: None,
+            compensation_mode: CompensationMode::default(),
+            fabric: None,
+            min_fill_area_mm2: 0.0,
+            manual_commands: Vec::new(),
+            chain_loop_mm: 1.0,
+            bean_repeats: 3,
+            cell_size_mm: 2.5,
+            density_end_mm: 1.2,
+            gradient_angle: 90.0,
+            density_follows_scale: false,
+            jitter_mm: 0.0,
+            motif_arrangement: motif::MotifArrangement::default(),
+            stroke_align: StrokeAlign::default(),
+            fill_underlay: fill::FillUnderlay::default(),
+            fill_focus: None,
+            short_stitch_enabled: false,
+            short_stitch_angle_deg: 15.0,
+        }
+    }
+}
+
+impl StitchParams {
+    /// The pull compensation to stitch with: the manual value, unless the
+    /// shape asks for fabric-derived compensation and carries a fabric hint.
+    pub fn effective_pull_compensation(&self, satin_width_mm: f64) -> f64 {
+        match (self.compensation_mode, self.fabric) {
+            (CompensationMode::Auto, Some(fabric)) => {
+                crate::fabric::recommend_compensation(fabric, satin_width_mm).0
+            }
+            _ => self.pull_compensation,
+        }
+    }
+
+    /// The row/zigzag spacing to stitch in world space for a node at
+    /// `world_scale`. With `density_follows_scale` the physical spacing is
+    /// `density` regardless of scale; otherwise it tracks the node's scale.
+    pub fn effective_density(&self, world_scale: f64) -> f64 {
+        if self.density_follows_scale {
+            self.density
+        } else {
+            self.density * world_scale
+        }
+    }
+}
+
+/// One inspector row of the [`StitchParams`] schema: enough metadata for
+/// the UI to render a control without hand-maintaining the panel.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParamDescriptor {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub field_type: String,
+    pub default: serde_json::Value,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub unit: Option<String>,
+    /// Techniques the field affects; empty means it applies to all.
+    pub applies_to: Vec<StitchType>,
+}
+
+/// Machine-readable schema of every [`StitchParams`] field, in declaration
+/// order. Kept next to the struct so additions show up in the inspector
+/// (and the sync test) immediately.
+pub fn stitch_params_schema() -> Vec<ParamDescriptor> {
+    use StitchType::{Bean, Chain, CrossStitch, EStitch, GradientFill, Running, Satin, Tatami};
+    let defaults = serde_json::to_value(StitchParams::default()).expect("params serialize");
+    let row = |name: &str,
+               field_type: &str,
+               min: Option<f64>,
+               max: Option<f64>,
+               unit: Option<&str>,
+               applies_to: &[StitchType]| ParamDescriptor {
+        name: name.to_string(),
+        field_type: field_type.to_string(),
+        default: defaults[name].clone(),
+        min,
+        max,
+        unit: unit.map(str::to_string),
+        applies_to: applies_to.to_vec(),
+    };
+    vec![
+        row("stitch_type", "enum", None, None, None, &[]),
+        row("density", "number", Some(0.1), Some(5.0), Some("mm"), &[Satin, Tatami, GradientFill, EStitch]),
+        row("angle_degrees", "number", Some(0.0), Some(360.0), Some("deg"), &[Tatami]),
+        row("pull_compensation", "number", Some(0.0), Some(2.0), Some("mm"), &[Satin]),
+        row("fill_edge_style", "enum", None, None, None, &[Tatami]),
+        row("dash", "tuple?", Some(0.1), None, Some("mm"), &[Running]),
+        row("color_override", "color?", None, None, None, &[]),
+        row("compensation_mode", "enum", None, None, None, &[Satin]),
+        row("fabric", "enum?", None, None, None, &[Satin]),
+        row("min_fill_area_mm2", "number", Some(0.0), None, Some("mm²"), &[Tatami]),
+        row("manual_commands", "list", None, None, None, &[]),
+        row("chain_loop_mm", "number", Some(0.2), Some(5.0), Some("mm"), &[Chain]),
+        row("bean_repeats", "number", Some(1.0), Some(9.0), None, &[Bean]),
+        row("cell_size_mm", "number", Some(0.5), Some(10.0), Some("mm"), &[CrossStitch]),
+        row("density_end_mm", "number", Some(0.1), Some(5.0), Some("mm"), &[GradientFill]),
+        row("gradient_angle", "number", Some(0.0), Some(360.0), Some("deg"), &[GradientFill]),
+        row("density_follows_scale", "bool", None, None, None, &[Satin, Tatami]),
+        row("jitter_mm", "number", Some(0.0), Some(2.0), Some("mm"), &[]),
+        row("motif_arrangement", "enum", None, None, None, &[]),
+        row("stroke_align", "enum", None, None, None, &[Satin]),
+        row("fill_underlay", "enum", None, None, None, &[Tatami]),
+        row("fill_focus", "point?", None, None, None, &[Tatami]),
+        row("short_stitch_enabled", "bool", None, None, None, &[Satin]),
+        row("short_stitch_angle_deg", "number", Some(5.0), Some(90.0), Some("deg"), &[Satin]),
+    ]
+}
+
+/// Perturb each normal penetration by a pseudo-random offset of up to
+/// `jitter_mm`, for a deliberately hand-stitched look. Jumps are left in
+/// place so travel geometry stays predictable. The generator is seeded, so
+/// the same seed always reproduces the same offsets.
+pub fn apply_jitter(stitches: &mut [Stitch], jitter_mm: f64, seed: u64) {
+    if jitter_mm <= 0.0 {
+        return;
+    }
+    // splitmix64: cheap, seedable, and identical on every platform.
+    let mut state = seed;
+    let mut next = || -> f64 {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    };
+    for s in stitches.iter_mut().filter(|s| !s.is_jump) {
+        let angle = next() * std::f64::consts::TAU;
+        let radius = next() * jitter_mm;
+        s.x += radius * angle.cos();
+        s.y += radius * angle.sin();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schema_tracks_the_struct_and_tags_units() {
+        let schema = stitch_params_schema();
+        // Every struct field is described, and nothing extra is.
+        let serde_json::Value::Object(fields) =
+            serde_json::to_value(StitchParams::default()).unwrap()
+        else {
+            unreachable!()
+        };
+        let names: Vec<&str> = schema.iter().map(|d| d.name.as_str()).collect();
+        for key in fields.keys() {
+            assert!(names.contains(&key.as_str()), "field {key} missing from schema");
+        }
+        assert_eq!(names.len(), fields.len());
+
+        let density = schema.iter().find(|d| d.name == "density").unwrap();
+        assert_eq!(density.unit.as_deref(), Some("mm"));
+        assert_eq!(density.default, serde_json::json!(0.4));
+
+        let align = schema.iter().find(|d| d.name == "stroke_align").unwrap();
+        assert_eq!(align.applies_to, vec![StitchType::Satin]);
+    }
+
+    #[test]
+    fn jitter_is_bounded_and_reproducible() {
+        let base: Vec<Stitch> = (0..50)
+            .map(|i| Stitch::normal(i as f64 * 2.0, 0.0))
+            .collect();
+        let mut a = base.clone();
+        let mut b = base.clone();
+        apply_jitter(&mut a, 0.3, 7);
+        apply_jitter(&mut b, 0.3, 7);
+        assert_eq!(a, b);
+        let mut moved = 0;
+        for (orig, jittered) in base.iter().zip(&a) {
+            let d = ((jittered.x - orig.x).powi(2) + (jittered.y - orig.y).powi(2)).sqrt();
+            assert!(d <= 0.3 + 1e-12);
+            if d > 0.0 {
+                moved += 1;
+            }
+        }
+        assert!(moved > 40);
+
+        let mut zero = base.clone();
+        apply_jitter(&mut zero, 0.0, 7);
+        assert_eq!(zero, base);
+    }
+}
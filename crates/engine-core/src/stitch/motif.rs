@@ -0,0 +1,316 @@
+//! Motif stitch: a small unit pattern tiled along a path.
+//!
+//! Motifs are defined in unit space — X runs 0..1 along the path direction,
+//! Y is the sideways amplitude — and are scaled/rotated onto each tile of
+//! the target polyline. Custom motifs live in a session registry keyed by
+//! id, but the pattern embeds its geometry when serialized so saved
+//! documents stay self-contained.
+
+use crate::geometry::Point;
+use crate::stitch::running::{cumulative_lengths, point_at};
+use crate::stitch::Stitch;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+
+thread_local! {
+    static MOTIF_REGISTRY: RefCell<Vec<Vec<Point>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Register a custom unit motif; returns its registry id. Points are the
+/// motif polyline in unit space and must start a tile (two points minimum).
+pub fn register_motif(points: Vec<Point>) -> Result<u32, String> {
+    if points.len() < 2 {
+        return Err("a motif needs at least two points".to_string());
+    }
+    MOTIF_REGISTRY.with(|r| {
+        let mut registry = r.borrow_mut();
+        registry.push(points);
+        Ok(registry.len() as u32 - 1)
+    })
+}
+
+/// The registered geometry for a motif id, if any.
+pub fn motif_points(id: u32) -> Option<Vec<Point>> {
+    MOTIF_REGISTRY.with(|r| r.borrow().get(id as usize).cloned())
+}
+
+/// The unit pattern tiled along a motif run.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(tag = "motif", rename_all = "snake_case")]
+pub enum MotifPattern {
+    #[default]
+    Zigzag,
+    Wave,
+    Diamond,
+    /// A user-registered motif. `points` is the embedded geometry so
+    /// documents round-trip without the session registry; it is filled from
+    /// the registry when the pattern is created via [`MotifPattern::custom`].
+    Custom {
+        id: u32,
+        #[serde(default)]
+        points: Vec<Point>,
+    },
+}
+
+impl MotifPattern {
+    /// A custom pattern snapshotting the registered geometry for `id`.
+    pub fn custom(id: u32) -> Result<Self, String> {
+        let points = motif_points(id).ok_or_else(|| format!("no motif registered for id {id}"))?;
+        Ok(Self::Custom { id, points })
+    }
+}
+
+/// The unit-space polyline for a pattern. Custom patterns prefer their
+/// embedded geometry and fall back to the registry.
+pub fn build_motif(pattern: &MotifPattern) -> Result<Vec<Point>, String> {
+    match pattern {
+        MotifPattern::Zigzag => Ok(vec![
+            Point::new(0.0, 0.0),
+            Point::new(0.25, -0.5),
+            Point::new(0.75, 0.5),
+            Point::new(1.0, 0.0),
+        ]),
+        MotifPattern::Wave => Ok(vec![
+            Point::new(0.0, 0.0),
+            Point::new(0.25, -0.4),
+            Point::new(0.5, 0.0),
+            Point::new(0.75, 0.4),
+            Point::new(1.0, 0.0),
+        ]),
+        MotifPattern::Diamond => Ok(vec![
+            Point::new(0.0, 0.0),
+            Point::new(0.5, -0.5),
+            Point::new(1.0, 0.0),
+            Point::new(0.5, 0.5),
+            Point::new(0.0, 0.0),
+            Point::new(1.0, 0.0),
+        ]),
+        MotifPattern::Custom { id, points } => {
+            if points.len() >= 2 {
+                return Ok(points.clone());
+            }
+            motif_points(*id).ok_or_else(|| format!("no motif registered for id {id}"))
+        }
+    }
+}
+
+/// How a motif fill lays its tiles out across a region.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(tag = "arrangement", rename_all = "snake_case")]
+pub enum MotifArrangement {
+    /// Rows and columns on `spacing` centers, axis-aligned.
+    #[default]
+    Grid,
+    /// Concentric rings around `center`, one every `spacing`, `rings` deep.
+    /// Each ring holds as many whole motifs as its circumference allows.
+    Polar { center: Point, rings: usize },
+}
+
+/// Tile the motif across a fill region. Instances whose geometry would
+/// cross the boundary are dropped, so the texture stays clipped inside the
+/// shape; each kept instance starts with a jump from the previous one.
+pub fn generate_motif_fill(
+    rings: &[Vec<Point>],
+    pattern: &MotifPattern,
+    motif_length: f64,
+    spacing: f64,
+    arrangement: &MotifArrangement,
+) -> Result<Vec<Stitch>, String> {
+    let motif = build_motif(pattern)?;
+    let mut out = Vec::new();
+    if motif_length <= 0.0 || spacing <= 0.0 {
+        return Err("motif length and spacing must be positive".to_string());
+    }
+    let mut place = |origin: Point, dir: Point| {
+        let normal = dir.perp();
+        let instance: Vec<Point> = motif
+            .iter()
+            .map(|m| origin + dir * (m.x * motif_length) + normal * (m.y * motif_length))
+            .collect();
+        if !instance
+            .iter()
+            .all(|p| crate::path::point_in_rings(rings, *p))
+        {
+            return;
+        }
+        for (j, p) in instance.iter().enumerate() {
+            if j == 0 {
+                out.push(Stitch::jump(p.x, p.y));
+            } else {
+                out.push(Stitch::normal(p.x, p.y));
+            }
+        }
+    };
+    match arrangement {
+        MotifArrangement::Grid => {
+            let mut bbox = crate::geometry::BoundingBox::empty();
+            for ring in rings {
+                for p in ring {
+                    bbox.include(*p);
+                }
+            }
+            if bbox.is_empty() {
+                return Ok(out);
+            }
+            let mut y = bbox.min_y;
+            while y <= bbox.max_y {
+                let mut x = bbox.min_x;
+                while x <= bbox.max_x {
+                    place(Point::new(x, y), Point::new(1.0, 0.0));
+                    x += spacing;
+                }
+                y += spacing;
+            }
+        }
+        MotifArrangement::Polar { center, rings: n } => {
+            for k in 1..=*n {
+                let radius = k as f64 * spacing;
+                let count = ((std::f64::consts::TAU * radius / motif_length).floor() as usize)
+                    .max(1);
+                let step = std::f64::consts::TAU / count as f64;
+                for i in 0..count {
+                    let angle = i as f64 * step;
+                    let radial = Point::new(angle.cos(), angle.sin());
+                    let origin = *center + radial * radius;
+                    // Motifs run tangentially, so the texture reads as
+                    // rings rather than spokes.
+                    place(origin, radial.perp());
+                }
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Tile the motif along a polyline. The tile count is chosen so whole motifs
+/// fit (`motif_length` stretches up to fill the path evenly); each tile is
+/// rotated onto its chord and scaled by the chord length on both axes.
+pub fn generate_motif_stitches(
+    points: &[Point],
+    pattern: &MotifPattern,
+    motif_length: f64,
+) -> Result<Vec<Stitch>, String> {
+    let motif = build_motif(pattern)?;
+    let mut out = Vec::new();
+    if points.len() < 2 || motif_length <= 0.0 {
+        return Ok(out);
+    }
+    let cumulative = cumulative_lengths(points);
+    let total = *cumulative.last().expect("non-empty lengths");
+    if total <= f64::EPSILON {
+        return Ok(out);
+    }
+    let count = ((total / motif_length).floor() as usize).max(1);
+    let step = total / count as f64;
+    for i in 0..count {
+        let a = point_at(points, &cumulative, i as f64 * step);
+        let b = point_at(points, &cumulative, (i + 1) as f64 * step);
+        let dir = (b - a).normalized();
+        let normal = dir.perp();
+        for (j, m) in motif.iter().enumerate() {
+            if i > 0 && j == 0 {
+                // Tiles share their boundary point.
+                continue;
+            }
+            let p = a + dir * (m.x * step) + normal * (m.y * step);
+            out.push(Stitch::normal(p.x, p.y));
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shapes::{RectShape, ShapeData};
+
+    #[test]
+    fn custom_square_motif_tiles_around_a_rect() {
+        let id = register_motif(vec![
+            Point::new(0.0, 0.0),
+            Point::new(0.0, -0.5),
+            Point::new(1.0, -0.5),
+            Point::new(1.0, 0.0),
+        ])
+        .unwrap();
+        let pattern = MotifPattern::custom(id).unwrap();
+
+        let rect = ShapeData::Rect(RectShape {
+            width: 10.0,
+            height: 10.0,
+        });
+        let ring = &rect.to_path().flatten(0.1)[0];
+        let stitches = generate_motif_stitches(ring, &pattern, 2.0).unwrap();
+        // 40 mm perimeter / 2 mm motifs = 20 tiles; tiles share boundary
+        // points, so 4 points for the first and 3 more per remaining tile.
+        assert_eq!(stitches.len(), 4 + 19 * 3);
+        // The square bump sticks out sideways by half the tile length.
+        let max_y = stitches.iter().map(|s| s.y.abs()).fold(0.0, f64::max);
+        assert!((max_y - 6.0).abs() < 1e-6, "max |y| = {max_y}");
+    }
+
+    #[test]
+    fn polar_fill_rings_grow_and_stay_inside() {
+        let rect = ShapeData::Rect(RectShape {
+            width: 30.0,
+            height: 30.0,
+        });
+        let rings = rect.to_path().flatten(0.1);
+        let arrangement = MotifArrangement::Polar {
+            center: Point::new(0.0, 0.0),
+            rings: 4,
+        };
+        let stitches =
+            generate_motif_fill(&rings, &MotifPattern::Zigzag, 2.0, 3.0, &arrangement).unwrap();
+        assert!(!stitches.is_empty());
+        for s in &stitches {
+            assert!(s.x.abs() <= 15.0 && s.y.abs() <= 15.0);
+        }
+        // Jumps mark instance starts; their radii step through the ring
+        // spacing and outer rings hold more motifs than inner ones.
+        let starts: Vec<f64> = stitches
+            .iter()
+            .filter(|s| s.is_jump)
+            .map(|s| (s.x * s.x + s.y * s.y).sqrt())
+            .collect();
+        let per_ring = |k: f64| {
+            starts
+                .iter()
+                .filter(|r| (**r - k * 3.0).abs() < 1.5)
+                .count()
+        };
+        assert!(per_ring(1.0) >= 1);
+        assert!(per_ring(4.0) > per_ring(1.0));
+        let max_r = starts.iter().cloned().fold(0.0, f64::max);
+        assert!((max_r - 12.0).abs() < 1.5, "outermost ring at {max_r}");
+    }
+
+    #[test]
+    fn custom_pattern_serializes_geometry_inline() {
+        let id = register_motif(vec![Point::new(0.0, 0.0), Point::new(1.0, 0.0)]).unwrap();
+        let pattern = MotifPattern::custom(id).unwrap();
+        let json = serde_json::to_string(&pattern).unwrap();
+        assert!(json.contains("points"));
+        // Round-trips to usable geometry without touching the registry.
+        let back: MotifPattern = serde_json::from_str(&json).unwrap();
+        assert_eq!(build_motif(&back).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn builtin_motifs_span_unit_x() {
+        for pattern in [
+            MotifPattern::Zigzag,
+            MotifPattern::Wave,
+            MotifPattern::Diamond,
+        ] {
+            let motif = build_motif(&pattern).unwrap();
+            assert_eq!(motif.first().unwrap().x, 0.0);
+            assert_eq!(motif.last().unwrap().x, 1.0);
+        }
+    }
+
+    #[test]
+    fn unknown_motif_id_errors() {
+        assert!(MotifPattern::custom(9999).is_err());
+    }
+}
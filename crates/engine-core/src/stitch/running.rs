@@ -0,0 +1,245 @@
+//! Running stitch: evenly spaced penetrations along a polyline.
+
+use crate::geometry::Point;
+use crate::stitch::Stitch;
+
+/// Generate running stitches along `points` with a target `stitch_length`
+/// (mm). The first and last points are always penetrated; interior spacing is
+/// adjusted per segment so stitches never exceed `stitch_length`.
+pub fn generate_running_stitches(points: &[Point], stitch_length: f64) -> Vec<Stitch> {
+    let mut out = Vec::new();
+    if points.len() < 2 || stitch_length <= 0.0 {
+        return out;
+    }
+    out.push(Stitch::normal(points[0].x, points[0].y));
+    for window in points.windows(2) {
+        let (a, b) = (window[0], window[1]);
+        let seg_len = a.distance_to(b);
+        if seg_len <= f64::EPSILON {
+            continue;
+        }
+        let steps = (seg_len / stitch_length).ceil().max(1.0) as usize;
+        for i in 1..=steps {
+            let t = i as f64 / steps as f64;
+            let p = a.lerp(b, t);
+            out.push(Stitch::normal(p.x, p.y));
+        }
+    }
+    out
+}
+
+/// Generate bean stitches: the running-stitch spacing, but each segment is
+/// sewn `repeats` times (forward-back-forward for the classic triple run)
+/// before advancing. Passes alternate endpoints, so no point is penetrated
+/// twice in a row and the endpoints never knot; an even `repeats` is bumped
+/// to the next odd count so every segment still ends at its far point.
+/// `repeats = 1` is a plain running stitch.
+pub fn generate_bean_stitches(points: &[Point], stitch_length: f64, repeats: usize) -> Vec<Stitch> {
+    let base = generate_running_stitches(points, stitch_length);
+    let repeats = repeats.max(1) | 1;
+    if repeats == 1 || base.len() < 2 {
+        return base;
+    }
+    let mut out = Vec::with_capacity(1 + (base.len() - 1) * repeats);
+    out.push(base[0]);
+    for pair in base.windows(2) {
+        for pass in 1..=repeats {
+            out.push(if pass % 2 == 1 { pair[1] } else { pair[0] });
+        }
+    }
+    out
+}
+
+/// Generate running stitches along only the span of `points` between the
+/// normalized arc-length parameters `start_t` and `end_t` (0..1, swapped
+/// if reversed). Both span endpoints are penetrated; `start_t = 0`,
+/// `end_t = 1` matches the full [`generate_running_stitches`] spacing
+/// behavior. Used for trims and targeted re-stitching.
+pub fn generate_running_stitches_range(
+    points: &[Point],
+    start_t: f64,
+    end_t: f64,
+    stitch_length: f64,
+) -> Vec<Stitch> {
+    let mut out = Vec::new();
+    if points.len() < 2 || stitch_length <= 0.0 {
+        return out;
+    }
+    let (lo, hi) = if start_t <= end_t {
+        (start_t, end_t)
+    } else {
+        (end_t, start_t)
+    };
+    let (lo, hi) = (lo.clamp(0.0, 1.0), hi.clamp(0.0, 1.0));
+    let cumulative = cumulative_lengths(points);
+    let total = *cumulative.last().unwrap();
+    let span = (hi - lo) * total;
+    if span <= f64::EPSILON {
+        return out;
+    }
+    let start_len = lo * total;
+    let steps = ((span / stitch_length).ceil() as usize).max(1);
+    for i in 0..=steps {
+        let p = point_at(
+            points,
+            &cumulative,
+            start_len + span * i as f64 / steps as f64,
+        );
+        out.push(Stitch::normal(p.x, p.y));
+    }
+    out
+}
+
+/// Cumulative arc lengths for a polyline (same length as `points`).
+pub(crate) fn cumulative_lengths(points: &[Point]) -> Vec<f64> {
+    let mut out = Vec::with_capacity(points.len());
+    out.push(0.0);
+    for w in points.windows(2) {
+        let last = *out.last().unwrap();
+        out.push(last + w[0].distance_to(w[1]));
+    }
+    out
+}
+
+/// The point at arc length `target` along the polyline.
+pub(crate) fn point_at(points: &[Point], cumulative: &[f64], target: f64) -> Point {
+    let total = *cumulative.last().unwrap();
+    let target = target.clamp(0.0, total);
+    let mut seg = 0;
+    while seg + 1 < cumulative.len() - 1 && cumulative[seg + 1] < target {
+        seg += 1;
+    }
+    let seg_len = cumulative[seg + 1] - cumulative[seg];
+    let t = if seg_len <= f64::EPSILON {
+        0.0
+    } else {
+        (target - cumulative[seg]) / seg_len
+    };
+    points[seg].lerp(points[seg + 1], t)
+}
+
+/// Generate a dashed (broken) run: `dash_mm` of running stitches, then a
+/// `gap_mm` emitted as a jump so the machine lifts between dashes. Used for
+/// basting and tear-away placement runs.
+pub fn generate_dashed_run(
+    points: &[Point],
+    dash_mm: f64,
+    gap_mm: f64,
+    stitch_length: f64,
+) -> Vec<Stitch> {
+    let mut out = Vec::new();
+    if points.len() < 2 || dash_mm <= 0.0 || gap_mm < 0.0 || stitch_length <= 0.0 {
+        return out;
+    }
+    let cumulative = cumulative_lengths(points);
+    let total = *cumulative.last().unwrap();
+    if total <= f64::EPSILON {
+        return out;
+    }
+
+    let mut cursor = 0.0;
+    while cursor < total {
+        let dash_end = (cursor + dash_mm).min(total);
+        let start = point_at(points, &cumulative, cursor);
+        if cursor > 0.0 {
+            out.push(Stitch::jump(start.x, start.y));
+        }
+        out.push(Stitch::normal(start.x, start.y));
+        let span = dash_end - cursor;
+        let steps = ((span / stitch_length).ceil() as usize).max(1);
+        for i in 1..=steps {
+            let p = point_at(points, &cumulative, cursor + span * i as f64 / steps as f64);
+            out.push(Stitch::normal(p.x, p.y));
+        }
+        cursor = dash_end + gap_mm;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn straight_line_splits_evenly() {
+        let pts = [Point::new(0.0, 0.0), Point::new(10.0, 0.0)];
+        let stitches = generate_running_stitches(&pts, 2.5);
+        assert_eq!(stitches.len(), 5);
+        assert_eq!(stitches.last().unwrap().x, 10.0);
+        for pair in stitches.windows(2) {
+            let d = ((pair[1].x - pair[0].x).powi(2) + (pair[1].y - pair[0].y).powi(2)).sqrt();
+            assert!((d - 2.5).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn half_range_covers_exactly_half_the_line() {
+        let pts = [Point::new(0.0, 0.0), Point::new(10.0, 0.0)];
+        let stitches = generate_running_stitches_range(&pts, 0.0, 0.5, 2.5);
+        assert_eq!(stitches.first().unwrap().x, 0.0);
+        assert_eq!(stitches.last().unwrap().x, 5.0);
+        assert!(stitches.iter().all(|s| s.x <= 5.0));
+
+        // Reversed parameters cover the same span.
+        let swapped = generate_running_stitches_range(&pts, 0.5, 0.0, 2.5);
+        assert_eq!(stitches, swapped);
+
+        // The full range matches the plain generator on a single segment.
+        let full = generate_running_stitches_range(&pts, 0.0, 1.0, 2.5);
+        assert_eq!(full, generate_running_stitches(&pts, 2.5));
+    }
+
+    #[test]
+    fn bean_triples_every_segment() {
+        let pts = [Point::new(0.0, 0.0), Point::new(10.0, 0.0)];
+        let running = generate_running_stitches(&pts, 2.5);
+        let bean = generate_bean_stitches(&pts, 2.5, 3);
+        assert_eq!(bean.len(), 1 + (running.len() - 1) * 3);
+        // Every segment goes forward, back, forward — consecutive points
+        // always differ, so the ends never knot.
+        for pair in bean.windows(2) {
+            assert_ne!((pair[0].x, pair[0].y), (pair[1].x, pair[1].y));
+        }
+        assert_eq!(bean.last().unwrap().x, 10.0);
+        // One pass degenerates to the plain running stitch, as does an even
+        // count rounded up.
+        assert_eq!(generate_bean_stitches(&pts, 2.5, 1), running);
+        assert_eq!(generate_bean_stitches(&pts, 2.5, 2), bean);
+    }
+
+    #[test]
+    fn short_segment_still_penetrates_endpoints() {
+        let pts = [Point::new(0.0, 0.0), Point::new(0.5, 0.0)];
+        let stitches = generate_running_stitches(&pts, 3.0);
+        assert_eq!(stitches.len(), 2);
+    }
+
+    #[test]
+    fn dashed_line_emits_expected_groups() {
+        let pts = [Point::new(0.0, 0.0), Point::new(20.0, 0.0)];
+        let stitches = generate_dashed_run(&pts, 4.0, 2.0, 2.0);
+        // Pattern over 20mm: dashes at 0–4, 6–10, 12–16, 18–20.
+        let jump_count = stitches.iter().filter(|s| s.is_jump).count();
+        assert_eq!(jump_count, 3);
+        let groups = stitches.split(|s| s.is_jump).count();
+        assert_eq!(groups, 4);
+        // Dashes cover their spans: the second dash starts at x=6.
+        let second_start = stitches.iter().position(|s| s.is_jump).unwrap();
+        assert!((stitches[second_start].x - 6.0).abs() < 1e-9);
+        assert!(stitches.last().unwrap().x >= 20.0 - 1e-9);
+    }
+
+    #[test]
+    fn no_dash_matches_solid_run() {
+        let pts = [Point::new(0.0, 0.0), Point::new(10.0, 3.0)];
+        let solid = generate_running_stitches(&pts, 2.5);
+        let dashed = generate_dashed_run(&pts, f64::INFINITY, 0.0, 2.5);
+        assert_eq!(solid.len(), dashed.len());
+    }
+
+    #[test]
+    fn degenerate_input_is_empty() {
+        assert!(generate_running_stitches(&[], 2.0).is_empty());
+        assert!(generate_running_stitches(&[Point::new(1.0, 1.0)], 2.0).is_empty());
+    }
+}
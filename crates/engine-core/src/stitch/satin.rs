@@ -0,0 +1,484 @@
+//! Satin (zigzag column) stitch generation over a pair of rails.
+
+use crate::geometry::Point;
+use crate::stitch::Stitch;
+
+/// Resample a polyline to `n` points spaced uniformly by arc length.
+pub fn resample_by_arclength(points: &[Point], n: usize) -> Vec<Point> {
+    if points.len() < 2 || n < 2 {
+        return points.to_vec();
+    }
+    let mut cumulative = Vec::with_capacity(points.len());
+    cumulative.push(0.0);
+    for w in points.windows(2) {
+        let last = *cumulative.last().unwrap();
+        cumulative.push(last + w[0].distance_to(w[1]));
+    }
+    let total = *cumulative.last().unwrap();
+    if total <= f64::EPSILON {
+        return vec![points[0]; n];
+    }
+    let mut out = Vec::with_capacity(n);
+    let mut seg = 0usize;
+    for i in 0..n {
+        let target = total * i as f64 / (n - 1) as f64;
+        while seg + 1 < cumulative.len() - 1 && cumulative[seg + 1] < target {
+            seg += 1;
+        }
+        let seg_len = cumulative[seg + 1] - cumulative[seg];
+        let t = if seg_len <= f64::EPSILON {
+            0.0
+        } else {
+            (target - cumulative[seg]) / seg_len
+        };
+        out.push(points[seg].lerp(points[seg + 1], t));
+    }
+    out
+}
+
+/// Miter limit for satin rail corners: tighter than the SVG default so
+/// near-hairpin corners bevel instead of spiking the column outward.
+const RAIL_MITER_LIMIT: f64 = 3.0;
+
+/// Offset a satin centerline into two rails at `±half_width`, mitered at
+/// corners (bevel past the limit) so right angles keep their full width
+/// instead of the averaged-normal pinch. The rails carry the join
+/// vertices, so their point counts differ from the centerline's; resample
+/// before pairing across the column.
+pub(crate) fn build_satin_rails(centerline: &[Point], half_width: f64) -> (Vec<Point>, Vec<Point>) {
+    if centerline.len() < 2 {
+        return (centerline.to_vec(), centerline.to_vec());
+    }
+    let closed = centerline.len() >= 4 && centerline.first() == centerline.last();
+    let join = crate::path::OffsetJoin::Miter(RAIL_MITER_LIMIT);
+    (
+        crate::path::offset_polyline_with_join(centerline, half_width, join, closed),
+        crate::path::offset_polyline_with_join(centerline, -half_width, join, closed),
+    )
+}
+
+/// Signed side of `p` relative to the polyline: the cross product against
+/// the closest segment (positive = the `perp` side of travel).
+fn signed_side(centerline: &[Point], p: Point) -> f64 {
+    let mut best_d = f64::INFINITY;
+    let mut side = 0.0;
+    for w in centerline.windows(2) {
+        let d = crate::scene::point_to_segment_dist_sq(p, w[0], w[1]);
+        if d < best_d {
+            best_d = d;
+            let dir = w[1] - w[0];
+            let dp = p - w[0];
+            side = dir.x * dp.y - dir.y * dp.x;
+        }
+    }
+    side
+}
+
+/// A rail point on the wrong side of the centerline is the signature of an
+/// offset wider than the local curvature radius (hairpins). Shrink the
+/// half-width locally — on both rails, so the column stays symmetric and
+/// the rails keep comparable lengths — until the sides are right, or
+/// collapse the offending column onto the centerline.
+fn repair_crossed_rails(centerline: &[Point], rail1: &mut [Point], rail2: &mut [Point]) {
+    for i in 0..centerline.len() {
+        let wrong = |r1: Point, r2: Point| {
+            signed_side(centerline, r1) < 0.0 || signed_side(centerline, r2) > 0.0
+        };
+        let mut guard = 0;
+        while wrong(rail1[i], rail2[i]) && guard < 6 {
+            rail1[i] = centerline[i].lerp(rail1[i], 0.5);
+            rail2[i] = centerline[i].lerp(rail2[i], 0.5);
+            guard += 1;
+        }
+        if wrong(rail1[i], rail2[i]) {
+            rail1[i] = centerline[i];
+            rail2[i] = centerline[i];
+        }
+    }
+}
+
+/// Generate a satin column over a centerline: resample it at `density`
+/// spacing, offset rails at `±half_width`, repair locally crossed rails,
+/// then zigzag between them. Pairing penetrations through their shared
+/// centerline sample (rather than resampling each rail independently)
+/// keeps the column aligned around sharp corners, where the outer rail is
+/// much longer than the inner one.
+pub fn generate_satin_shape_stitches(
+    centerline: &[Point],
+    half_width: f64,
+    density: f64,
+) -> Vec<Stitch> {
+    if centerline.len() < 2 || density <= 0.0 {
+        return Vec::new();
+    }
+    let length: f64 = centerline.windows(2).map(|w| w[0].distance_to(w[1])).sum();
+    if length <= f64::EPSILON {
+        return Vec::new();
+    }
+    let n = ((length / density).ceil() as usize).max(2);
+    // Offset the raw centerline — resampling first would shave its corners
+    // off before the miter join ever sees them — then space each rail by
+    // its own arc length so penetrations stay even where the outer arc
+    // runs longer.
+    let (rail1, rail2) = build_satin_rails(centerline, half_width);
+    let samples = resample_by_arclength(centerline, n);
+    let mut rail1 = resample_by_arclength(&rail1, n);
+    let mut rail2 = resample_by_arclength(&rail2, n);
+    repair_crossed_rails(&samples, &mut rail1, &mut rail2);
+
+    let mut out = Vec::with_capacity(n * 2);
+    for i in 0..n {
+        let (a, b) = if i % 2 == 0 {
+            (rail1[i], rail2[i])
+        } else {
+            (rail2[i], rail1[i])
+        };
+        out.push(Stitch::normal(a.x, a.y));
+        out.push(Stitch::normal(b.x, b.y));
+    }
+    out
+}
+
+/// Fraction of the column a short stitch crosses, measured from the outer
+/// rail: deep enough to bind into the column, short of the inner rail so
+/// the inside doesn't bunch further.
+const SHORT_STITCH_REACH: f64 = 0.6;
+
+/// Unsigned turn angle (radians) at `at` between the incoming and
+/// outgoing segments.
+fn turn_angle(prev: Point, at: Point, next: Point) -> f64 {
+    let a = at - prev;
+    let b = next - at;
+    (a.x * b.y - a.y * b.x).atan2(a.x * b.x + a.y * b.y).abs()
+}
+
+/// [`generate_satin_stitches`] with short-stitch compensation: where a
+/// rail turns more than `short_angle_deg` between columns, an extra pair
+/// is inserted whose penetrations sit on the outer (longer) rail and
+/// partway across the column. The outer edge's coverage keeps up with its
+/// longer arc without packing more thread into the already-bunched inner
+/// edge. Straight columns never cross the threshold and come out
+/// identical to the uncompensated generator.
+pub fn generate_satin_stitches_short(
+    rail1: &[Point],
+    rail2: &[Point],
+    density: f64,
+    short_angle_deg: f64,
+) -> Vec<Stitch> {
+    if rail1.len() < 2 || rail2.len() < 2 || density <= 0.0 {
+        return Vec::new();
+    }
+    let len1: f64 = rail1.windows(2).map(|w| w[0].distance_to(w[1])).sum();
+    let len2: f64 = rail2.windows(2).map(|w| w[0].distance_to(w[1])).sum();
+    let column_len = (len1 + len2) * 0.5;
+    let n = ((column_len / density).ceil() as usize).max(2);
+    let r1 = resample_by_arclength(rail1, n);
+    let r2 = resample_by_arclength(rail2, n);
+    let threshold = short_angle_deg.to_radians();
+
+    let mut out = Vec::with_capacity(n * 2);
+    for i in 0..n {
+        let (a, b) = if i.is_multiple_of(2) {
+            (r1[i], r2[i])
+        } else {
+            (r2[i], r1[i])
+        };
+        out.push(Stitch::normal(a.x, a.y));
+        out.push(Stitch::normal(b.x, b.y));
+        // Between this column and the next, compensate if either rail
+        // turns past the threshold at the current sample.
+        if i == 0 || i + 1 >= n {
+            continue;
+        }
+        let t1 = turn_angle(r1[i - 1], r1[i], r1[i + 1]);
+        let t2 = turn_angle(r2[i - 1], r2[i], r2[i + 1]);
+        if t1.max(t2) <= threshold {
+            continue;
+        }
+        // The outer rail is the one covering more ground over this step.
+        let (outer, inner) = if r1[i].distance_to(r1[i + 1]) >= r2[i].distance_to(r2[i + 1]) {
+            (&r1, &r2)
+        } else {
+            (&r2, &r1)
+        };
+        let outer_mid = outer[i].lerp(outer[i + 1], 0.5);
+        let inner_mid = inner[i].lerp(inner[i + 1], 0.5);
+        let short_end = outer_mid.lerp(inner_mid, SHORT_STITCH_REACH);
+        // Start the inserted pair on whichever end is closer to the
+        // needle's current position to keep travel minimal.
+        let (p, q) = if b.distance_to(short_end) < b.distance_to(outer_mid) {
+            (short_end, outer_mid)
+        } else {
+            (outer_mid, short_end)
+        };
+        out.push(Stitch::normal(p.x, p.y));
+        out.push(Stitch::normal(q.x, q.y));
+    }
+    out
+}
+
+/// [`generate_satin_shape_stitches`] with the band placed per `align`:
+/// `Center` straddles the centerline; `Inside` and `Outside` run the whole
+/// band on one side by offsetting the centerline itself by a half-width
+/// first. For open paths `Inside` means the negative-`perp` side of
+/// travel; closed outlines resolve true inside/outside from their winding
+/// in the export pipeline.
+pub fn generate_satin_shape_stitches_aligned(
+    centerline: &[Point],
+    half_width: f64,
+    density: f64,
+    align: crate::stitch::StrokeAlign,
+) -> Vec<Stitch> {
+    match align {
+        crate::stitch::StrokeAlign::Center => {
+            generate_satin_shape_stitches(centerline, half_width, density)
+        }
+        crate::stitch::StrokeAlign::Inside | crate::stitch::StrokeAlign::Outside => {
+            let (plus, minus) = build_satin_rails(centerline, half_width);
+            let shifted = if align == crate::stitch::StrokeAlign::Outside {
+                plus
+            } else {
+                minus
+            };
+            generate_satin_shape_stitches(&shifted, half_width, density)
+        }
+    }
+}
+
+/// Generate an E-stitch (blanket/comb) run between two rails: a running
+/// pass along `rail1` with a perpendicular leg of `leg_length_mm` reaching
+/// toward `rail2` every `spacing_mm`, each leg returning to its base so
+/// the baseline stays continuous. Legs longer than the local rail gap are
+/// clamped to end on the far rail.
+pub fn generate_e_stitch(
+    rail1: &[Point],
+    rail2: &[Point],
+    spacing_mm: f64,
+    leg_length_mm: f64,
+) -> Vec<Stitch> {
+    if rail1.len() < 2 || rail2.len() < 2 || spacing_mm <= 0.0 || leg_length_mm <= 0.0 {
+        return Vec::new();
+    }
+    let baseline: f64 = rail1.windows(2).map(|w| w[0].distance_to(w[1])).sum();
+    if baseline <= f64::EPSILON {
+        return Vec::new();
+    }
+    let n = ((baseline / spacing_mm).round() as usize).max(1) + 1;
+    let base = resample_by_arclength(rail1, n);
+    let far = resample_by_arclength(rail2, n);
+
+    let mut out = Vec::with_capacity(n * 3);
+    for i in 0..n {
+        let b = base[i];
+        out.push(Stitch::normal(b.x, b.y));
+        let gap = b.distance_to(far[i]);
+        if gap <= f64::EPSILON {
+            continue;
+        }
+        let tip = b.lerp(far[i], (leg_length_mm / gap).min(1.0));
+        out.push(Stitch::normal(tip.x, tip.y));
+        out.push(Stitch::normal(b.x, b.y));
+    }
+    out
+}
+
+/// Generate a satin column between two rails: alternating penetrations on
+/// each rail, spaced roughly `density` mm apart along the column.
+pub fn generate_satin_stitches(rail1: &[Point], rail2: &[Point], density: f64) -> Vec<Stitch> {
+    if rail1.len() < 2 || rail2.len() < 2 || density <= 0.0 {
+        return Vec::new();
+    }
+    let len1: f64 = rail1.windows(2).map(|w| w[0].distance_to(w[1])).sum();
+    let len2: f64 = rail2.windows(2).map(|w| w[0].distance_to(w[1])).sum();
+    let column_len = (len1 + len2) * 0.5;
+    let n = ((column_len / density).ceil() as usize).max(2);
+    let r1 = resample_by_arclength(rail1, n);
+    let r2 = resample_by_arclength(rail2, n);
+
+    let mut out = Vec::with_capacity(n * 2);
+    for i in 0..n {
+        let (a, b) = if i % 2 == 0 {
+            (r1[i], r2[i])
+        } else {
+            (r2[i], r1[i])
+        };
+        out.push(Stitch::normal(a.x, a.y));
+        out.push(Stitch::normal(b.x, b.y));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn straight_column_alternates_rails() {
+        let rail1: Vec<Point> = (0..=10).map(|i| Point::new(i as f64, 0.0)).collect();
+        let rail2: Vec<Point> = (0..=10).map(|i| Point::new(i as f64, 2.0)).collect();
+        let stitches = generate_satin_stitches(&rail1, &rail2, 0.5);
+        assert!(stitches.len() >= 40);
+        // Consecutive penetrations swap between the two rails.
+        for pair in stitches.chunks(2) {
+            assert!((pair[0].y - pair[1].y).abs() > 1.9);
+        }
+    }
+
+    #[test]
+    fn hairpin_centerline_keeps_rails_on_their_sides() {
+        // A near-180° hairpin with a half-width far wider than the corner
+        // radius: naive offsetting sends a rail across the centerline.
+        let centerline = [
+            Point::new(0.0, 0.0),
+            Point::new(10.0, 0.5),
+            Point::new(0.0, 1.0),
+        ];
+        let stitches = generate_satin_shape_stitches(&centerline, 3.0, 0.5);
+        assert!(!stitches.is_empty());
+        // A flipped rail puts both ends of a zigzag pair well onto the same
+        // side of the centerline. Repaired rails may collapse onto the
+        // centerline (side ≈ 0) but never land both ends across it.
+        for (k, pair) in stitches.chunks(2).enumerate() {
+            let sa = signed_side(&centerline, Point::new(pair[0].x, pair[0].y));
+            let sb = signed_side(&centerline, Point::new(pair[1].x, pair[1].y));
+            assert!(
+                sa * sb <= 0.25,
+                "pair {k} has both penetrations on one side: {sa} / {sb}"
+            );
+        }
+    }
+
+    #[test]
+    fn right_angle_corner_keeps_full_column_width() {
+        // An L-shaped centerline. Averaged normals used to pull the corner
+        // rail to exactly `half_width` from the vertex; the mitered rail
+        // reaches the full √2 × half_width tip so the column doesn't pinch.
+        let centerline = [
+            Point::new(0.0, 0.0),
+            Point::new(10.0, 0.0),
+            Point::new(10.0, 10.0),
+        ];
+        let half_width = 1.5;
+        let stitches = generate_satin_shape_stitches(&centerline, half_width, 0.5);
+        let corner = Point::new(10.0, 0.0);
+        let reach = stitches
+            .iter()
+            .map(|s| corner.distance_to(Point::new(s.x, s.y)))
+            .fold(f64::INFINITY, f64::min);
+        // The outer rail passes the corner at (or near) the miter tip; a
+        // pinched column would bring the nearest penetration to ~0.
+        let max_out = stitches
+            .iter()
+            .map(|s| {
+                let p = Point::new(s.x, s.y);
+                (crate::scene::point_to_segment_dist_sq(p, centerline[0], centerline[1])
+                    .min(crate::scene::point_to_segment_dist_sq(p, centerline[1], centerline[2])))
+                .sqrt()
+            })
+            .fold(0.0_f64, f64::max);
+        assert!(reach <= half_width * 1.05, "corner reach {reach}");
+        assert!(
+            max_out > half_width * 1.2,
+            "outer corner never reaches the miter tip: {max_out}"
+        );
+    }
+
+    #[test]
+    fn short_stitches_densify_the_outer_edge_of_an_arc() {
+        // Concentric half-circle rails: the outer arc runs ~43% longer, so
+        // density measured straight across leaves its edge sparse.
+        let arc = |r: f64| -> Vec<Point> {
+            (0..=60)
+                .map(|i| {
+                    let a = std::f64::consts::PI * i as f64 / 60.0;
+                    Point::new(r * a.cos(), -r * a.sin())
+                })
+                .collect()
+        };
+        let outer = arc(10.0);
+        let inner = arc(7.0);
+        let plain = generate_satin_stitches(&outer, &inner, 0.5);
+        let comp = generate_satin_stitches_short(&outer, &inner, 0.5, 2.0);
+        assert!(comp.len() > plain.len(), "no short stitches inserted");
+        // The extra penetrations land on (or near) the outer rail.
+        let outer_count = |stitches: &[Stitch]| {
+            stitches
+                .iter()
+                .filter(|s| Point::new(s.x, s.y).distance_to(Point::default()) > 8.5)
+                .count()
+        };
+        assert!(outer_count(&comp) > outer_count(&plain) + 20);
+
+        // A straight column never crosses the angle threshold: the
+        // compensated generator is byte-for-byte the plain one.
+        let rail1: Vec<Point> = (0..=10).map(|i| Point::new(i as f64, 0.0)).collect();
+        let rail2: Vec<Point> = (0..=10).map(|i| Point::new(i as f64, 2.0)).collect();
+        assert_eq!(
+            generate_satin_stitches_short(&rail1, &rail2, 0.5, 2.0),
+            generate_satin_stitches(&rail1, &rail2, 0.5)
+        );
+    }
+
+    #[test]
+    fn uneven_centerline_still_spaces_penetrations_evenly() {
+        // One long segment followed by a cluster of short ones.
+        let centerline = [
+            Point::new(0.0, 0.0),
+            Point::new(8.0, 0.0),
+            Point::new(8.5, 0.0),
+            Point::new(9.0, 0.0),
+            Point::new(9.5, 0.0),
+            Point::new(10.0, 0.0),
+        ];
+        let stitches = generate_satin_shape_stitches(&centerline, 1.5, 0.5);
+        // Walk one rail (every other penetration pair shares an index) and
+        // check consecutive spacing along the column stays uniform.
+        let rail_xs: Vec<f64> = stitches
+            .chunks(2)
+            .map(|pair| (pair[0].x + pair[1].x) * 0.5)
+            .collect();
+        let steps: Vec<f64> = rail_xs.windows(2).map(|w| w[1] - w[0]).collect();
+        let mean = steps.iter().sum::<f64>() / steps.len() as f64;
+        for step in &steps {
+            assert!(
+                (step - mean).abs() < 0.05,
+                "uneven spacing {step} vs mean {mean}"
+            );
+        }
+    }
+
+    #[test]
+    fn e_stitch_legs_land_on_the_far_rail_at_the_spacing() {
+        let rail1: Vec<Point> = (0..=10).map(|i| Point::new(i as f64, 0.0)).collect();
+        let rail2: Vec<Point> = (0..=10).map(|i| Point::new(i as f64, 2.0)).collect();
+        let stitches = generate_e_stitch(&rail1, &rail2, 1.0, 2.0);
+        // base + tip + base per leg: 10 mm baseline at 1 mm spacing = 11 legs.
+        assert_eq!(stitches.len(), 11 * 3);
+        let legs: Vec<&Stitch> = stitches.iter().filter(|s| s.y > 0.0).collect();
+        assert_eq!(legs.len(), 11);
+        for tip in legs {
+            assert!((tip.y - 2.0).abs() < 1e-9, "leg stops short at {}", tip.y);
+        }
+        // A leg longer than the gap clamps onto the far rail.
+        let long = generate_e_stitch(&rail1, &rail2, 1.0, 5.0);
+        assert!(long.iter().all(|s| s.y <= 2.0 + 1e-9));
+        // Shorter legs stop proportionally short of it.
+        let short = generate_e_stitch(&rail1, &rail2, 1.0, 1.0);
+        let max_y = short.iter().map(|s| s.y).fold(0.0_f64, f64::max);
+        assert!((max_y - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn resample_is_uniform_by_arclength() {
+        let pts = [
+            Point::new(0.0, 0.0),
+            Point::new(8.0, 0.0),
+            Point::new(10.0, 0.0),
+        ];
+        let r = resample_by_arclength(&pts, 6);
+        assert_eq!(r.len(), 6);
+        for w in r.windows(2) {
+            assert!((w[0].distance_to(w[1]) - 2.0).abs() < 1e-9);
+        }
+    }
+}
@@ -0,0 +1,69 @@
+//! Built-in single-line (Hershey-style) stroke font for lettering.
+//!
+//! Glyphs are polyline strokes on a 4×6 grid: x spans 0..=4, y spans 0..=6
+//! with 0 at the top (design space is Y-down) and 6 on the baseline. The
+//! table carries uppercase letters, digits, and light punctuation; lookups
+//! are case-insensitive and unknown characters render as blank advances so
+//! mixed text never fails.
+
+/// One glyph: its strokes as grid polylines.
+pub type Glyph = &'static [&'static [(i8, i8)]];
+
+/// Grid height of the cap box; `to_path` scales this to `height_mm`.
+pub const GRID_HEIGHT: f64 = 6.0;
+
+/// Grid advance from one glyph origin to the next (4 wide + 1 gap).
+pub const GRID_ADVANCE: f64 = 5.0;
+
+/// The glyph strokes for `c`, or `None` for characters the table lacks
+/// (including space, which is pure advance).
+pub fn glyph(c: char) -> Option<Glyph> {
+    let upper = c.to_ascii_uppercase();
+    GLYPHS
+        .iter()
+        .find(|(g, _)| *g == upper)
+        .map(|(_, strokes)| *strokes)
+}
+
+#[rustfmt::skip]
+const GLYPHS: &[(char, Glyph)] = &[
+    ('A', &[&[(0, 6), (2, 0), (4, 6)], &[(1, 4), (3, 4)]]),
+    ('B', &[&[(0, 0), (0, 6)], &[(0, 0), (3, 0), (4, 1), (4, 2), (3, 3), (0, 3)], &[(3, 3), (4, 4), (4, 5), (3, 6), (0, 6)]]),
+    ('C', &[&[(4, 1), (3, 0), (1, 0), (0, 1), (0, 5), (1, 6), (3, 6), (4, 5)]]),
+    ('D', &[&[(0, 0), (0, 6)], &[(0, 0), (3, 0), (4, 1), (4, 5), (3, 6), (0, 6)]]),
+    ('E', &[&[(4, 0), (0, 0), (0, 6), (4, 6)], &[(0, 3), (3, 3)]]),
+    ('F', &[&[(4, 0), (0, 0), (0, 6)], &[(0, 3), (3, 3)]]),
+    ('G', &[&[(4, 1), (3, 0), (1, 0), (0, 1), (0, 5), (1, 6), (3, 6), (4, 5), (4, 3), (2, 3)]]),
+    ('H', &[&[(0, 0), (0, 6)], &[(4, 0), (4, 6)], &[(0, 3), (4, 3)]]),
+    ('I', &[&[(1, 0), (3, 0)], &[(2, 0), (2, 6)], &[(1, 6), (3, 6)]]),
+    ('J', &[&[(4, 0), (4, 5), (3, 6), (1, 6), (0, 5)]]),
+    ('K', &[&[(0, 0), (0, 6)], &[(4, 0), (0, 3), (4, 6)]]),
+    ('L', &[&[(0, 0), (0, 6), (4, 6)]]),
+    ('M', &[&[(0, 6), (0, 0), (2, 3), (4, 0), (4, 6)]]),
+    ('N', &[&[(0, 6), (0, 0), (4, 6), (4, 0)]]),
+    ('O', &[&[(1, 0), (3, 0), (4, 1), (4, 5), (3, 6), (1, 6), (0, 5), (0, 1), (1, 0)]]),
+    ('P', &[&[(0, 6), (0, 0), (3, 0), (4, 1), (4, 2), (3, 3), (0, 3)]]),
+    ('Q', &[&[(1, 0), (3, 0), (4, 1), (4, 5), (3, 6), (1, 6), (0, 5), (0, 1), (1, 0)], &[(2, 4), (4, 6)]]),
+    ('R', &[&[(0, 6), (0, 0), (3, 0), (4, 1), (4, 2), (3, 3), (0, 3)], &[(2, 3), (4, 6)]]),
+    ('S', &[&[(4, 1), (3, 0), (1, 0), (0, 1), (0, 2), (1, 3), (3, 3), (4, 4), (4, 5), (3, 6), (1, 6), (0, 5)]]),
+    ('T', &[&[(0, 0), (4, 0)], &[(2, 0), (2, 6)]]),
+    ('U', &[&[(0, 0), (0, 5), (1, 6), (3, 6), (4, 5), (4, 0)]]),
+    ('V', &[&[(0, 0), (2, 6), (4, 0)]]),
+    ('W', &[&[(0, 0), (1, 6), (2, 2), (3, 6), (4, 0)]]),
+    ('X', &[&[(0, 0), (4, 6)], &[(4, 0), (0, 6)]]),
+    ('Y', &[&[(0, 0), (2, 3), (4, 0)], &[(2, 3), (2, 6)]]),
+    ('Z', &[&[(0, 0), (4, 0), (0, 6), (4, 6)]]),
+    ('0', &[&[(1, 0), (3, 0), (4, 1), (4, 5), (3, 6), (1, 6), (0, 5), (0, 1), (1, 0)], &[(1, 5), (3, 1)]]),
+    ('1', &[&[(1, 1), (2, 0), (2, 6)], &[(1, 6), (3, 6)]]),
+    ('2', &[&[(0, 1), (1, 0), (3, 0), (4, 1), (4, 2), (0, 6), (4, 6)]]),
+    ('3', &[&[(0, 1), (1, 0), (3, 0), (4, 1), (4, 2), (3, 3), (1, 3)], &[(3, 3), (4, 4), (4, 5), (3, 6), (1, 6), (0, 5)]]),
+    ('4', &[&[(3, 6), (3, 0), (0, 4), (4, 4)]]),
+    ('5', &[&[(4, 0), (0, 0), (0, 3), (3, 3), (4, 4), (4, 5), (3, 6), (1, 6), (0, 5)]]),
+    ('6', &[&[(4, 1), (3, 0), (1, 0), (0, 1), (0, 5), (1, 6), (3, 6), (4, 5), (4, 4), (3, 3), (0, 3)]]),
+    ('7', &[&[(0, 0), (4, 0), (1, 6)]]),
+    ('8', &[&[(1, 0), (3, 0), (4, 1), (4, 2), (3, 3), (1, 3), (0, 2), (0, 1), (1, 0)], &[(1, 3), (0, 4), (0, 5), (1, 6), (3, 6), (4, 5), (4, 4), (3, 3)]]),
+    ('9', &[&[(4, 3), (1, 3), (0, 2), (0, 1), (1, 0), (3, 0), (4, 1), (4, 5), (3, 6), (1, 6)]]),
+    ('-', &[&[(1, 3), (3, 3)]]),
+    ('.', &[&[(2, 5), (2, 6)]]),
+    ('!', &[&[(2, 0), (2, 4)], &[(2, 5), (2, 6)]]),
+];
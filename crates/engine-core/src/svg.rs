@@ -0,0 +1,1042 @@
+//! SVG import and export: a minimal parser for the subset of SVG that
+//! vector editors produce (paths, rects, ellipses/circles, nested `<g>`
+//! with transforms), and a scene renderer for proofs.
+//!
+//! The document walk is recursive: group transforms compose down the tree
+//! (`parent ∘ child`) and shape-element transforms are baked into the
+//! geometry. The structured importer keeps the group hierarchy so it can map
+//! onto scene `Group` nodes; the flat importer bakes everything to world
+//! space. We scan the XML ourselves — the subset is small and it keeps the
+//! wasm binary free of a full XML dependency.
+
+use crate::geometry::{Point, Transform};
+use crate::path::{PathCommand, VectorPath};
+use crate::shapes::{Color, KAPPA};
+use serde::{Deserialize, Serialize};
+
+/// Tight tolerance for the clean pass run on every imported shape: drops
+/// exact duplicates and true collinear noise without moving geometry.
+const IMPORT_CLEAN_EPSILON_MM: f64 = 1e-6;
+
+/// One imported shape with its paint, geometry in the owning group's space.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SvgShape {
+    pub path: VectorPath,
+    pub fill: Option<Color>,
+    pub stroke: Option<Color>,
+    /// `(dash_mm, gap_mm)` from `stroke-dasharray`, ready to drop into
+    /// [`StitchParams::dash`](crate::stitch::StitchParams) so a dashed SVG
+    /// stroke exports as dashed running stitches.
+    #[serde(default)]
+    pub dash: Option<(f64, f64)>,
+    /// `stroke-dashoffset`, kept for hosts that shift the pattern start.
+    #[serde(default)]
+    pub dash_offset: f64,
+}
+
+/// A node of the imported document tree. Groups carry their transform;
+/// shapes have their own element transform already baked in.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "node", rename_all = "snake_case")]
+pub enum SvgNode {
+    Group {
+        transform: Transform,
+        children: Vec<SvgNode>,
+    },
+    Shape(SvgShape),
+}
+
+/// Import a document keeping its group structure.
+pub fn import_svg_document_structured(svg: &str) -> Result<Vec<SvgNode>, String> {
+    let mut tags = TagScanner::new(svg);
+    let mut stack: Vec<(Transform, Vec<SvgNode>)> = vec![(Transform::identity(), Vec::new())];
+    // Depth of skipped (unknown) open elements at the top of the stack.
+    let mut skip_depth = 0usize;
+    while let Some(tag) = tags.next()? {
+        match tag {
+            Tag::Open { name, attrs, self_closing } => {
+                if skip_depth > 0 {
+                    if !self_closing {
+                        skip_depth += 1;
+                    }
+                    continue;
+                }
+                match name {
+                    "svg" if !self_closing => {}
+                    "g" => {
+                        let transform = attr(&attrs, "transform")
+                            .map(parse_transform)
+                            .transpose()?
+                            .unwrap_or_else(Transform::identity);
+                        if self_closing {
+                            let top = stack.last_mut().expect("stack is non-empty");
+                            top.1.push(SvgNode::Group {
+                                transform,
+                                children: Vec::new(),
+                            });
+                        } else {
+                            stack.push((transform, Vec::new()));
+                        }
+                    }
+                    "path" | "rect" | "ellipse" | "circle" => {
+                        if let Some(shape) = parse_shape_element(name, &attrs)? {
+                            let top = stack.last_mut().expect("stack is non-empty");
+                            top.1.push(SvgNode::Shape(shape));
+                        }
+                        if !self_closing {
+                            skip_depth += 1;
+                        }
+                    }
+                    _ => {
+                        if !self_closing {
+                            skip_depth += 1;
+                        }
+                    }
+                }
+            }
+            Tag::Close(name) => {
+                if skip_depth > 0 {
+                    skip_depth -= 1;
+                    continue;
+                }
+                if name == "g" {
+                    let (transform, children) = stack
+                        .pop()
+                        .ok_or_else(|| "unbalanced </g>".to_string())?;
+                    if stack.is_empty() {
+                        return Err("unbalanced </g>".to_string());
+                    }
+                    let top = stack.last_mut().expect("checked non-empty");
+                    top.1.push(SvgNode::Group {
+                        transform,
+                        children,
+                    });
+                }
+            }
+        }
+    }
+    if stack.len() != 1 {
+        return Err("unclosed <g> element".to_string());
+    }
+    Ok(stack.pop().expect("stack is non-empty").1)
+}
+
+/// Import a document flattened to world space: every group transform is
+/// baked into the shape geometry, in document order.
+pub fn parse_svg_document(svg: &str) -> Result<Vec<SvgShape>, String> {
+    let tree = import_svg_document_structured(svg)?;
+    let mut out = Vec::new();
+    flatten_into(&tree, &Transform::identity(), &mut out);
+    Ok(out)
+}
+
+fn flatten_into(nodes: &[SvgNode], world: &Transform, out: &mut Vec<SvgShape>) {
+    for node in nodes {
+        match node {
+            SvgNode::Group {
+                transform,
+                children,
+            } => flatten_into(children, &world.compose(transform), out),
+            SvgNode::Shape(shape) => out.push(SvgShape {
+                path: shape.path.transformed(world),
+                fill: shape.fill,
+                stroke: shape.stroke,
+                dash: shape.dash,
+                dash_offset: shape.dash_offset,
+            }),
+        }
+    }
+}
+
+/// Build a shape from a geometry element, baking the element's own
+/// transform. Returns `None` for invisible geometry (e.g. zero-size rects).
+fn parse_shape_element(name: &str, attrs: &[(String, String)]) -> Result<Option<SvgShape>, String> {
+    let num = |key: &str| -> Result<f64, String> {
+        match attr(attrs, key) {
+            Some(v) => v
+                .trim()
+                .parse::<f64>()
+                .map_err(|_| format!("invalid {key}: {v:?}")),
+            None => Ok(0.0),
+        }
+    };
+    let path = match name {
+        "path" => {
+            let Some(d) = attr(attrs, "d") else {
+                return Ok(None);
+            };
+            parse_path_data(d)?
+        }
+        "rect" => {
+            let (x, y, w, h) = (num("x")?, num("y")?, num("width")?, num("height")?);
+            if w <= 0.0 || h <= 0.0 {
+                return Ok(None);
+            }
+            VectorPath::from_polygon(&[
+                Point::new(x, y),
+                Point::new(x + w, y),
+                Point::new(x + w, y + h),
+                Point::new(x, y + h),
+            ])
+        }
+        "ellipse" | "circle" => {
+            let (cx, cy) = (num("cx")?, num("cy")?);
+            let (rx, ry) = if name == "circle" {
+                let r = num("r")?;
+                (r, r)
+            } else {
+                (num("rx")?, num("ry")?)
+            };
+            if rx <= 0.0 || ry <= 0.0 {
+                return Ok(None);
+            }
+            ellipse_path(cx, cy, rx, ry)
+        }
+        _ => return Ok(None),
+    };
+    let transform = attr(attrs, "transform")
+        .map(parse_transform)
+        .transpose()?
+        .unwrap_or_else(Transform::identity);
+    Ok(Some(SvgShape {
+        path: crate::path::clean(&path.transformed(&transform), IMPORT_CLEAN_EPSILON_MM),
+        fill: attr(attrs, "fill").map(parse_paint).transpose()?.flatten(),
+        stroke: attr(attrs, "stroke").map(parse_paint).transpose()?.flatten(),
+        dash: attr(attrs, "stroke-dasharray")
+            .map(parse_dasharray)
+            .transpose()?
+            .flatten(),
+        dash_offset: match attr(attrs, "stroke-dashoffset") {
+            Some(v) => v
+                .trim()
+                .parse::<f64>()
+                .map_err(|_| format!("invalid stroke-dashoffset: {v:?}"))?,
+            None => 0.0,
+        },
+    }))
+}
+
+/// Parse `stroke-dasharray` to a `(dash, gap)` pair. SVG allows any even
+/// run of lengths (odd lists repeat to even); stitch generation only has
+/// one dash/gap slot, so longer patterns collapse to their first pair. A
+/// single value means dash and gap are equal, per the spec.
+fn parse_dasharray(text: &str) -> Result<Option<(f64, f64)>, String> {
+    let text = text.trim();
+    if text.is_empty() || text == "none" {
+        return Ok(None);
+    }
+    let values: Vec<f64> = text
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            s.parse::<f64>()
+                .map_err(|_| format!("invalid stroke-dasharray: {text:?}"))
+        })
+        .collect::<Result<_, _>>()?;
+    match values.as_slice() {
+        [] => Ok(None),
+        [d, rest @ ..] if *d > 0.0 => {
+            let gap = rest.first().copied().unwrap_or(*d);
+            Ok(Some((*d, gap.max(0.0))))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Four-arc cubic approximation of an ellipse, matching `EllipseShape`.
+fn ellipse_path(cx: f64, cy: f64, rx: f64, ry: f64) -> VectorPath {
+    let kx = rx * KAPPA;
+    let ky = ry * KAPPA;
+    VectorPath {
+        commands: vec![
+            PathCommand::MoveTo {
+                to: Point::new(cx + rx, cy),
+            },
+            PathCommand::CurveTo {
+                c1: Point::new(cx + rx, cy + ky),
+                c2: Point::new(cx + kx, cy + ry),
+                to: Point::new(cx, cy + ry),
+            },
+            PathCommand::CurveTo {
+                c1: Point::new(cx - kx, cy + ry),
+                c2: Point::new(cx - rx, cy + ky),
+                to: Point::new(cx - rx, cy),
+            },
+            PathCommand::CurveTo {
+                c1: Point::new(cx - rx, cy - ky),
+                c2: Point::new(cx - kx, cy - ry),
+                to: Point::new(cx, cy - ry),
+            },
+            PathCommand::CurveTo {
+                c1: Point::new(cx + kx, cy - ry),
+                c2: Point::new(cx + rx, cy - ky),
+                to: Point::new(cx + rx, cy),
+            },
+            PathCommand::Close,
+        ],
+    }
+}
+
+/// Parse a `transform` attribute: a whitespace/comma separated sequence of
+/// `matrix`, `translate`, `scale`, `rotate`, `skewX`, and `skewY`
+/// functions, composed left to right.
+pub fn parse_transform(text: &str) -> Result<Transform, String> {
+    let mut result = Transform::identity();
+    let mut rest = text.trim();
+    while !rest.is_empty() {
+        let open = rest
+            .find('(')
+            .ok_or_else(|| format!("malformed transform: {text:?}"))?;
+        let close = rest[open..]
+            .find(')')
+            .map(|i| open + i)
+            .ok_or_else(|| format!("malformed transform: {text:?}"))?;
+        let name = rest[..open].trim();
+        let args: Vec<f64> = rest[open + 1..close]
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|s| !s.is_empty())
+            .map(|s| s.parse::<f64>().map_err(|_| format!("bad number in transform: {s:?}")))
+            .collect::<Result<_, _>>()?;
+        let t = match (name, args.as_slice()) {
+            ("matrix", [a, b, c, d, e, f]) => Transform {
+                a: *a,
+                b: *b,
+                c: *c,
+                d: *d,
+                tx: *e,
+                ty: *f,
+            },
+            ("translate", [x]) => Transform::translation(*x, 0.0),
+            ("translate", [x, y]) => Transform::translation(*x, *y),
+            ("scale", [s]) => Transform::scale(*s, *s),
+            ("scale", [x, y]) => Transform::scale(*x, *y),
+            ("rotate", [deg]) => Transform::rotation(deg.to_radians()),
+            ("rotate", [deg, cx, cy]) => Transform::translation(*cx, *cy)
+                .compose(&Transform::rotation(deg.to_radians()))
+                .compose(&Transform::translation(-cx, -cy)),
+            ("skewX", [deg]) => Transform {
+                c: deg.to_radians().tan(),
+                ..Transform::identity()
+            },
+            ("skewY", [deg]) => Transform {
+                b: deg.to_radians().tan(),
+                ..Transform::identity()
+            },
+            _ => return Err(format!("unsupported transform function: {name:?}")),
+        };
+        result = result.compose(&t);
+        rest = rest[close + 1..].trim_start_matches([',', ' ', '\t', '\n', '\r']);
+    }
+    Ok(result)
+}
+
+/// Parse SVG path data. Quadratics are promoted to cubics and arcs become
+/// cubic segments via [`arc_to_cubics`].
+pub fn parse_path_data(d: &str) -> Result<VectorPath, String> {
+    let mut commands = Vec::new();
+    let mut lexer = NumberLexer::new(d);
+    let mut cursor = Point::default();
+    let mut start = Point::default();
+    let mut last_cubic_c2: Option<Point> = None;
+
+    while let Some(op) = lexer.next_op() {
+        let relative = op.is_ascii_lowercase();
+        let base = |cursor: Point| if relative { cursor } else { Point::default() };
+        match op.to_ascii_lowercase() {
+            'm' => {
+                let mut first = true;
+                while first || lexer.peek_number() {
+                    let p = base(cursor) + lexer.point()?;
+                    if first {
+                        commands.push(PathCommand::MoveTo { to: p });
+                        start = p;
+                    } else {
+                        // Extra coordinate pairs are implicit line-tos.
+                        commands.push(PathCommand::LineTo { to: p });
+                    }
+                    cursor = p;
+                    first = false;
+                }
+                last_cubic_c2 = None;
+            }
+            'l' => {
+                while lexer.peek_number() {
+                    cursor = base(cursor) + lexer.point()?;
+                    commands.push(PathCommand::LineTo { to: cursor });
+                }
+                last_cubic_c2 = None;
+            }
+            'h' => {
+                while lexer.peek_number() {
+                    let x = lexer.number()?;
+                    cursor = Point::new(if relative { cursor.x + x } else { x }, cursor.y);
+                    commands.push(PathCommand::LineTo { to: cursor });
+                }
+                last_cubic_c2 = None;
+            }
+            'v' => {
+                while lexer.peek_number() {
+                    let y = lexer.number()?;
+                    cursor = Point::new(cursor.x, if relative { cursor.y + y } else { y });
+                    commands.push(PathCommand::LineTo { to: cursor });
+                }
+                last_cubic_c2 = None;
+            }
+            'c' => {
+                while lexer.peek_number() {
+                    let c1 = base(cursor) + lexer.point()?;
+                    let c2 = base(cursor) + lexer.point()?;
+                    let to = base(cursor) + lexer.point()?;
+                    commands.push(PathCommand::CurveTo { c1, c2, to });
+                    last_cubic_c2 = Some(c2);
+                    cursor = to;
+                }
+            }
+            's' => {
+                while lexer.peek_number() {
+                    let c1 = match last_cubic_c2 {
+                        Some(prev) => cursor + (cursor - prev),
+                        None => cursor,
+                    };
+                    let c2 = base(cursor) + lexer.point()?;
+                    let to = base(cursor) + lexer.point()?;
+                    commands.push(PathCommand::CurveTo { c1, c2, to });
+                    last_cubic_c2 = Some(c2);
+                    cursor = to;
+                }
+            }
+            'q' => {
+                while lexer.peek_number() {
+                    let q = base(cursor) + lexer.point()?;
+                    let to = base(cursor) + lexer.point()?;
+                    // Exact quadratic-to-cubic promotion.
+                    let c1 = cursor + (q - cursor) * (2.0 / 3.0);
+                    let c2 = to + (q - to) * (2.0 / 3.0);
+                    commands.push(PathCommand::CurveTo { c1, c2, to });
+                    cursor = to;
+                }
+                last_cubic_c2 = None;
+            }
+            'a' => {
+                while lexer.peek_number() {
+                    let rx = lexer.number()?;
+                    let ry = lexer.number()?;
+                    let rotation = lexer.number()?;
+                    let large_arc = lexer.number()? != 0.0;
+                    let sweep = lexer.number()? != 0.0;
+                    let to = base(cursor) + lexer.point()?;
+                    arc_to_cubics(
+                        &mut commands,
+                        cursor,
+                        rx,
+                        ry,
+                        rotation,
+                        large_arc,
+                        sweep,
+                        to,
+                    );
+                    cursor = to;
+                }
+                last_cubic_c2 = None;
+            }
+            'z' => {
+                commands.push(PathCommand::Close);
+                cursor = start;
+                last_cubic_c2 = None;
+            }
+            other => return Err(format!("unsupported path command: {other:?}")),
+        }
+    }
+    Ok(VectorPath { commands })
+}
+
+/// Convert one elliptical arc from SVG's endpoint parameterization into
+/// cubic segments of at most 90° each, appended as `CurveTo` commands. The
+/// center and sweep come from the W3C F.6.5 equations; out-of-range radii
+/// scale up per F.6.6 and zero radii degrade to a line, both as the spec
+/// prescribes.
+#[allow(clippy::too_many_arguments)]
+fn arc_to_cubics(
+    commands: &mut Vec<PathCommand>,
+    from: Point,
+    rx: f64,
+    ry: f64,
+    rotation_degrees: f64,
+    large_arc: bool,
+    sweep: bool,
+    to: Point,
+) {
+    if from == to {
+        return;
+    }
+    let (mut rx, mut ry) = (rx.abs(), ry.abs());
+    if rx <= 1e-12 || ry <= 1e-12 {
+        commands.push(PathCommand::LineTo { to });
+        return;
+    }
+    let phi = rotation_degrees.to_radians();
+    let (sin_phi, cos_phi) = phi.sin_cos();
+
+    // Endpoints in the axis-aligned midpoint frame.
+    let dx = (from.x - to.x) * 0.5;
+    let dy = (from.y - to.y) * 0.5;
+    let x1 = cos_phi * dx + sin_phi * dy;
+    let y1 = -sin_phi * dx + cos_phi * dy;
+
+    // Radii too small to span the endpoints scale up uniformly.
+    let lambda = (x1 / rx).powi(2) + (y1 / ry).powi(2);
+    if lambda > 1.0 {
+        let s = lambda.sqrt();
+        rx *= s;
+        ry *= s;
+    }
+
+    let num = (rx * ry).powi(2) - (rx * y1).powi(2) - (ry * x1).powi(2);
+    let den = (rx * y1).powi(2) + (ry * x1).powi(2);
+    let mut coef = (num.max(0.0) / den).sqrt();
+    if large_arc == sweep {
+        coef = -coef;
+    }
+    let cx1 = coef * rx * y1 / ry;
+    let cy1 = -coef * ry * x1 / rx;
+    let cx = cos_phi * cx1 - sin_phi * cy1 + (from.x + to.x) * 0.5;
+    let cy = sin_phi * cx1 + cos_phi * cy1 + (from.y + to.y) * 0.5;
+
+    let theta1 = ((y1 - cy1) / ry).atan2((x1 - cx1) / rx);
+    let mut delta = ((-y1 - cy1) / ry).atan2((-x1 - cx1) / rx) - theta1;
+    if sweep && delta < 0.0 {
+        delta += std::f64::consts::TAU;
+    } else if !sweep && delta > 0.0 {
+        delta -= std::f64::consts::TAU;
+    }
+
+    let at = |t: f64| {
+        let (s, c) = t.sin_cos();
+        Point::new(
+            cx + rx * c * cos_phi - ry * s * sin_phi,
+            cy + rx * c * sin_phi + ry * s * cos_phi,
+        )
+    };
+    let tangent = |t: f64| {
+        let (s, c) = t.sin_cos();
+        Point::new(
+            -rx * s * cos_phi - ry * c * sin_phi,
+            -rx * s * sin_phi + ry * c * cos_phi,
+        )
+    };
+
+    let segments = ((delta.abs() / std::f64::consts::FRAC_PI_2).ceil() as usize).max(1);
+    let step = delta / segments as f64;
+    // Standard cubic arc approximation constant for a `step` sweep.
+    let k = 4.0 / 3.0 * (step * 0.25).tan();
+    for i in 0..segments {
+        let t0 = theta1 + step * i as f64;
+        let t1 = t0 + step;
+        let p0 = at(t0);
+        let p1 = if i + 1 == segments { to } else { at(t1) };
+        commands.push(PathCommand::CurveTo {
+            c1: p0 + tangent(t0) * k,
+            c2: p1 + tangent(t1) * -k,
+            to: p1,
+        });
+    }
+}
+
+/// Parse a paint attribute into a color; `none` yields `None`.
+fn parse_paint(text: &str) -> Result<Option<Color>, String> {
+    let text = text.trim();
+    if text.eq_ignore_ascii_case("none") {
+        return Ok(None);
+    }
+    if let Some(hex) = text.strip_prefix('#') {
+        let expand = |c: u8| -> u8 { c << 4 | c };
+        let digit = |i: usize| -> Result<u8, String> {
+            u8::from_str_radix(&hex[i..i + 1], 16).map_err(|_| format!("invalid color: {text:?}"))
+        };
+        return match hex.len() {
+            3 => Ok(Some(Color::rgb(
+                expand(digit(0)?),
+                expand(digit(1)?),
+                expand(digit(2)?),
+            ))),
+            6 => {
+                let byte = |i: usize| -> Result<u8, String> {
+                    u8::from_str_radix(&hex[i..i + 2], 16)
+                        .map_err(|_| format!("invalid color: {text:?}"))
+                };
+                Ok(Some(Color::rgb(byte(0)?, byte(2)?, byte(4)?)))
+            }
+            _ => Err(format!("invalid color: {text:?}")),
+        };
+    }
+    if let Some(args) = text
+        .strip_prefix("rgb(")
+        .and_then(|t| t.strip_suffix(')'))
+    {
+        let parts: Vec<u8> = args
+            .split(',')
+            .map(|s| s.trim().parse::<u8>().map_err(|_| format!("invalid color: {text:?}")))
+            .collect::<Result<_, _>>()?;
+        if let [r, g, b] = parts.as_slice() {
+            return Ok(Some(Color::rgb(*r, *g, *b)));
+        }
+        return Err(format!("invalid color: {text:?}"));
+    }
+    match text.to_ascii_lowercase().as_str() {
+        "black" => Ok(Some(Color::BLACK)),
+        "white" => Ok(Some(Color::rgb(255, 255, 255))),
+        "red" => Ok(Some(Color::rgb(255, 0, 0))),
+        "green" => Ok(Some(Color::rgb(0, 128, 0))),
+        "blue" => Ok(Some(Color::rgb(0, 0, 255))),
+        _ => Err(format!("unsupported color: {text:?}")),
+    }
+}
+
+fn attr<'a>(attrs: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    attrs
+        .iter()
+        .find(|(k, _)| k == name)
+        .map(|(_, v)| v.as_str())
+}
+
+/// A scanned XML tag. Other markup (text, comments, declarations) is skipped.
+enum Tag<'a> {
+    Open {
+        name: &'a str,
+        attrs: Vec<(String, String)>,
+        self_closing: bool,
+    },
+    Close(&'a str),
+}
+
+/// Minimal XML tag scanner: enough for SVG markup, not a general parser.
+struct TagScanner<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> TagScanner<'a> {
+    fn new(text: &'a str) -> Self {
+        Self { text, pos: 0 }
+    }
+
+    fn next(&mut self) -> Result<Option<Tag<'a>>, String> {
+        loop {
+            let Some(open) = self.text[self.pos..].find('<') else {
+                return Ok(None);
+            };
+            let at = self.pos + open;
+            let rest = &self.text[at..];
+            if let Some(r) = rest.strip_prefix("<!--") {
+                let end = r.find("-->").ok_or("unterminated comment")?;
+                self.pos = at + 4 + end + 3;
+                continue;
+            }
+            if rest.starts_with("<?") || rest.starts_with("<!") {
+                let end = rest.find('>').ok_or("unterminated declaration")?;
+                self.pos = at + end + 1;
+                continue;
+            }
+            let end = rest.find('>').ok_or("unterminated tag")?;
+            let inner = &rest[1..end];
+            self.pos = at + end + 1;
+            if let Some(name) = inner.strip_prefix('/') {
+                return Ok(Some(Tag::Close(name.trim())));
+            }
+            let (inner, self_closing) = match inner.strip_suffix('/') {
+                Some(stripped) => (stripped, true),
+                None => (inner, false),
+            };
+            let name_end = inner
+                .find(|c: char| c.is_whitespace())
+                .unwrap_or(inner.len());
+            let name = &inner[..name_end];
+            let attrs = parse_attrs(&inner[name_end..])?;
+            return Ok(Some(Tag::Open {
+                name,
+                attrs,
+                self_closing,
+            }));
+        }
+    }
+}
+
+fn parse_attrs(mut text: &str) -> Result<Vec<(String, String)>, String> {
+    let mut attrs = Vec::new();
+    loop {
+        text = text.trim_start();
+        if text.is_empty() {
+            return Ok(attrs);
+        }
+        let eq = text
+            .find('=')
+            .ok_or_else(|| format!("malformed attribute near {text:?}"))?;
+        let name = text[..eq].trim().to_string();
+        let rest = text[eq + 1..].trim_start();
+        let quote = rest
+            .chars()
+            .next()
+            .filter(|c| *c == '"' || *c == '\'')
+            .ok_or_else(|| format!("unquoted attribute value near {rest:?}"))?;
+        let value_end = rest[1..]
+            .find(quote)
+            .ok_or_else(|| format!("unterminated attribute value near {rest:?}"))?;
+        attrs.push((name, rest[1..1 + value_end].to_string()));
+        text = &rest[2 + value_end..];
+    }
+}
+
+/// Pulls numbers and op letters out of path data.
+struct NumberLexer<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> NumberLexer<'a> {
+    fn new(text: &'a str) -> Self {
+        Self { text, pos: 0 }
+    }
+
+    fn skip_separators(&mut self) {
+        let bytes = self.text.as_bytes();
+        while self.pos < bytes.len() && (bytes[self.pos].is_ascii_whitespace() || bytes[self.pos] == b',') {
+            self.pos += 1;
+        }
+    }
+
+    fn next_op(&mut self) -> Option<char> {
+        self.skip_separators();
+        let c = self.text[self.pos..].chars().next()?;
+        if c.is_ascii_alphabetic() {
+            self.pos += 1;
+            Some(c)
+        } else {
+            None
+        }
+    }
+
+    fn peek_number(&mut self) -> bool {
+        self.skip_separators();
+        matches!(
+            self.text[self.pos..].chars().next(),
+            Some(c) if c.is_ascii_digit() || c == '-' || c == '+' || c == '.'
+        )
+    }
+
+    fn number(&mut self) -> Result<f64, String> {
+        self.skip_separators();
+        let bytes = self.text.as_bytes();
+        let start = self.pos;
+        let mut pos = self.pos;
+        if pos < bytes.len() && (bytes[pos] == b'-' || bytes[pos] == b'+') {
+            pos += 1;
+        }
+        let mut seen_dot = false;
+        let mut seen_exp = false;
+        while pos < bytes.len() {
+            match bytes[pos] {
+                b'0'..=b'9' => pos += 1,
+                b'.' if !seen_dot && !seen_exp => {
+                    seen_dot = true;
+                    pos += 1;
+                }
+                b'e' | b'E' if !seen_exp => {
+                    seen_exp = true;
+                    pos += 1;
+                    if pos < bytes.len() && (bytes[pos] == b'-' || bytes[pos] == b'+') {
+                        pos += 1;
+                    }
+                }
+                _ => break,
+            }
+        }
+        self.pos = pos;
+        self.text[start..pos]
+            .parse::<f64>()
+            .map_err(|_| format!("expected number at {:?}", &self.text[start..]))
+    }
+
+    fn point(&mut self) -> Result<Point, String> {
+        let x = self.number()?;
+        let y = self.number()?;
+        Ok(Point::new(x, y))
+    }
+}
+
+/// Serialize a path's commands as SVG path data. Closed subpaths carry
+/// their `Z`.
+fn path_data(path: &VectorPath) -> String {
+    let mut d = String::new();
+    for cmd in &path.commands {
+        if !d.is_empty() {
+            d.push(' ');
+        }
+        match cmd {
+            PathCommand::MoveTo { to } => d.push_str(&format!("M {:.3} {:.3}", to.x, to.y)),
+            PathCommand::LineTo { to } => d.push_str(&format!("L {:.3} {:.3}", to.x, to.y)),
+            PathCommand::CurveTo { c1, c2, to } => d.push_str(&format!(
+                "C {:.3} {:.3} {:.3} {:.3} {:.3} {:.3}",
+                c1.x, c1.y, c2.x, c2.y, to.x, to.y
+            )),
+            PathCommand::Close => d.push('Z'),
+        }
+    }
+    d
+}
+
+/// A paint attribute value: `rgba()` so alpha survives, `none` for unset.
+fn paint_value(color: Option<Color>) -> String {
+    match color {
+        Some(c) => format!("rgba({},{},{},{:.3})", c.r, c.g, c.b, c.a as f64 / 255.0),
+        None => "none".to_string(),
+    }
+}
+
+/// Render the scene to a standalone SVG for previews and proofs: one
+/// `<path>` per visible shape with the world transform baked into its
+/// coordinates, wrapped in a viewBox derived from the union content
+/// bounds. Hidden nodes never enter `render_list`, so hidden layers are
+/// excluded by construction.
+pub fn export_scene_svg(scene: &crate::scene::Scene) -> String {
+    let margin = 2.0;
+    let bounds = scene.content_bounds();
+    let (min_x, min_y, w, h) = match &bounds {
+        Some(b) => (
+            b.min_x - margin,
+            b.min_y - margin,
+            b.width() + margin * 2.0,
+            b.height() + margin * 2.0,
+        ),
+        None => (0.0, 0.0, 1.0, 1.0),
+    };
+    let mut svg = format!(
+        concat!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" "#,
+            r#"viewBox="{:.3} {:.3} {:.3} {:.3}">"#,
+            "\n"
+        ),
+        min_x, min_y, w, h
+    );
+    for item in scene.render_list() {
+        let Ok(node) = scene.node(item.node_id) else {
+            continue;
+        };
+        let crate::scene::NodeKind::Shape(shape) = &node.kind else {
+            continue;
+        };
+        let path = shape.data.to_path().transformed(&item.world_transform);
+        svg.push_str(&format!(
+            r#"<path d="{}" fill="{}" stroke="{}" stroke-width="{:.3}"/>"#,
+            path_data(&path),
+            paint_value(shape.style.fill),
+            paint_value(shape.style.stroke),
+            shape.style.stroke_width,
+        ));
+        svg.push('\n');
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path_in_translated_group_is_offset() {
+        let svg = r##"<svg xmlns="http://www.w3.org/2000/svg">
+            <g transform="translate(10, 20)">
+                <path d="M 0 0 L 5 0 L 5 5 Z" fill="#ff0000"/>
+            </g>
+        </svg>"##;
+        let shapes = parse_svg_document(svg).unwrap();
+        assert_eq!(shapes.len(), 1);
+        let flat = shapes[0].path.flatten(0.1);
+        assert_eq!(flat[0][0], Point::new(10.0, 20.0));
+        assert_eq!(flat[0][1], Point::new(15.0, 20.0));
+        assert_eq!(shapes[0].fill, Some(Color::rgb(255, 0, 0)));
+    }
+
+    #[test]
+    fn scene_export_emits_one_path_per_visible_shape() {
+        use crate::scene::{NodeKind, Scene, ShapeNode};
+        use crate::shapes::{RectShape, ShapeData, ShapeStyle};
+
+        let rect = |w: f64, h: f64| {
+            NodeKind::Shape(Box::new(ShapeNode {
+                data: ShapeData::Rect(RectShape {
+                    width: w,
+                    height: h,
+                }),
+                style: ShapeStyle::default(),
+                stitch: crate::stitch::StitchParams::default(),
+                sequencer: Default::default(),
+            }))
+        };
+        let mut scene = Scene::new();
+        scene.add_node(rect(10.0, 10.0), None).unwrap();
+        scene.add_node(rect(4.0, 4.0), None).unwrap();
+        let hidden = scene.add_node(rect(6.0, 6.0), None).unwrap();
+        scene.node_mut(hidden).unwrap().visible = false;
+
+        let svg = export_scene_svg(&scene);
+        assert_eq!(svg.matches("<path ").count(), 2);
+        assert!(svg.contains("viewBox="));
+        // Closed primitives carry their Z; paints serialize as rgba().
+        assert!(svg.contains('Z'));
+        assert!(svg.contains("rgba("));
+    }
+
+    #[test]
+    fn rect_in_translated_group_lands_at_the_offset() {
+        let svg = r#"<svg>
+            <g transform="translate(100, 50)">
+                <rect x="0" y="0" width="4" height="3"/>
+            </g>
+        </svg>"#;
+        let shapes = parse_svg_document(svg).unwrap();
+        assert_eq!(shapes.len(), 1);
+        let flat = shapes[0].path.flatten(0.1);
+        for p in &flat[0] {
+            assert!((100.0..=104.0).contains(&p.x), "x {p:?}");
+            assert!((50.0..=53.0).contains(&p.y), "y {p:?}");
+        }
+    }
+
+    #[test]
+    fn skew_transforms_shear_the_axes() {
+        let sx = parse_transform("skewX(45)").unwrap();
+        let p = sx.apply(Point::new(0.0, 2.0));
+        assert!((p.x - 2.0).abs() < 1e-9 && (p.y - 2.0).abs() < 1e-9);
+        let sy = parse_transform("skewY(45)").unwrap();
+        let q = sy.apply(Point::new(2.0, 0.0));
+        assert!((q.x - 2.0).abs() < 1e-9 && (q.y - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn structured_import_keeps_group_hierarchy() {
+        let svg = r#"<svg>
+            <g transform="translate(10 0)">
+                <g transform="scale(2)">
+                    <rect x="0" y="0" width="3" height="3"/>
+                </g>
+                <path d="M0 0 L1 1"/>
+            </g>
+        </svg>"#;
+        let tree = import_svg_document_structured(svg).unwrap();
+        assert_eq!(tree.len(), 1);
+        let SvgNode::Group {
+            transform,
+            children,
+        } = &tree[0]
+        else {
+            panic!("expected a group");
+        };
+        assert_eq!(*transform, Transform::translation(10.0, 0.0));
+        assert_eq!(children.len(), 2);
+        assert!(matches!(&children[0], SvgNode::Group { children, .. } if children.len() == 1));
+        assert!(matches!(&children[1], SvgNode::Shape(_)));
+    }
+
+    #[test]
+    fn nested_transforms_compose_in_document_order() {
+        let svg = r#"<svg>
+            <g transform="translate(10 0)"><g transform="scale(2)">
+                <path d="M1 1 L2 1"/>
+            </g></g>
+        </svg>"#;
+        let shapes = parse_svg_document(svg).unwrap();
+        let flat = shapes[0].path.flatten(0.1);
+        // translate(10,0) ∘ scale(2) applied to (1,1) = (12, 2).
+        assert_eq!(flat[0][0], Point::new(12.0, 2.0));
+    }
+
+    #[test]
+    fn transform_attribute_parses_function_sequences() {
+        let t = parse_transform("translate(5,5) rotate(90)").unwrap();
+        let p = t.apply(Point::new(1.0, 0.0));
+        assert!((p.x - 5.0).abs() < 1e-9);
+        assert!((p.y - 6.0).abs() < 1e-9);
+        let m = parse_transform("matrix(1 0 0 1 -3 4)").unwrap();
+        assert_eq!(m, Transform::translation(-3.0, 4.0));
+    }
+
+    #[test]
+    fn path_data_supports_relative_and_shorthand_commands() {
+        let path = parse_path_data("m 1 1 h 4 v 2 l -4 0 z").unwrap();
+        assert!(path.is_closed());
+        let flat = path.flatten(0.1);
+        assert_eq!(
+            flat[0],
+            vec![
+                Point::new(1.0, 1.0),
+                Point::new(5.0, 1.0),
+                Point::new(5.0, 3.0),
+                Point::new(1.0, 3.0),
+                Point::new(1.0, 1.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn quadratics_promote_to_cubics() {
+        let path = parse_path_data("M0 0 Q 5 10 10 0").unwrap();
+        assert!(matches!(path.commands[1], PathCommand::CurveTo { .. }));
+        // The promoted curve still passes near the quadratic midpoint (5,5).
+        let flat = path.flatten(0.01);
+        let closest = flat[0]
+            .iter()
+            .map(|p| p.distance_to(Point::new(5.0, 5.0)))
+            .fold(f64::INFINITY, f64::min);
+        assert!(closest < 0.05, "closest {closest}");
+    }
+
+    #[test]
+    fn arc_command_stays_on_the_circle() {
+        let path = parse_path_data("M0,0 A5,5 0 0 1 10,0").unwrap();
+        let rings = path.flatten(0.05);
+        assert_eq!(rings.len(), 1);
+        let points = &rings[0];
+        assert!(points.len() > 8);
+        // A half circle of radius 5 centered on (5, 0): every flattened
+        // point sits in a tight band around that radius.
+        let center = Point::new(5.0, 0.0);
+        for p in points {
+            let r = p.distance_to(center);
+            assert!((r - 5.0).abs() < 0.05, "point {p:?} at radius {r}");
+        }
+        let last = points.last().unwrap();
+        assert!(last.distance_to(Point::new(10.0, 0.0)) < 1e-9);
+        // The arc bulges to one side, reaching the full radius.
+        let apex = points.iter().map(|p| p.y.abs()).fold(0.0_f64, f64::max);
+        assert!((apex - 5.0).abs() < 0.05, "apex {apex}");
+    }
+
+    #[test]
+    fn relative_arcs_chain_coordinate_sets() {
+        // Two chained relative half-circles: one command, two arcs.
+        let path = parse_path_data("m0,0 a5,5 0 0 1 10,0 10,10 0 0 0 20,0").unwrap();
+        let rings = path.flatten(0.05);
+        let last = rings[0].last().unwrap();
+        assert!(last.distance_to(Point::new(30.0, 0.0)) < 1e-9);
+        // Zero radii degrade to a straight line per the spec.
+        let line = parse_path_data("M0,0 A0,0 0 0 1 10,0").unwrap();
+        assert_eq!(line.commands.len(), 2);
+        assert!(matches!(line.commands[1], PathCommand::LineTo { .. }));
+    }
+
+    #[test]
+    fn dasharray_lands_on_the_shape() {
+        let svg = r##"<svg><path d="M0 0 L10 0" stroke="#000000"
+            stroke-dasharray="4 2" stroke-dashoffset="1.5"/></svg>"##;
+        let shapes = parse_svg_document(svg).unwrap();
+        assert_eq!(shapes[0].dash, Some((4.0, 2.0)));
+        assert_eq!(shapes[0].dash_offset, 1.5);
+
+        // Single value: gap equals dash. "none" and garbage behave.
+        assert_eq!(parse_dasharray("3").unwrap(), Some((3.0, 3.0)));
+        assert_eq!(parse_dasharray("none").unwrap(), None);
+        assert_eq!(parse_dasharray("0 2").unwrap(), None);
+        assert!(parse_dasharray("4 x").is_err());
+    }
+}
@@ -0,0 +1,172 @@
+//! Thread catalogs: manufacturer code ↔ color lookups.
+//!
+//! Machine formats reference threads by numeric code (PEC palette indexes
+//! are Brother codes), so imports need code → name/RGB and exports need the
+//! nearest code for an arbitrary design color. The Brother table here is the
+//! same commonly used subset the PEC writer has always mapped against.
+
+use crate::export_pipeline::ThreadEntry;
+use crate::shapes::Color;
+use serde::{Deserialize, Serialize};
+
+/// The Brother (PEC palette) thread chart, as `(code, name, rgb)`. Codes
+/// match the machine's color list; nearest-color mapping only needs good
+/// coverage of the hue wheel, not all 64 entries.
+pub const BROTHER_THREADS: &[(u16, &str, [u8; 3])] = &[
+    (1, "Prussian Blue", [0x1a, 0x0a, 0x94]),
+    (2, "Blue", [0x0f, 0x75, 0xff]),
+    (3, "Teal Green", [0x00, 0x93, 0x4c]),
+    (4, "Cornflower Blue", [0xba, 0xbd, 0xfe]),
+    (5, "Red", [0xec, 0x00, 0x00]),
+    (6, "Reddish Brown", [0xe4, 0x99, 0x5a]),
+    (7, "Magenta", [0xcc, 0x48, 0xab]),
+    (8, "Light Lilac", [0xfd, 0xc4, 0xfa]),
+    (9, "Lilac", [0xdd, 0x84, 0xcd]),
+    (10, "Mint Green", [0x6b, 0xd3, 0x8a]),
+    (11, "Deep Gold", [0xe4, 0xa9, 0x45]),
+    (12, "Orange", [0xff, 0xbd, 0x42]),
+    (13, "Yellow", [0xff, 0xe6, 0x00]),
+    (14, "Lime Green", [0x6c, 0xd9, 0x00]),
+    (15, "Brass", [0xc1, 0xa9, 0x41]),
+    (16, "Silver", [0xb5, 0xad, 0x97]),
+    (17, "Russet Brown", [0xba, 0x9c, 0x5f]),
+    (18, "Cream Brown", [0xfa, 0xf5, 0x9e]),
+    (19, "Pewter", [0x80, 0x80, 0x80]),
+    (20, "Black", [0x00, 0x00, 0x00]),
+    (21, "Ultramarine", [0x00, 0x1c, 0xdf]),
+    (22, "Royal Purple", [0xdf, 0x00, 0xb8]),
+    (23, "Dark Gray", [0x62, 0x62, 0x62]),
+    (24, "Dark Brown", [0x69, 0x26, 0x0d]),
+    (25, "Deep Rose", [0xff, 0x00, 0x60]),
+    (26, "Light Brown", [0xbf, 0x82, 0x00]),
+    (27, "Salmon Pink", [0xf3, 0x91, 0x78]),
+    (28, "Vermilion", [0xff, 0x68, 0x05]),
+    (29, "White", [0xf0, 0xf0, 0xf0]),
+    (30, "Violet", [0xc8, 0x32, 0xcd]),
+    (32, "Sky Blue", [0x65, 0xbf, 0xeb]),
+    (33, "Pumpkin", [0xff, 0xba, 0x04]),
+    (37, "Leaf Green", [0x37, 0xa9, 0x23]),
+    (38, "Peacock Blue", [0x23, 0x46, 0x5f]),
+    (39, "Gray", [0xa6, 0xa6, 0x95]),
+    (43, "Pink", [0xff, 0x99, 0xd7]),
+];
+
+/// The catalog entry for a Brother thread code, for resolving imported
+/// color lists to names and RGB.
+pub fn brother_by_code(code: u16) -> Option<ThreadEntry> {
+    BROTHER_THREADS
+        .iter()
+        .find(|(c, _, _)| *c == code)
+        .map(|(_, name, rgb)| ThreadEntry {
+            name: name.to_string(),
+            color: Color::rgb(rgb[0], rgb[1], rgb[2]),
+        })
+}
+
+/// Nearest Brother code for a design color (RGB distance; machine palettes
+/// are coarse enough that perceptual ΔE buys nothing here). This is what
+/// the PEC writer stores in its color list.
+pub fn brother_code_for(color: Color) -> u16 {
+    BROTHER_THREADS
+        .iter()
+        .min_by_key(|(_, _, rgb)| {
+            let dr = rgb[0] as i32 - color.r as i32;
+            let dg = rgb[1] as i32 - color.g as i32;
+            let db = rgb[2] as i32 - color.b as i32;
+            dr * dr + dg * dg + db * db
+        })
+        .map(|(code, _, _)| *code)
+        .expect("catalog is non-empty")
+}
+
+/// Which manufacturer chart to match against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThreadBrand {
+    /// The Brother/PEC palette above.
+    #[default]
+    Brother,
+    /// The Pfaff/Viking rayon chart the VP3 writer names threads from.
+    Viking,
+}
+
+fn catalog(brand: ThreadBrand) -> Vec<ThreadEntry> {
+    match brand {
+        ThreadBrand::Brother => BROTHER_THREADS
+            .iter()
+            .map(|(_, name, rgb)| ThreadEntry {
+                name: name.to_string(),
+                color: Color::rgb(rgb[0], rgb[1], rgb[2]),
+            })
+            .collect(),
+        ThreadBrand::Viking => crate::format::vp3::VP3_THREADS
+            .iter()
+            .map(|(name, rgb)| ThreadEntry {
+                name: name.to_string(),
+                color: Color::rgb(rgb[0], rgb[1], rgb[2]),
+            })
+            .collect(),
+    }
+}
+
+fn rgb_distance_sq(a: Color, b: Color) -> i32 {
+    let dr = a.r as i32 - b.r as i32;
+    let dg = a.g as i32 - b.g as i32;
+    let db = a.b as i32 - b.b as i32;
+    dr * dr + dg * dg + db * db
+}
+
+/// The top-`n` catalog matches for each color, nearest first — one call
+/// for a whole palette panel instead of one lookup per color.
+pub fn suggest_for_colors(
+    colors: &[Color],
+    brand: ThreadBrand,
+    n: usize,
+) -> Vec<Vec<ThreadEntry>> {
+    let entries = catalog(brand);
+    colors
+        .iter()
+        .map(|&c| {
+            let mut ranked: Vec<&ThreadEntry> = entries.iter().collect();
+            ranked.sort_by_key(|e| rgb_distance_sq(c, e.color));
+            ranked.into_iter().take(n).cloned().collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn code_round_trips_through_color() {
+        for code in [5u16, 20, 29, 37] {
+            let entry = brother_by_code(code).unwrap();
+            assert_eq!(brother_code_for(entry.color), code);
+        }
+        assert!(brother_by_code(999).is_none());
+        assert_eq!(brother_by_code(5).unwrap().name, "Red");
+    }
+
+    #[test]
+    fn batch_suggestions_lead_with_the_single_lookup_result() {
+        let colors = [
+            Color::rgb(240, 10, 10),
+            Color::rgb(10, 10, 220),
+            Color::rgb(0xf0, 0xf0, 0xf0),
+        ];
+        let suggestions = suggest_for_colors(&colors, ThreadBrand::Brother, 3);
+        assert_eq!(suggestions.len(), colors.len());
+        for (color, ranked) in colors.iter().zip(&suggestions) {
+            assert_eq!(ranked.len(), 3);
+            let single = brother_by_code(brother_code_for(*color)).unwrap();
+            assert_eq!(ranked[0], single);
+        }
+
+        let viking = suggest_for_colors(&colors, ThreadBrand::Viking, 1);
+        for (color, ranked) in colors.iter().zip(&viking) {
+            let (name, _) = crate::format::vp3::nearest_thread(*color);
+            assert_eq!(ranked[0].name, name);
+        }
+    }
+}
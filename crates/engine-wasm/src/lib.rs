@@ -0,0 +1,1099 @@
+//! wasm-bindgen bridge over `engine-core`.
+//!
+//! The bridge owns a single thread-local scene (the WASM instance is the
+//! document session) and keeps every binding a thin parse/delegate/serialize
+//! wrapper — logic stays in `engine-core` where it is natively testable.
+
+use engine_core::command::{CheckpointId, Command, CommandHistory};
+use engine_core::error::EngineError;
+use engine_core::export_pipeline::{
+    scene_to_export_design, scene_to_export_design_with_routing, RoutingOptions,
+};
+use engine_core::geometry::Transform;
+use engine_core::scene::{NodeId, NodeKind, Scene};
+use std::cell::RefCell;
+use wasm_bindgen::prelude::*;
+
+struct Session {
+    scene: Scene,
+    history: CommandHistory,
+    export_cache: engine_core::export_pipeline::ExportCache,
+}
+
+impl Session {
+    fn new() -> Self {
+        Self {
+            scene: Scene::new(),
+            history: CommandHistory::new(),
+            export_cache: engine_core::export_pipeline::ExportCache::default(),
+        }
+    }
+}
+
+thread_local! {
+    static SESSION: RefCell<Session> = RefCell::new(Session::new());
+    static EXPORT_CANCEL: engine_core::cancel::CancelToken =
+        engine_core::cancel::CancelToken::new();
+    static STREAMERS: RefCell<Vec<Option<engine_core::export_pipeline::DesignStreamer>>> =
+        const { RefCell::new(Vec::new()) };
+}
+
+fn with_scene<R>(f: impl FnOnce(&mut Scene) -> Result<R, EngineError>) -> Result<R, JsError> {
+    SESSION.with(|s| f(&mut s.borrow_mut().scene).map_err(|e| JsError::new(&e.to_string())))
+}
+
+fn with_session<R>(f: impl FnOnce(&mut Session) -> Result<R, EngineError>) -> Result<R, JsError> {
+    SESSION.with(|s| f(&mut s.borrow_mut()).map_err(|e| JsError::new(&e.to_string())))
+}
+
+/// Reset the session to an empty scene with empty history.
+#[wasm_bindgen]
+pub fn scene_reset() {
+    SESSION.with(|s| *s.borrow_mut() = Session::new());
+}
+
+/// Add a node from its JSON `NodeKind` payload (undoable); returns the new
+/// node ID.
+#[wasm_bindgen]
+pub fn scene_add_node(kind_json: &str, parent_id: Option<NodeId>) -> Result<NodeId, JsError> {
+    let kind: NodeKind =
+        serde_json::from_str(kind_json).map_err(|e| JsError::new(&e.to_string()))?;
+    with_session(|s| {
+        s.history
+            .apply(
+                &mut s.scene,
+                Command::AddNode {
+                    kind,
+                    parent: parent_id,
+                },
+            )
+            .map(|id| id.expect("AddNode returns an id"))
+    })
+}
+
+/// Remove a node and its subtree (undoable).
+#[wasm_bindgen]
+pub fn scene_remove_node(node_id: NodeId) -> Result<(), JsError> {
+    with_session(|s| {
+        s.history
+            .apply(&mut s.scene, Command::RemoveNode { id: node_id })
+            .map(|_| ())
+    })
+}
+
+/// Set a node's local transform from JSON (undoable).
+#[wasm_bindgen]
+pub fn scene_set_transform(node_id: NodeId, transform_json: &str) -> Result<(), JsError> {
+    let transform: Transform =
+        serde_json::from_str(transform_json).map_err(|e| JsError::new(&e.to_string()))?;
+    with_session(|s| {
+        s.history
+            .apply(
+                &mut s.scene,
+                Command::SetTransform {
+                    id: node_id,
+                    transform,
+                },
+            )
+            .map(|_| ())
+    })
+}
+
+/// Shape IDs whose stitch-sequence position disagrees with their render
+/// (layer) position, as JSON, so the UI can warn about stitch order.
+#[wasm_bindgen]
+pub fn scene_sequence_render_mismatches() -> Result<String, JsError> {
+    with_scene(|scene| {
+        serde_json::to_string(&scene.sequence_render_mismatches()).map_err(|e| EngineError::Serialization(e.to_string()))
+    })
+}
+
+/// Rewrite all sequencer indexes to match render order (undoable).
+#[wasm_bindgen]
+pub fn scene_reset_sequence_to_render_order() -> Result<(), JsError> {
+    with_session(|s| {
+        s.history
+            .apply(&mut s.scene, Command::ResetSequenceToRenderOrder)
+            .map(|_| ())
+    })
+}
+
+/// Replace shapes `a` and `b` with the boolean of their geometry
+/// (`"union"`, `"intersection"`, or `"difference"`) as one undoable step.
+/// Returns the created node IDs as JSON; a multi-piece result becomes
+/// sibling nodes.
+#[wasm_bindgen]
+pub fn scene_boolean(a_id: NodeId, b_id: NodeId, op: &str) -> Result<String, JsError> {
+    let op: engine_core::path::BooleanOp =
+        serde_json::from_value(serde_json::Value::String(op.to_string()))
+            .map_err(|e| JsError::new(&e.to_string()))?;
+    with_session(|s| {
+        let ids = s.history.apply_boolean(&mut s.scene, a_id, b_id, op)?;
+        serde_json::to_string(&ids).map_err(|e| EngineError::Serialization(e.to_string()))
+    })
+}
+
+/// Boolean of two closed paths given as JSON, without touching the scene
+/// (`"union"`, `"intersection"`, or `"difference"`). Returns the result
+/// loops as a JSON array of paths.
+#[wasm_bindgen]
+pub fn path_boolean(a_json: &str, b_json: &str, op: &str) -> Result<String, JsError> {
+    let a: engine_core::path::VectorPath =
+        serde_json::from_str(a_json).map_err(|e| JsError::new(&e.to_string()))?;
+    let b: engine_core::path::VectorPath =
+        serde_json::from_str(b_json).map_err(|e| JsError::new(&e.to_string()))?;
+    let op: engine_core::path::BooleanOp =
+        serde_json::from_value(serde_json::Value::String(op.to_string()))
+            .map_err(|e| JsError::new(&e.to_string()))?;
+    let loops = match op {
+        engine_core::path::BooleanOp::Union => engine_core::path::union(&a, &b),
+        engine_core::path::BooleanOp::Intersection => engine_core::path::intersection(&a, &b),
+        engine_core::path::BooleanOp::Difference => engine_core::path::difference(&a, &b),
+    };
+    serde_json::to_string(&loops).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Offset a flat `[x0, y0, x1, y1, ..]` polyline sideways by `distance`
+/// mm with mitered corners (beveling past the limit). A polyline whose
+/// last pair repeats the first is treated as a closed ring. Returns the
+/// offset polyline in the same flat layout.
+#[wasm_bindgen]
+pub fn path_offset(path: &[f64], distance: f64) -> Result<Vec<f64>, JsError> {
+    if !path.len().is_multiple_of(2) {
+        return Err(JsError::new("path must contain x/y pairs"));
+    }
+    let points: Vec<engine_core::geometry::Point> = path
+        .chunks_exact(2)
+        .map(|c| engine_core::geometry::Point::new(c[0], c[1]))
+        .collect();
+    let closed = points.len() >= 4 && points.first() == points.last();
+    let offset = engine_core::path::offset_polyline_with_join(
+        &points,
+        distance,
+        engine_core::path::OffsetJoin::Miter(4.0),
+        closed,
+    );
+    Ok(offset.iter().flat_map(|p| [p.x, p.y]).collect())
+}
+
+/// Replace the whole stitch sequence with a JSON array of block IDs
+/// (undoable). The list must be a permutation of the current blocks; an
+/// incomplete or inflated list errors without touching the sequence.
+#[wasm_bindgen]
+pub fn scene_set_sequence_order(block_ids_json: &str) -> Result<(), JsError> {
+    let ids: Vec<NodeId> =
+        serde_json::from_str(block_ids_json).map_err(|e| JsError::new(&e.to_string()))?;
+    with_session(|s| {
+        s.history
+            .apply(&mut s.scene, Command::SetSequenceOrder { ids })
+            .map(|_| ())
+    })
+}
+
+/// Set (or clear, with `"null"`) a shape block's thread color override
+/// (undoable). The override changes the stitched thread without touching the
+/// shape's fill or stroke.
+#[wasm_bindgen]
+pub fn scene_set_block_color(node_id: NodeId, color_json: &str) -> Result<(), JsError> {
+    let color: Option<engine_core::shapes::Color> =
+        serde_json::from_str(color_json).map_err(|e| JsError::new(&e.to_string()))?;
+    with_session(|s| {
+        s.history
+            .apply(&mut s.scene, Command::SetBlockColor { id: node_id, color })
+            .map(|_| ())
+    })
+}
+
+/// Upgrade a running-stitch line to a satin column of `width_mm` at
+/// `density` mm zigzag spacing (undoable).
+#[wasm_bindgen]
+pub fn scene_running_to_satin(
+    node_id: NodeId,
+    width_mm: f64,
+    density: f64,
+) -> Result<(), JsError> {
+    with_session(|s| {
+        s.history
+            .apply(
+                &mut s.scene,
+                Command::RunningToSatin {
+                    id: node_id,
+                    width_mm,
+                    density,
+                },
+            )
+            .map(|_| ())
+    })
+}
+
+/// Downgrade a satin column to a monoline running stitch (undoable).
+#[wasm_bindgen]
+pub fn scene_satin_to_running(node_id: NodeId) -> Result<(), JsError> {
+    with_session(|s| {
+        s.history
+            .apply(&mut s.scene, Command::SatinToRunning { id: node_id })
+            .map(|_| ())
+    })
+}
+
+/// Insert a manual machine command (`"jump"`, `"trim"`, `"stop"`, or
+/// `"color_change"`) before stitch `at_index` of a shape's block (undoable).
+#[wasm_bindgen]
+pub fn scene_insert_stitch_command(
+    node_id: NodeId,
+    at_index: usize,
+    kind: &str,
+) -> Result<(), JsError> {
+    let kind: engine_core::stitch::ManualCommandKind =
+        serde_json::from_value(serde_json::Value::String(kind.to_string()))
+            .map_err(|e| JsError::new(&e.to_string()))?;
+    with_session(|s| {
+        s.history
+            .apply(
+                &mut s.scene,
+                Command::InsertStitchCommand {
+                    id: node_id,
+                    at_index,
+                    kind,
+                },
+            )
+            .map(|_| ())
+    })
+}
+
+/// Remove the manual command anchored at `at_index` of a shape's block
+/// (undoable).
+#[wasm_bindgen]
+pub fn scene_remove_stitch_command(node_id: NodeId, at_index: usize) -> Result<(), JsError> {
+    with_session(|s| {
+        s.history
+            .apply(
+                &mut s.scene,
+                Command::RemoveStitchCommand {
+                    id: node_id,
+                    at_index,
+                },
+            )
+            .map(|_| ())
+    })
+}
+
+/// Run the geometry clean pass on a Path shape, dropping duplicate points
+/// and collinear noise within `epsilon` mm (undoable).
+#[wasm_bindgen]
+pub fn scene_clean_path(node_id: NodeId, epsilon: f64) -> Result<(), JsError> {
+    with_session(|s| {
+        s.history
+            .apply(
+                &mut s.scene,
+                Command::CleanPath {
+                    id: node_id,
+                    epsilon_mm: epsilon,
+                },
+            )
+            .map(|_| ())
+    })
+}
+
+/// Closest visible outline point within `max_dist` of `(x, y)` as JSON
+/// `{node_id, x, y, distance}`, or `"null"` when nothing is near.
+#[wasm_bindgen]
+pub fn scene_closest_point(x: f64, y: f64, max_dist: f64) -> Result<String, JsError> {
+    with_scene(|scene| {
+        let hit = scene.closest_point(engine_core::geometry::Point::new(x, y), max_dist);
+        serde_json::to_string(&hit).map_err(|e| EngineError::Serialization(e.to_string()))
+    })
+}
+
+/// Shape area in world units².
+#[wasm_bindgen]
+pub fn scene_node_area(node_id: NodeId) -> Result<f64, JsError> {
+    with_scene(|scene| scene.node_area(node_id))
+}
+
+/// Shape perimeter in world units.
+#[wasm_bindgen]
+pub fn scene_node_perimeter(node_id: NodeId) -> Result<f64, JsError> {
+    with_scene(|scene| scene.node_perimeter(node_id))
+}
+
+/// World-space point at arc length `length` along a shape's outline, as
+/// JSON.
+#[wasm_bindgen]
+pub fn path_point_at_length(node_id: NodeId, length: f64) -> Result<String, JsError> {
+    with_scene(|scene| {
+        let p = scene.node_point_at_length(node_id, length)?;
+        serde_json::to_string(&p).map_err(|e| EngineError::Serialization(e.to_string()))
+    })
+}
+
+/// Structural integrity issues in the scene graph, as a JSON array (empty
+/// for a healthy scene).
+#[wasm_bindgen]
+pub fn scene_validate() -> Result<String, JsError> {
+    with_scene(|scene| {
+        serde_json::to_string(&scene.validate())
+            .map_err(|e| EngineError::Serialization(e.to_string()))
+    })
+}
+
+/// Rebuild all state derived from the node map (ID allocator, cached
+/// bboxes, stitch-sequence pins) after host-side edits that bypassed the
+/// command layer, e.g. loading an externally modified document.
+#[wasm_bindgen]
+pub fn scene_rebuild_stitch_plan() -> Result<(), JsError> {
+    with_scene(|scene| {
+        scene.rebuild_derived_state();
+        Ok(())
+    })
+}
+
+/// Fix all reported integrity issues; returns the repair actions taken as
+/// a JSON array (empty when the scene was already healthy).
+#[wasm_bindgen]
+pub fn scene_repair() -> Result<String, JsError> {
+    with_scene(|scene| {
+        serde_json::to_string(&scene.repair())
+            .map_err(|e| EngineError::Serialization(e.to_string()))
+    })
+}
+
+/// Replicate a node's subtree across a `rows` x `cols` grid with
+/// `(dx, dy)` mm spacing, as one undo step. Returns the clone root IDs as
+/// a JSON array in row-major order.
+#[wasm_bindgen]
+pub fn scene_array_replicate(
+    node_id: u64,
+    rows: usize,
+    cols: usize,
+    dx: f64,
+    dy: f64,
+) -> Result<String, JsError> {
+    with_session(|s| {
+        let ids = s
+            .history
+            .apply_array_replicate(&mut s.scene, node_id, rows, cols, dx, dy)?;
+        serde_json::to_string(&ids).map_err(|e| EngineError::Serialization(e.to_string()))
+    })
+}
+
+/// Mark a node as render-only proofing geometry: it keeps drawing but the
+/// export skips it.
+#[wasm_bindgen]
+pub fn scene_set_export_excluded(node_id: NodeId, excluded: bool) -> Result<(), JsError> {
+    with_scene(|scene| scene.set_export_excluded(node_id, excluded).map(|_| ()))
+}
+
+/// Set the design name embedded in exports. Blank names fall back to the
+/// default.
+#[wasm_bindgen]
+pub fn scene_set_design_name(name: &str) -> Result<(), JsError> {
+    with_scene(|scene| {
+        scene.set_design_name(name);
+        Ok(())
+    })
+}
+
+/// Union world bbox of the node IDs in `ids_json` (a JSON array), as JSON
+/// or `"null"` for an empty selection.
+#[wasm_bindgen]
+pub fn scene_selection_bbox(ids_json: &str) -> Result<String, JsError> {
+    let ids: Vec<NodeId> =
+        serde_json::from_str(ids_json).map_err(|e| JsError::new(&e.to_string()))?;
+    with_scene(|scene| {
+        serde_json::to_string(&scene.selection_bbox(&ids))
+            .map_err(|e| EngineError::Serialization(e.to_string()))
+    })
+}
+
+/// IDs of all visible shapes matching a node on the enabled
+/// `SimilarityCriteria` (JSON, e.g. `{"fill":true}`), as a JSON array.
+#[wasm_bindgen]
+pub fn scene_find_similar(node_id: NodeId, criteria_json: &str) -> Result<String, JsError> {
+    let criteria: engine_core::scene::SimilarityCriteria =
+        serde_json::from_str(criteria_json).map_err(|e| JsError::new(&e.to_string()))?;
+    with_scene(|scene| {
+        let ids = scene.find_similar(node_id, criteria)?;
+        serde_json::to_string(&ids).map_err(|e| EngineError::Serialization(e.to_string()))
+    })
+}
+
+/// Undo the latest edit; returns whether anything was undone.
+#[wasm_bindgen]
+pub fn scene_undo() -> Result<bool, JsError> {
+    with_session(|s| s.history.undo(&mut s.scene))
+}
+
+/// Redo the latest undone edit; returns whether anything was redone.
+#[wasm_bindgen]
+pub fn scene_redo() -> Result<bool, JsError> {
+    with_session(|s| s.history.redo(&mut s.scene))
+}
+
+/// Approximate bytes retained by the undo/redo history.
+#[wasm_bindgen]
+pub fn scene_history_memory() -> usize {
+    SESSION.with(|s| s.borrow().history.memory_estimate())
+}
+
+/// Cap retained history at `n` entries, trimming the oldest immediately.
+#[wasm_bindgen]
+pub fn scene_set_max_history(n: usize) {
+    SESSION.with(|s| s.borrow_mut().history.set_max_history(n));
+}
+
+/// Mark the current state (e.g. at save) for a later partial revert.
+#[wasm_bindgen]
+pub fn scene_set_checkpoint() -> i64 {
+    SESSION.with(|s| s.borrow_mut().history.checkpoint() as i64)
+}
+
+/// Replay undos back to a checkpoint from `scene_set_checkpoint`.
+#[wasm_bindgen]
+pub fn scene_revert_to_checkpoint(checkpoint: i64) -> Result<(), JsError> {
+    with_session(|s| s.history.undo_to(&mut s.scene, checkpoint as CheckpointId))
+}
+
+/// Generate a tatami fill over JSON rings (`[[{x,y},..],..]`), invoking
+/// `progress` with a 0..1 float as rows complete. Returns stitches as JSON.
+#[wasm_bindgen]
+pub fn generate_tatami_fill_with_progress(
+    rings_json: &str,
+    angle_degrees: f64,
+    density: f64,
+    stitch_length: f64,
+    progress: &js_sys::Function,
+) -> Result<String, JsError> {
+    let rings: Vec<Vec<engine_core::geometry::Point>> =
+        serde_json::from_str(rings_json).map_err(|e| JsError::new(&e.to_string()))?;
+    let stitches = engine_core::stitch::fill::generate_tatami_fill_with_progress(
+        &rings,
+        angle_degrees,
+        density,
+        stitch_length,
+        engine_core::stitch::fill::FillEdgeStyle::default(),
+        &mut |p| {
+            let _ = progress.call1(&JsValue::NULL, &JsValue::from_f64(p as f64));
+        },
+    );
+    serde_json::to_string(&stitches).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Generate running stitches along only the normalized arc-length range
+/// `start_t..end_t` of a JSON polyline (`[{x,y},..]`). Returns stitches as
+/// JSON.
+#[wasm_bindgen]
+pub fn generate_running_stitches_range_flat(
+    points_json: &str,
+    start_t: f64,
+    end_t: f64,
+    stitch_length: f64,
+) -> Result<String, JsError> {
+    let points: Vec<engine_core::geometry::Point> =
+        serde_json::from_str(points_json).map_err(|e| JsError::new(&e.to_string()))?;
+    let stitches = engine_core::stitch::running::generate_running_stitches_range(
+        &points,
+        start_t,
+        end_t,
+        stitch_length,
+    );
+    serde_json::to_string(&stitches).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Generate bean stitches (each segment sewn `repeats` times, typically 3)
+/// along a JSON polyline (`[{x,y},..]`). Returns stitches as JSON.
+#[wasm_bindgen]
+pub fn generate_bean_stitches_flat(
+    points_json: &str,
+    stitch_length: f64,
+    repeats: usize,
+) -> Result<String, JsError> {
+    let points: Vec<engine_core::geometry::Point> =
+        serde_json::from_str(points_json).map_err(|e| JsError::new(&e.to_string()))?;
+    let stitches =
+        engine_core::stitch::running::generate_bean_stitches(&points, stitch_length, repeats);
+    serde_json::to_string(&stitches).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Generate a concentric spiral fill around `(cx, cy)` out to `radius` mm,
+/// with `spacing` mm between turns. Returns stitches as JSON.
+#[wasm_bindgen]
+pub fn generate_spiral_fill(
+    cx: f64,
+    cy: f64,
+    radius: f64,
+    spacing: f64,
+    stitch_length: f64,
+) -> Result<String, JsError> {
+    let stitches = engine_core::stitch::fill::generate_spiral_fill(
+        engine_core::geometry::Point::new(cx, cy),
+        radius,
+        spacing,
+        stitch_length,
+    );
+    serde_json::to_string(&stitches).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Capability table for an export format (`"dst"` or `"pes"`), as JSON.
+#[wasm_bindgen]
+pub fn format_capabilities(fmt: &str) -> Result<String, JsError> {
+    let fmt: engine_core::format::ExportFormat =
+        serde_json::from_value(serde_json::Value::String(fmt.to_string()))
+            .map_err(|e| JsError::new(&e.to_string()))?;
+    serde_json::to_string(&engine_core::format::capabilities(fmt))
+        .map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Recommended `[pull_mm, push_mm]` compensation for a satin column of
+/// `width` mm on the given fabric (e.g. `"stretchy"`), as JSON.
+#[wasm_bindgen]
+pub fn recommend_compensation(fabric: &str, width: f64) -> Result<String, JsError> {
+    let fabric: engine_core::fabric::Fabric =
+        serde_json::from_value(serde_json::Value::String(fabric.to_string()))
+            .map_err(|e| JsError::new(&e.to_string()))?;
+    let (pull, push) = engine_core::fabric::recommend_compensation(fabric, width);
+    serde_json::to_string(&[pull, push]).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Register a custom unit motif from flat `[x0, y0, x1, y1, ...]` points;
+/// returns the motif id to reference via `MotifPattern::Custom`.
+#[wasm_bindgen]
+pub fn register_motif(points_flat: &[f64]) -> Result<u32, JsError> {
+    if !points_flat.len().is_multiple_of(2) {
+        return Err(JsError::new("points_flat must contain x/y pairs"));
+    }
+    let points = points_flat
+        .chunks_exact(2)
+        .map(|c| engine_core::geometry::Point::new(c[0], c[1]))
+        .collect();
+    engine_core::stitch::motif::register_motif(points).map_err(|e| JsError::new(&e))
+}
+
+/// Parse an SVG document keeping its group hierarchy; returns the tree of
+/// groups and shapes as JSON for mapping onto scene nodes.
+#[wasm_bindgen]
+pub fn import_svg_document_structured(svg: &str) -> Result<String, JsError> {
+    let tree = engine_core::svg::import_svg_document_structured(svg)
+        .map_err(|e| JsError::new(&e))?;
+    serde_json::to_string(&tree).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Diff the session scene against a serialized target scene; returns the
+/// delta list as JSON.
+#[wasm_bindgen]
+pub fn scene_diff(target_scene_json: &str) -> Result<String, JsError> {
+    let target: Scene =
+        serde_json::from_str(target_scene_json).map_err(|e| JsError::new(&e.to_string()))?;
+    with_scene(|scene| serde_json::to_string(&scene.diff(&target)).map_err(|e| EngineError::Serialization(e.to_string())))
+}
+
+/// Apply a JSON array of deltas (from `scene_diff`) to the session scene.
+#[wasm_bindgen]
+pub fn scene_apply_deltas(deltas_json: &str) -> Result<(), JsError> {
+    let deltas: Vec<engine_core::scene::SceneDelta> =
+        serde_json::from_str(deltas_json).map_err(|e| JsError::new(&e.to_string()))?;
+    with_scene(|scene| {
+        for delta in deltas {
+            scene.apply_delta(delta)?;
+        }
+        Ok(())
+    })
+}
+
+/// Flattened render traversal as JSON, in draw order.
+#[wasm_bindgen]
+pub fn scene_get_render_list() -> Result<String, JsError> {
+    with_scene(|scene| {
+        serde_json::to_string(&scene.render_list()).map_err(|e| EngineError::Serialization(e.to_string()))
+    })
+}
+
+/// Render traversal with translations in `units` (`"mm"`, `"cm"`, or
+/// `"inch"`) and transform components rounded to `decimals` places, as
+/// JSON. Matches UI display units and shrinks the payload.
+#[wasm_bindgen]
+pub fn scene_get_render_list_units(units: &str, decimals: u32) -> Result<String, JsError> {
+    let units: engine_core::geometry::Units =
+        serde_json::from_value(serde_json::Value::String(units.to_string()))
+            .map_err(|e| JsError::new(&e.to_string()))?;
+    with_scene(|scene| {
+        serde_json::to_string(&scene.render_list_in_units(units, decimals))
+            .map_err(|e| EngineError::Serialization(e.to_string()))
+    })
+}
+
+/// Render traversal with shapes flattened at `tolerance` mm, as JSON. The
+/// caller derives the tolerance from the camera zoom so curves stay smooth
+/// when zoomed in without wasting points when zoomed out.
+#[wasm_bindgen]
+pub fn scene_get_render_list_lod(tolerance: f64) -> Result<String, JsError> {
+    with_scene(|scene| {
+        serde_json::to_string(&scene.render_list_lod(tolerance)?)
+            .map_err(|e| EngineError::Serialization(e.to_string()))
+    })
+}
+
+/// Union bounding box of visible geometry as JSON, or `"null"`.
+#[wasm_bindgen]
+pub fn scene_content_bounds() -> Result<String, JsError> {
+    with_scene(|scene| serde_json::to_string(&scene.content_bounds()).map_err(|e| EngineError::Serialization(e.to_string())))
+}
+
+/// Export the scene with default routing; returns the design as JSON.
+#[wasm_bindgen]
+pub fn scene_export_design(stitch_length: f64) -> Result<String, JsError> {
+    with_scene(|scene| {
+        let design = scene_to_export_design(scene, stitch_length)?;
+        serde_json::to_string(&design).map_err(|e| EngineError::Serialization(e.to_string()))
+    })
+}
+
+/// Flatten every visible shape to the world-space polylines that feed
+/// stitch generation; returns a JSON array of `{node_id, subpaths, color}`.
+#[wasm_bindgen]
+pub fn scene_flattened_paths(tolerance: f64) -> Result<String, JsError> {
+    with_scene(|scene| {
+        let shapes = engine_core::export_pipeline::scene_to_flattened_paths(scene, tolerance);
+        serde_json::to_string(&shapes).map_err(|e| EngineError::Serialization(e.to_string()))
+    })
+}
+
+/// Build the full job-ticket report (quality metrics, color blocks,
+/// production estimate at `spm` stitches/minute, fit for the named hoop,
+/// lints) as JSON.
+#[wasm_bindgen]
+pub fn scene_design_report(
+    stitch_length: f64,
+    routing_json: &str,
+    hoop_name: &str,
+    spm: f64,
+) -> Result<String, JsError> {
+    let routing: RoutingOptions =
+        serde_json::from_str(routing_json).map_err(|e| JsError::new(&e.to_string()))?;
+    with_scene(|scene| {
+        let report = engine_core::export_pipeline::design_report(
+            scene,
+            stitch_length,
+            &routing,
+            hoop_name,
+            spm,
+        )?;
+        serde_json::to_string(&report).map_err(|e| EngineError::Serialization(e.to_string()))
+    })
+}
+
+/// Export the scene with default routing and a basting rectangle prepended
+/// `inset` mm in from the edge of the named hoop; returns the design as JSON.
+#[wasm_bindgen]
+pub fn scene_export_with_basting(
+    stitch_length: f64,
+    hoop_name: &str,
+    inset: f64,
+) -> Result<String, JsError> {
+    let (hoop_w, hoop_h) = engine_core::export_pipeline::hoop_size(hoop_name)
+        .ok_or_else(|| JsError::new(&format!("unknown hoop \"{hoop_name}\"")))?;
+    with_scene(|scene| {
+        let mut design = scene_to_export_design(scene, stitch_length)?;
+        engine_core::export_pipeline::prepend_basting(
+            &mut design,
+            hoop_w,
+            hoop_h,
+            inset,
+            stitch_length,
+        )?;
+        serde_json::to_string(&design).map_err(|e| EngineError::Serialization(e.to_string()))
+    })
+}
+
+/// Export the scene and flag cells stitched denser than is safe for the
+/// given fabric (empty string for no fabric hint); returns a JSON array of
+/// warnings.
+#[wasm_bindgen]
+pub fn scene_density_warnings(stitch_length: f64, fabric: &str) -> Result<String, JsError> {
+    let fabric: Option<engine_core::fabric::Fabric> = if fabric.is_empty() {
+        None
+    } else {
+        Some(
+            serde_json::from_value(serde_json::Value::String(fabric.to_string()))
+                .map_err(|e| JsError::new(&e.to_string()))?,
+        )
+    };
+    with_scene(|scene| {
+        let warnings =
+            engine_core::export_pipeline::scene_density_warnings(scene, stitch_length, fabric)?;
+        serde_json::to_string(&warnings).map_err(|e| EngineError::Serialization(e.to_string()))
+    })
+}
+
+/// Export the scene and open a streaming cursor over the assembled design;
+/// returns a handle for `export_stream_next`.
+#[wasm_bindgen]
+pub fn export_stream_begin(stitch_length: f64) -> Result<u32, JsError> {
+    let streamer = with_scene(|scene| {
+        Ok(engine_core::export_pipeline::DesignStreamer::new(
+            scene_to_export_design(scene, stitch_length)?,
+        ))
+    })?;
+    STREAMERS.with(|s| {
+        let mut streamers = s.borrow_mut();
+        streamers.push(Some(streamer));
+        Ok(streamers.len() as u32 - 1)
+    })
+}
+
+/// The next batch of up to `n` stitches from a streaming cursor as a JSON
+/// array, or `"null"` once the design is exhausted (which frees the handle).
+#[wasm_bindgen]
+pub fn export_stream_next(handle: u32, n: usize) -> Result<String, JsError> {
+    STREAMERS.with(|s| {
+        let mut streamers = s.borrow_mut();
+        let slot = streamers
+            .get_mut(handle as usize)
+            .ok_or_else(|| JsError::new("unknown export stream handle"))?;
+        let streamer = slot
+            .as_mut()
+            .ok_or_else(|| JsError::new("export stream already exhausted"))?;
+        match streamer.next_batch(n) {
+            Some(batch) => serde_json::to_string(&batch).map_err(|e| JsError::new(&e.to_string())),
+            None => {
+                *slot = None;
+                Ok("null".to_string())
+            }
+        }
+    })
+}
+
+/// Export the scene and split it into one recentered single-color design per
+/// color block; returns a JSON array of designs.
+#[wasm_bindgen]
+pub fn scene_export_by_color(stitch_length: f64) -> Result<String, JsError> {
+    with_scene(|scene| {
+        let design = scene_to_export_design(scene, stitch_length)?;
+        let pieces = engine_core::export_pipeline::explode_by_color(&design);
+        serde_json::to_string(&pieces).map_err(|e| EngineError::Serialization(e.to_string()))
+    })
+}
+
+/// Export the scene, then merge near-identical thread colors: colors within
+/// `threshold` ΔE collapse together and at most `max_colors` remain. Returns
+/// the reduced design as JSON.
+#[wasm_bindgen]
+pub fn scene_reduce_colors(
+    stitch_length: f64,
+    max_colors: usize,
+    threshold: f64,
+) -> Result<String, JsError> {
+    with_scene(|scene| {
+        let mut design = scene_to_export_design(scene, stitch_length)?;
+        engine_core::export_pipeline::reduce_colors(&mut design, max_colors, threshold);
+        serde_json::to_string(&design).map_err(|e| EngineError::Serialization(e.to_string()))
+    })
+}
+
+/// Export the scene, then remap its colors to the nearest spools in
+/// `inventory_json` (a JSON array of `{name, color}` entries). Returns
+/// `{design, unfulfilled}` as JSON, where `unfulfilled` lists colors no
+/// owned spool covers.
+#[wasm_bindgen]
+pub fn scene_assign_inventory_colors(
+    stitch_length: f64,
+    inventory_json: &str,
+) -> Result<String, JsError> {
+    let inventory: Vec<engine_core::export_pipeline::ThreadEntry> =
+        serde_json::from_str(inventory_json).map_err(|e| JsError::new(&e.to_string()))?;
+    with_scene(|scene| {
+        let mut design = scene_to_export_design(scene, stitch_length)?;
+        let unfulfilled =
+            engine_core::export_pipeline::assign_from_inventory(&mut design, &inventory);
+        serde_json::to_string(&serde_json::json!({
+            "design": design,
+            "unfulfilled": unfulfilled,
+        }))
+        .map_err(|e| EngineError::Serialization(e.to_string()))
+    })
+}
+
+/// The Brother thread catalog entry for a numeric code, as JSON
+/// `{name, color}`, or `"null"` for an unknown code.
+#[wasm_bindgen]
+pub fn brother_thread_by_code(code: u16) -> Result<String, JsError> {
+    serde_json::to_string(&engine_core::thread::brother_by_code(code))
+        .map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Top-`n` thread suggestions per design color from the `brand`
+/// (`"brother"` or `"viking"`) catalog, as a JSON array of arrays in the
+/// design's color order.
+#[wasm_bindgen]
+pub fn scene_thread_suggestions(
+    stitch_length: f64,
+    brand: &str,
+    n: usize,
+) -> Result<String, JsError> {
+    let brand: engine_core::thread::ThreadBrand =
+        serde_json::from_value(serde_json::Value::String(brand.to_string()))
+            .map_err(|e| JsError::new(&e.to_string()))?;
+    with_scene(|scene| {
+        let design = scene_to_export_design(scene, stitch_length)?;
+        let suggestions =
+            engine_core::thread::suggest_for_colors(&design.colors, brand, n);
+        serde_json::to_string(&suggestions).map_err(|e| EngineError::Serialization(e.to_string()))
+    })
+}
+
+/// Export the scene positioned in a region of the named hoop: the design
+/// bbox is translated to `anchor` (`"center"`, `"top_left"`, ...) with
+/// `margin` mm clearance from the hoop edges. Returns the design as JSON.
+#[wasm_bindgen]
+pub fn scene_export_positioned(
+    stitch_length: f64,
+    hoop_name: &str,
+    anchor: &str,
+    margin: f64,
+) -> Result<String, JsError> {
+    let (hoop_w, hoop_h) = engine_core::export_pipeline::hoop_size(hoop_name)
+        .ok_or_else(|| JsError::new(&format!("unknown hoop \"{hoop_name}\"")))?;
+    let anchor: engine_core::export_pipeline::HoopAnchor =
+        serde_json::from_value(serde_json::Value::String(anchor.to_string()))
+            .map_err(|e| JsError::new(&e.to_string()))?;
+    with_scene(|scene| {
+        let mut design = scene_to_export_design(scene, stitch_length)?;
+        engine_core::export_pipeline::position_in_hoop(
+            &mut design,
+            hoop_w,
+            hoop_h,
+            anchor,
+            margin,
+        )?;
+        serde_json::to_string(&design).map_err(|e| EngineError::Serialization(e.to_string()))
+    })
+}
+
+/// Machine-readable schema of every `StitchParams` field (name, type,
+/// default, range, unit, applicable techniques) as JSON, for
+/// auto-generating the inspector panel.
+#[wasm_bindgen]
+pub fn stitch_params_schema() -> Result<String, JsError> {
+    serde_json::to_string(&engine_core::stitch::stitch_params_schema())
+        .map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Re-export after a small edit: blocks for the JSON array of changed
+/// node IDs regenerate, every other block splices in from the session's
+/// per-node cache. Returns the design as JSON, identical to a full export.
+#[wasm_bindgen]
+pub fn scene_reexport_incremental(
+    stitch_length: f64,
+    changed_nodes_json: &str,
+) -> Result<String, JsError> {
+    let changed: Vec<NodeId> =
+        serde_json::from_str(changed_nodes_json).map_err(|e| JsError::new(&e.to_string()))?;
+    with_session(|s| {
+        let design = engine_core::export_pipeline::scene_to_export_design_incremental(
+            &s.scene,
+            stitch_length,
+            &engine_core::export_pipeline::RoutingOptions::default(),
+            &mut s.export_cache,
+            &changed,
+        )?;
+        serde_json::to_string(&design).map_err(|e| EngineError::Serialization(e.to_string()))
+    })
+}
+
+/// Export the scene at a named quality profile (`"draft"`, `"normal"`,
+/// or `"high"`) with default routing; returns the design as JSON. Draft
+/// trades fidelity for speed, High the reverse.
+#[wasm_bindgen]
+pub fn scene_export_design_profile(stitch_length: f64, profile: &str) -> Result<String, JsError> {
+    let profile: engine_core::export_pipeline::GenerationProfile =
+        serde_json::from_value(serde_json::Value::String(profile.to_string()))
+            .map_err(|e| JsError::new(&e.to_string()))?;
+    with_scene(|scene| {
+        let design = engine_core::export_pipeline::scene_to_export_design_profile(
+            scene,
+            stitch_length,
+            profile,
+        )?;
+        serde_json::to_string(&design).map_err(|e| EngineError::Serialization(e.to_string()))
+    })
+}
+
+/// Per-shape outline polylines and generated stitches in one JSON payload
+/// (`[{node_id, subpaths, color, stitches}, ..]`), so the canvas can draw
+/// geometry and a live stitch preview from a single call.
+#[wasm_bindgen]
+pub fn scene_get_render_list_with_stitches(stitch_length: f64) -> Result<String, JsError> {
+    with_scene(|scene| {
+        let previews = engine_core::export_pipeline::scene_to_preview(
+            scene,
+            stitch_length,
+            engine_core::path::DEFAULT_FLATTEN_TOLERANCE,
+        )?;
+        serde_json::to_string(&previews).map_err(|e| EngineError::Serialization(e.to_string()))
+    })
+}
+
+/// The current scene's stitch program in the CSV fixture format
+/// (`x,y,type` lines with `#` metadata headers).
+#[wasm_bindgen]
+pub fn scene_export_stitch_csv(stitch_length: f64) -> Result<String, JsError> {
+    with_scene(|scene| {
+        let design = scene_to_export_design(scene, stitch_length)?;
+        Ok(engine_core::format::export_stitch_csv(&design))
+    })
+}
+
+/// Parse CSV fixture text and return the resulting design as JSON, for
+/// loading scripted stitch data without going through a scene.
+#[wasm_bindgen]
+pub fn import_stitch_csv(text: &str) -> Result<String, JsError> {
+    let design =
+        engine_core::format::import_stitch_csv(text).map_err(|e| JsError::new(&e))?;
+    serde_json::to_string(&design).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Estimated thread use per color as JSON `[{color, top_thread_mm,
+/// bobbin_mm}, ..]`: stitched length × `take_up`, bobbin at
+/// `bobbin_ratio` of the top thread.
+#[wasm_bindgen]
+pub fn scene_thread_consumption(
+    stitch_length: f64,
+    take_up: f64,
+    bobbin_ratio: f64,
+) -> Result<String, JsError> {
+    with_scene(|scene| {
+        let design = scene_to_export_design(scene, stitch_length)?;
+        let usage =
+            engine_core::export_pipeline::thread_consumption(&design, take_up, bobbin_ratio);
+        serde_json::to_string(&usage).map_err(|e| EngineError::Serialization(e.to_string()))
+    })
+}
+
+/// Extents of the assembled export (stitched, not geometric) as JSON:
+/// `{min_x, min_y, max_x, max_y, width, height}`.
+#[wasm_bindgen]
+pub fn scene_export_extents(stitch_length: f64, routing_json: &str) -> Result<String, JsError> {
+    let routing: RoutingOptions =
+        serde_json::from_str(routing_json).map_err(|e| JsError::new(&e.to_string()))?;
+    with_scene(|scene| {
+        let design = scene_to_export_design_with_routing(scene, stitch_length, &routing)?;
+        serde_json::to_string(&design.export_extents()).map_err(|e| EngineError::Serialization(e.to_string()))
+    })
+}
+
+/// Every jump in the assembled export, longest first, as a JSON array of
+/// `{index, from, to, length_mm, has_trim_before}`.
+#[wasm_bindgen]
+pub fn scene_jump_report(stitch_length: f64, routing_json: &str) -> Result<String, JsError> {
+    let routing: RoutingOptions =
+        serde_json::from_str(routing_json).map_err(|e| JsError::new(&e.to_string()))?;
+    with_scene(|scene| {
+        let design = scene_to_export_design_with_routing(scene, stitch_length, &routing)?;
+        serde_json::to_string(&engine_core::export_pipeline::jump_report(&design))
+            .map_err(|e| EngineError::Serialization(e.to_string()))
+    })
+}
+
+/// Encode a design (as JSON from one of the export endpoints) to PES bytes.
+#[wasm_bindgen]
+pub fn export_pes(design_json: &str) -> Result<Vec<u8>, JsError> {
+    let design: engine_core::export_pipeline::ExportDesign =
+        serde_json::from_str(design_json).map_err(|e| JsError::new(&e.to_string()))?;
+    engine_core::format::pes::export_pes(&design).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Encode a design (as JSON from one of the export endpoints) to JEF
+/// (Janome) bytes.
+#[wasm_bindgen]
+pub fn export_jef(design_json: &str) -> Result<Vec<u8>, JsError> {
+    let design: engine_core::export_pipeline::ExportDesign =
+        serde_json::from_str(design_json).map_err(|e| JsError::new(&e.to_string()))?;
+    engine_core::format::jef::export_jef(&design).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Encode a design (as JSON from one of the export endpoints) to DST bytes.
+#[wasm_bindgen]
+pub fn export_dst(design_json: &str) -> Result<Vec<u8>, JsError> {
+    let design: engine_core::export_pipeline::ExportDesign =
+        serde_json::from_str(design_json).map_err(|e| JsError::new(&e.to_string()))?;
+    engine_core::format::dst::export_dst(&design).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Parse DST bytes and return the resulting design as JSON. Colors come
+/// back as black placeholders — DST only records the change count.
+#[wasm_bindgen]
+pub fn import_dst(bytes: &[u8]) -> Result<String, JsError> {
+    let design = engine_core::format::dst::import_dst(bytes).map_err(|e| JsError::new(&e))?;
+    serde_json::to_string(&design).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Render the scene's visible shapes to a standalone SVG for previews and
+/// customer proofs.
+#[wasm_bindgen]
+pub fn scene_export_svg() -> Result<String, JsError> {
+    with_scene(|scene| Ok(engine_core::svg::export_scene_svg(scene)))
+}
+
+/// Assemble the scene and render it as a self-animating SVG preview that
+/// draws `spf` stitches per frame.
+#[wasm_bindgen]
+pub fn scene_export_animated_svg(stitch_length: f64, spf: f64) -> Result<String, JsError> {
+    with_scene(|scene| {
+        let design = scene_to_export_design(scene, stitch_length)?;
+        Ok(engine_core::export_pipeline::design_to_animated_svg(
+            &design, spf,
+        ))
+    })
+}
+
+/// Encode an `ExportDesign` JSON payload as VP3 bytes.
+#[wasm_bindgen]
+pub fn export_vp3(design_json: &str) -> Result<Vec<u8>, JsError> {
+    let design: engine_core::export_pipeline::ExportDesign =
+        serde_json::from_str(design_json).map_err(|e| JsError::new(&e.to_string()))?;
+    engine_core::format::vp3::export_vp3(&design).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Flag the session cancel token; a concurrently running cancellable export
+/// returns the "cancelled" error promptly.
+#[wasm_bindgen]
+pub fn request_export_cancel() {
+    EXPORT_CANCEL.with(|token| token.cancel());
+}
+
+/// Export honoring the session cancel token (see `request_export_cancel`).
+/// The token resets when the export finishes either way.
+#[wasm_bindgen]
+pub fn scene_export_design_cancellable(
+    stitch_length: f64,
+    routing_json: &str,
+) -> Result<String, JsError> {
+    let routing: RoutingOptions =
+        serde_json::from_str(routing_json).map_err(|e| JsError::new(&e.to_string()))?;
+    let token = EXPORT_CANCEL.with(|t| t.clone());
+    let result = with_scene(|scene| {
+        let design = engine_core::export_pipeline::scene_to_export_design_cancellable(
+            scene,
+            stitch_length,
+            &routing,
+            &token,
+        )?;
+        serde_json::to_string(&design).map_err(|e| EngineError::Serialization(e.to_string()))
+    });
+    token.reset();
+    result
+}
+
+/// Export the scene with routing options supplied as JSON.
+#[wasm_bindgen]
+pub fn scene_export_design_with_routing(
+    stitch_length: f64,
+    routing_json: &str,
+) -> Result<String, JsError> {
+    let routing: RoutingOptions =
+        serde_json::from_str(routing_json).map_err(|e| JsError::new(&e.to_string()))?;
+    with_scene(|scene| {
+        let design = scene_to_export_design_with_routing(scene, stitch_length, &routing)?;
+        serde_json::to_string(&design).map_err(|e| EngineError::Serialization(e.to_string()))
+    })
+}